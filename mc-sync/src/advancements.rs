@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use anyhow::Context as _;
+use chrono::DateTime;
+use serde::Deserialize;
+use serde::Serialize;
+use serenity::utils;
+
+/// How an advancement is announced in game, from least to most difficult.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Frame {
+    Task,
+    Goal,
+    Challenge,
+}
+
+impl Frame {
+    /// Determine the frame from the server's announcement, e.g.
+    /// `Steve has completed the challenge [Arbalistic]`.
+    pub fn of(announcement: &str) -> Self {
+        if announcement.contains(" has completed the challenge [") {
+            Frame::Challenge
+        } else if announcement.contains(" has reached the goal [") {
+            Frame::Goal
+        } else {
+            Frame::Task
+        }
+    }
+
+    /// Heading of the in-game toast.
+    pub fn heading(self) -> &'static str {
+        match self {
+            Frame::Task => "Advancement Made!",
+            Frame::Goal => "Goal Reached!",
+            Frame::Challenge => "🏆 Challenge Complete! 🏆",
+        }
+    }
+
+    /// Color of the advancement's name in game.
+    pub fn colour(self) -> utils::Colour {
+        match self {
+            Frame::Task | Frame::Goal => utils::Colour::from_rgb(85, 255, 85),
+            Frame::Challenge => utils::Colour::from_rgb(170, 0, 170),
+        }
+    }
+}
+
+/// Vanilla advancement, as shown in the advancements screen.
+#[derive(Debug)]
+pub struct Advancement {
+    /// ID without the `minecraft:` namespace, e.g. `story/mine_stone`.
+    pub id: &'static str,
+    pub title: &'static str,
+    pub description: &'static str,
+
+    /// Item ID (without the `minecraft:` namespace) of the icon.
+    pub icon: &'static str,
+}
+
+/// Look up a vanilla advancement by its English title.
+pub fn find(title: &str) -> Option<Advancement> {
+    ADVANCEMENTS
+        .iter()
+        .find(|(_, candidate, _, _)| *candidate == title)
+        .map(advancement)
+}
+
+/// Look up a vanilla advancement by its ID, e.g. `minecraft:story/mine_stone`.
+pub fn by_id(id: &str) -> Option<Advancement> {
+    let id = id.strip_prefix("minecraft:")?;
+    ADVANCEMENTS
+        .iter()
+        .find(|(candidate, _, _, _)| *candidate == id)
+        .map(advancement)
+}
+
+/// Number of vanilla advancements.
+pub fn count() -> usize {
+    ADVANCEMENTS.len()
+}
+
+fn advancement(
+    &(id, title, description, icon): &(&'static str, &'static str, &'static str, &'static str),
+) -> Advancement {
+    Advancement {
+        id,
+        title,
+        description,
+        icon,
+    }
+}
+
+/// Advancements a player has made, as of the server's last save.
+#[derive(Clone, Debug, Default)]
+pub struct Progress {
+    /// Newest first. Recipes, which the game tracks as advancements, are
+    /// left out.
+    pub made: Vec<Made>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Made {
+    /// e.g. `minecraft:story/mine_stone`, or another namespace for a data pack.
+    pub id: String,
+    /// Seconds since the Unix epoch, when the last criterion was met.
+    pub time: i64,
+}
+
+impl Made {
+    /// English title of a vanilla advancement, or else a readable ID.
+    pub fn title(&self) -> String {
+        match by_id(&self.id) {
+            Some(advancement) => advancement.title.to_owned(),
+            None => {
+                let name = self.id.rsplit(['/', ':']).next().unwrap_or(&self.id);
+                name.replace('_', " ")
+            }
+        }
+    }
+}
+
+impl Progress {
+    /// How many vanilla advancements have been made, out of `count()`.
+    pub fn vanilla(&self) -> usize {
+        self.made
+            .iter()
+            .filter(|made| by_id(&made.id).is_some())
+            .count()
+    }
+
+    /// When the player made their latest vanilla advancement, e.g. to break
+    /// ties by who got there first.
+    pub fn latest(&self) -> Option<i64> {
+        self.made
+            .iter()
+            .find(|made| by_id(&made.id).is_some())
+            .map(|made| made.time)
+    }
+}
+
+/// Per-advancement progress in `<world>/advancements/<uuid>.json`.
+#[derive(Deserialize)]
+struct Entry {
+    /// Times each criterion was met, e.g. `2023-06-12 18:30:45 +0200`.
+    #[serde(default)]
+    criteria: HashMap<String, String>,
+    done: bool,
+}
+
+/// Read the advancements of the player with `uuid` from `world`, or `None`
+/// if they haven't played in it.
+pub fn read(world: &Path, uuid: &str) -> anyhow::Result<Option<Progress>> {
+    let path = world.join("advancements").join(format!("{}.json", uuid));
+    match fs::read_to_string(&path) {
+        Ok(text) => parse(&text)
+            .map(Some)
+            .with_context(|| format!("Failed to parse advancements `{}`", path.display())),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => {
+            Err(error).with_context(|| format!("Failed to read advancements `{}`", path.display()))
+        }
+    }
+}
+
+/// Read every player's advancements from `world`, by UUID.
+pub fn read_all(world: &Path) -> anyhow::Result<Vec<(String, Progress)>> {
+    let directory = world.join("advancements");
+    let entries = match fs::read_dir(&directory) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => {
+            return Err(error).with_context(|| format!("Failed to read `{}`", directory.display()))
+        }
+    };
+
+    let mut players = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let uuid = match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(uuid)
+                if path
+                    .extension()
+                    .is_some_and(|extension| extension == "json") =>
+            {
+                uuid
+            }
+            _ => continue,
+        };
+        match read(world, uuid) {
+            Ok(Some(progress)) => players.push((uuid.to_owned(), progress)),
+            Ok(None) => (),
+            Err(error) => tracing::warn!("{:?}", error),
+        }
+    }
+    Ok(players)
+}
+
+fn parse(text: &str) -> anyhow::Result<Progress> {
+    let file = serde_json::from_str::<HashMap<String, serde_json::Value>>(text)?;
+    let mut made = file
+        .into_iter()
+        .filter(|(id, _)| id.contains(':') && !id.starts_with("minecraft:recipes/"))
+        .filter_map(|(id, value)| {
+            let entry = serde_json::from_value::<Entry>(value).ok()?;
+            if !entry.done {
+                return None;
+            }
+            let time = entry
+                .criteria
+                .values()
+                .filter_map(|time| DateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S %z").ok())
+                .map(|time| time.timestamp())
+                .max()
+                .unwrap_or(0);
+            Some(Made { id, time })
+        })
+        .collect::<Vec<_>>();
+    made.sort_by(|a, b| b.time.cmp(&a.time).then_with(|| a.id.cmp(&b.id)));
+    Ok(Progress { made })
+}
+
+// ID, title, description, and icon of each advancement as of 1.20.
+// https://minecraft.wiki/w/Advancement#List_of_advancements
+static ADVANCEMENTS: &[(&str, &str, &str, &str)] = &[
+    // Minecraft
+    ("story/root", "Minecraft", "The heart and story of the game", "grass_block"),
+    ("story/mine_stone", "Stone Age", "Mine Stone with your new Pickaxe", "wooden_pickaxe"),
+    ("story/upgrade_tools", "Getting an Upgrade", "Construct a better Pickaxe", "stone_pickaxe"),
+    ("story/smelt_iron", "Acquire Hardware", "Smelt an Iron Ingot", "iron_ingot"),
+    ("story/obtain_armor", "Suit Up", "Protect yourself with a piece of iron armor", "iron_chestplate"),
+    ("story/lava_bucket", "Hot Stuff", "Fill a Bucket with lava", "lava_bucket"),
+    ("story/iron_tools", "Isn't It Iron Pick", "Upgrade your Pickaxe", "iron_pickaxe"),
+    ("story/deflect_arrow", "Not Today, Thank You", "Deflect a projectile with a Shield", "shield"),
+    ("story/form_obsidian", "Ice Bucket Challenge", "Obtain a block of Obsidian", "obsidian"),
+    ("story/mine_diamond", "Diamonds!", "Acquire diamonds", "diamond"),
+    ("story/enter_the_nether", "We Need to Go Deeper", "Build, light and enter a Nether Portal", "flint_and_steel"),
+    ("story/shiny_gear", "Cover Me with Diamonds", "Diamond armor saves lives", "diamond_chestplate"),
+    ("story/enchant_item", "Enchanter", "Enchant an item at an Enchanting Table", "enchanted_book"),
+    ("story/cure_zombie_villager", "Zombie Doctor", "Weaken and then cure a Zombie Villager", "golden_apple"),
+    ("story/follow_ender_eye", "Eye Spy", "Follow an Eye of Ender", "ender_eye"),
+    ("story/enter_the_end", "The End?", "Enter the End Portal", "end_stone"),
+
+    // Nether
+    ("nether/root", "Nether", "Bring summer clothes", "red_nether_bricks"),
+    ("nether/return_to_sender", "Return to Sender", "Destroy a Ghast with a fireball", "fire_charge"),
+    ("nether/find_bastion", "Those Were the Days", "Enter a Bastion Remnant", "polished_blackstone_bricks"),
+    ("nether/obtain_ancient_debris", "Hidden in the Depths", "Obtain Ancient Debris", "ancient_debris"),
+    ("nether/fast_travel", "Subspace Bubble", "Use the Nether to travel 7 km in the Overworld", "map"),
+    ("nether/find_fortress", "A Terrible Fortress", "Break your way into a Nether Fortress", "nether_bricks"),
+    ("nether/obtain_crying_obsidian", "Who is Cutting Onions?", "Obtain Crying Obsidian", "crying_obsidian"),
+    ("nether/distract_piglin", "Oh Shiny", "Distract Piglins with gold", "gold_ingot"),
+    ("nether/ride_strider", "This Boat Has Legs", "Ride a Strider with a Warped Fungus on a Stick", "warped_fungus_on_a_stick"),
+    ("nether/uneasy_alliance", "Uneasy Alliance", "Rescue a Ghast from the Nether, bring it safely home to the Overworld... and then kill it", "ghast_tear"),
+    ("nether/loot_bastion", "War Pigs", "Loot a Chest in a Bastion Remnant", "chest"),
+    ("nether/use_lodestone", "Country Lode, Take Me Home", "Use a Compass on a Lodestone", "lodestone"),
+    ("nether/netherite_armor", "Cover Me in Debris", "Get a full suit of Netherite armor", "netherite_chestplate"),
+    ("nether/get_wither_skull", "Spooky Scary Skeleton", "Obtain a Wither Skeleton's skull", "wither_skeleton_skull"),
+    ("nether/obtain_blaze_rod", "Into Fire", "Relieve a Blaze of its rod", "blaze_rod"),
+    ("nether/charge_respawn_anchor", "Not Quite \"Nine\" Lives", "Charge a Respawn Anchor to the maximum", "respawn_anchor"),
+    ("nether/ride_strider_in_overworld_lava", "Feels Like Home", "Take a Strider for a loooong ride on a lava lake in the Overworld", "warped_fungus_on_a_stick"),
+    ("nether/explore_nether", "Hot Tourist Destinations", "Explore all Nether biomes", "netherite_boots"),
+    ("nether/summon_wither", "Withering Heights", "Summon the Wither", "nether_star"),
+    ("nether/brew_potion", "Local Brewery", "Brew a Potion", "potion"),
+    ("nether/create_beacon", "Bring Home the Beacon", "Construct and place a Beacon", "beacon"),
+    ("nether/all_potions", "A Furious Cocktail", "Have every potion effect applied at the same time", "milk_bucket"),
+    ("nether/create_full_beacon", "Beaconator", "Bring a Beacon to full power", "beacon"),
+    ("nether/all_effects", "How Did We Get Here?", "Have every effect applied at the same time", "fermented_spider_eye"),
+
+    // The End
+    ("end/root", "The End", "Or the beginning?", "end_stone"),
+    ("end/kill_dragon", "Free the End", "Good luck", "dragon_head"),
+    ("end/dragon_egg", "The Next Generation", "Hold the Dragon Egg", "dragon_egg"),
+    ("end/enter_end_gateway", "Remote Getaway", "Escape the island", "ender_pearl"),
+    ("end/respawn_dragon", "The End... Again...", "Respawn the Ender Dragon", "end_crystal"),
+    ("end/dragon_breath", "You Need a Mint", "Collect Dragon's Breath in a Glass Bottle", "dragon_breath"),
+    ("end/find_end_city", "The City at the End of the Game", "Go on in, what could happen?", "purpur_block"),
+    ("end/elytra", "Sky's the Limit", "Find Elytra", "elytra"),
+    ("end/levitate", "Great View From Up Here", "Levitate up 50 blocks from the attacks of a Shulker", "shulker_shell"),
+
+    // Adventure
+    ("adventure/root", "Adventure", "Adventure, exploration and combat", "map"),
+    ("adventure/voluntary_exile", "Voluntary Exile", "Kill a raid captain. Maybe consider staying away from villages for the time being...", "white_banner"),
+    ("adventure/spyglass_at_parrot", "Is It a Bird?", "Look at a Parrot through a Spyglass", "spyglass"),
+    ("adventure/kill_a_mob", "Monster Hunter", "Kill any hostile monster", "iron_sword"),
+    ("adventure/read_power_of_chiseled_bookshelf", "The Power of Books", "Read the power signal of a Chiseled Bookshelf using a Comparator", "chiseled_bookshelf"),
+    ("adventure/trade", "What a Deal!", "Successfully trade with a Villager", "emerald"),
+    ("adventure/trim_with_any_armor_pattern", "Crafting a New Look", "Craft a trimmed armor at a Smithing Table", "dune_armor_trim_smithing_template"),
+    ("adventure/honey_block_slide", "Sticky Situation", "Jump into a Honey Block to break your fall", "honey_block"),
+    ("adventure/ol_betsy", "Ol' Betsy", "Shoot a Crossbow", "crossbow"),
+    ("adventure/lightning_rod_with_villager_no_fire", "Surge Protector", "Protect a Villager from an undesired shock without starting a fire", "lightning_rod"),
+    ("adventure/fall_from_world_height", "Caves & Cliffs", "Free fall from the top of the world (build limit) to the bottom of the world and survive", "powder_snow_bucket"),
+    ("adventure/salvage_sherd", "Respecting the Remnants", "Brush a Suspicious block to obtain a Pottery Sherd", "brush"),
+    ("adventure/avoid_vibration", "Sneak 100", "Sneak near a Sculk Sensor or Warden to prevent it from detecting you", "sculk_sensor"),
+    ("adventure/sleep_in_bed", "Sweet Dreams", "Sleep in a Bed to change your respawn point", "red_bed"),
+    ("adventure/hero_of_the_village", "Hero of the Village", "Successfully defend a village from a raid", "white_banner"),
+    ("adventure/spyglass_at_ghast", "Is It a Balloon?", "Look at a Ghast through a Spyglass", "spyglass"),
+    ("adventure/throw_trident", "A Throwaway Joke", "Throw a Trident at something.", "trident"),
+    ("adventure/kill_mob_near_sculk_catalyst", "It Spreads", "Kill a mob near a Sculk Catalyst", "sculk"),
+    ("adventure/shoot_arrow", "Take Aim", "Shoot something with an Arrow", "bow"),
+    ("adventure/kill_all_mobs", "Monsters Hunted", "Kill one of every hostile monster", "diamond_sword"),
+    ("adventure/totem_of_undying", "Postmortal", "Use a Totem of Undying to cheat death", "totem_of_undying"),
+    ("adventure/summon_iron_golem", "Hired Help", "Summon an Iron Golem to help defend a village", "carved_pumpkin"),
+    ("adventure/trade_at_world_height", "Star Trader", "Trade with a Villager at the build height limit", "emerald"),
+    ("adventure/trim_with_all_exclusive_armor_patterns", "Smithing with Style", "Apply these smithing templates at least once: Spire, Snout, Rib, Ward, Silence, Vex, Tide, Wayfinder", "silence_armor_trim_smithing_template"),
+    ("adventure/two_birds_one_arrow", "Two Birds, One Arrow", "Kill two Phantoms with a piercing Arrow", "crossbow"),
+    ("adventure/whos_the_pillager_now", "Who's the Pillager Now?", "Give a Pillager a taste of their own medicine", "crossbow"),
+    ("adventure/arbalistic", "Arbalistic", "Kill five unique mobs with one crossbow shot", "crossbow"),
+    ("adventure/craft_decorated_pot_using_only_sherds", "Careful Restoration", "Make a Decorated Pot out of 4 Pottery Sherds", "decorated_pot"),
+    ("adventure/adventuring_time", "Adventuring Time", "Discover every biome", "diamond_boots"),
+    ("adventure/play_jukebox_in_meadows", "Sound of Music", "Make the Meadows come alive with the sound of music from a Jukebox", "jukebox"),
+    ("adventure/walk_on_powder_snow_with_leather_boots", "Light as a Rabbit", "Walk on Powder Snow... without sinking in it", "leather_boots"),
+    ("adventure/spyglass_at_dragon", "Is It a Plane?", "Look at the Ender Dragon through a Spyglass", "spyglass"),
+    ("adventure/very_very_frightening", "Very Very Frightening", "Strike a Villager with lightning", "trident"),
+    ("adventure/sniper_duel", "Sniper Duel", "Kill a Skeleton from at least 50 meters away", "arrow"),
+    ("adventure/bullseye", "Bullseye", "Hit the bullseye of a Target block from at least 30 meters away", "target"),
+
+    // Husbandry
+    ("husbandry/root", "Husbandry", "The world is full of friends and food", "hay_block"),
+    ("husbandry/safely_harvest_honey", "Bee Our Guest", "Use a Campfire to collect Honey from a Beehive using a Glass Bottle without aggravating the Bees", "honey_bottle"),
+    ("husbandry/breed_an_animal", "The Parrots and the Bats", "Breed two animals together", "wheat"),
+    ("husbandry/allay_deliver_item_to_player", "You've Got a Friend in Me", "Have an Allay deliver items to you", "cookie"),
+    ("husbandry/ride_a_boat_with_a_goat", "Whatever Floats Your Goat!", "Get in a Boat and float with a Goat", "oak_boat"),
+    ("husbandry/tame_an_animal", "Best Friends Forever", "Tame an animal", "lead"),
+    ("husbandry/make_a_sign_glow", "Glow and Behold!", "Make the signs of any color glow", "glow_ink_sac"),
+    ("husbandry/fishy_business", "Fishy Business", "Catch a fish", "fishing_rod"),
+    ("husbandry/silk_touch_nest", "Total Beelocation", "Move a Bee Nest, with 3 Bees inside, using Silk Touch", "bee_nest"),
+    ("husbandry/tadpole_in_a_bucket", "Bukkit Bukkit", "Catch a Tadpole in a Bucket", "tadpole_bucket"),
+    ("husbandry/obtain_sniffer_egg", "Smells Interesting", "Obtain a Sniffer Egg", "sniffer_egg"),
+    ("husbandry/plant_seed", "A Seedy Place", "Plant a seed and watch it grow", "wheat_seeds"),
+    ("husbandry/wax_on", "Wax On", "Apply Honeycomb to a Copper block!", "honeycomb"),
+    ("husbandry/bred_all_animals", "Two by Two", "Breed all the animals!", "golden_carrot"),
+    ("husbandry/allay_deliver_cake_to_note_block", "Birthday Song", "Have an Allay drop a Cake at a Note Block", "cake"),
+    ("husbandry/complete_catalogue", "A Complete Catalogue", "Tame all Cat variants!", "cod"),
+    ("husbandry/tactical_fishing", "Tactical Fishing", "Catch a Fish... without a Fishing Rod!", "pufferfish_bucket"),
+    ("husbandry/leash_all_frog_variants", "When the Squad Hops into Town", "Get each Frog variant on a Lead", "lead"),
+    ("husbandry/feed_snifflet", "Little Sniffs", "Feed a Snifflet", "torchflower_seeds"),
+    ("husbandry/balanced_diet", "A Balanced Diet", "Eat everything that is edible, even if it's not good for you", "apple"),
+    ("husbandry/obtain_netherite_hoe", "Serious Dedication", "Use a Netherite Ingot to upgrade a Hoe, and then reevaluate your life choices", "netherite_hoe"),
+    ("husbandry/wax_off", "Wax Off", "Scrape Wax off of a Copper block!", "stone_axe"),
+    ("husbandry/axolotl_in_a_bucket", "The Cutest Predator", "Catch an Axolotl in a Bucket", "axolotl_bucket"),
+    ("husbandry/froglights", "With Our Powers Combined!", "Have all Froglights in your inventory", "verdant_froglight"),
+    ("husbandry/plant_any_sniffer_seed", "Planting the Past", "Plant any Sniffer seed", "pitcher_pod"),
+    ("husbandry/kill_axolotl_target", "The Healing Power of Friendship!", "Team up with an axolotl and win a fight", "tropical_fish_bucket"),
+];
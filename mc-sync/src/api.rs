@@ -0,0 +1,569 @@
+use std::fmt;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_tungstenite::tokio::TokioAdapter;
+use async_tungstenite::tungstenite::protocol::Role;
+use async_tungstenite::tungstenite::Message;
+use async_tungstenite::WebSocketStream;
+use futures::sink::SinkExt as _;
+use futures::stream::StreamExt as _;
+use joinery::JoinableIterator;
+use serde::Deserialize;
+use serde_json::json;
+use serde_json::Value;
+use sha1::Digest as _;
+use tokio::io::AsyncReadExt as _;
+use tokio::io::AsyncWriteExt as _;
+use tokio::net;
+use tokio::sync::broadcast;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+use tokio::time;
+
+use crate::commands;
+use crate::config;
+use crate::events::Event;
+use crate::metrics::Metrics;
+use crate::metrics::Registry;
+use crate::sink;
+
+/// Longest request head, and body, to accept.
+static HEAD_LIMIT: usize = 16 * 1024;
+static BODY_LIMIT: usize = 64 * 1024;
+
+/// How long to wait for the bridge to carry out a request.
+static TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long a client has to send the request's head, and then its body.
+static READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Most requests to serve at once. More connections wait to be accepted.
+static CONNECTION_LIMIT: usize = 64;
+
+/// Most log lines returned by `/api/log`.
+static LOG_LIMIT: usize = 1000;
+
+/// Kind of streamed log lines.
+static LOG: &str = "log";
+
+/// Appended to a WebSocket client's key to accept the connection.
+static WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// What an API token is allowed to do.
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    Chat,
+    Command,
+    Online,
+    Backup,
+    Restart,
+    Log,
+    /// Subscribe to server events at `/api/events`. Log lines also need `log`.
+    Events,
+}
+
+/// Something for the bridge to do on behalf of an API client.
+#[derive(Clone, Debug)]
+pub enum Request {
+    /// Broadcast a message in Minecraft chat, and post it to Discord.
+    Chat { user: String, message: String },
+    /// Run a console command.
+    Command { command: String },
+    /// List who's online.
+    Online,
+    /// Back up the world now.
+    Backup,
+    /// Restart the server after warning players.
+    Restart { minutes: u64 },
+    /// Read the end of the server log.
+    Log { lines: usize },
+}
+
+impl Request {
+    fn permission(&self) -> Permission {
+        match self {
+            Request::Chat { .. } => Permission::Chat,
+            Request::Command { .. } => Permission::Command,
+            Request::Online => Permission::Online,
+            Request::Backup => Permission::Backup,
+            Request::Restart { .. } => Permission::Restart,
+            Request::Log { .. } => Permission::Log,
+        }
+    }
+}
+
+/// The result of a request: a JSON body, or why the bridge refused.
+pub type Outcome = Result<Value, String>;
+
+/// Where the bridge sends the outcome of a request. Only the first is sent.
+#[derive(Clone)]
+pub struct Reply(Arc<Mutex<Option<oneshot::Sender<Outcome>>>>);
+
+impl Reply {
+    pub fn send(&self, result: Outcome) {
+        if let Some(tx) = self.0.lock().unwrap().take() {
+            tx.send(result).ok();
+        }
+    }
+}
+
+impl fmt::Debug for Reply {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str("Reply")
+    }
+}
+
+/// A server event or log line, already encoded as JSON, for `/api/events`.
+#[derive(Debug)]
+pub struct Streamed {
+    /// Name of the server, as in the `server` parameter.
+    pub server: String,
+    /// What it is, e.g. `join`, or `log` for a log line.
+    pub kind: &'static str,
+    pub json: String,
+}
+
+impl Streamed {
+    pub fn event(server: &str, name: Option<&str>, payload: &sink::Payload) -> Option<Self> {
+        Some(Streamed {
+            server: server.to_owned(),
+            kind: payload.kind().as_str(),
+            json: String::from_utf8(payload.to_json(name).ok()?).ok()?,
+        })
+    }
+
+    pub fn log(server: &str, name: Option<&str>, line: &str) -> Self {
+        let json = json!({
+            "server": name,
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "event": LOG,
+            "line": line,
+        });
+        Streamed {
+            server: server.to_owned(),
+            kind: LOG,
+            json: json.to_string(),
+        }
+    }
+}
+
+/// Serve the API over HTTP, passing each request to the bridge for the
+/// server it names.
+pub struct Server {
+    listener: net::TcpListener,
+    config: Arc<config::Api>,
+    registry: Arc<Registry>,
+    events: broadcast::Sender<Arc<Streamed>>,
+    connections: Arc<Semaphore>,
+}
+
+impl Server {
+    /// Serve the API, streaming what's sent on `events` to subscribers.
+    pub async fn new(
+        config: config::Api,
+        registry: Arc<Registry>,
+        events: broadcast::Sender<Arc<Streamed>>,
+    ) -> anyhow::Result<Self> {
+        let listener = net::TcpListener::bind(&config.address).await?;
+        Ok(Server {
+            listener,
+            config: Arc::new(config),
+            registry,
+            events,
+            connections: Arc::new(Semaphore::new(CONNECTION_LIMIT)),
+        })
+    }
+
+    pub async fn start(self) -> anyhow::Result<()> {
+        loop {
+            let permit = Arc::clone(&self.connections).acquire_owned().await?;
+            let (stream, _) = self.listener.accept().await?;
+            let config = Arc::clone(&self.config);
+            let registry = Arc::clone(&self.registry);
+            let events = self.events.clone();
+            tokio::spawn(async move {
+                if let Err(error) = serve(stream, permit, &config, &registry, &events).await {
+                    tracing::warn!("Failed to serve API request: {}", error);
+                }
+            });
+        }
+    }
+}
+
+/// An HTTP response: status line and JSON body.
+type Response = (&'static str, Value);
+
+fn error(status: &'static str, message: &str) -> Response {
+    (status, json!({ "error": message }))
+}
+
+async fn serve(
+    mut stream: net::TcpStream,
+    permit: OwnedSemaphorePermit,
+    config: &config::Api,
+    registry: &Registry,
+    events: &broadcast::Sender<Arc<Streamed>>,
+) -> anyhow::Result<()> {
+    let timed_out = || error("408 Request Timeout", "Took too long to send the request");
+    let (head, rest) = match time::timeout(READ_TIMEOUT, read_head(&mut stream)).await {
+        Ok(result) => match result? {
+            Ok(request) => request,
+            Err(response) => return respond(stream, response).await,
+        },
+        Err(_) => return respond(stream, timed_out()).await,
+    };
+    // Before reading any more from someone who may not have a token.
+    let (method, url, token) = match authenticate(&head, config) {
+        Ok(request) => request,
+        Err(response) => return respond(stream, response).await,
+    };
+
+    if url.path() == "/api/events" {
+        // Subscribers stay connected, and only count against the limit
+        // until they're authenticated.
+        drop(permit);
+        return subscribe(stream, &head, &url, token, registry, events).await;
+    }
+
+    let body = match time::timeout(READ_TIMEOUT, read_body(&mut stream, &head, rest)).await {
+        Ok(result) => match result? {
+            Ok(body) => body,
+            Err(response) => return respond(stream, response).await,
+        },
+        Err(_) => return respond(stream, timed_out()).await,
+    };
+    let response = handle(method, &url, token, &body, registry).await;
+    respond(stream, response).await
+}
+
+async fn respond(mut stream: net::TcpStream, (status, body): Response) -> anyhow::Result<()> {
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body,
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Read the request's head, and whatever was read of the body with it.
+async fn read_head(
+    stream: &mut net::TcpStream,
+) -> anyhow::Result<Result<(String, Vec<u8>), Response>> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 4096];
+    let end = loop {
+        if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end;
+        }
+        if buffer.len() > HEAD_LIMIT {
+            return Ok(Err(error(
+                "431 Request Header Fields Too Large",
+                "Head too large",
+            )));
+        }
+        match stream.read(&mut chunk).await? {
+            0 => return Ok(Err(error("400 Bad Request", "Incomplete request"))),
+            read => buffer.extend_from_slice(&chunk[..read]),
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..end]).into_owned();
+    let rest = buffer.split_off(end + 4);
+    Ok(Ok((head, rest)))
+}
+
+/// Read the rest of the request's body, as long as its `Content-Length`.
+async fn read_body(
+    stream: &mut net::TcpStream,
+    head: &str,
+    mut body: Vec<u8>,
+) -> anyhow::Result<Result<Vec<u8>, Response>> {
+    let length = header(head, "content-length")
+        .map(|length| length.parse::<usize>())
+        .transpose();
+    let length = match length {
+        Ok(length) => length.unwrap_or(0),
+        Err(_) => return Ok(Err(error("400 Bad Request", "Invalid Content-Length"))),
+    };
+    if length > BODY_LIMIT {
+        return Ok(Err(error("413 Payload Too Large", "Body too large")));
+    }
+
+    let mut chunk = [0; 4096];
+    while body.len() < length {
+        match stream.read(&mut chunk).await? {
+            0 => return Ok(Err(error("400 Bad Request", "Incomplete body"))),
+            read => body.extend_from_slice(&chunk[..read]),
+        }
+    }
+    body.truncate(length);
+    Ok(Ok(body))
+}
+
+/// The value of header `name`, which is matched case-insensitively.
+fn header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+    head.lines().skip(1).find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        if key.trim().eq_ignore_ascii_case(name) {
+            Some(value.trim())
+        } else {
+            None
+        }
+    })
+}
+
+/// The request's method and URL, and the token it was made with, from the
+/// `Authorization` header, or the `token` parameter for browsers, which can't
+/// set headers on WebSockets.
+fn authenticate<'a>(
+    head: &'a str,
+    config: &'a config::Api,
+) -> Result<(&'a str, reqwest::Url, &'a config::ApiToken), Response> {
+    let mut request_line = head.lines().next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default();
+    let target = request_line.next().unwrap_or_default();
+    let url = reqwest::Url::parse("http://localhost")
+        .and_then(|base| base.join(target))
+        .map_err(|_| error("400 Bad Request", "Invalid path"))?;
+
+    let token = header(head, "authorization")
+        .and_then(|authorization| authorization.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_owned())
+        .or_else(|| query(&url, "token"))
+        .and_then(|token| {
+            config
+                .tokens
+                .iter()
+                .find(|candidate| equal(candidate.token.as_bytes(), token.as_bytes()))
+        })
+        .ok_or_else(|| error("401 Unauthorized", "Missing or unknown token"))?;
+
+    Ok((method, url, token))
+}
+
+fn query(url: &reqwest::Url, key: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(name, _)| name == key)
+        .map(|(_, value)| value.into_owned())
+}
+
+/// The named server, or the first.
+fn server<'a>(
+    registry: &'a Registry,
+    url: &reqwest::Url,
+) -> Result<&'a (String, Arc<Metrics>, mpsc::Sender<Event>), Response> {
+    let server = query(url, "server");
+    match &server {
+        None => registry.servers.first(),
+        Some(server) => registry.servers.iter().find(|(name, _, _)| name == server),
+    }
+    .ok_or_else(|| error("404 Not Found", "No such server"))
+}
+
+async fn handle(
+    method: &str,
+    url: &reqwest::Url,
+    token: &config::ApiToken,
+    body: &[u8],
+    registry: &Registry,
+) -> Response {
+    let query = |key: &str| query(url, key);
+    let body = if body.is_empty() {
+        Ok(json!({}))
+    } else {
+        serde_json::from_slice::<Value>(body)
+    };
+    let body = match body {
+        Ok(body) => body,
+        Err(_) => return error("400 Bad Request", "Body isn't valid JSON"),
+    };
+    let field = |key: &str| body[key].as_str().map(String::from);
+
+    let request = match (method, url.path()) {
+        ("GET", "/api/online") => Request::Online,
+        ("GET", "/api/log") => match query("lines").map(|lines| lines.parse::<usize>()) {
+            None => Request::Log { lines: 100 },
+            Some(Ok(lines)) => Request::Log {
+                lines: lines.min(LOG_LIMIT),
+            },
+            Some(Err(_)) => return error("400 Bad Request", "Invalid `lines`"),
+        },
+        ("POST", "/api/chat") => {
+            // The user is shown as is, and a line break would run a second
+            // console command.
+            let user = field("user").unwrap_or_else(|| token.name.clone());
+            if user.trim().is_empty() || user.contains(char::is_control) {
+                return error("400 Bad Request", "Invalid `user`");
+            }
+            match field("message") {
+                Some(message) if !message.trim().is_empty() => Request::Chat { user, message },
+                _ => return error("400 Bad Request", "Missing `message`"),
+            }
+        }
+        ("POST", "/api/command") => match field("command") {
+            Some(command) if !command.trim().is_empty() => Request::Command { command },
+            _ => return error("400 Bad Request", "Missing `command`"),
+        },
+        ("POST", "/api/backup") => Request::Backup,
+        ("POST", "/api/restart") => match &body["minutes"] {
+            Value::Null => Request::Restart {
+                minutes: commands::SERVER_DELAY,
+            },
+            minutes => match minutes.as_u64() {
+                Some(minutes) => Request::Restart { minutes },
+                None => return error("400 Bad Request", "Invalid `minutes`"),
+            },
+        },
+        _ => return error("404 Not Found", "No such route"),
+    };
+
+    if !token.permissions.contains(&request.permission()) {
+        return error("403 Forbidden", "Token doesn't have permission");
+    }
+
+    let tx = match server(registry, url) {
+        Ok((_, _, tx)) => tx,
+        Err(response) => return response,
+    };
+
+    let (reply, rx) = oneshot::channel();
+    let event = Event::Api {
+        token: token.name.clone(),
+        request,
+        reply: Reply(Arc::new(Mutex::new(Some(reply)))),
+    };
+    if tx.send(event).await.is_err() {
+        return error("503 Service Unavailable", "The bridge has stopped");
+    }
+
+    match time::timeout(TIMEOUT, rx).await {
+        Ok(Ok(Ok(body))) => ("200 OK", body),
+        Ok(Ok(Err(refused))) => error("409 Conflict", &refused),
+        Ok(Err(_)) => error(
+            "500 Internal Server Error",
+            "The bridge failed to handle it",
+        ),
+        Err(_) => error("504 Gateway Timeout", "The bridge took too long"),
+    }
+}
+
+/// Stream events and log lines from one server over a WebSocket, filtered by
+/// the comma-separated `events` parameter (default: everything the token may
+/// see).
+async fn subscribe(
+    stream: net::TcpStream,
+    head: &str,
+    url: &reqwest::Url,
+    token: &config::ApiToken,
+    registry: &Registry,
+    events: &broadcast::Sender<Arc<Streamed>>,
+) -> anyhow::Result<()> {
+    let key = match header(head, "sec-websocket-key") {
+        Some(key)
+            if header(head, "upgrade")
+                .is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket")) =>
+        {
+            key
+        }
+        _ => {
+            return respond(
+                stream,
+                error("426 Upgrade Required", "Connect with a WebSocket"),
+            )
+            .await
+        }
+    };
+    if !token.permissions.contains(&Permission::Events) {
+        return respond(
+            stream,
+            error("403 Forbidden", "Token doesn't have permission"),
+        )
+        .await;
+    }
+    let server = match server(registry, url) {
+        Ok((server, _, _)) => server.clone(),
+        Err(response) => return respond(stream, response).await,
+    };
+
+    let allowed = sink::Kind::ALL
+        .iter()
+        .map(|kind| kind.as_str())
+        .chain(Some(LOG).filter(|_| token.permissions.contains(&Permission::Log)))
+        .collect::<Vec<_>>();
+    let kinds = match query(url, "events") {
+        None => allowed,
+        Some(kinds) => match kinds
+            .split(',')
+            .map(|kind| {
+                allowed
+                    .iter()
+                    .copied()
+                    .find(|allowed| *allowed == kind.trim())
+            })
+            .collect::<Option<Vec<_>>>()
+        {
+            Some(kinds) => kinds,
+            None => {
+                let message = format!(
+                    "`events` may only include {}",
+                    allowed.iter().join_with(", ")
+                );
+                return respond(stream, error("400 Bad Request", &message)).await;
+            }
+        },
+    };
+
+    let mut rx = events.subscribe();
+    let mut stream = stream;
+    let mut sha1 = sha1::Sha1::new();
+    sha1.update(key.as_bytes());
+    sha1.update(WEBSOCKET_GUID.as_bytes());
+    let accept = base64::encode(sha1.finalize());
+    let response = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept,
+    );
+    stream.write_all(response.as_bytes()).await?;
+
+    let mut socket =
+        WebSocketStream::from_raw_socket(TokioAdapter(stream), Role::Server, None).await;
+    tracing::info!(
+        "API token `{}` subscribed to {}",
+        token.name,
+        kinds.iter().join_with(", ")
+    );
+
+    loop {
+        tokio::select! {
+            // Pings are answered while reading.
+            message = socket.next() => match message {
+                None | Some(Err(_)) | Some(Ok(Message::Close(_))) => return Ok(()),
+                Some(Ok(_)) => (),
+            },
+            streamed = rx.recv() => match streamed {
+                Ok(streamed) if streamed.server == server && kinds.contains(&streamed.kind) => {
+                    socket.send(Message::Text(streamed.json.clone())).await?;
+                }
+                Ok(_) => (),
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    tracing::warn!("API subscriber fell behind, and missed {} events", missed);
+                }
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            },
+        }
+    }
+}
+
+/// Compare tokens in time that doesn't depend on where they differ.
+pub fn equal(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
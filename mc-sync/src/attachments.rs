@@ -0,0 +1,41 @@
+use serenity::model::channel;
+
+/// Describe what a Discord message carries besides its text, e.g. `sent an
+/// image: <url>`, since only text can be shown in Minecraft chat.
+pub fn describe(message: &channel::Message) -> Vec<String> {
+    let attachments = message.attachments.iter().map(|attachment| {
+        let kind = attachment
+            .content_type
+            .as_deref()
+            .and_then(|content_type| content_type.split('/').next())
+            .unwrap_or("");
+        let kind = match kind {
+            "image" => "an image",
+            "video" => "a video",
+            "audio" => "audio",
+            _ => "a file",
+        };
+        format!("sent {}: {}", kind, attachment.url)
+    });
+
+    // Newer API versions only include `sticker_items`, which this version of
+    // serenity doesn't parse, so this only catches stickers from older clients.
+    let stickers = message
+        .stickers
+        .iter()
+        .map(|sticker| format!("sent a sticker: {}", sticker.name));
+
+    // Link previews are already covered by the link in the message itself.
+    let embeds = message
+        .embeds
+        .iter()
+        .filter(|embed| embed.kind == "rich")
+        .map(|embed| match (&embed.title, &embed.url) {
+            (Some(title), Some(url)) => format!("sent an embed: {} {}", title, url),
+            (Some(title), None) => format!("sent an embed: {}", title),
+            (None, Some(url)) => format!("sent an embed: {}", url),
+            (None, None) => String::from("sent an embed"),
+        });
+
+    attachments.chain(stickers).chain(embeds).collect()
+}
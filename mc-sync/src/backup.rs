@@ -0,0 +1,530 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Stdio;
+
+use anyhow::anyhow;
+use anyhow::Context as _;
+use chrono::Datelike as _;
+use chrono::Local;
+use chrono::NaiveDateTime;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::Deserialize;
+use tokio::io::AsyncWriteExt as _;
+use tokio::process;
+
+use crate::minecraft;
+use crate::schedule::Schedule;
+
+static TIMESTAMP: &str = "%Y-%m-%dT%H-%M-%S";
+static EXTENSION: &str = ".tar.gz";
+
+/// Periodically archive the world directory, along with the Nether and End
+/// where servers like Paper keep them beside it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Backup {
+    pub schedule: Schedule,
+    pub world: PathBuf,
+    pub directory: PathBuf,
+    pub format: Format,
+    pub retention: Retention,
+    /// Where to upload each new archive.
+    pub remotes: Vec<Remote>,
+}
+
+/// How each backup is stored.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Format {
+    /// A compressed `.tar.gz` of the whole world, with a directory for each
+    /// of its directories.
+    #[default]
+    Archive,
+
+    /// A directory with a copy of each of the world's directories, which
+    /// hardlinks files unchanged since the previous snapshot, like
+    /// `rsync --link-dest`, so each one only takes up the space of the region
+    /// files that changed.
+    Snapshot,
+}
+
+/// How many archives to keep. Archives matching any rule are kept, and
+/// nothing is deleted if no rules are set.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Retention {
+    /// Most recent archives.
+    pub last: Option<usize>,
+
+    /// Most recent archive from each of the last N days with one.
+    pub daily: Option<usize>,
+
+    /// Most recent archive from each of the last N weeks with one.
+    pub weekly: Option<usize>,
+}
+
+/// Off-site storage for archives, uploaded to with the provider's own tool,
+/// which must be installed and configured with credentials.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Remote {
+    /// An S3-compatible bucket, with the AWS CLI (`aws`).
+    S3 {
+        bucket: String,
+        /// Prepended to the archive's name, e.g. `minecraft/`.
+        #[serde(default)]
+        prefix: String,
+        /// For providers other than AWS.
+        #[serde(default)]
+        endpoint: Option<String>,
+    },
+
+    /// A Backblaze B2 bucket, with the B2 CLI (`b2`).
+    B2 {
+        bucket: String,
+        #[serde(default)]
+        prefix: String,
+    },
+
+    /// A directory on an SFTP server, with `sftp`, so keys and ports are read
+    /// from the SSH config.
+    Sftp {
+        host: String,
+        #[serde(default)]
+        directory: String,
+    },
+
+    /// Any `rclone` remote path, like `b2:bucket/minecraft`.
+    Rclone { destination: String },
+}
+
+impl Remote {
+    /// Upload an archive under its own name.
+    pub async fn upload(&self, archive: &Path) -> anyhow::Result<()> {
+        let name = archive
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| anyhow!("Invalid backup `{}`", archive.display()))?;
+
+        let mut input = None;
+        let mut command;
+        match self {
+            Remote::S3 {
+                bucket,
+                prefix,
+                endpoint,
+            } => {
+                command = process::Command::new("aws");
+                command
+                    .args(["s3", "cp", "--only-show-errors"])
+                    .arg(archive)
+                    .arg(format!("s3://{}/{}{}", bucket, prefix, name));
+                if let Some(endpoint) = endpoint {
+                    command.arg("--endpoint-url").arg(endpoint);
+                }
+            }
+            Remote::B2 { bucket, prefix } => {
+                command = process::Command::new("b2");
+                command
+                    .args(["upload-file", "--quiet"])
+                    .arg(bucket)
+                    .arg(archive)
+                    .arg(format!("{}{}", prefix, name));
+            }
+            Remote::Sftp { host, directory } => {
+                command = process::Command::new("sftp");
+                command.args(["-b", "-"]).arg(host);
+                let remote = match directory.trim_end_matches('/') {
+                    "" => name.to_owned(),
+                    directory => format!("{}/{}", directory, name),
+                };
+                input = Some(format!("put \"{}\" \"{}\"\n", archive.display(), remote));
+            }
+            Remote::Rclone { destination } => {
+                command = process::Command::new("rclone");
+                command.arg("copyto").arg(archive).arg(format!(
+                    "{}/{}",
+                    destination.trim_end_matches('/'),
+                    name
+                ));
+            }
+        }
+
+        let mut child = command
+            .stdin(if input.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| {
+                let program = command.as_std().get_program();
+                format!("Failed to run `{}`", program.to_string_lossy())
+            })?;
+
+        if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+            stdin.write_all(input.as_bytes()).await?;
+        }
+
+        let output = child.wait_with_output().await?;
+        if output.status.success() {
+            return Ok(());
+        }
+        Err(anyhow!(
+            "Upload exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim(),
+        ))
+    }
+}
+
+impl fmt::Display for Remote {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Remote::S3 { bucket, prefix, .. } => write!(fmt, "s3://{}/{}", bucket, prefix),
+            Remote::B2 { bucket, prefix } => write!(fmt, "b2://{}/{}", bucket, prefix),
+            Remote::Sftp { host, directory } => write!(fmt, "sftp://{}/{}", host, directory),
+            Remote::Rclone { destination } => write!(fmt, "{}", destination),
+        }
+    }
+}
+
+impl Backup {
+    /// Archive the world directory and prune old archives. Blocks until
+    /// finished, so the world should not be saved to in the meantime.
+    pub fn run(&self) -> anyhow::Result<PathBuf> {
+        let archive = match self.format {
+            Format::Archive => self.archive()?,
+            Format::Snapshot => self.snapshot()?,
+        };
+        for pruned in self.prune()? {
+            let removed = if pruned.is_dir() {
+                fs::remove_dir_all(&pruned)
+            } else {
+                fs::remove_file(&pruned)
+            };
+            removed.with_context(|| format!("Failed to remove backup `{}`", pruned.display()))?;
+        }
+        Ok(archive)
+    }
+
+    fn prefix(&self) -> anyhow::Result<String> {
+        self.world
+            .file_name()
+            .map(|name| format!("{}-", name.to_string_lossy()))
+            .ok_or_else(|| anyhow!("Invalid world directory `{}`", self.world.display()))
+    }
+
+    /// The directory holding the world, and the world's name.
+    fn level(&self) -> anyhow::Result<(&Path, String)> {
+        let parent = self
+            .world
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        let level = self
+            .world
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .ok_or_else(|| anyhow!("Invalid world directory `{}`", self.world.display()))?;
+        Ok((parent, level))
+    }
+
+    /// Each of the world's directories, and its name.
+    fn worlds(&self) -> anyhow::Result<Vec<(String, PathBuf)>> {
+        if !self.world.is_dir() {
+            return Err(anyhow!("World `{}` doesn't exist", self.world.display()));
+        }
+        let (parent, level) = self.level()?;
+        Ok(named(minecraft::worlds(parent, &level)))
+    }
+
+    fn archive(&self) -> anyhow::Result<PathBuf> {
+        fs::create_dir_all(&self.directory).with_context(|| {
+            format!(
+                "Failed to create backup directory `{}`",
+                self.directory.display()
+            )
+        })?;
+
+        let prefix = self.prefix()?;
+        let name = format!("{}{}", prefix, Local::now().format(TIMESTAMP));
+        let path = self.directory.join(format!("{}{}", name, EXTENSION));
+        let partial = self
+            .directory
+            .join(format!("{}{}.partial", name, EXTENSION));
+
+        let file = fs::File::create(&partial)
+            .with_context(|| format!("Failed to create backup `{}`", partial.display()))?;
+        let mut archive = tar::Builder::new(GzEncoder::new(file, flate2::Compression::default()));
+        for (name, world) in self.worlds()? {
+            archive
+                .append_dir_all(name, &world)
+                .with_context(|| format!("Failed to archive `{}`", world.display()))?;
+        }
+        archive.into_inner()?.finish()?;
+
+        fs::rename(&partial, &path)?;
+        Ok(path)
+    }
+
+    fn snapshot(&self) -> anyhow::Result<PathBuf> {
+        fs::create_dir_all(&self.directory).with_context(|| {
+            format!(
+                "Failed to create backup directory `{}`",
+                self.directory.display()
+            )
+        })?;
+
+        let name = format!("{}{}", self.prefix()?, Local::now().format(TIMESTAMP));
+        let path = self.directory.join(&name);
+        let partial = self.directory.join(format!("{}.partial", name));
+
+        // Left behind if the bridge stopped partway through.
+        if partial.exists() {
+            fs::remove_dir_all(&partial)?;
+        }
+
+        let previous = self
+            .list()?
+            .into_iter()
+            .filter(|(_, path)| path.is_dir())
+            .max()
+            .map(|(_, path)| path);
+
+        fs::create_dir(&partial)?;
+        for (name, world) in self.worlds()? {
+            // Older snapshots hold just the world's own directory, and so
+            // have nothing to link to.
+            let previous = previous.as_ref().map(|previous| previous.join(&name));
+            link(&world, &partial.join(&name), previous.as_deref())
+                .with_context(|| format!("Failed to snapshot `{}`", world.display()))?;
+        }
+        fs::rename(&partial, &path)?;
+        Ok(path)
+    }
+
+    /// The backup taken at the time `id`, as formatted by [`id`].
+    pub fn find(&self, id: &str) -> anyhow::Result<Option<PathBuf>> {
+        Ok(self
+            .list()?
+            .into_iter()
+            .find(|(time, _)| self::id(time) == id)
+            .map(|(_, path)| path))
+    }
+
+    /// Replace each of the world's directories in `backup`, an archive or
+    /// snapshot, keeping the current ones beside them as safety copies. The
+    /// main world's is returned. Blocks until finished, so the server must be
+    /// stopped.
+    pub fn restore(&self, backup: &Path) -> anyhow::Result<PathBuf> {
+        let (parent, level) = self.level()?;
+        let time = Local::now().format(TIMESTAMP).to_string();
+        let safety = |name: &str| parent.join(format!("{}-before-restore-{}", name, time));
+
+        // Unpacked next to the world first, so it can be moved into place.
+        let staging = parent.join(format!("{}-restoring-{}", level, time));
+        let staged = self
+            .stage(backup, &staging, &level)
+            .with_context(|| format!("Failed to restore backup `{}`", backup.display()));
+        let staged = match staged {
+            Ok(staged) => staged,
+            Err(error) => {
+                fs::remove_dir_all(&staging).ok();
+                return Err(error);
+            }
+        };
+
+        let mut moved = Vec::new();
+        let mut placed = Vec::new();
+        let mut swap = || -> anyhow::Result<()> {
+            for (name, world) in &staged {
+                let current = parent.join(name);
+                if current.exists() {
+                    fs::rename(&current, safety(name)).with_context(|| {
+                        format!("Failed to move aside world `{}`", current.display())
+                    })?;
+                    moved.push(name.clone());
+                }
+                fs::rename(world, &current)?;
+                placed.push(current);
+            }
+            Ok(())
+        };
+
+        if let Err(error) = swap() {
+            // Put the world back as it was.
+            for current in placed {
+                fs::remove_dir_all(current)?;
+            }
+            for name in moved {
+                fs::rename(safety(&name), parent.join(&name))?;
+            }
+            fs::remove_dir_all(&staging).ok();
+            return Err(error)
+                .with_context(|| format!("Failed to restore backup `{}`", backup.display()));
+        }
+
+        fs::remove_dir_all(&staging).ok();
+        Ok(safety(&level))
+    }
+
+    /// Copy or unpack the world's directories in `backup` into `staging`,
+    /// returning each one's name and where it was put.
+    fn stage(
+        &self,
+        backup: &Path,
+        staging: &Path,
+        level: &str,
+    ) -> anyhow::Result<Vec<(String, PathBuf)>> {
+        // Snapshots share files with each other, so they're copied rather than
+        // linked, and archives unpack into a directory for each of the world's.
+        if backup.is_dir() {
+            fs::create_dir(staging)?;
+            let worlds = named(minecraft::worlds(backup, level));
+            if worlds.is_empty() {
+                // Older snapshots are the world's own directory.
+                link(backup, &staging.join(level), None)?;
+            }
+            for (name, world) in worlds {
+                link(&world, &staging.join(&name), None)?;
+            }
+        } else {
+            fs::File::open(backup)
+                .map(GzDecoder::new)
+                .map(tar::Archive::new)
+                .and_then(|mut archive| archive.unpack(staging))?;
+        }
+
+        if !staging.join(level).is_dir() {
+            return Err(anyhow!("Backup doesn't contain the world `{}`", level));
+        }
+        Ok(named(minecraft::worlds(staging, level)))
+    }
+
+    /// Archives in the backup directory that no retention rule keeps.
+    fn prune(&self) -> anyhow::Result<Vec<PathBuf>> {
+        let Retention {
+            last,
+            daily,
+            weekly,
+        } = self.retention;
+
+        if last.is_none() && daily.is_none() && weekly.is_none() {
+            return Ok(Vec::new());
+        }
+
+        let mut archives = self.list()?;
+        archives.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+        let mut keep = HashSet::new();
+        keep.extend(0..last.unwrap_or(0));
+
+        let mut days = Vec::new();
+        let mut weeks = Vec::new();
+        for (index, (time, _)) in archives.iter().enumerate() {
+            let day = time.date();
+            if days.len() < daily.unwrap_or(0) && !days.contains(&day) {
+                days.push(day);
+                keep.insert(index);
+            }
+
+            let week = (time.iso_week().year(), time.iso_week().week());
+            if weeks.len() < weekly.unwrap_or(0) && !weeks.contains(&week) {
+                weeks.push(week);
+                keep.insert(index);
+            }
+        }
+
+        Ok(archives
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| !keep.contains(index))
+            .map(|(_, (_, path))| path)
+            .collect())
+    }
+
+    /// Archives in the backup directory, with the time each was taken.
+    pub fn list(&self) -> anyhow::Result<Vec<(NaiveDateTime, PathBuf)>> {
+        let prefix = self.prefix()?;
+        let mut archives = Vec::new();
+
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            // Snapshots are named like archives, without the extension.
+            let time = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix(&prefix))
+                .map(|name| name.strip_suffix(EXTENSION).unwrap_or(name))
+                .and_then(|time| NaiveDateTime::parse_from_str(time, TIMESTAMP).ok());
+
+            if let Some(time) = time {
+                archives.push((time, path));
+            }
+        }
+
+        Ok(archives)
+    }
+}
+
+/// Identify a backup by when it was taken, e.g. `2021-06-01T12-00-00`.
+pub fn id(time: &NaiveDateTime) -> String {
+    time.format(TIMESTAMP).to_string()
+}
+
+/// Pair each of the world's directories with its name.
+fn named(worlds: Vec<PathBuf>) -> Vec<(String, PathBuf)> {
+    worlds
+        .into_iter()
+        .filter_map(|world| {
+            let name = world.file_name()?.to_string_lossy().into_owned();
+            Some((name, world))
+        })
+        .collect()
+}
+
+/// Copy the directory `source` to `target`, hardlinking files that have the
+/// same size and modification time in `previous` instead.
+fn link(source: &Path, target: &Path, previous: Option<&Path>) -> anyhow::Result<()> {
+    fs::create_dir(target)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        let from = entry.path();
+        let to = target.join(entry.file_name());
+        let previous = previous.map(|previous| previous.join(entry.file_name()));
+
+        if metadata.is_dir() {
+            link(&from, &to, previous.as_deref())?;
+            continue;
+        } else if !metadata.is_file() {
+            continue;
+        }
+
+        let modified = metadata.modified()?;
+        let unchanged = previous.as_ref().filter(|previous| {
+            fs::metadata(previous).is_ok_and(|old| {
+                old.len() == metadata.len() && old.modified().ok() == Some(modified)
+            })
+        });
+
+        match unchanged {
+            Some(previous) => fs::hard_link(previous, &to)?,
+            None => {
+                fs::copy(&from, &to)?;
+                // Keep the time, to compare against next time.
+                fs::File::options()
+                    .write(true)
+                    .open(&to)?
+                    .set_modified(modified)?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,162 @@
+use std::borrow::Cow;
+
+use serde_json::json;
+use serde_json::Value;
+
+/// Start of each line logged by Bedrock Dedicated Server, e.g.
+/// `[2024-01-01 12:34:56:789 INFO] `.
+pub static PREFIX: &str = r"^[^<]*?\[\d{4}-\d{2}-\d{2} [\d:]+ INFO\] ";
+
+/// Matches nothing, for events the server doesn't log, like chat and deaths.
+static NEVER: &str = r"$.^";
+
+/// Logged in response to `save query` while the world is still being saved.
+static SAVE_PENDING: &str = "A previous save has not been completed.";
+
+/// Default pattern for `regex.<name>`, in place of the Java Edition one.
+pub fn pattern(name: &str) -> &'static str {
+    match name {
+        "join" => r"Player connected: (.*), xuid: .*",
+        "quit" => r"Player disconnected: (.*), xuid: .*",
+        "saved" => r"Data saved\. Files are now ready to be copied\.",
+        // Players are listed on the next line, without a prefix.
+        "list" => r"There are \d+/(\d+) players online:()",
+        "version" => r"Version:? (\d[\d.]*)",
+        "done" => r"Server started\.",
+        "stopping" => r"Server stop requested\.|Stopping server\.\.\.",
+        "gametime" => r"Time is (\d+)",
+        "whitelist" => {
+            r"(?:(Player (?:added to|removed from) allowlist)|(Player already in allowlist|Player not in allowlist))"
+        }
+        "moderation" => r"(?:(Kicked .* from the game.*)|(No targets matched selector))",
+        _ => NEVER,
+    }
+}
+
+/// Whether the server is still saving the world, and `save query` should be
+/// asked again.
+pub fn save_pending(message: &str) -> bool {
+    message.trim() == SAVE_PENDING
+}
+
+/// Translate a Java Edition command, as the bridge sends them, into the
+/// Bedrock equivalent, or `None` if there isn't one.
+pub fn command(command: &str) -> Option<String> {
+    let command = command.trim().trim_start_matches('/');
+    let (name, arguments) = match command.split_once(' ') {
+        Some((name, arguments)) => (name, arguments.trim()),
+        None => (command, ""),
+    };
+
+    let command = match (name, arguments) {
+        // Bedrock saves the world while it's held, and reports when the files
+        // are safe to copy.
+        ("save-off", _) => String::from("save hold"),
+        ("save-all", "flush") => String::from("save query"),
+        ("save-all", _) => return None,
+        ("save-on", _) => String::from("save resume"),
+        ("whitelist", arguments) => match arguments.split_once(' ') {
+            Some((action, player)) => format!("allowlist {} {}", action, target(player.trim())),
+            None => format!("allowlist {}", arguments),
+        },
+        ("seed", _) | ("tps", _) | ("difficulty", "") => return None,
+        ("tellraw", arguments) => {
+            // Gamertags can contain spaces, but not the start of a component.
+            let quoted = match arguments.strip_prefix('"') {
+                Some(rest) => rest.find('"')? + 2,
+                None => 0,
+            };
+            let json = quoted + arguments[quoted..].find(['{', '[', '"'])?;
+            let (player, json) = arguments.split_at(json);
+            let player = target(player.trim());
+            let component = serde_json::from_str::<Value>(json).ok()?;
+            if component.get("rawtext").is_some() {
+                return Some(format!("tellraw {} {}", player, json));
+            }
+            let mut text = String::new();
+            flatten(&component, &mut text);
+            format!(
+                "tellraw {} {}",
+                player,
+                json!({ "rawtext": [{ "text": text }] })
+            )
+        }
+        _ => command.to_owned(),
+    };
+    Some(command)
+}
+
+/// Quote a gamertag with spaces, so it's read as one argument.
+pub fn target(player: &str) -> Cow<'_, str> {
+    if player.contains(' ') && !player.starts_with('"') {
+        Cow::Owned(format!("\"{}\"", player))
+    } else {
+        Cow::Borrowed(player)
+    }
+}
+
+/// Append the text of a Java Edition chat component, with its color and
+/// style as `§` formatting codes. Click and hover events are dropped.
+fn flatten(component: &Value, text: &mut String) {
+    match component {
+        Value::String(string) => text.push_str(string),
+        Value::Array(components) => components
+            .iter()
+            .for_each(|component| flatten(component, text)),
+        Value::Object(object) => {
+            let mut formatted = false;
+            if let Some(code) = object.get("color").and_then(Value::as_str).and_then(color) {
+                text.push('§');
+                text.push(code);
+                formatted = true;
+            }
+            for (style, code) in [
+                ("bold", 'l'),
+                ("italic", 'o'),
+                ("underlined", 'n'),
+                ("strikethrough", 'm'),
+                ("obfuscated", 'k'),
+            ] {
+                if object.get(style).and_then(Value::as_bool) == Some(true) {
+                    text.push('§');
+                    text.push(code);
+                    formatted = true;
+                }
+            }
+            if let Some(Value::String(string)) = object.get("text") {
+                text.push_str(string);
+            }
+            if let Some(extra) = object.get("extra") {
+                flatten(extra, text);
+            }
+            if formatted {
+                text.push_str("§r");
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Formatting code for a named color. Hex colors have none.
+fn color(name: &str) -> Option<char> {
+    let code = match name {
+        "black" => '0',
+        "dark_blue" => '1',
+        "dark_green" => '2',
+        "dark_aqua" => '3',
+        "dark_red" => '4',
+        "dark_purple" => '5',
+        "gold" => '6',
+        "gray" => '7',
+        "dark_gray" => '8',
+        "blue" => '9',
+        "green" => 'a',
+        "aqua" => 'b',
+        "red" => 'c',
+        "light_purple" => 'd',
+        "yellow" => 'e',
+        "white" => 'f',
+        _ => return None,
+    };
+    Some(code)
+}
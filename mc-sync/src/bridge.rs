@@ -0,0 +1,5106 @@
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::IsTerminal as _;
+use std::mem;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use anyhow::anyhow;
+use chrono::Local;
+use joinery::JoinableIterator;
+use serde_json::json;
+use serde_json::Value;
+use serenity::builder;
+use serenity::client::bridge::gateway::ShardMessenger;
+use serenity::model::channel;
+use serenity::model::gateway;
+use serenity::model::id;
+use serenity::model::interactions;
+use serenity::model::interactions::application_command;
+use serenity::model::interactions::autocomplete;
+use serenity::model::interactions::message_component;
+use serenity::model::user;
+use serenity::model::webhook;
+use serenity::prelude::Mentionable as _;
+use serenity::utils;
+use tokio::io;
+use tokio::io::AsyncWriteExt as _;
+use tokio::sync::broadcast;
+use tokio::sync::mpsc;
+use tokio::time;
+use tracing::Instrument as _;
+
+use crate::advancements;
+use crate::api;
+use crate::attachments;
+use crate::backup;
+use crate::bedrock;
+use crate::chat::ChatBridge;
+use crate::color;
+use crate::commands;
+use crate::commands::Command;
+use crate::config;
+use crate::emoji;
+use crate::events;
+use crate::events::Event;
+use crate::events::Flavor;
+use crate::events::Log;
+use crate::history;
+use crate::ingame;
+use crate::journal;
+use crate::link;
+use crate::markdown;
+use crate::mentions;
+use crate::metrics;
+use crate::minecraft;
+#[cfg(feature = "mqtt")]
+use crate::mqtt;
+use crate::permissions::Capability;
+use crate::ping;
+use crate::playerdata;
+use crate::playtime;
+use crate::properties::Properties;
+use crate::query;
+use crate::rcon;
+use crate::restart;
+use crate::retry;
+use crate::roles;
+use crate::schedule::Throttle;
+use crate::scoreboard;
+use crate::sink;
+use crate::stats;
+use crate::stdin;
+use crate::store;
+use crate::tag;
+use crate::template;
+use crate::usage;
+
+/// Route events between the local console, the Minecraft server, and Discord.
+pub struct Bridge {
+    tx: mpsc::Sender<Event>,
+    rx: mpsc::Receiver<Event>,
+    metrics: Arc<metrics::Metrics>,
+    /// Bridges for the other servers, to relay chat to.
+    peers: Vec<mpsc::Sender<Event>>,
+    /// Other chat platforms to relay to.
+    chats: Vec<Arc<dyn ChatBridge>>,
+    /// HTTP endpoints to send server events to.
+    sinks: Vec<sink::Sink>,
+    journal: Option<journal::Recorder>,
+    #[cfg(feature = "mqtt")]
+    mqtt: Option<mqtt::Publisher>,
+    /// Subscribers to `/api/events`.
+    stream: Option<broadcast::Sender<Arc<api::Streamed>>>,
+    console: minecraft::Console,
+    stdout: io::BufWriter<io::Stdout>,
+    /// Whether stdout is a terminal, which can show colors.
+    terminal: bool,
+    printer: Option<stdin::Printer>,
+    players: Option<stdin::Players>,
+    http: Arc<serenity::CacheAndHttp>,
+    owner: id::UserId,
+    opt: config::Opt,
+    settings: config::Settings,
+    webhook: Option<(String, webhook::Webhook)>,
+    store: store::Store,
+    /// Server events and commands, if recorded.
+    history: Option<history::History>,
+    /// Players who had joined before the bridge started, so they aren't
+    /// welcomed as new.
+    usercache: BTreeSet<String>,
+    playtime: playtime::Playtime,
+    linking: link::Linking,
+    /// Users given the player role by the last sync.
+    player_roles: BTreeSet<u64>,
+    max_players: Option<usize>,
+    shard: Option<ShardMessenger>,
+    presence: Throttle,
+    status_channel: Throttle,
+    status_channel_name: Option<String>,
+    topic: Throttle,
+    console_buffer: String,
+    console_pending: bool,
+    verbose_buffer: Vec<String>,
+    verbose_pending: bool,
+    /// Discord chat held until the server finishes starting.
+    waiting: Vec<channel::Message>,
+    /// Whether users were told that `waiting` is full.
+    waiting_full: bool,
+    /// Messages that couldn't be posted to Discord yet, oldest first.
+    outbox: VecDeque<Outgoing>,
+    /// Whether Discord is reachable: since connecting, and until a message
+    /// fails to post. Otherwise, messages go straight to the outbox.
+    connected: bool,
+    /// Messages dropped from a full outbox.
+    missed: usize,
+    /// Recent messages relayed to Minecraft, and who sent them.
+    relayed: VecDeque<(id::MessageId, String)>,
+    topic_text: Option<String>,
+    pending: Option<Pending>,
+    /// Output of the command that ran last, if it may still be logging.
+    capture: Option<Capture>,
+    /// Commands waiting for the current one to finish.
+    captures: VecDeque<(String, Recipient)>,
+    next_capture: u64,
+    /// Quit announcements held in case the player rejoins, by lowercase name.
+    held: HashMap<String, (u64, String)>,
+    next_held: u64,
+    /// Reconnects in a row, and when the last one was, by lowercase name.
+    reconnects: HashMap<String, (u32, Instant)>,
+    /// Waypoints waiting for the server to say where the player is, by
+    /// lowercase name: the waypoint's name, and their position once known.
+    waypoint_requests: HashMap<String, (String, Option<[f64; 3]>)>,
+    /// When each online player last did something, and whether they've been
+    /// announced as AFK since, by lowercase name.
+    activity: HashMap<String, (Instant, bool)>,
+    version: Option<String>,
+    /// Server software and plugins, as last reported by a query.
+    plugins: Option<String>,
+    /// As last logged by the `seed` and `difficulty` commands.
+    seed: Option<String>,
+    difficulty: Option<String>,
+    tps: Option<f64>,
+    /// The last `time query gametime` response, and when it arrived.
+    gametime: Option<(u64, Instant)>,
+    /// Consecutive TPS samples below the `[lag]` threshold.
+    lag_samples: u32,
+    /// The last measurement of the server process, and when it was taken.
+    usage: Option<(usage::Usage, Instant)>,
+    /// CPU use between the last two measurements, as a percent of one core.
+    cpu: Option<f64>,
+    /// Whether the memory alert was posted, and memory use hasn't dropped since.
+    memory_alerted: bool,
+    /// Consecutive measurements above the CPU alert threshold.
+    cpu_samples: u32,
+    /// When the server last logged anything, for the watchdog.
+    last_output: Instant,
+    restarting: bool,
+    /// Stopped from Discord, so keep running until started again.
+    stopping: bool,
+    /// Counting down to stop or restart from Discord.
+    counting_down: bool,
+    listing: bool,
+    /// Max players from a Bedrock `list` response, whose players are listed
+    /// on the next line.
+    list_header: Option<usize>,
+    /// Which server behind the proxy each player is on.
+    backends: HashMap<String, String>,
+    launched: SystemTime,
+    crashes: u32,
+    backup: Backup,
+    /// Backups started, to tell whether a save timeout is for the current one.
+    backups: u64,
+    /// Backup to restore the world from once the server stops.
+    restoring: Option<PathBuf>,
+    /// Launch the server once the backup being archived is done, since it
+    /// was due to launch meanwhile.
+    launch_after_backup: bool,
+}
+
+/// Delay before relaunching after the first crash, doubled after each consecutive crash.
+static BACKOFF: Duration = Duration::from_secs(5);
+static BACKOFF_MAX: Duration = Duration::from_secs(5 * 60);
+
+/// Discord allows renaming a channel twice every ten minutes.
+static STATUS_CHANNEL_THROTTLE: Duration = Duration::from_secs(5 * 60);
+
+/// How long to collect server output before posting it to the console channel.
+static CONSOLE_DELAY: Duration = Duration::from_secs(2);
+
+/// Leave room for the code block around the output within Discord's 2000 character limit.
+static CONSOLE_LIMIT: usize = 1900;
+
+/// Longest message Discord allows.
+static DISCORD_LIMIT: usize = 2000;
+
+/// Longest line to broadcast in Minecraft, which rejects longer chat messages.
+static MINECRAFT_LIMIT: usize = 256;
+
+/// How long to wait for the server to respond to a slash command.
+static RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to give the server to log its seed and difficulty for `/serverinfo`.
+static SERVER_INFO_DELAY: Duration = Duration::from_secs(1);
+
+/// Most suggestions Discord accepts for an autocompleted option.
+static AUTOCOMPLETE_LIMIT: usize = 25;
+
+/// Most backups shown by `/backup list`.
+static BACKUP_LIST_LIMIT: usize = 10;
+
+/// Discord messages to hold while the server is starting.
+static WAITING_LIMIT: usize = 20;
+
+/// Minutes of inactivity before Discord archives a mirror's thread.
+static THREAD_ARCHIVE: u16 = 1440;
+
+/// Relayed messages to remember, to announce if they're deleted.
+static RELAYED_LIMIT: usize = 100;
+
+/// Characters of the original message to show in game with a Discord reply.
+static REPLY_EXCERPT: usize = 50;
+
+/// Characters of each message to show in `/history` and `/search`.
+static HISTORY_EXCERPT: usize = 150;
+
+/// Latest advancements to show in `/advancements`.
+static ADVANCEMENTS_RECENT: usize = 10;
+
+/// Characters in an embed's description.
+static EMBED_LIMIT: usize = 4096;
+
+/// How long to wait before asking Bedrock again whether the world is saved.
+static SAVE_QUERY_DELAY: Duration = Duration::from_secs(1);
+
+/// How long to wait for the server to save the world before a backup.
+static SAVE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// How long to collect the server's output after a command.
+static CAPTURE_WINDOW: Duration = Duration::from_millis(500);
+
+/// Uptime after which the server is considered stable again.
+static STABLE: Duration = Duration::from_secs(10 * 60);
+
+/// Progress of the current backup. The server must not write to the world
+/// directory while it's being archived.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Backup {
+    Idle,
+    Saving,
+    Archiving,
+}
+
+/// Message for Discord, kept while Discord is unreachable.
+enum Outgoing {
+    Text {
+        channel: id::ChannelId,
+        text: String,
+    },
+    Embed {
+        channel: id::ChannelId,
+        color: u32,
+        description: String,
+    },
+    /// Text with files attached, like a crash report.
+    Files {
+        channel: id::ChannelId,
+        text: String,
+        /// Names and contents of the files.
+        files: Vec<(String, Vec<u8>)>,
+    },
+    /// Chat relayed through the webhook, under the player's name.
+    Webhook {
+        player: String,
+        avatar_url: String,
+        text: String,
+    },
+}
+
+impl fmt::Display for Outgoing {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Outgoing::Text { text, .. } => write!(fmt, "{}", text),
+            Outgoing::Embed { description, .. } => write!(fmt, "{}", description),
+            Outgoing::Files { text, files, .. } => {
+                write!(fmt, "{} ({} files)", text, files.len())
+            }
+            Outgoing::Webhook { player, text, .. } => write!(fmt, "<{}> {}", player, text),
+        }
+    }
+}
+
+/// Command collecting what the server logs after it, for whoever ran it.
+struct Capture {
+    id: u64,
+    command: String,
+    lines: Vec<String>,
+    recipient: Recipient,
+}
+
+/// Where the output of a captured command goes.
+enum Recipient {
+    Rcon(rcon::Reply),
+    /// A reply to the command's message in the console channel.
+    Message(id::ChannelId, id::MessageId),
+    /// The deferred reply to `/command`.
+    Interaction(Box<application_command::ApplicationCommandInteraction>),
+}
+
+/// Slash command waiting for the server to log its response.
+struct Pending {
+    interaction: Box<application_command::ApplicationCommandInteraction>,
+    title: &'static str,
+    /// Record the result in the audit channel.
+    audit: bool,
+}
+
+impl Bridge {
+    pub fn new(
+        tx: mpsc::Sender<Event>,
+        rx: mpsc::Receiver<Event>,
+        console: minecraft::Console,
+        http: Arc<serenity::CacheAndHttp>,
+        owner: id::UserId,
+        opt: config::Opt,
+        settings: config::Settings,
+    ) -> anyhow::Result<Self> {
+        let store = store::Store::open(&settings.data_file)?;
+        let history = settings
+            .history_file
+            .as_deref()
+            .map(|path| history::History::open(path, settings.name.as_deref()))
+            .transpose()?;
+        let usercache = match minecraft::usercache(&settings.server_directory) {
+            Ok(usercache) => usercache,
+            Err(error) => {
+                tracing::debug!("Starting without a user cache: {:?}", error);
+                BTreeSet::new()
+            }
+        };
+        let sinks = sinks(&settings)?;
+        let journal = journal(&settings)?;
+        Ok(Bridge {
+            tx,
+            rx,
+            metrics: Arc::default(),
+            peers: Vec::new(),
+            chats: Vec::new(),
+            sinks,
+            journal,
+            #[cfg(feature = "mqtt")]
+            mqtt: None,
+            stream: None,
+            console,
+            stdout: io::BufWriter::new(io::stdout()),
+            terminal: std::io::stdout().is_terminal(),
+            printer: None,
+            players: None,
+            http,
+            owner,
+            opt,
+            settings,
+            webhook: None,
+            store,
+            history,
+            usercache,
+            playtime: playtime::Playtime::default(),
+            linking: link::Linking::default(),
+            player_roles: BTreeSet::new(),
+            max_players: None,
+            shard: None,
+            presence: Throttle::default(),
+            status_channel: Throttle::default(),
+            status_channel_name: None,
+            topic: Throttle::default(),
+            console_buffer: String::new(),
+            console_pending: false,
+            verbose_buffer: Vec::new(),
+            verbose_pending: false,
+            waiting: Vec::new(),
+            waiting_full: false,
+            outbox: VecDeque::new(),
+            connected: false,
+            missed: 0,
+            relayed: VecDeque::new(),
+            topic_text: None,
+            pending: None,
+            capture: None,
+            captures: VecDeque::new(),
+            next_capture: 0,
+            held: HashMap::new(),
+            next_held: 0,
+            reconnects: HashMap::new(),
+            waypoint_requests: HashMap::new(),
+            activity: HashMap::new(),
+            version: None,
+            plugins: None,
+            seed: None,
+            difficulty: None,
+            tps: None,
+            gametime: None,
+            lag_samples: 0,
+            usage: None,
+            cpu: None,
+            memory_alerted: false,
+            cpu_samples: 0,
+            last_output: Instant::now(),
+            restarting: false,
+            stopping: false,
+            counting_down: false,
+            listing: false,
+            list_header: None,
+            backends: HashMap::new(),
+            launched: SystemTime::now(),
+            crashes: 0,
+            backup: Backup::Idle,
+            backups: 0,
+            restoring: None,
+            launch_after_backup: false,
+        })
+    }
+
+    pub fn metrics(&self) -> Arc<metrics::Metrics> {
+        Arc::clone(&self.metrics)
+    }
+
+    /// Relay chat to and from the bridges for other servers, if federated.
+    pub fn set_peers(&mut self, peers: Vec<mpsc::Sender<Event>>) {
+        self.peers = peers;
+    }
+
+    /// Print server output above the terminal's prompt, instead of straight
+    /// to stdout.
+    pub fn set_printer(&mut self, printer: stdin::Printer) {
+        self.printer = Some(printer);
+    }
+
+    /// Keep the players that the terminal completes up to date.
+    pub fn set_players(&mut self, players: stdin::Players) {
+        self.players = Some(players);
+    }
+
+    /// Stream server events and log lines to API subscribers.
+    pub fn set_stream(&mut self, stream: broadcast::Sender<Arc<api::Streamed>>) {
+        self.stream = Some(stream);
+    }
+
+    /// Relay chat and server events to another platform too. Its messages
+    /// should be sent as [`Event::Chat`].
+    pub fn add_chat(&mut self, chat: Arc<dyn ChatBridge>) {
+        self.chats.push(chat);
+    }
+
+    /// Publish server events to an MQTT broker too.
+    #[cfg(feature = "mqtt")]
+    pub fn set_mqtt(&mut self, mqtt: mqtt::Publisher) {
+        self.mqtt = Some(mqtt);
+    }
+
+    /// Run until the server exits without being restarted.
+    pub async fn start(mut self) -> anyhow::Result<()> {
+        if self.console.is_owned() {
+            // The server was just launched, so nobody can be online yet.
+            self.store.data.online.clear();
+        } else {
+            // The server kept running while we were away, so start from
+            // who was online before, and ask it who's online now.
+            for player in &self.store.data.online {
+                self.playtime.join(player);
+            }
+            if let Err(error) = self.list().await {
+                tracing::warn!("Failed to list online players: {:?}", error);
+            }
+        }
+        self.save();
+        self.metrics.running.store(true, Ordering::Relaxed);
+        self.metrics.responsive.store(true, Ordering::Relaxed);
+        if !self.console.is_owned() {
+            self.metrics.started.store(true, Ordering::Relaxed);
+        }
+
+        while let Some(event) = self.rx.recv().await {
+            self.metrics.handle();
+            match self.handle(event).await {
+                Ok(true) => (),
+                Ok(false) => return Ok(()),
+                Err(error) => self.report(&error).await,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether the bridge should keep running.
+    async fn handle(&mut self, event: Event) -> anyhow::Result<bool> {
+        match event {
+            Event::Discord(message) => self.handle_discord(&message, false).await?,
+            Event::Edited {
+                guild_id,
+                channel_id,
+                message_id,
+            } => self.handle_edited(guild_id, channel_id, message_id).await?,
+            Event::Deleted(message_id) => self.handle_deleted(message_id).await?,
+            Event::Command(interaction) => self.handle_command(&interaction).await?,
+            Event::Autocomplete(interaction) => self.handle_autocomplete(&interaction).await?,
+            Event::Component(interaction) => self.handle_component(&interaction).await?,
+            Event::Minecraft(line) => self.handle_minecraft(line).await?,
+            Event::Stdin(line) => {
+                self.remember(&history::Entry::command("terminal", &line));
+                self.handle_stdin(&line).await?
+            }
+            Event::Mqtt(command) => self.handle_mqtt(&command).await?,
+            Event::Rcon { command, reply } => self.handle_rcon(command, reply).await?,
+            Event::Held(id) => {
+                let player = self
+                    .held
+                    .iter()
+                    .find(|(_, (held, _))| *held == id)
+                    .map(|(player, _)| player.clone());
+                if let Some((_, message)) = player.and_then(|player| {
+                    self.reconnects.remove(&player);
+                    self.held.remove(&player)
+                }) {
+                    self.relay_event(&message).await;
+                    let style = Some(self.settings.embeds.quit.clone())
+                        .filter(|_| self.settings.embeds.enabled);
+                    self.post_general(message, style).await;
+                }
+            }
+            Event::Captured(id) => {
+                if self.capture.as_ref().map(|capture| capture.id) == Some(id) {
+                    self.finish_capture().await?;
+                }
+            }
+            Event::Api {
+                token,
+                request,
+                reply,
+            } => {
+                let result = self.handle_api(&token, request).await?;
+                reply.send(result);
+            }
+            Event::Federated {
+                server,
+                player,
+                message,
+            } => self.handle_federated(&server, &player, &message).await?,
+            Event::Chat {
+                platform,
+                user,
+                message,
+            } => self.handle_chat(&platform, &user, &message).await?,
+            Event::Reload => {
+                if let Err(error) = self.reload() {
+                    tracing::error!("Failed to reload configuration: {:?}", error);
+                }
+                self.update_presence();
+            }
+            Event::Restart => {
+                self.counting_down = false;
+                self.console.send("/save-all").await?;
+                self.console.send("/stop").await?;
+                self.restarting = self.console.is_owned();
+            }
+            Event::Stop => {
+                self.counting_down = false;
+                self.console.send("/save-all").await?;
+                self.console.send("/stop").await?;
+                self.stopping = true;
+            }
+            Event::Exited { status, stopped } => {
+                return self.handle_exited(status, stopped).await;
+            }
+            // Launched once the world is restored.
+            Event::Launch if self.restoring.is_some() => {
+                tracing::info!("Not launching the server while restoring a backup");
+            }
+            // Launching now would write to the world while it's archived.
+            Event::Launch if self.backup == Backup::Archiving => {
+                tracing::info!("Launching the server once the backup is done");
+                self.launch_after_backup = true;
+            }
+            Event::Launch => self.launch().await?,
+            Event::Backup => self.handle_backup().await?,
+            Event::BackedUp(result) => self.handle_backed_up(result).await?,
+            Event::SaveTimeout(backup) => self.handle_save_timeout(backup).await?,
+            Event::Uploaded { remote, result } => self.handle_uploaded(remote, result).await,
+            Event::Restored(result) => self.handle_restored(result).await?,
+            Event::List => {
+                if let Some(address) = self.settings.ping_address.clone() {
+                    let tx = self.tx.clone();
+                    tokio::spawn(
+                        async move {
+                            let result = ping::ping(&address)
+                                .await
+                                .map_err(|error| format!("{:#}", error));
+                            tx.send(Event::Pinged(result)).await.unwrap_or(());
+                        }
+                        .in_current_span(),
+                    );
+                } else if self.settings.query_address.is_some() {
+                    self.query();
+                } else if let Err(error) = self.list().await {
+                    tracing::warn!("Failed to list online players: {:?}", error);
+                }
+                self.check_afk().await;
+                self.sync_roles().await;
+            }
+            Event::Pinged(result) => self.handle_pinged(result).await?,
+            Event::Queried(result) => self.handle_queried(result).await?,
+            Event::Left { guild_id, user } => self.handle_left(guild_id, user).await?,
+            Event::Ready(shard) => {
+                self.shard = Some(shard);
+                self.set_presence();
+                self.connected = true;
+                self.flush_outbox().await;
+                if let Err(error) = self.post_panel().await {
+                    tracing::warn!("Failed to post control panel: {:?}", error);
+                }
+            }
+            Event::Resumed => {
+                self.connected = true;
+                self.flush_outbox().await;
+            }
+            Event::Presence => self.set_presence(),
+            Event::StatusChannel => self.set_status_channel(),
+            Event::Topic => self.set_topic(),
+            Event::SampleTps => self.sample_tps().await?,
+            Event::SampleUsage => self.sample_usage().await,
+            Event::Announcement(index) => self.handle_announcement(index).await?,
+            Event::Watchdog => self.watchdog().await?,
+            Event::Kill(launch) => {
+                let quiet = self
+                    .settings
+                    .watchdog
+                    .as_ref()
+                    .is_some_and(|watchdog| self.last_output.elapsed() >= watchdog.kill_after);
+                if quiet && launch == self.metrics.restarts.load(Ordering::Relaxed) {
+                    self.console.kill().await;
+                }
+            }
+            Event::FlushConsole => {
+                self.console_pending = false;
+                self.flush_console().await?;
+            }
+            Event::FlushVerbose => {
+                self.verbose_pending = false;
+                self.flush_verbose().await?;
+            }
+            Event::ServerInfo(interaction) => self.reply_server_info(&interaction, true).await?,
+            Event::ResponseTimeout(interaction_id) => {
+                if self.pending.as_ref().map(|pending| pending.interaction.id)
+                    == Some(interaction_id)
+                {
+                    let error = String::from("The server didn't respond.");
+                    self.finish_response(Err(error)).await?;
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Log an error from handling one event, and post it to the error channel,
+    /// so the bridge can carry on with the next.
+    async fn report(&mut self, error: &anyhow::Error) {
+        tracing::error!("{:?}", error);
+        self.metrics.errors.fetch_add(1, Ordering::Relaxed);
+        if !self.settings.discord {
+            return;
+        }
+
+        let channel =
+            id::ChannelId::from(self.settings.error_id.unwrap_or(self.settings.verbose_id));
+        let message = format!("⚠️ {}", markdown::escape(&format!("{:#}", error)));
+        for piece in template::split(&message, DISCORD_LIMIT) {
+            if let Err(error) = channel.say(&self.http.http, piece).await {
+                tracing::warn!("Failed to report error to Discord: {}", error);
+                return;
+            }
+        }
+    }
+
+    /// Relay a new or `edited` Discord message.
+    async fn handle_discord(
+        &mut self,
+        message: &channel::Message,
+        edited: bool,
+    ) -> anyhow::Result<()> {
+        if message.author.id == self.http.cache.current_user_id().await {
+            return Ok(());
+        }
+
+        // Webhook messages are also marked as coming from a bot.
+        let ignore = &self.settings.ignore;
+        let ignored = match message.webhook_id {
+            None => ignore.bots && message.author.bot,
+            Some(webhook_id) => {
+                ignore.webhooks
+                    || Some(webhook_id) == self.webhook.as_ref().map(|(_, webhook)| webhook.id)
+            }
+        };
+        if ignored {
+            tracing::debug!(user = %message.author.name, "Ignoring message from a bot or webhook");
+            return Ok(());
+        }
+
+        // Including direct messages, and other servers' channels.
+        // Commands can also be typed in today's console thread.
+        let route = match self.settings.route(self.parent(message.channel_id)) {
+            Some(route) if message.guild_id.is_some() => route,
+            _ => {
+                tracing::debug!(
+                    channel = message.channel_id.0,
+                    "Ignoring message from an unrouted channel"
+                );
+                return Ok(());
+            }
+        };
+
+        let roles = match (&message.member, message.guild_id) {
+            (Some(member), _) => member.roles.clone(),
+            // Messages fetched after an edit don't include the member.
+            (None, Some(guild_id)) => guild_id
+                .member(&self.http, message.author.id)
+                .await
+                .map(|member| member.roles)
+                .unwrap_or_default(),
+            (None, None) => Vec::new(),
+        };
+
+        if route == config::Route::Console {
+            // Edits don't run the command again.
+            if edited {
+                return Ok(());
+            }
+            if !self.allows(message.author.id, &roles, Capability::Console) {
+                tracing::debug!(user = %message.author.name, "Ignoring console command without `console`");
+                return Ok(());
+            }
+            self.remember(&history::Entry::command(
+                &message.author.name,
+                &message.content,
+            ));
+            if self.console.is_owned() {
+                let recipient = Recipient::Message(message.channel_id, message.id);
+                self.capture(message.content.clone(), recipient).await?;
+            } else {
+                self.handle_stdin(&message.content).await?;
+            }
+            return Ok(());
+        }
+
+        if !self.allows(message.author.id, &roles, Capability::Chat) {
+            tracing::debug!(user = %message.author.name, "Not relaying message without `chat`");
+            return Ok(());
+        }
+
+        // Commands written while the server is starting may be lost, so hold
+        // chat until it's done.
+        if self.metrics.running.load(Ordering::Relaxed)
+            && !self.metrics.started.load(Ordering::Relaxed)
+        {
+            if let Some(waiting) = self
+                .waiting
+                .iter_mut()
+                .find(|waiting| waiting.id == message.id)
+            {
+                // Relay the edited message in place of the original.
+                *waiting = message.clone();
+            } else if edited {
+                tracing::debug!(user = %message.author.name, "Not relaying edit while the server is starting");
+            } else if self.waiting.len() < WAITING_LIMIT {
+                self.waiting.push(message.clone());
+            } else if !mem::replace(&mut self.waiting_full, true) {
+                self.say(
+                    message.channel_id,
+                    "The server is still starting, so new messages won't reach Minecraft until it's done.",
+                )
+                .await?;
+            }
+            return Ok(());
+        }
+
+        let mut content = mentions::to_minecraft(&self.http, message).await;
+        // Separated by spaces, so punctuation doesn't run into the URLs.
+        let attachments = attachments::describe(message).join(" | ");
+        if content.is_empty() {
+            content = attachments;
+        } else if !attachments.is_empty() {
+            content = format!("{} | {}", content, attachments);
+        }
+
+        if self.settings.emoji.shortcodes {
+            content = emoji::to_shortcodes(&content);
+        }
+
+        // e.g. a message whose only sticker wasn't parsed.
+        if content.is_empty() {
+            tracing::debug!(user = %message.author.name, "Not relaying empty message");
+            return Ok(());
+        }
+
+        if let Some(referenced) = message.referenced_message.as_ref().filter(|_| !edited) {
+            self.broadcast_reply(referenced).await?;
+        }
+
+        let said = self
+            .broadcast(&message.author, &content, &message.link(), edited)
+            .await?;
+        for line in said.lines() {
+            self.record(tag::Source::Discord, line);
+        }
+        // Relayed chat is only mirrored with tags, which set it apart from the
+        // server's output.
+        if self.settings.mirror_tags.is_some() {
+            for line in said.lines() {
+                self.output(tag::Source::Discord, line).await?;
+            }
+        }
+        self.metrics.to_minecraft.fetch_add(1, Ordering::Relaxed);
+        tracing::debug!(user = %message.author.name, edited, "Relayed message to Minecraft");
+
+        if !edited {
+            if self.relayed.len() == RELAYED_LIMIT {
+                self.relayed.pop_front();
+            }
+            self.relayed
+                .push_back((message.id, message.author.name.clone()));
+        }
+        Ok(())
+    }
+
+    /// Relay an edit to a message in a chat channel, unless edits are ignored.
+    async fn handle_edited(
+        &mut self,
+        guild_id: Option<id::GuildId>,
+        channel_id: id::ChannelId,
+        message_id: id::MessageId,
+    ) -> anyhow::Result<()> {
+        if self.settings.templates.edited.is_empty()
+            || self.settings.route(channel_id.0) != Some(config::Route::Chat)
+        {
+            return Ok(());
+        }
+
+        let mut message = channel_id.message(&self.http.http, message_id).await?;
+        message.guild_id = guild_id;
+        self.handle_discord(&message, true).await
+    }
+
+    /// Tell players a relayed message was deleted, if announced.
+    async fn handle_deleted(&mut self, message_id: id::MessageId) -> anyhow::Result<()> {
+        let user = match self.relayed.iter().position(|(id, _)| *id == message_id) {
+            Some(index) if !self.settings.templates.deleted.is_empty() => {
+                match self.relayed.remove(index) {
+                    Some((_, user)) => user,
+                    None => return Ok(()),
+                }
+            }
+            _ => return Ok(()),
+        };
+
+        let notice = template::render(&self.settings.templates.deleted, &[("user", &user)]);
+        self.broadcast_notice(&notice).await
+    }
+
+    async fn handle_command(
+        &mut self,
+        interaction: &application_command::ApplicationCommandInteraction,
+    ) -> anyhow::Result<()> {
+        if !self.owns(interaction.channel_id) {
+            return Ok(());
+        }
+
+        let (ephemeral, reply) = match Command::parse(&interaction.data) {
+            None => (true, String::from("Unknown command.")),
+            Some(command)
+                if !self.allows(
+                    interaction.user.id,
+                    interaction
+                        .member
+                        .as_ref()
+                        .map(|member| member.roles.as_slice())
+                        .unwrap_or_default(),
+                    command.capability(),
+                ) =>
+            {
+                (
+                    true,
+                    String::from("You don't have permission to use this command."),
+                )
+            }
+            Some(Command::Whitelist { action }) => {
+                return self
+                    .handle_response(
+                        interaction,
+                        "Whitelist",
+                        action.command(self.settings.flavor),
+                        false,
+                    )
+                    .await;
+            }
+            Some(Command::Moderate { action }) => {
+                return self
+                    .handle_response(
+                        interaction,
+                        "Moderation",
+                        action.command(self.settings.flavor),
+                        true,
+                    )
+                    .await;
+            }
+            Some(Command::ServerInfo) => return self.handle_server_info(interaction).await,
+            Some(Command::Seen { player }) => (false, self.seen(&player, true)),
+            Some(Command::Stats { player }) => return self.reply_stats(interaction, &player).await,
+            Some(Command::Advancements {
+                player: Some(player),
+            }) => return self.reply_advancements(interaction, &player).await,
+            Some(Command::Advancements { player: None }) => (false, self.advancement_race().await),
+            Some(Command::Scoreboard {
+                objective: Some(objective),
+            }) => return self.reply_scoreboard(interaction, &objective).await,
+            Some(Command::Scoreboard { objective: None }) => (true, self.objectives()),
+            Some(Command::Waypoints) => {
+                let waypoints = &self.store.data.waypoints;
+                let reply = match waypoints.is_empty() {
+                    true => format!(
+                        "No waypoints yet. Players can save one with `{}setwaypoint <name>` in game.",
+                        self.settings.in_game.prefix,
+                    ),
+                    false => waypoints
+                        .values()
+                        .map(|waypoint| {
+                            format!(
+                                "**{}**: {}, by {}",
+                                markdown::escape(&waypoint.name),
+                                markdown::escape(&describe_waypoint(waypoint)),
+                                markdown::escape(&waypoint.player),
+                            )
+                        })
+                        .join_with("\n")
+                        .to_string(),
+                };
+                (false, template::truncate(&reply, DISCORD_LIMIT - 1))
+            }
+            Some(Command::Whereis { player }) => (true, self.whereis(&player)),
+            Some(Command::Inventory { player }) => (true, self.inventory(&player)),
+            Some(Command::History { player, count }) => {
+                let filter = history::Filter {
+                    player: Some(&player),
+                    ..history::Filter::default()
+                };
+                let title = format!("History: {}", player);
+                return self
+                    .reply_history(interaction, &title, &filter, count)
+                    .await;
+            }
+            Some(Command::Search { text, count }) => {
+                let filter = history::Filter {
+                    message: Some(&text),
+                    ..history::Filter::default()
+                };
+                let title = format!("Search: {}", text);
+                return self
+                    .reply_history(interaction, &title, &filter, count)
+                    .await;
+            }
+            Some(Command::Online) => {
+                let tps = match self.tps {
+                    None => String::new(),
+                    Some(tps) => format!("\nTPS: {:.1}", tps),
+                };
+                (
+                    false,
+                    format!("{}: {}{}", self.online_count(), self.online(), tps),
+                )
+            }
+            Some(Command::Say { message }) => {
+                let link = match interaction.guild_id {
+                    Some(guild_id) => format!(
+                        "https://discord.com/channels/{}/{}",
+                        guild_id, interaction.channel_id,
+                    ),
+                    None => format!(
+                        "https://discord.com/channels/@me/{}",
+                        interaction.channel_id
+                    ),
+                };
+                let say = self
+                    .broadcast(&interaction.user, &message, &link, false)
+                    .await?;
+                self.metrics.to_minecraft.fetch_add(1, Ordering::Relaxed);
+                (false, say)
+            }
+            // Reply with what the server logs after the command, once it's done.
+            Some(Command::Command { command }) if self.console.is_owned() => {
+                self.remember(&history::Entry::command(&interaction.user.name, &command));
+                interaction
+                    .create_interaction_response(&self.http.http, |response| {
+                        response
+                            .kind(interactions::InteractionResponseType::DeferredChannelMessageWithSource)
+                            .interaction_response_data(|data| {
+                                data.flags(
+                                    interactions::InteractionApplicationCommandCallbackDataFlags::EPHEMERAL,
+                                )
+                            })
+                    })
+                    .await?;
+                let recipient = Recipient::Interaction(Box::new(interaction.clone()));
+                return self.capture(command, recipient).await;
+            }
+            Some(Command::Command { command }) => {
+                self.remember(&history::Entry::command(&interaction.user.name, &command));
+                match self.console.send(&command).await? {
+                    None => (true, format!("Sent `{}` to the server.", command)),
+                    Some(response) if response.is_empty() => (true, format!("Ran `{}`.", command)),
+                    Some(response) => (true, response),
+                }
+            }
+            Some(Command::Server { action }) => (false, self.handle_server(action)),
+            Some(Command::Property { action }) => (
+                true,
+                self.handle_property(interaction.user.id, action).await?,
+            ),
+            Some(Command::Backup { action }) => (
+                false,
+                self.handle_backups(interaction.user.id, action).await?,
+            ),
+            Some(Command::Reload) => match self.reload() {
+                Ok(()) => (true, String::from("Reloaded configuration.")),
+                Err(error) => (true, format!("Failed to reload configuration: {:#}", error)),
+            },
+            Some(Command::Link) => {
+                let code = self.linking.issue(interaction.user.id);
+                let replaces = match self.store.data.links.get(&interaction.user.id.0) {
+                    None => String::new(),
+                    Some(player) => {
+                        format!(" This replaces your link to {}.", markdown::escape(player))
+                    }
+                };
+                (
+                    true,
+                    format!(
+                        "Type `{}link {}` in Minecraft chat within {} minutes to link your account.{}",
+                        self.settings.in_game.prefix,
+                        code,
+                        link::EXPIRY.as_secs() / 60,
+                        replaces,
+                    ),
+                )
+            }
+            Some(Command::Unlink) => match self.store.data.links.remove(&interaction.user.id.0) {
+                None => (true, String::from("Your account isn't linked.")),
+                Some(player) => {
+                    self.save();
+                    self.sync_roles().await;
+                    (true, format!("Unlinked {}.", markdown::escape(&player)))
+                }
+            },
+            Some(Command::Playtime {
+                player: Some(player),
+            }) => {
+                let total = self.playtime.total(&player, &self.store.data);
+                (
+                    false,
+                    format!(
+                        "{} has played for {}.",
+                        markdown::escape(&player),
+                        template::duration(total),
+                    ),
+                )
+            }
+            Some(Command::Deaths {
+                player: Some(player),
+            }) => {
+                let deaths = self.store.data.deaths.get(&player).copied().unwrap_or(0);
+                (
+                    false,
+                    format!(
+                        "{} has died {} time{}.",
+                        markdown::escape(&player),
+                        deaths,
+                        if deaths == 1 { "" } else { "s" },
+                    ),
+                )
+            }
+            Some(Command::Deaths { player: None }) => {
+                let leaderboard = self.store.data.most_deaths(10);
+                if leaderboard.is_empty() {
+                    (false, String::from("Nobody has died yet."))
+                } else {
+                    let lines = leaderboard
+                        .iter()
+                        .enumerate()
+                        .map(|(rank, (player, deaths))| {
+                            format!("{}. {} ({})", rank + 1, markdown::escape(player), deaths)
+                        })
+                        .join_with("\n");
+                    (false, format!("Top players by deaths:\n{}", lines))
+                }
+            }
+            Some(Command::Playtime { player: None }) => {
+                let leaderboard = self.playtime.leaderboard(&self.store.data, 10);
+                if leaderboard.is_empty() {
+                    (false, String::from("Nobody has played yet."))
+                } else {
+                    let lines = leaderboard
+                        .iter()
+                        .enumerate()
+                        .map(|(rank, (player, total))| {
+                            format!(
+                                "{}. {} ({})",
+                                rank + 1,
+                                markdown::escape(player),
+                                template::duration(*total),
+                            )
+                        })
+                        .join_with("\n");
+                    (false, format!("Top players by time played:\n{}", lines))
+                }
+            }
+        };
+
+        interaction
+            .create_interaction_response(&self.http.http, |response| {
+                response
+                    .kind(interactions::InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|data| {
+                        if ephemeral {
+                            data.flags(
+                                interactions::InteractionApplicationCommandCallbackDataFlags::EPHEMERAL,
+                            );
+                        }
+                        data.content(reply)
+                    })
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Suggest player names matching what the user has typed, starting with
+    /// those online.
+    async fn handle_autocomplete(
+        &mut self,
+        interaction: &autocomplete::AutocompleteInteraction,
+    ) -> anyhow::Result<()> {
+        if !self.owns(interaction.channel_id) {
+            return Ok(());
+        }
+
+        let partial = match Command::partial_player(&interaction.data) {
+            None => return Ok(()),
+            Some(partial) => partial.to_lowercase(),
+        };
+
+        let known = self.store.data.known_players();
+        let mut seen = BTreeSet::new();
+        let suggestions = self
+            .store
+            .data
+            .online
+            .iter()
+            .map(String::as_str)
+            .chain(known.iter().copied())
+            .filter(|player| player.to_lowercase().contains(&partial))
+            .filter(|player| seen.insert(player.to_lowercase()))
+            .take(AUTOCOMPLETE_LIMIT)
+            .collect::<Vec<_>>();
+
+        interaction
+            .create_autocomplete_response(&self.http.http, |response| {
+                for player in suggestions {
+                    response.add_string_choice(player, player);
+                }
+                response
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Start the server, or warn players and then stop or restart it. Returns
+    /// the reply.
+    fn handle_server(&mut self, action: commands::Server) -> String {
+        let running = self.metrics.running.load(Ordering::Relaxed);
+        let (verb, minutes, event) = match action {
+            _ if !self.console.is_owned() => {
+                return String::from("The server is managed by someone else.");
+            }
+            _ if self.counting_down => {
+                return String::from("The server is already about to stop.");
+            }
+            commands::Server::Start if running => {
+                return String::from("The server is already running.");
+            }
+            commands::Server::Start if self.restoring.is_some() => {
+                return String::from(
+                    "A backup is being restored, and the server will start once it's done.",
+                );
+            }
+            commands::Server::Start if self.backup == Backup::Archiving => {
+                return String::from("A backup is being taken. Try again once it's done.");
+            }
+            commands::Server::Start => {
+                let tx = self.tx.clone();
+                tokio::spawn(async move { tx.send(Event::Launch).await.unwrap_or(()) });
+                return String::from("Starting the server.");
+            }
+            _ if !running => return String::from("The server isn't running."),
+            commands::Server::Stop { minutes } => ("stopping", minutes, Event::Stop),
+            commands::Server::Restart { minutes } => ("restarting", minutes, Event::Restart),
+        };
+
+        self.counting_down = true;
+        let console = self.console.clone();
+        let tx = self.tx.clone();
+        tokio::spawn(
+            async move {
+                if let Err(error) = restart::countdown(&console, verb, minutes).await {
+                    tracing::warn!("Failed to warn players: {:?}", error);
+                }
+                tx.send(event).await.unwrap_or(());
+            }
+            .in_current_span(),
+        );
+
+        let plural = if minutes == 1 { "" } else { "s" };
+        match minutes {
+            0 => format!("Server {} now.", verb),
+            _ => format!("Server {} in {} minute{}.", verb, minutes, plural),
+        }
+    }
+
+    /// Carry out a button press on the control panel, and reply privately to
+    /// whoever pressed it.
+    async fn handle_component(
+        &mut self,
+        interaction: &message_component::MessageComponentInteraction,
+    ) -> anyhow::Result<()> {
+        // Every bridge sees every button press, but only one posted this panel.
+        match &self.store.data.panel {
+            Some(panel) if panel.message_id == interaction.message.id.0 => (),
+            _ => return Ok(()),
+        }
+
+        let reply = match commands::Button::parse(&interaction.data) {
+            None => String::from("Unknown button."),
+            Some(button)
+                if !self.allows(
+                    interaction.user.id,
+                    interaction
+                        .member
+                        .as_ref()
+                        .map(|member| member.roles.as_slice())
+                        .unwrap_or_default(),
+                    button.capability(),
+                ) =>
+            {
+                String::from("You don't have permission to use this button.")
+            }
+            Some(commands::Button::Restart) => self.handle_server(commands::Server::Restart {
+                minutes: commands::SERVER_DELAY,
+            }),
+            Some(commands::Button::Backup) => {
+                if self.settings.backup.is_none() {
+                    String::from("Backups aren't enabled.")
+                } else if self.backup != Backup::Idle {
+                    String::from("A backup is already running.")
+                } else if !self.metrics.running.load(Ordering::Relaxed) {
+                    String::from("The server isn't running.")
+                } else {
+                    self.handle_backup().await?;
+                    String::from("Backing up the world.")
+                }
+            }
+            Some(commands::Button::Whitelist) => {
+                match minecraft::whitelist(&self.settings.server_directory) {
+                    Ok(whitelist) if whitelist.is_empty() => {
+                        String::from("Nobody is whitelisted. Use `/whitelist add` to add players.")
+                    }
+                    Ok(whitelist) => format!(
+                        "{} whitelisted: {}\nUse `/whitelist add` or `/whitelist remove` to make changes.",
+                        whitelist.len(),
+                        whitelist.iter().join_with(", "),
+                    ),
+                    Err(error) => format!("Failed to read the whitelist: {:#}", error),
+                }
+            }
+            Some(commands::Button::Players) => {
+                format!("{}: {}", self.online_count(), self.online())
+            }
+        };
+
+        interaction
+            .create_interaction_response(&self.http.http, |response| {
+                response
+                    .kind(interactions::InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|data| {
+                        data.flags(
+                            interactions::InteractionApplicationCommandCallbackDataFlags::EPHEMERAL,
+                        )
+                        .content(reply)
+                    })
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Post and pin the control panel, if configured, or bring the one already
+    /// posted up to date.
+    async fn post_panel(&mut self) -> anyhow::Result<()> {
+        let channel = match self.settings.panel_id {
+            Some(panel_id) if self.settings.discord => id::ChannelId::from(panel_id),
+            _ => return Ok(()),
+        };
+
+        let content = match &self.settings.name {
+            None => String::from("**Server controls**"),
+            Some(name) => format!("**Server controls for {}**", name),
+        };
+
+        if let Some(panel) = &self.store.data.panel {
+            if panel.channel_id == channel.0 {
+                match channel
+                    .edit_message(&self.http.http, panel.message_id, |edit| {
+                        edit.content(&content).components(commands::Button::panel)
+                    })
+                    .await
+                {
+                    Ok(_) => return Ok(()),
+                    Err(error) => tracing::info!("Reposting control panel: {}", error),
+                }
+            }
+        }
+
+        let message = retry::discord(|| {
+            channel.send_message(&self.http.http, |create| {
+                create.content(&content).components(commands::Button::panel)
+            })
+        })
+        .await?;
+
+        if let Err(error) = channel.pin(&self.http.http, message.id).await {
+            tracing::warn!("Failed to pin control panel: {}", error);
+        }
+
+        self.store.data.panel = Some(store::Panel {
+            channel_id: channel.0,
+            message_id: message.id.0,
+        });
+        self.save();
+        Ok(())
+    }
+
+    /// Run a server command on behalf of a slash command, and reply with the
+    /// result. Over RCON, the response is handled immediately; otherwise, the
+    /// reply is deferred until the server logs its response.
+    async fn handle_response(
+        &mut self,
+        interaction: &application_command::ApplicationCommandInteraction,
+        title: &'static str,
+        command: Result<String, &'static str>,
+        audit: bool,
+    ) -> anyhow::Result<()> {
+        let command = match command {
+            Ok(_) if self.pending.is_some() => {
+                let error = String::from("Another command is still waiting for the server.");
+                return self.reply(interaction, title, Err(error)).await;
+            }
+            Ok(command) => command,
+            Err(error) => return self.reply(interaction, title, Err(error.to_owned())).await,
+        };
+
+        if !self.console.is_owned() {
+            let result = match self.console.send(&command).await? {
+                Some(response) if !response.is_empty() => events::parse_whitelist(&response)
+                    .or_else(|| events::parse_moderation(&response))
+                    .unwrap_or(Err(response)),
+                _ => Err(String::from("The server didn't respond.")),
+            };
+            if audit {
+                self.audit(interaction.user.id, title, &result).await?;
+            }
+            if result.is_ok() {
+                self.sync_roles().await;
+            }
+            return self.reply(interaction, title, result).await;
+        }
+
+        // Acknowledge the command before Discord's three second deadline.
+        interaction
+            .create_interaction_response(&self.http.http, |response| {
+                response
+                    .kind(interactions::InteractionResponseType::DeferredChannelMessageWithSource)
+            })
+            .await?;
+
+        self.console.send(&command).await?;
+        self.pending = Some(Pending {
+            interaction: Box::new(interaction.clone()),
+            title,
+            audit,
+        });
+
+        let tx = self.tx.clone();
+        let interaction_id = interaction.id;
+        tokio::spawn(async move {
+            time::sleep(RESPONSE_TIMEOUT).await;
+            tx.send(Event::ResponseTimeout(interaction_id))
+                .await
+                .unwrap_or(());
+        });
+
+        Ok(())
+    }
+
+    async fn reply(
+        &self,
+        interaction: &application_command::ApplicationCommandInteraction,
+        title: &str,
+        result: Result<String, String>,
+    ) -> anyhow::Result<()> {
+        interaction
+            .create_interaction_response(&self.http.http, |response| {
+                response
+                    .kind(interactions::InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|data| {
+                        if result.is_err() {
+                            data.flags(
+                                interactions::InteractionApplicationCommandCallbackDataFlags::EPHEMERAL,
+                            );
+                        }
+                        data.create_embed(|embed| result_embed(embed, title, &result))
+                    })
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Reply with the most recent `count` history entries matching `filter`,
+    /// only to the user who asked, since they may be private.
+    async fn reply_history(
+        &self,
+        interaction: &application_command::ApplicationCommandInteraction,
+        title: &str,
+        filter: &history::Filter<'_>,
+        count: u64,
+    ) -> anyhow::Result<()> {
+        let entries = match &self.history {
+            None => Err(String::from("History isn't recorded for this server.")),
+            Some(history) => history.recent(filter, count as usize).map_err(|error| {
+                tracing::error!("Failed to read history: {:?}", error);
+                String::from("Failed to read the history.")
+            }),
+        };
+
+        let mut description = String::new();
+        if let Ok(entries) = &entries {
+            // Oldest first, like chat.
+            for entry in entries.iter().rev() {
+                let line = history_line(entry);
+                if description.len() + line.len() + 1 > EMBED_LIMIT {
+                    break;
+                }
+                description.push_str(&line);
+                description.push('\n');
+            }
+            if entries.is_empty() {
+                description.push_str("Nothing found.");
+            }
+        }
+
+        interaction
+            .create_interaction_response(&self.http.http, |response| {
+                response
+                    .kind(interactions::InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|data| {
+                        data.flags(
+                            interactions::InteractionApplicationCommandCallbackDataFlags::EPHEMERAL,
+                        )
+                        .create_embed(|embed| match &entries {
+                            Ok(_) => embed.title(title).description(&description),
+                            Err(error) => result_embed(embed, title, &Err(error.clone())),
+                        })
+                    })
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Reply with text only the user who asked can see, e.g. why a lookup
+    /// failed.
+    async fn reply_privately(
+        &self,
+        interaction: &application_command::ApplicationCommandInteraction,
+        text: String,
+    ) -> anyhow::Result<()> {
+        interaction
+            .create_interaction_response(&self.http.http, |response| {
+                response
+                    .kind(interactions::InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|data| {
+                        data.flags(
+                            interactions::InteractionApplicationCommandCallbackDataFlags::EPHEMERAL,
+                        )
+                        .content(text)
+                    })
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Reply to `/stats` with an embed of what the server has counted for
+    /// `player`, or why it can't.
+    async fn reply_stats(
+        &self,
+        interaction: &application_command::ApplicationCommandInteraction,
+        player: &str,
+    ) -> anyhow::Result<()> {
+        let directory = &self.settings.server_directory;
+        let stats = match minecraft::uuid(directory, player) {
+            Ok(None) => Err(format!(
+                "{} hasn't joined the server recently.",
+                markdown::escape(player),
+            )),
+            Ok(Some((name, uuid))) => match stats::read(&minecraft::world(directory), &uuid) {
+                Ok(None) => Err(format!(
+                    "There are no stats for {} yet.",
+                    markdown::escape(&name)
+                )),
+                Ok(Some(stats)) => Ok((name, stats)),
+                Err(error) => {
+                    tracing::error!("Failed to read stats: {:?}", error);
+                    Err(String::from("Failed to read the player's stats."))
+                }
+            },
+            Err(error) => {
+                tracing::error!("Failed to look up player: {:?}", error);
+                Err(String::from("Failed to read the server's user cache."))
+            }
+        };
+
+        let (name, stats) = match stats {
+            Ok(stats) => stats,
+            Err(error) => return self.reply_privately(interaction, error).await,
+        };
+
+        let most = |most: &Option<(String, u64)>| match most {
+            None => String::new(),
+            Some((name, count)) => format!("\nMost: {} ({})", name, count),
+        };
+        let fields = vec![
+            ("Time played", template::duration(stats.play_time), true),
+            (
+                "Distance traveled",
+                format!("{:.1} km", stats.distance as f64 / 100_000.0),
+                true,
+            ),
+            ("Jumps", stats.jumps.to_string(), true),
+            (
+                "Blocks mined",
+                format!("{}{}", stats.blocks_mined, most(&stats.most_mined)),
+                true,
+            ),
+            (
+                "Mobs killed",
+                format!("{}{}", stats.mob_kills, most(&stats.most_killed)),
+                true,
+            ),
+            ("Players killed", stats.player_kills.to_string(), true),
+            ("Deaths", stats.deaths.to_string(), true),
+            ("Items crafted", stats.items_crafted.to_string(), true),
+        ];
+        let title = format!("Stats: {}", name);
+
+        interaction
+            .create_interaction_response(&self.http.http, |response| {
+                response
+                    .kind(interactions::InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|data| {
+                        data.create_embed(|embed| {
+                            embed
+                                .title(&title)
+                                .fields(fields)
+                                .footer(|footer| footer.text("As of the server's last save"))
+                        })
+                    })
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Reply to `/advancements <player>` with how many vanilla advancements
+    /// they've made, and their latest.
+    async fn reply_advancements(
+        &self,
+        interaction: &application_command::ApplicationCommandInteraction,
+        player: &str,
+    ) -> anyhow::Result<()> {
+        let directory = &self.settings.server_directory;
+        let progress = match minecraft::uuid(directory, player) {
+            Ok(None) => Err(format!(
+                "{} hasn't joined the server recently.",
+                markdown::escape(player),
+            )),
+            Ok(Some((name, uuid))) => {
+                match advancements::read(&minecraft::world(directory), &uuid) {
+                    Ok(None) => Err(format!(
+                        "There are no advancements for {} yet.",
+                        markdown::escape(&name),
+                    )),
+                    Ok(Some(progress)) => Ok((name, progress)),
+                    Err(error) => {
+                        tracing::error!("Failed to read advancements: {:?}", error);
+                        Err(String::from("Failed to read the player's advancements."))
+                    }
+                }
+            }
+            Err(error) => {
+                tracing::error!("Failed to look up player: {:?}", error);
+                Err(String::from("Failed to read the server's user cache."))
+            }
+        };
+
+        let (name, progress) = match progress {
+            Ok(progress) => progress,
+            Err(error) => return self.reply_privately(interaction, error).await,
+        };
+
+        let made = progress.vanilla();
+        let total = advancements::count();
+        let mut description = format!(
+            "{} of {} advancements ({}%)",
+            made,
+            total,
+            made * 100 / total,
+        );
+        if !progress.made.is_empty() {
+            description.push_str("\n\n**Latest**");
+        }
+        for made in progress.made.iter().take(ADVANCEMENTS_RECENT) {
+            description.push_str(&format!(
+                "\n<t:{}:R> {}",
+                made.time,
+                markdown::escape(&made.title()),
+            ));
+        }
+        let title = format!("Advancements: {}", name);
+
+        interaction
+            .create_interaction_response(&self.http.http, |response| {
+                response
+                    .kind(interactions::InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|data| {
+                        data.create_embed(|embed| {
+                            embed
+                                .title(&title)
+                                .description(&description)
+                                .footer(|footer| footer.text("As of the server's last save"))
+                        })
+                    })
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// List the objectives published with `/scoreboard`.
+    fn objectives(&self) -> String {
+        let objectives = &self.settings.scoreboard.objectives;
+        if objectives.is_empty() {
+            return String::from("No scoreboard objectives are published.");
+        }
+        format!(
+            "Published objectives: {}",
+            objectives
+                .iter()
+                .map(|objective| format!("`{}`", objective))
+                .join_with(", "),
+        )
+    }
+
+    /// Reply to `/scoreboard <objective>` with an embed of the top scores,
+    /// if the objective is published.
+    async fn reply_scoreboard(
+        &self,
+        interaction: &application_command::ApplicationCommandInteraction,
+        objective: &str,
+    ) -> anyhow::Result<()> {
+        let objective = match self
+            .settings
+            .scoreboard
+            .objectives
+            .iter()
+            .find(|published| published.eq_ignore_ascii_case(objective))
+        {
+            Some(published) => {
+                let world = minecraft::world(&self.settings.server_directory);
+                match scoreboard::read(&world, published) {
+                    Ok(Some(objective)) => Ok(objective),
+                    Ok(None) => Err(format!(
+                        "The server hasn't saved `{}` yet.",
+                        markdown::escape(published),
+                    )),
+                    Err(error) => {
+                        tracing::error!("Failed to read scoreboard: {:?}", error);
+                        Err(String::from("Failed to read the scoreboard."))
+                    }
+                }
+            }
+            None => Err(self.objectives()),
+        };
+
+        let objective = match objective {
+            Ok(objective) => objective,
+            Err(error) => return self.reply_privately(interaction, error).await,
+        };
+
+        let description = match objective.scores.is_empty() {
+            true => String::from("Nobody has a score yet."),
+            false => objective
+                .scores
+                .iter()
+                .take(10)
+                .enumerate()
+                .map(|(rank, (player, score))| {
+                    format!("{}. {} — {}", rank + 1, markdown::escape(player), score)
+                })
+                .join_with("\n")
+                .to_string(),
+        };
+
+        interaction
+            .create_interaction_response(&self.http.http, |response| {
+                response
+                    .kind(interactions::InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|data| {
+                        data.create_embed(|embed| {
+                            embed
+                                .title(&objective.display_name)
+                                .description(&description)
+                                .footer(|footer| footer.text("As of the server's last save"))
+                        })
+                    })
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Read what the server saved about `player`, with their name as the
+    /// server has cached it, or describe why it can't.
+    fn player_data(&self, player: &str) -> Result<(String, playerdata::PlayerData), String> {
+        let directory = &self.settings.server_directory;
+        let (name, uuid) = match minecraft::uuid(directory, player) {
+            Ok(Some(user)) => user,
+            Ok(None) => {
+                return Err(format!(
+                    "{} hasn't joined the server recently.",
+                    markdown::escape(player),
+                ))
+            }
+            Err(error) => {
+                tracing::error!("Failed to look up player: {:?}", error);
+                return Err(String::from("Failed to read the server's user cache."));
+            }
+        };
+        match playerdata::read(&minecraft::world(directory), &uuid) {
+            Ok(Some(data)) => Ok((name, data)),
+            Ok(None) => Err(format!(
+                "The server hasn't saved {} yet.",
+                markdown::escape(&name),
+            )),
+            Err(error) => {
+                tracing::error!("Failed to read player data: {:?}", error);
+                Err(String::from("Failed to read the player's data."))
+            }
+        }
+    }
+
+    /// Reply to `/whereis`.
+    fn whereis(&self, player: &str) -> String {
+        let (name, data) = match self.player_data(player) {
+            Ok(data) => data,
+            Err(error) => return error,
+        };
+        let position = match data.position {
+            None => {
+                return format!(
+                    "The server hasn't saved where {} is.",
+                    markdown::escape(&name)
+                )
+            }
+            Some([x, y, z]) => format!("{:.0}, {:.0}, {:.0}", x.floor(), y.floor(), z.floor()),
+        };
+        let dimension = match data.dimension.as_deref() {
+            None => String::new(),
+            Some(dimension) => format!(" in {}", markdown::escape(&dimension_name(dimension))),
+        };
+        let online = self
+            .store
+            .data
+            .online
+            .iter()
+            .any(|online| online.eq_ignore_ascii_case(&name));
+        format!(
+            "{} {} at `{}`{}, as of the server's last save.",
+            markdown::escape(&name),
+            if online { "was" } else { "was last" },
+            position,
+            dimension,
+        )
+    }
+
+    /// Reply to `/inventory`, with items totaled across stacks.
+    fn inventory(&self, player: &str) -> String {
+        let (name, data) = match self.player_data(player) {
+            Ok(data) => data,
+            Err(error) => return error,
+        };
+        let list = |items: &[playerdata::Item]| {
+            if items.is_empty() {
+                return String::from("Nothing");
+            }
+            playerdata::PlayerData::totals(items)
+                .iter()
+                .map(|(item, count)| format!("{} × {}", count, markdown::escape(item)))
+                .join_with(", ")
+                .to_string()
+        };
+
+        let mut reply = format!("**{}**", markdown::escape(&name));
+        if let Some(health) = data.health {
+            reply.push_str(&format!(" ❤️ {:.0}/20", health));
+        }
+        if let Some(food) = data.food {
+            reply.push_str(&format!(" 🍗 {}/20", food));
+        }
+        if let Some(level) = data.xp_level {
+            reply.push_str(&format!(" ✨ level {}", level));
+        }
+        reply.push_str(&format!(
+            "\n**Inventory:** {}\n**Ender chest:** {}\nAs of the server's last save.",
+            list(&data.inventory),
+            list(&data.ender_chest),
+        ));
+        template::truncate(&reply, DISCORD_LIMIT - 1)
+    }
+
+    /// The top players by vanilla advancements made, where whoever got there
+    /// first wins a tie.
+    async fn advancement_race(&self) -> String {
+        let directory = self.settings.server_directory.clone();
+        let race = tokio::task::spawn_blocking(move || {
+            let names = minecraft::names(&directory)?;
+            let players = advancements::read_all(&minecraft::world(&directory))?;
+            let mut race = players
+                .into_iter()
+                .filter_map(|(uuid, progress)| {
+                    let name = names.get(&uuid)?.clone();
+                    Some((name, progress.vanilla(), progress.latest()))
+                })
+                .filter(|(_, made, _)| *made > 0)
+                .collect::<Vec<_>>();
+            race.sort_by(|(_, a, a_latest), (_, b, b_latest)| {
+                b.cmp(a).then_with(|| a_latest.cmp(b_latest))
+            });
+            race.truncate(10);
+            Ok::<_, anyhow::Error>(race)
+        })
+        .await
+        .unwrap_or_else(|error| Err(error.into()));
+
+        let race = match race {
+            Ok(race) => race,
+            Err(error) => {
+                tracing::error!("Failed to read advancements: {:?}", error);
+                return String::from("Failed to read advancements.");
+            }
+        };
+
+        if race.is_empty() {
+            return String::from("Nobody has made an advancement yet.");
+        }
+        let total = advancements::count();
+        let lines = race
+            .iter()
+            .enumerate()
+            .map(|(rank, (player, made, _))| {
+                format!(
+                    "{}. {} ({}, {}%)",
+                    rank + 1,
+                    markdown::escape(player),
+                    made,
+                    made * 100 / total,
+                )
+            })
+            .join_with("\n");
+        format!("Top players by advancements:\n{}", lines)
+    }
+
+    /// Complete the deferred reply to the pending command, if any.
+    async fn finish_response(&mut self, result: Result<String, String>) -> anyhow::Result<()> {
+        let pending = match self.pending.take() {
+            None => return Ok(()),
+            Some(pending) => pending,
+        };
+
+        if pending.audit {
+            self.audit(pending.interaction.user.id, pending.title, &result)
+                .await?;
+        }
+
+        pending
+            .interaction
+            .edit_original_interaction_response(&self.http.http, |response| {
+                response.create_embed(|embed| result_embed(embed, pending.title, &result))
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Read or change a setting in `server.properties`. Returns the reply.
+    async fn handle_property(
+        &mut self,
+        user: id::UserId,
+        action: commands::Property,
+    ) -> anyhow::Result<String> {
+        let mut properties = match Properties::read(&self.settings.server_directory) {
+            Ok(properties) => properties,
+            Err(error) => return Ok(format!("{:#}", error)),
+        };
+
+        let (key, value) = match action {
+            commands::Property::Get { key } | commands::Property::Set { key, .. }
+                if key.contains("password") || key.contains("secret") =>
+            {
+                return Ok(String::from("That property can't be used from Discord."));
+            }
+            commands::Property::Get { key } => {
+                return Ok(match properties.get(&key) {
+                    None => format!("There's no property `{}`.", key),
+                    Some("") => format!("`{}` is empty.", key),
+                    Some(value) => format!("`{}` is {}.", key, markdown::escape(value)),
+                });
+            }
+            commands::Property::Set { value, .. } if value.contains(char::is_control) => {
+                return Ok(String::from("The value can't contain line breaks."));
+            }
+            commands::Property::Set { key, value } => (key, value),
+        };
+
+        let old = match properties.set(&key, &value) {
+            None => return Ok(format!("There's no property `{}`.", key)),
+            Some(old) => old,
+        };
+        properties.write()?;
+
+        let describe = |value: &str| match value {
+            "" => String::from("empty"),
+            value => markdown::escape(value),
+        };
+        let change = format!(
+            "Set `{}` to {} (was {})",
+            key,
+            describe(&value),
+            describe(&old),
+        );
+        self.post_audit("Property", format!("{}\nBy {}", change, user.mention()))
+            .await?;
+        Ok(format!(
+            "{}. Restart the server for the change to take effect.",
+            change
+        ))
+    }
+
+    /// List backups, or restore one once the server stops. Returns the reply.
+    async fn handle_backups(
+        &mut self,
+        user: id::UserId,
+        action: commands::Backup,
+    ) -> anyhow::Result<String> {
+        let settings = match &self.settings.backup {
+            None => return Ok(String::from("Backups aren't enabled.")),
+            Some(backup) => backup.clone(),
+        };
+
+        let id = match action {
+            commands::Backup::List => {
+                let mut backups = match settings.list() {
+                    Ok(backups) => backups,
+                    Err(error) => return Ok(format!("Failed to list backups: {:#}", error)),
+                };
+                if backups.is_empty() {
+                    return Ok(String::from("There are no backups yet."));
+                }
+                backups.sort_by(|(a, _), (b, _)| b.cmp(a));
+                let lines = backups
+                    .iter()
+                    .take(BACKUP_LIST_LIMIT)
+                    .map(|(time, path)| match std::fs::metadata(path) {
+                        Ok(metadata) if metadata.is_file() => {
+                            format!(
+                                "`{}` ({})",
+                                backup::id(time),
+                                template::size(metadata.len())
+                            )
+                        }
+                        _ => format!("`{}` (snapshot)", backup::id(time)),
+                    })
+                    .join_with("\n");
+                return Ok(format!("Most recent backups:\n{}", lines));
+            }
+            commands::Backup::Restore { id } => id,
+        };
+
+        if !self.console.is_owned() {
+            return Ok(String::from("The server is managed by someone else."));
+        }
+        if self.backup != Backup::Idle || self.restoring.is_some() {
+            return Ok(String::from("A backup or restore is already in progress."));
+        }
+        if self.counting_down {
+            return Ok(String::from("The server is already about to stop."));
+        }
+
+        let path = match settings.find(&id) {
+            Ok(Some(path)) => path,
+            Ok(None) => {
+                return Ok(format!(
+                    "There's no backup `{}`. See `/backup list` for the most recent ones.",
+                    markdown::escape(&id),
+                ));
+            }
+            Err(error) => return Ok(format!("Failed to list backups: {:#}", error)),
+        };
+
+        self.post_audit(
+            "Restore",
+            format!("Restoring `{}`\nBy {}", id, user.mention()),
+        )
+        .await?;
+        self.restoring = Some(path);
+
+        if self.metrics.running.load(Ordering::Relaxed) {
+            let reply = self.handle_server(commands::Server::Stop {
+                minutes: commands::SERVER_DELAY,
+            });
+            Ok(format!(
+                "Restoring `{}` once the server stops. {}",
+                id, reply
+            ))
+        } else {
+            self.restore();
+            Ok(format!("Restoring `{}`...", id))
+        }
+    }
+
+    /// Reply to `/serverinfo`. Over RCON, the seed and difficulty are returned
+    /// immediately; otherwise, the reply is deferred while the server logs them.
+    async fn handle_server_info(
+        &mut self,
+        interaction: &application_command::ApplicationCommandInteraction,
+    ) -> anyhow::Result<()> {
+        if !self.console.is_owned() {
+            if let Some(response) = self.console.send("/seed").await? {
+                self.seed = events::parse_seed(&response).or_else(|| self.seed.take());
+            }
+            if let Some(response) = self.console.send("/difficulty").await? {
+                self.difficulty =
+                    events::parse_difficulty(&response).or_else(|| self.difficulty.take());
+            }
+            return self.reply_server_info(interaction, false).await;
+        }
+
+        if !self.metrics.running.load(Ordering::Relaxed) {
+            return self.reply_server_info(interaction, false).await;
+        }
+
+        interaction
+            .create_interaction_response(&self.http.http, |response| {
+                response
+                    .kind(interactions::InteractionResponseType::DeferredChannelMessageWithSource)
+            })
+            .await?;
+
+        self.console.send("/seed").await?;
+        self.console.send("/difficulty").await?;
+
+        let tx = self.tx.clone();
+        let interaction = Box::new(interaction.clone());
+        tokio::spawn(async move {
+            time::sleep(SERVER_INFO_DELAY).await;
+            tx.send(Event::ServerInfo(interaction)).await.unwrap_or(());
+        });
+
+        Ok(())
+    }
+
+    /// Reply to `/serverinfo` with an embed, completing the deferred reply if
+    /// `deferred` is set.
+    async fn reply_server_info(
+        &self,
+        interaction: &application_command::ApplicationCommandInteraction,
+        deferred: bool,
+    ) -> anyhow::Result<()> {
+        let title = match &self.settings.name {
+            None => String::from("Server info"),
+            Some(name) => format!("Server info: {}", name),
+        };
+        let fields = self.server_info().await;
+        let embed = |embed: &mut builder::CreateEmbed| {
+            embed.title(&title).fields(fields.clone());
+        };
+
+        if deferred {
+            interaction
+                .edit_original_interaction_response(&self.http.http, |response| {
+                    response.create_embed(|create| {
+                        embed(create);
+                        create
+                    })
+                })
+                .await?;
+        } else {
+            interaction
+                .create_interaction_response(&self.http.http, |response| {
+                    response
+                        .kind(interactions::InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|data| {
+                            data.create_embed(|create| {
+                                embed(create);
+                                create
+                            })
+                        })
+                })
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Fields for `/serverinfo`, from what the server has logged and its
+    /// `server.properties`.
+    async fn server_info(&self) -> Vec<(&'static str, String, bool)> {
+        let properties = match Properties::read(&self.settings.server_directory) {
+            Ok(properties) => Some(properties),
+            Err(error) => {
+                tracing::warn!("Failed to read server properties: {:?}", error);
+                None
+            }
+        };
+        let property = |key: &str| {
+            properties
+                .as_ref()?
+                .get(key)
+                .filter(|value| !value.is_empty())
+                .map(markdown::escape)
+        };
+        let unknown = || String::from("Unknown");
+
+        let mut fields = vec![(
+            "Version",
+            self.version
+                .as_deref()
+                .map(markdown::escape)
+                .unwrap_or_else(unknown),
+            true,
+        )];
+        if !self.settings.hide_seed {
+            let seed = self
+                .seed
+                .clone()
+                .or_else(|| property("level-seed"))
+                .map(|seed| format!("`{}`", seed))
+                .unwrap_or_else(unknown);
+            fields.push(("Seed", seed, true));
+        }
+        fields.push((
+            "Difficulty",
+            self.difficulty
+                .clone()
+                .or_else(|| property("difficulty"))
+                .unwrap_or_else(unknown),
+            true,
+        ));
+        fields.push((
+            "Game mode",
+            property("gamemode").unwrap_or_else(unknown),
+            true,
+        ));
+        fields.push((
+            "View distance",
+            property("view-distance")
+                .map(|distance| format!("{} chunks", distance))
+                .unwrap_or_else(unknown),
+            true,
+        ));
+
+        let level = properties
+            .as_ref()
+            .and_then(|properties| properties.get("level-name"))
+            .unwrap_or("world")
+            .to_owned();
+        let worlds = minecraft::worlds(&self.settings.server_directory, &level);
+        let size = tokio::task::spawn_blocking(move || {
+            worlds
+                .iter()
+                .map(|world| minecraft::disk_usage(world))
+                .sum::<std::io::Result<u64>>()
+                .ok()
+                .filter(|size| *size > 0)
+        })
+        .await
+        .ok()
+        .flatten();
+        fields.push((
+            "World size",
+            size.map(template::size).unwrap_or_else(unknown),
+            true,
+        ));
+
+        if let Some((usage, _)) = &self.usage {
+            let limit = self
+                .settings
+                .resources
+                .as_ref()
+                .and_then(|resources| resources.memory_limit)
+                .or(usage.heap);
+            let memory = match limit {
+                None => template::size(usage.memory),
+                Some(limit) => format!(
+                    "{} of {}",
+                    template::size(usage.memory),
+                    template::size(limit)
+                ),
+            };
+            fields.push(("Memory", memory, true));
+        }
+        if let Some(cpu) = self.cpu {
+            fields.push(("CPU", format!("{:.0}%", cpu), true));
+        }
+        if let Some(plugins) = &self.plugins {
+            // Embed field values are limited to 1024 characters.
+            let plugins = template::truncate(&markdown::escape(plugins), 1000);
+            fields.push(("Plugins", plugins, false));
+        }
+
+        fields
+    }
+
+    /// Record a successful moderation command in the audit channel, if configured.
+    async fn audit(
+        &self,
+        user: id::UserId,
+        title: &str,
+        result: &Result<String, String>,
+    ) -> anyhow::Result<()> {
+        match result {
+            Ok(message) => {
+                let description = format!("{}\nBy {}", markdown::escape(message), user.mention());
+                self.post_audit(title, description).await
+            }
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Post an entry to the audit channel, if configured, without pinging anyone.
+    async fn post_audit(&self, title: &str, description: String) -> anyhow::Result<()> {
+        let audit_id = match self.settings.audit_id {
+            Some(audit_id) if self.settings.discord => audit_id,
+            _ => return Ok(()),
+        };
+
+        let channel = id::ChannelId::from(audit_id);
+        retry::discord(|| {
+            channel.send_message(&self.http.http, |create| {
+                create
+                    .embed(|embed| embed.title(title).description(&description))
+                    .allowed_mentions(|mentions| mentions.empty_parse())
+            })
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Remove a member's linked account from the whitelist when they leave, or
+    /// are kicked or banned from, the guild, if enabled.
+    async fn handle_left(&mut self, guild_id: id::GuildId, user: id::UserId) -> anyhow::Result<()> {
+        if !self.settings.enforce_membership || self.guild_id().await != Some(guild_id) {
+            return Ok(());
+        }
+
+        let player = match self.store.data.links.get(&user.0) {
+            None => return Ok(()),
+            Some(player) => player.clone(),
+        };
+
+        let response = self
+            .console
+            .send(&format!("/whitelist remove {}", player))
+            .await?;
+
+        // Otherwise, the server's response is logged like any other.
+        if let Some(response) = response {
+            if let Err(error) = events::parse_whitelist(&response).unwrap_or(Err(response)) {
+                tracing::error!("Failed to remove {} from the whitelist: {}", player, error);
+                return Ok(());
+            }
+            self.sync_roles().await;
+        }
+
+        let description = format!(
+            "Removed {} from the whitelist, because {} left Discord",
+            markdown::escape(&player),
+            user.mention(),
+        );
+        self.post_audit("Membership", description).await
+    }
+
+    /// Describe when `player` was last online, and for how long, from the
+    /// history. Times are shown relative to now, with Discord's timestamps if
+    /// `discord` is set.
+    fn seen(&self, player: &str, discord: bool) -> String {
+        let history = match &self.history {
+            None => return String::from("History isn't recorded for this server."),
+            Some(history) => history,
+        };
+        let filter = history::Filter {
+            kinds: &[history::Kind::Join, history::Kind::Quit],
+            player: Some(player),
+            ..history::Filter::default()
+        };
+        let entries = match history.recent(&filter, 2) {
+            Ok(entries) => entries,
+            Err(error) => {
+                tracing::error!("Failed to read history: {:?}", error);
+                return String::from("Failed to read the history.");
+            }
+        };
+
+        let now = history::now();
+        let ago = |time: i64| match discord {
+            true => format!("<t:{}:R>", time),
+            false => format!(
+                "{} ago",
+                template::duration(Duration::from_secs((now - time).max(0) as u64)),
+            ),
+        };
+        let length =
+            |from: i64, to: i64| template::duration(Duration::from_secs((to - from).max(0) as u64));
+        let online = self
+            .store
+            .data
+            .online
+            .iter()
+            .any(|online| online.eq_ignore_ascii_case(player));
+
+        let last = entries.first();
+        let name = last.map_or(player, |entry| entry.player.as_str());
+        let name = match discord {
+            true => markdown::escape(name),
+            false => name.to_owned(),
+        };
+        let joined = entries
+            .iter()
+            .find(|entry| entry.kind == history::Kind::Join);
+        match (last, joined) {
+            (_, Some(joined)) if online && last == Some(joined) => {
+                format!("{} is online now, for {}.", name, length(joined.time, now))
+            }
+            _ if online => format!("{} is online now.", name),
+            (None, _) => format!("{} hasn't been seen on the server.", name),
+            (Some(left), Some(joined)) if left.kind == history::Kind::Quit => format!(
+                "{} was last online {}, for {}.",
+                name,
+                ago(left.time),
+                length(joined.time, left.time),
+            ),
+            (Some(last), _) if last.kind == history::Kind::Quit => {
+                format!("{} was last online {}.", name, ago(last.time))
+            }
+            // Their quit was missed, e.g. when the server crashed.
+            (Some(last), _) => format!("{} last joined {}.", name, ago(last.time)),
+        }
+    }
+
+    /// Whether `player` is joining for the first time: the server didn't know
+    /// them when the bridge started, and the bridge hasn't seen them since.
+    fn is_new(&self, player: &str) -> bool {
+        !self.usercache.contains(&player.to_lowercase())
+            && !self
+                .store
+                .data
+                .known_players()
+                .iter()
+                .any(|known| known.eq_ignore_ascii_case(player))
+    }
+
+    /// Link `player` to whoever was issued `code`, and tell them how it went.
+    async fn link(&mut self, player: &str, code: &str) -> anyhow::Result<()> {
+        let (color, text) = match self.linking.redeem(code) {
+            None => (
+                "red",
+                String::from(
+                    "That code is invalid or expired. Run /link in Discord for a new one.",
+                ),
+            ),
+            Some(user_id) => {
+                let links = &mut self.store.data.links;
+                links.retain(|_, linked| !linked.eq_ignore_ascii_case(player));
+                links.insert(user_id.0, player.to_owned());
+                self.save();
+                self.sync_roles().await;
+
+                let name = match user_id.to_user(&*self.http).await {
+                    Ok(user) => user.tag(),
+                    Err(_) => user_id.to_string(),
+                };
+                ("green", format!("Linked to Discord user {}.", name))
+            }
+        };
+
+        let tellraw = json!({ "text": text, "color": color });
+        self.console
+            .send(&format!("/tellraw {} {}", player, tellraw))
+            .await?;
+        Ok(())
+    }
+
+    /// Whether `user`, who has `roles` in the guild, may do `capability`.
+    /// Whether messages and commands from `channel` are meant for this server.
+    /// Each server takes its own channels, and the primary server everything else.
+    fn owns(&self, channel: id::ChannelId) -> bool {
+        self.settings.channels().contains(&channel.0)
+            || (self.settings.primary && !self.settings.others.contains(&channel.0))
+    }
+
+    fn allows(&self, user: id::UserId, roles: &[id::RoleId], capability: Capability) -> bool {
+        user == self.owner || self.settings.permissions.allows(user, roles, capability)
+    }
+
+    /// Buffer a line of server output for the console channel.
+    async fn mirror(&mut self, line: &str) -> anyhow::Result<()> {
+        if self.settings.console_id.is_none() {
+            return Ok(());
+        }
+
+        let line = match self.settings.console_colors {
+            true => color::to_discord(line),
+            false => color::strip(line),
+        };
+        if self.console_buffer.len() + line.len() >= CONSOLE_LIMIT {
+            self.flush_console().await?;
+        }
+
+        let mut end = line.len().min(CONSOLE_LIMIT);
+        while !line.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.console_buffer.push_str(&line[..end]);
+        self.console_buffer.push('\n');
+
+        if !self.console_pending {
+            self.console_pending = true;
+            let tx = self.tx.clone();
+            tokio::spawn(async move {
+                time::sleep(CONSOLE_DELAY).await;
+                tx.send(Event::FlushConsole).await.unwrap_or(());
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn flush_console(&mut self) -> anyhow::Result<()> {
+        let output = mem::take(&mut self.console_buffer);
+        let console_id = match self.settings.console_id {
+            Some(console_id) if !output.is_empty() && self.settings.discord => console_id,
+            _ => return Ok(()),
+        };
+
+        // Keep the output from closing the code block early.
+        let language = match self.settings.console_colors {
+            true => "ansi",
+            false => "",
+        };
+        let output = format!(
+            "```{}\n{}```",
+            language,
+            output.replace("```", "`\u{200B}``")
+        );
+        let channel = id::ChannelId::from(console_id);
+        let channel = if self.settings.threads.console {
+            self.thread(channel, "Console").await?
+        } else {
+            channel
+        };
+        retry::discord(|| channel.say(&self.http.http, &output)).await?;
+        Ok(())
+    }
+
+    /// Today's thread in `channel`, named like `Console 2024-05-01`, which is
+    /// created (and yesterday's archived) when the date changes.
+    async fn thread(
+        &mut self,
+        channel: id::ChannelId,
+        label: &str,
+    ) -> anyhow::Result<id::ChannelId> {
+        let date = Local::now().format("%Y-%m-%d").to_string();
+        match self.store.data.threads.get(&channel.0) {
+            Some(thread) if thread.date == date => return Ok(id::ChannelId::from(thread.id)),
+            Some(thread) => {
+                let previous = id::ChannelId::from(thread.id);
+                if let Err(error) = previous
+                    .edit_thread(&self.http.http, |thread| thread.archived(true))
+                    .await
+                {
+                    tracing::warn!("Failed to archive thread: {}", error);
+                }
+            }
+            None => (),
+        }
+
+        // Public threads start from a message in the channel.
+        let starter = retry::discord(|| {
+            channel.say(&self.http.http, format!("{} output for {}", label, date))
+        })
+        .await?;
+        let thread = channel
+            .create_public_thread(&self.http.http, starter.id, |thread| {
+                thread
+                    .name(format!("{} {}", label, date))
+                    .auto_archive_duration(THREAD_ARCHIVE)
+            })
+            .await?;
+
+        self.store.data.threads.insert(
+            channel.0,
+            store::Thread {
+                id: thread.id.0,
+                date,
+            },
+        );
+        self.save();
+        Ok(thread.id)
+    }
+
+    /// The channel a thread was mirrored from, or `channel` itself.
+    fn parent(&self, channel: id::ChannelId) -> u64 {
+        self.store
+            .data
+            .threads
+            .iter()
+            .find(|(_, thread)| thread.id == channel.0)
+            .map(|(parent, _)| *parent)
+            .unwrap_or(channel.0)
+    }
+
+    /// Buffer a line of server output for the verbose channel, so busy servers
+    /// post a message every few seconds instead of every line.
+    async fn log_verbose(&mut self, line: String) -> anyhow::Result<()> {
+        self.verbose_buffer.push(line);
+
+        let batch = &self.settings.verbose;
+        if batch.interval == 0 || self.verbose_buffer.len() >= batch.lines {
+            return self.flush_verbose().await;
+        }
+
+        if !self.verbose_pending {
+            self.verbose_pending = true;
+            let interval = Duration::from_secs(batch.interval);
+            let tx = self.tx.clone();
+            tokio::spawn(async move {
+                time::sleep(interval).await;
+                tx.send(Event::FlushVerbose).await.unwrap_or(());
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn flush_verbose(&mut self) -> anyhow::Result<()> {
+        if self.verbose_buffer.is_empty() {
+            return Ok(());
+        }
+        let output = mem::take(&mut self.verbose_buffer).join("\n");
+        let channel = id::ChannelId::from(self.settings.verbose_id);
+        let channel = if self.settings.threads.verbose {
+            self.thread(channel, "Verbose").await?
+        } else {
+            channel
+        };
+        self.say(channel, &output).await
+    }
+
+    /// Post an advancement to the general channel as an embed, styled like the
+    /// in-game toast, with the description and icon of vanilla advancements.
+    async fn announce_advancement(
+        &self,
+        message: String,
+        achievement: &str,
+        frame: advancements::Frame,
+    ) -> anyhow::Result<()> {
+        if !self.settings.discord {
+            return Ok(());
+        }
+        let advancement = advancements::find(achievement);
+        let description = match &advancement {
+            None => message,
+            Some(advancement) => format!("{}\n*{}*", message, advancement.description),
+        };
+        let icon_url = self
+            .settings
+            .advancements
+            .icon_url
+            .as_ref()
+            .zip(advancement)
+            .map(|(icon_url, advancement)| {
+                template::render(icon_url, &[("item", advancement.icon)])
+            });
+
+        let channel = id::ChannelId::from(self.settings.general_id);
+        retry::discord(|| {
+            channel.send_message(&self.http.http, |create| {
+                create.embed(|embed| {
+                    embed
+                        .title(frame.heading())
+                        .colour(frame.colour())
+                        .description(&description);
+                    if let Some(icon_url) = &icon_url {
+                        embed.thumbnail(icon_url);
+                    }
+                    embed
+                })
+            })
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Show which message a Discord reply responds to, just before the reply
+    /// itself, since Minecraft chat has no threads.
+    async fn broadcast_reply(&self, referenced: &channel::Message) -> anyhow::Result<()> {
+        let mut excerpt = mentions::to_minecraft(&self.http, referenced).await;
+        if excerpt.is_empty() {
+            excerpt = attachments::describe(referenced).join(" | ");
+        }
+        if self.settings.emoji.shortcodes {
+            excerpt = emoji::to_shortcodes(&excerpt);
+        }
+
+        // A line break would let the message run a second command.
+        let user = referenced.author.name.lines().join_with(" ").to_string();
+        let excerpt = excerpt.lines().join_with(" ").to_string();
+        let reply = template::render(
+            &self.settings.templates.reply,
+            &[
+                ("user", &user),
+                ("message", &template::truncate(&excerpt, REPLY_EXCERPT)),
+            ],
+        );
+        self.broadcast_notice(&reply).await
+    }
+
+    /// Show a line about Discord chat in Minecraft, set apart from the chat itself.
+    async fn broadcast_notice(&self, notice: &str) -> anyhow::Result<()> {
+        if !self.settings.tellraw.enabled {
+            self.console.send(&format!("/say {}", notice)).await?;
+            return Ok(());
+        }
+
+        let tellraw = json!({ "text": notice, "color": "gray", "italic": true });
+        self.console
+            .send(&format!("/tellraw @a {}", tellraw))
+            .await?;
+        Ok(())
+    }
+
+    /// Show a Discord message in Minecraft chat, and return it as plain text.
+    async fn broadcast(
+        &self,
+        user: &user::User,
+        message: &str,
+        link: &str,
+        edited: bool,
+    ) -> anyhow::Result<String> {
+        let template = if edited {
+            &self.settings.templates.edited
+        } else {
+            &self.settings.templates.discord
+        };
+        let player = self.store.data.links.get(&user.id.0).unwrap_or(&user.name);
+        let tellraw = &self.settings.tellraw;
+        let style = |name: &str| {
+            json!({
+                "text": name,
+                "color": tellraw.color,
+                "bold": tellraw.bold,
+                "hoverEvent": {
+                    "action": "show_text",
+                    "contents": "Sent from Discord (click to open)",
+                },
+                "clickEvent": {
+                    "action": "open_url",
+                    "value": link,
+                },
+            })
+        };
+
+        // Each line of the message is broadcast separately, since `/say` and
+        // `tellraw` don't show line breaks.
+        let mut said = Vec::new();
+        for line in message
+            .lines()
+            .flat_map(|line| template::split(line, MINECRAFT_LIMIT))
+        {
+            let say = template::render(
+                template,
+                &[("user", &user.name), ("player", player), ("message", &line)],
+            );
+
+            if !tellraw.enabled {
+                self.console.send(&format!("/say {}", say)).await?;
+                said.push(say);
+                continue;
+            }
+            said.push(say);
+
+            let components = template::render_json(
+                template,
+                &[
+                    ("user", style(&user.name)),
+                    ("player", style(player)),
+                    ("message", template::linkify(&line, tellraw.shorten_urls)),
+                ],
+            );
+            self.console
+                .send(&format!("/tellraw @a {}", components))
+                .await?;
+        }
+        Ok(said.join("\n"))
+    }
+
+    /// Send chat to the other servers' bridges, in the background so two busy
+    /// bridges can't wait on each other.
+    fn federate(&self, player: &str, message: &str) {
+        let server = match &self.settings.name {
+            Some(server) if self.settings.federate => server,
+            _ => return,
+        };
+
+        for peer in &self.peers {
+            let peer = peer.clone();
+            let event = Event::Federated {
+                server: server.clone(),
+                player: player.to_owned(),
+                message: message.to_owned(),
+            };
+            tokio::spawn(async move {
+                peer.send(event).await.unwrap_or(());
+            });
+        }
+    }
+
+    async fn handle_federated(
+        &self,
+        server: &str,
+        player: &str,
+        message: &str,
+    ) -> anyhow::Result<()> {
+        if !self.settings.federate {
+            return Ok(());
+        }
+
+        let template = &self.settings.templates.federated;
+        let tellraw = &self.settings.tellraw;
+        if !tellraw.enabled {
+            let say = template::render(
+                template,
+                &[("server", server), ("player", player), ("message", message)],
+            );
+            self.console.send(&format!("/say {}", say)).await?;
+            return Ok(());
+        }
+
+        let components = template::render_json(
+            template,
+            &[
+                ("server", json!({ "text": server, "color": "gray" })),
+                ("player", Value::from(player)),
+                ("message", template::linkify(message, tellraw.shorten_urls)),
+            ],
+        );
+        self.console
+            .send(&format!("/tellraw @a {}", components))
+            .await?;
+        Ok(())
+    }
+
+    /// Show a message from another chat platform in Minecraft and Discord.
+    async fn handle_chat(
+        &mut self,
+        platform: &str,
+        user: &str,
+        message: &str,
+    ) -> anyhow::Result<()> {
+        // A line break would let any of them run a second command.
+        let platform = platform.lines().join_with(" ").to_string();
+        let user = user.lines().join_with(" ").to_string();
+        let message = message.lines().join_with(" ").to_string();
+        // Like chat from Minecraft, so no one can ping everyone.
+        let escaped = mentions::to_discord(&self.http, self.guild_id().await, &message).await;
+        let template = &self.settings.templates.platform;
+        let text = template::render(
+            template,
+            &[
+                ("platform", &markdown::escape(&platform)),
+                ("user", &markdown::escape(&user)),
+                ("message", &escaped),
+            ],
+        );
+
+        let tellraw = &self.settings.tellraw;
+        if !tellraw.enabled {
+            let say = template::render(
+                template,
+                &[
+                    ("platform", &platform),
+                    ("user", &user),
+                    ("message", &message),
+                ],
+            );
+            self.console.send(&format!("/say {}", say)).await?;
+        } else {
+            let components = template::render_json(
+                template,
+                &[
+                    ("platform", json!({ "text": platform, "color": "gray" })),
+                    ("user", Value::from(user.as_str())),
+                    ("message", template::linkify(&message, tellraw.shorten_urls)),
+                ],
+            );
+            self.console
+                .send(&format!("/tellraw @a {}", components))
+                .await?;
+        }
+
+        let channel = id::ChannelId::from(self.settings.general_id);
+        self.post(Outgoing::Text { channel, text }).await;
+        Ok(())
+    }
+
+    /// Relay a player's message to the other chat platforms.
+    async fn relay_message(&self, player: &str, message: &str) {
+        for chat in &self.chats {
+            if let Err(error) = chat.send_message(player, message).await {
+                tracing::warn!("Failed to relay message to {}: {:?}", chat.name(), error);
+            }
+        }
+    }
+
+    /// Relay a server event to the other chat platforms.
+    async fn relay_event(&self, text: &str) {
+        for chat in &self.chats {
+            if let Err(error) = chat.send_event(text).await {
+                tracing::warn!("Failed to relay event to {}: {:?}", chat.name(), error);
+            }
+        }
+    }
+
+    /// Post `text` to `channel`, split across messages if it's too long for one.
+    async fn say(&self, channel: id::ChannelId, text: &str) -> anyhow::Result<()> {
+        if !self.settings.discord {
+            return Ok(());
+        }
+        for piece in template::split(text, DISCORD_LIMIT) {
+            retry::discord(|| channel.say(&self.http.http, &piece)).await?;
+        }
+        Ok(())
+    }
+
+    /// Post a lifecycle announcement to the general channel, unless its
+    /// template is empty.
+    async fn announce(&mut self, message: &str) {
+        if message.is_empty() {
+            return;
+        }
+        self.record(tag::Source::System, message);
+        if self.settings.mirror_tags.is_some() {
+            if let Err(error) = self.output(tag::Source::System, message).await {
+                tracing::warn!("Failed to mirror announcement: {:?}", error);
+            }
+        }
+        self.relay_event(message).await;
+        let channel = id::ChannelId::from(self.settings.general_id);
+        self.post(Outgoing::Text {
+            channel,
+            text: message.to_owned(),
+        })
+        .await;
+    }
+
+    async fn handle_stdin(&mut self, line: &str) -> anyhow::Result<()> {
+        if let Some(response) = self.console.send(line).await? {
+            for line in response.lines().filter(|line| !line.is_empty()) {
+                self.handle_minecraft(line.to_owned()).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Run a command from an RCON client, and reply with its output. Over
+    /// RCON, that's the server's response; otherwise, it's captured.
+    async fn handle_rcon(&mut self, command: String, reply: rcon::Reply) -> anyhow::Result<()> {
+        tracing::info!("Running command from RCON: {}", command);
+        self.remember(&history::Entry::command("RCON", &command));
+        if self.console.is_owned() {
+            return self.capture(command, Recipient::Rcon(reply)).await;
+        }
+
+        if !self.metrics.running.load(Ordering::Relaxed) {
+            reply.send(String::from("The server isn't running."));
+            return Ok(());
+        }
+        let response = self.console.send(&command).await?.unwrap_or_default();
+        for line in response.lines().filter(|line| !line.is_empty()) {
+            self.handle_minecraft(line.to_owned()).await?;
+        }
+        reply.send(response);
+        Ok(())
+    }
+
+    /// Run a command on the server's console, and send what the server logs
+    /// shortly afterward to `recipient`. Commands run one at a time, so their
+    /// output doesn't mix.
+    async fn capture(&mut self, command: String, recipient: Recipient) -> anyhow::Result<()> {
+        if !self.metrics.running.load(Ordering::Relaxed) {
+            let error = String::from("The server isn't running.");
+            return self.deliver(&command, recipient, Err(error)).await;
+        }
+        if self.capture.is_some() {
+            self.captures.push_back((command, recipient));
+            return Ok(());
+        }
+
+        self.console.send(&command).await?;
+        let id = self.next_capture;
+        self.next_capture += 1;
+        self.capture = Some(Capture {
+            id,
+            command,
+            lines: Vec::new(),
+            recipient,
+        });
+
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            time::sleep(CAPTURE_WINDOW).await;
+            tx.send(Event::Captured(id)).await.unwrap_or(());
+        });
+        Ok(())
+    }
+
+    /// Send the output of the current command, and run the next.
+    async fn finish_capture(&mut self) -> anyhow::Result<()> {
+        if let Some(capture) = self.capture.take() {
+            self.deliver(&capture.command, capture.recipient, Ok(capture.lines))
+                .await?;
+        }
+        while self.capture.is_none() {
+            match self.captures.pop_front() {
+                Some((command, recipient)) => self.capture(command, recipient).await?,
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Send a command's output, or why it didn't run, to `recipient`.
+    async fn deliver(
+        &self,
+        command: &str,
+        recipient: Recipient,
+        output: Result<Vec<String>, String>,
+    ) -> anyhow::Result<()> {
+        let text = match (&recipient, output) {
+            (Recipient::Rcon(reply), output) => {
+                reply.send(
+                    output
+                        .map(|lines| lines.join("\n"))
+                        .unwrap_or_else(|error| error),
+                );
+                return Ok(());
+            }
+            // The output is mirrored in the console anyway.
+            (Recipient::Message(..), Ok(lines)) if lines.is_empty() => return Ok(()),
+            (_, Ok(lines)) if lines.is_empty() => format!("Ran `{}`.", command),
+            (_, Ok(lines)) => code_block(&lines),
+            (_, Err(error)) => error,
+        };
+
+        match recipient {
+            Recipient::Rcon(_) => (),
+            Recipient::Message(channel, message) => {
+                retry::discord(|| {
+                    channel.send_message(&self.http.http, |reply| {
+                        reply
+                            .content(&text)
+                            .reference_message((channel, message))
+                            .allowed_mentions(|mentions| mentions.replied_user(false))
+                    })
+                })
+                .await?;
+            }
+            Recipient::Interaction(interaction) => {
+                interaction
+                    .edit_original_interaction_response(&self.http.http, |response| {
+                        response.content(&text)
+                    })
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Carry out a request from the HTTP API, or explain why not.
+    async fn handle_api(
+        &mut self,
+        token: &str,
+        request: api::Request,
+    ) -> anyhow::Result<api::Outcome> {
+        let running = self.metrics.running.load(Ordering::Relaxed);
+        let by = format!("By API token `{}`", markdown::escape(token));
+        let result = match request {
+            api::Request::Online => Ok(json!({
+                "online": self.store.data.online,
+                "max": self.max_players,
+            })),
+            api::Request::Log { lines } => {
+                match minecraft::log_tail(&self.settings.server_directory, lines) {
+                    Ok(log) => Ok(json!({ "lines": log.lines().collect::<Vec<_>>() })),
+                    Err(error) => Err(format!("{:#}", error)),
+                }
+            }
+            _ if !running => Err(String::from("The server isn't running.")),
+            api::Request::Chat { user, message } => {
+                self.handle_chat(token, &user, &message).await?;
+                Ok(json!({}))
+            }
+            api::Request::Command { command } => {
+                tracing::info!("Running command from API token `{}`: {}", token, command);
+                self.remember(&history::Entry::command(token, &command));
+                self.post_audit(
+                    "API",
+                    format!("Ran `{}`\n{}", markdown::escape(&command), by),
+                )
+                .await?;
+                let response = self.console.send(&command).await?;
+                for line in response.iter().flat_map(|response| response.lines()) {
+                    if !line.is_empty() {
+                        self.handle_minecraft(line.to_owned()).await?;
+                    }
+                }
+                Ok(json!({ "response": response }))
+            }
+            api::Request::Backup => {
+                if self.settings.backup.is_none() {
+                    Err(String::from("Backups aren't enabled."))
+                } else if self.backup != Backup::Idle || self.restoring.is_some() {
+                    Err(String::from("A backup is already running."))
+                } else {
+                    self.post_audit("API", format!("Backed up the world\n{}", by))
+                        .await?;
+                    self.handle_backup().await?;
+                    Ok(json!({ "message": "Backing up the world." }))
+                }
+            }
+            api::Request::Restart { minutes } => {
+                let counting_down = self.counting_down;
+                let message = self.handle_server(commands::Server::Restart { minutes });
+                if !counting_down && self.counting_down {
+                    self.post_audit("API", format!("{}\n{}", message, by))
+                        .await?;
+                    Ok(json!({ "message": message }))
+                } else {
+                    Err(message)
+                }
+            }
+        };
+        Ok(result)
+    }
+
+    /// Run a console command from MQTT, recording it in the audit channel.
+    async fn handle_mqtt(&mut self, command: &str) -> anyhow::Result<()> {
+        tracing::info!("Running command from MQTT: {}", command);
+        self.remember(&history::Entry::command("MQTT", command));
+        self.post_audit("MQTT", format!("`{}`", markdown::escape(command)))
+            .await?;
+        self.handle_stdin(command).await
+    }
+
+    /// Print a line to the terminal and mirror it to the console channel,
+    /// tagged with its source if configured.
+    async fn output(&mut self, source: tag::Source, line: &str) -> anyhow::Result<()> {
+        let line = match &self.settings.mirror_tags {
+            Some(format) => tag::tag(format, source, line),
+            None => line.to_owned(),
+        };
+        let output = match self.terminal {
+            true => color::to_terminal(&line),
+            false => color::strip(&line),
+        };
+        match &mut self.printer {
+            Some(printer) => printer.print(format!("{}\n", output))?,
+            None => {
+                self.stdout.write_all(output.as_bytes()).await?;
+                self.stdout.write_all(b"\n").await?;
+                self.stdout.flush().await?;
+            }
+        }
+        self.mirror(&line).await
+    }
+
+    /// Record an event in the history, if configured.
+    fn remember(&self, entry: &history::Entry) {
+        if let Some(history) = &self.history {
+            if let Err(error) = history.record(entry) {
+                tracing::warn!(
+                    "Failed to record {} in history: {:?}",
+                    entry.kind.as_str(),
+                    error
+                );
+            }
+        }
+    }
+
+    /// Record a line in the journal, if configured.
+    fn record(&self, source: tag::Source, line: &str) {
+        if let Some(journal) = &self.journal {
+            journal.record(source, line);
+        }
+    }
+
+    async fn handle_minecraft(&mut self, line: String) -> anyhow::Result<()> {
+        self.last_output = Instant::now();
+        self.record(tag::Source::Minecraft, &line);
+        self.output(tag::Source::Minecraft, &line).await?;
+
+        // Colors only belong in the terminal and console channel.
+        let line = color::strip(&line);
+
+        // Bedrock lists players on the line after `list`'s count, without a
+        // prefix.
+        let listed_players = match self.list_header.take() {
+            Some(max) if self.settings.parser.message(&line).is_none() => {
+                self.reconcile(max, events::players(&line));
+                true
+            }
+            // Nobody's online.
+            Some(max) => {
+                self.reconcile(max, Vec::new());
+                false
+            }
+            None => false,
+        };
+
+        // Only encode for subscribers, if there are any.
+        let stream = self
+            .stream
+            .as_ref()
+            .filter(|stream| stream.receiver_count() > 0);
+        let name = self.settings.name.as_deref();
+        let server = name.unwrap_or("default");
+        if let Some(stream) = stream {
+            stream
+                .send(Arc::new(api::Streamed::log(server, name, &line)))
+                .ok();
+        }
+        if listed_players {
+            return Ok(());
+        }
+
+        let log = self.settings.parser.parse(&line);
+        match &log {
+            None => {
+                self.metrics.parse_failures.fetch_add(1, Ordering::Relaxed);
+                tracing::trace!(%line, "Line matched no pattern");
+            }
+            Some(log) => tracing::debug!(?log, "Parsed server log"),
+        }
+
+        if let Some(entry) = log.as_ref().and_then(history::Entry::new) {
+            self.remember(&entry);
+        }
+
+        if let Some(payload) = log.as_ref().and_then(sink::Payload::new) {
+            for sink in &self.sinks {
+                sink.send(&payload);
+            }
+            #[cfg(feature = "mqtt")]
+            if let Some(mqtt) = &self.mqtt {
+                mqtt.publish(&payload);
+            }
+            if let Some(stream) = stream {
+                if let Some(streamed) = api::Streamed::event(server, name, &payload) {
+                    stream.send(Arc::new(streamed)).ok();
+                }
+            }
+        }
+
+        if let Some(log) = &log {
+            self.active(log);
+        }
+
+        // Don't flood the verbose channel with our own periodic `list` commands.
+        let listed = matches!(log, Some(Log::List { .. })) && mem::take(&mut self.listing);
+
+        // Player activity isn't output of the command.
+        if let Some(capture) = &mut self.capture {
+            if let (false, false, None, Some(message)) = (
+                listed,
+                matches!(log, Some(Log::Activity { .. })),
+                log.as_ref().and_then(sink::Payload::new),
+                self.settings.parser.message(&line),
+            ) {
+                capture.lines.push(message.to_owned());
+            }
+        }
+        if !listed && self.settings.verbose.mirrors(&line) {
+            self.log_verbose(markdown::escape(&line)).await?;
+        }
+
+        let embeds = &self.settings.embeds;
+        let style = match &log {
+            Some(Log::Join { .. }) => Some(embeds.join.clone()),
+            Some(Log::Quit { .. }) => Some(embeds.quit.clone()),
+            Some(Log::Death { .. }) => Some(embeds.death.clone()),
+            Some(Log::Message { .. }) => Some(embeds.chat.clone()),
+            _ => None,
+        }
+        .filter(|_| embeds.enabled);
+
+        let chat = matches!(log, Some(Log::Message { .. }));
+        let templates = &self.settings.templates;
+        let message = match log {
+            // Bedrock is still saving, so ask again.
+            None if self.backup == Backup::Saving
+                && self.settings.flavor == Flavor::Bedrock
+                && self
+                    .settings
+                    .parser
+                    .message(&line)
+                    .is_some_and(bedrock::save_pending) =>
+            {
+                let tx = self.tx.clone();
+                tokio::spawn(async move {
+                    time::sleep(SAVE_QUERY_DELAY).await;
+                    tx.send(Event::Stdin(String::from("/save-all flush")))
+                        .await
+                        .unwrap_or(());
+                });
+                return Ok(());
+            }
+            None => return Ok(()),
+            Some(Log::Join { player }) => {
+                let first = self.is_new(&player);
+                let template = match first && !templates.first_join.is_empty() {
+                    true => &templates.first_join,
+                    false => &templates.join,
+                };
+                let escaped = markdown::escape(&player);
+                let message = template::render(template, &[("player", &escaped)]);
+                if first && !templates.welcome.is_empty() {
+                    let welcome = template::render(&templates.welcome, &[("player", &player)]);
+                    let tellraw = json!({ "text": welcome, "color": "gold" });
+                    self.console
+                        .send(&format!("/tellraw {} {}", player, tellraw))
+                        .await?;
+                }
+                let reconnected = self.reconnected(&player);
+                self.playtime.join(&player);
+                self.store.data.online.insert(player);
+                self.save();
+                self.update_presence();
+                match reconnected {
+                    None => message,
+                    Some(true) if !self.settings.templates.unstable.is_empty() => {
+                        template::render(&self.settings.templates.unstable, &[("player", &escaped)])
+                    }
+                    Some(_) => return Ok(()),
+                }
+            }
+            Some(Log::Quit { player }) => {
+                self.store.data.online.remove(&player);
+                self.activity.remove(&player.to_lowercase());
+                self.backends.remove(&player);
+                self.playtime.quit(&player, &mut self.store.data);
+                self.save();
+                self.update_presence();
+                let message = template::render(
+                    &self.settings.templates.quit,
+                    &[("player", &markdown::escape(&player))],
+                );
+
+                // Wait to see whether they come right back.
+                let delay = self.settings.reconnects.delay;
+                if delay > 0 {
+                    let id = self.next_held;
+                    self.next_held += 1;
+                    self.held.insert(player.to_lowercase(), (id, message));
+                    let tx = self.tx.clone();
+                    tokio::spawn(async move {
+                        time::sleep(Duration::from_secs(delay)).await;
+                        tx.send(Event::Held(id)).await.unwrap_or(());
+                    });
+                    return Ok(());
+                }
+                message
+            }
+            Some(Log::Switch { player, server }) => {
+                // Connecting to the first server is part of joining.
+                let moved = self.backends.insert(player.clone(), server.clone());
+                if moved.is_none() || templates.switch.is_empty() {
+                    return Ok(());
+                }
+                template::render(
+                    &templates.switch,
+                    &[
+                        ("player", &markdown::escape(&player)),
+                        ("server", &markdown::escape(&server)),
+                    ],
+                )
+            }
+            Some(Log::Achievement {
+                player,
+                achievement,
+                frame,
+            }) => {
+                let message = template::render(
+                    &templates.advancement,
+                    &[
+                        ("player", &markdown::escape(&player)),
+                        ("advancement", &markdown::escape(&achievement)),
+                    ],
+                );
+                if !self.settings.advancements.embed {
+                    message
+                } else {
+                    self.relay_event(&message).await;
+                    self.announce_advancement(message, &achievement, frame)
+                        .await?;
+                    return Ok(());
+                }
+            }
+            Some(Log::Death { player, message }) => {
+                let escaped = markdown::escape(&player);
+                let mut message = template::render(
+                    &templates.death,
+                    &[
+                        ("player", &escaped),
+                        ("message", &markdown::escape(&message)),
+                    ],
+                );
+
+                let deaths = self.store.data.deaths.entry(player).or_default();
+                *deaths += 1;
+                let deaths = *deaths;
+                self.save();
+
+                if self
+                    .settings
+                    .death_milestone
+                    .is_some_and(|milestone| deaths.is_multiple_of(milestone))
+                {
+                    message.push('\n');
+                    message.push_str(&template::render(
+                        &templates.death_milestone,
+                        &[("player", &escaped), ("count", &template::ordinal(deaths))],
+                    ));
+                }
+                message
+            }
+            Some(Log::Saved) if self.backup == Backup::Saving => {
+                self.archive();
+                return Ok(());
+            }
+            Some(Log::Saved) => return Ok(()),
+            Some(Log::Version { version }) => {
+                let message = template::render(
+                    &templates.starting,
+                    &[("version", &markdown::escape(&version))],
+                );
+                self.version = Some(version);
+                self.announce(&message).await;
+                return Ok(());
+            }
+            Some(Log::Done { seconds }) => {
+                self.metrics.started.store(true, Ordering::Relaxed);
+                self.waiting_full = false;
+                let waiting = mem::take(&mut self.waiting);
+                let tx = self.tx.clone();
+                tokio::spawn(async move {
+                    for message in waiting {
+                        if tx.send(Event::Discord(Box::new(message))).await.is_err() {
+                            return;
+                        }
+                    }
+                });
+                let seconds = seconds
+                    .unwrap_or_else(|| self.launched.elapsed().unwrap_or_default().as_secs_f64());
+                let time = format!("{:.1}s", seconds);
+                let message = template::render(&templates.online, &[("time", &time)]);
+                self.announce(&message).await;
+                return Ok(());
+            }
+            Some(Log::Stopping) => {
+                let message = templates.stopping.clone();
+                self.announce(&message).await;
+                return Ok(());
+            }
+            Some(Log::Sleeping { player }) => {
+                if !self.settings.announce.sleeping {
+                    return Ok(());
+                }
+                let message = template::render(
+                    &templates.sleeping,
+                    &[("player", &markdown::escape(&player))],
+                );
+                self.announce(&message).await;
+                return Ok(());
+            }
+            Some(Log::NightSkipped) => {
+                if !self.settings.announce.night_skipped {
+                    return Ok(());
+                }
+                let message = templates.night_skipped.clone();
+                self.announce(&message).await;
+                return Ok(());
+            }
+            Some(Log::Weather { weather }) => {
+                if !self.settings.announce.weather {
+                    return Ok(());
+                }
+                let weather = match weather.as_str() {
+                    "rain & thunder" => "thundering",
+                    "rain" => "raining",
+                    other => other,
+                };
+                let message = template::render(&templates.weather, &[("weather", weather)]);
+                self.announce(&message).await;
+                return Ok(());
+            }
+            Some(Log::Tps { tps }) => {
+                self.record_tps(tps).await;
+                return Ok(());
+            }
+            Some(Log::Gametime { ticks }) => {
+                self.record_gametime(ticks).await;
+                return Ok(());
+            }
+            Some(Log::Seed { seed }) => {
+                self.seed = Some(seed);
+                return Ok(());
+            }
+            Some(Log::Difficulty { difficulty }) => {
+                self.difficulty = Some(difficulty);
+                return Ok(());
+            }
+            Some(Log::EntityData { player, data }) => {
+                self.handle_entity_data(&player, &data).await?;
+                return Ok(());
+            }
+            Some(Log::List { max, .. }) if self.settings.flavor == Flavor::Bedrock => {
+                self.list_header = Some(max);
+                return Ok(());
+            }
+            Some(Log::List { max, players }) => {
+                self.reconcile(max, players);
+                return Ok(());
+            }
+            Some(Log::Whitelist { result }) => {
+                if result.is_ok() {
+                    self.sync_roles().await;
+                }
+                self.finish_response(result).await?;
+                return Ok(());
+            }
+            Some(Log::Moderation { result }) => {
+                self.finish_response(result).await?;
+                return Ok(());
+            }
+            Some(Log::Activity { .. }) => return Ok(()),
+            Some(Log::Custom { channel, message }) => {
+                let channel = id::ChannelId::from(channel.unwrap_or(self.settings.general_id));
+                self.post(Outgoing::Text {
+                    channel,
+                    text: message,
+                })
+                .await;
+                return Ok(());
+            }
+            Some(Log::Message { player, message }) => {
+                // Keep commands out of Discord, especially link codes, even
+                // though they only work once.
+                if let Some(command) = ingame::parse(&self.settings.in_game, &message) {
+                    self.handle_in_game(&player, command).await?;
+                    return Ok(());
+                }
+
+                self.federate(&player, &message);
+                self.relay_message(&player, &message).await;
+                self.metrics.to_discord.fetch_add(1, Ordering::Relaxed);
+
+                let message = if self.settings.emoji.emoticons {
+                    emoji::from_emoticons(&message)
+                } else {
+                    message
+                };
+                let message =
+                    mentions::to_discord(&self.http, self.guild_id().await, &message).await;
+                if self.settings.webhook_url.is_none() {
+                    template::render(
+                        &self.settings.templates.chat,
+                        &[
+                            ("player", &markdown::escape(&player)),
+                            ("message", &message),
+                        ],
+                    )
+                } else {
+                    let avatar_url =
+                        template::render(&self.settings.avatar_url, &[("player", &player)]);
+                    self.post(Outgoing::Webhook {
+                        player,
+                        avatar_url,
+                        text: message,
+                    })
+                    .await;
+                    return Ok(());
+                }
+            }
+        };
+
+        if !chat {
+            self.relay_event(&message).await;
+        }
+        self.post_general(message, style).await;
+        Ok(())
+    }
+
+    /// Post a server event or chat to the general channel, in an embed if
+    /// `style` is set.
+    async fn post_general(&mut self, message: String, style: Option<config::EmbedStyle>) {
+        let channel = id::ChannelId::from(self.settings.general_id);
+        let outgoing = match style {
+            None => Outgoing::Text {
+                channel,
+                text: message,
+            },
+            Some(style) => Outgoing::Embed {
+                channel,
+                color: style.color,
+                description: if style.emoji.is_empty() {
+                    message
+                } else {
+                    format!("{} {}", style.emoji, message)
+                },
+            },
+        };
+        self.post(outgoing).await;
+    }
+
+    /// Answer a command `player` typed in Minecraft chat.
+    async fn handle_in_game(
+        &mut self,
+        player: &str,
+        command: ingame::Command,
+    ) -> anyhow::Result<()> {
+        let prefix = &self.settings.in_game.prefix;
+        let text = match command {
+            ingame::Command::Link { code } => return self.link(player, &code).await,
+            ingame::Command::SetWaypoint { name } => return self.set_waypoint(player, &name).await,
+            ingame::Command::Discord => {
+                let url = self.settings.in_game.discord.clone().unwrap_or_default();
+                let tellraw = json!([
+                    { "text": "Join us on Discord: ", "color": "gray" },
+                    {
+                        "text": url,
+                        "color": "aqua",
+                        "underlined": true,
+                        "clickEvent": { "action": "open_url", "value": url },
+                    },
+                ]);
+                self.console
+                    .send(&format!("/tellraw {} {}", player, tellraw))
+                    .await?;
+                return Ok(());
+            }
+            ingame::Command::Help => ingame::help(&self.settings.in_game),
+            ingame::Command::Usage(usage) => usage,
+            ingame::Command::Online => format!("{}: {}", self.online_count(), self.online()),
+            ingame::Command::Playtime { player: target } => {
+                let target = target.as_deref().unwrap_or(player);
+                // Playtime is kept by name as the server logged it.
+                let known = self.store.data.known_players();
+                let target = known
+                    .iter()
+                    .find(|known| known.eq_ignore_ascii_case(target))
+                    .copied()
+                    .unwrap_or(target);
+                match commands::check_player(target, self.settings.flavor) {
+                    Ok(target) => format!(
+                        "{} has played for {}.",
+                        target,
+                        template::duration(self.playtime.total(target, &self.store.data)),
+                    ),
+                    Err(error) => String::from(error),
+                }
+            }
+            ingame::Command::Seen { player: target } => {
+                match commands::check_player(&target, self.settings.flavor) {
+                    Ok(target) => self.seen(target, false),
+                    Err(error) => String::from(error),
+                }
+            }
+            ingame::Command::Waypoints if self.store.data.waypoints.is_empty() => {
+                format!(
+                    "No waypoints yet. Save one with {}setwaypoint <name>.",
+                    prefix
+                )
+            }
+            ingame::Command::Waypoints => self
+                .store
+                .data
+                .waypoints
+                .values()
+                .map(|waypoint| {
+                    format!(
+                        "{}: {} ({})",
+                        waypoint.name,
+                        describe_waypoint(waypoint),
+                        waypoint.player,
+                    )
+                })
+                .join_with("\n")
+                .to_string(),
+            ingame::Command::DeleteWaypoint { name } => self.delete_waypoint(player, &name),
+            ingame::Command::Reply { text } => template::render(&text, &[("player", player)]),
+        };
+        self.tell(player, &text).await
+    }
+
+    /// Reply privately to `player` in game.
+    async fn tell(&self, player: &str, text: &str) -> anyhow::Result<()> {
+        let tellraw = json!({ "text": text, "color": "gray" });
+        self.console
+            .send(&format!("/tellraw {} {}", player, tellraw))
+            .await?;
+        Ok(())
+    }
+
+    /// Start saving a waypoint named `name` where `player` is standing, once
+    /// the server says where that is.
+    async fn set_waypoint(&mut self, player: &str, name: &str) -> anyhow::Result<()> {
+        if let Err(error) = check_waypoint(name) {
+            return self.tell(player, error).await;
+        }
+        if let Some(existing) = self.store.data.waypoints.get(&name.to_lowercase()) {
+            if !existing.player.eq_ignore_ascii_case(player) {
+                let text = format!(
+                    "{} already saved the waypoint {}.",
+                    existing.player, existing.name
+                );
+                return self.tell(player, &text).await;
+            }
+        }
+
+        self.waypoint_requests
+            .insert(player.to_lowercase(), (name.to_owned(), None));
+        // Over RCON, the responses come back directly, rather than in the log.
+        for path in &["Pos", "Dimension"] {
+            let command = format!("/data get entity {} {}", player, path);
+            if let Some(response) = self.console.send(&command).await? {
+                if let Some((player, data)) = events::parse_entity_data(&response) {
+                    self.handle_entity_data(&player, &data).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Save the waypoint `player` asked for, once the server has said both
+    /// where they are and in which dimension.
+    async fn handle_entity_data(&mut self, player: &str, data: &str) -> anyhow::Result<()> {
+        let key = player.to_lowercase();
+        let request = match self.waypoint_requests.get_mut(&key) {
+            None => return Ok(()),
+            Some(request) => request,
+        };
+        if let Some(position) = events::parse_position(data) {
+            request.1 = Some(position);
+            return Ok(());
+        }
+
+        let dimension = data.trim().trim_matches(|char| char == '"' || char == '\'');
+        let dimension = dimension.strip_prefix("minecraft:").unwrap_or(dimension);
+        let (name, position) = match self.waypoint_requests.remove(&key) {
+            Some((name, Some(position))) => (name, position),
+            _ => return Ok(()),
+        };
+
+        let waypoint = store::Waypoint {
+            name,
+            player: player.to_owned(),
+            position: position.map(|coordinate| coordinate.floor() as i64),
+            dimension: dimension.to_owned(),
+        };
+        let text = format!(
+            "Saved waypoint {}: {}.",
+            waypoint.name,
+            describe_waypoint(&waypoint),
+        );
+        self.store
+            .data
+            .waypoints
+            .insert(waypoint.name.to_lowercase(), waypoint);
+        self.save();
+        self.tell(player, &text).await
+    }
+
+    /// Delete the waypoint named `name`, if `player` saved it.
+    fn delete_waypoint(&mut self, player: &str, name: &str) -> String {
+        let key = name.to_lowercase();
+        match self.store.data.waypoints.get(&key) {
+            None => format!("There's no waypoint named {}.", name),
+            Some(waypoint) if !waypoint.player.eq_ignore_ascii_case(player) => {
+                format!("Only {} can delete {}.", waypoint.player, waypoint.name)
+            }
+            Some(_) => {
+                let waypoint = self.store.data.waypoints.remove(&key);
+                self.save();
+                format!(
+                    "Deleted waypoint {}.",
+                    waypoint.map(|waypoint| waypoint.name).unwrap_or_default()
+                )
+            }
+        }
+    }
+
+    /// Whether `player` is rejoining before their quit was announced, and if
+    /// so, whether it's the reconnect in a row that makes them unstable.
+    /// Their quit isn't announced either way.
+    fn reconnected(&mut self, player: &str) -> Option<bool> {
+        let player = player.to_lowercase();
+        self.held.remove(&player)?;
+        let reconnects = &self.settings.reconnects;
+        let window = Duration::from_secs(reconnects.window);
+        let (count, last) = self.reconnects.entry(player).or_insert((0, Instant::now()));
+        if last.elapsed() > window {
+            *count = 0;
+        }
+        *count += 1;
+        *last = Instant::now();
+        Some(*count == reconnects.unstable)
+    }
+
+    /// Post to Discord, or keep the message until Discord is reachable again.
+    async fn post(&mut self, outgoing: Outgoing) {
+        self.record(tag::Source::Relay, &outgoing.to_string());
+        if !self.settings.discord {
+            return;
+        }
+        // Keep messages in order behind any that are still waiting, until
+        // they're flushed on reconnecting. Retrying here would hold up the
+        // server's output for each line while Discord is down.
+        if !self.connected || !self.outbox.is_empty() || self.missed > 0 {
+            self.keep(outgoing);
+            return;
+        }
+        if let Err(error) = self.send(&outgoing).await {
+            tracing::warn!("Failed to post to Discord, keeping message: {:?}", error);
+            self.connected = false;
+            self.keep(outgoing);
+        }
+    }
+
+    fn keep(&mut self, outgoing: Outgoing) {
+        let offline = &self.settings.offline;
+        if self.outbox.len() < offline.limit {
+            self.outbox.push_back(outgoing);
+            return;
+        }
+        self.missed += 1;
+        if offline.overflow == config::Overflow::DropOldest {
+            self.outbox.pop_front();
+            self.outbox.push_back(outgoing);
+        }
+    }
+
+    /// Post messages kept while Discord was unreachable, stopping at the first
+    /// failure.
+    async fn flush_outbox(&mut self) {
+        if self.missed > 0 {
+            let notice = format!(
+                "⚠️ Missed {} message{} while Discord was unreachable.",
+                self.missed,
+                if self.missed == 1 { "" } else { "s" },
+            );
+            let channel = id::ChannelId::from(self.settings.general_id);
+            if let Err(error) = self.say(channel, &notice).await {
+                tracing::warn!("Failed to post to Discord: {:?}", error);
+                self.connected = false;
+                return;
+            }
+            self.missed = 0;
+        }
+
+        while let Some(outgoing) = self.outbox.pop_front() {
+            if let Err(error) = self.send(&outgoing).await {
+                tracing::warn!("Failed to post to Discord: {:?}", error);
+                self.connected = false;
+                self.outbox.push_front(outgoing);
+                return;
+            }
+        }
+    }
+
+    async fn send(&mut self, outgoing: &Outgoing) -> anyhow::Result<()> {
+        match outgoing {
+            Outgoing::Text { channel, text } => self.say(*channel, text).await?,
+            Outgoing::Embed {
+                channel,
+                color,
+                description,
+            } => {
+                retry::discord(|| {
+                    channel.send_message(&self.http.http, |create| {
+                        create.embed(|embed| embed.colour(*color).description(description))
+                    })
+                })
+                .await?;
+            }
+            Outgoing::Files {
+                channel,
+                text,
+                files,
+            } => {
+                retry::discord(|| {
+                    let files = files
+                        .iter()
+                        .map(|(name, data)| (data.as_slice(), name.as_str()));
+                    channel.send_files(&self.http.http, files, |create| create.content(text))
+                })
+                .await?;
+            }
+            Outgoing::Webhook {
+                player,
+                avatar_url,
+                text,
+            } => match self.webhook().await? {
+                // The webhook was removed from the config file since.
+                None => {
+                    let channel = id::ChannelId::from(self.settings.general_id);
+                    let text = template::render(
+                        &self.settings.templates.chat,
+                        &[("player", &markdown::escape(player)), ("message", text)],
+                    );
+                    self.say(channel, &text).await?;
+                }
+                Some(webhook) => {
+                    for piece in template::split(text, DISCORD_LIMIT) {
+                        retry::discord(|| {
+                            webhook.execute(&self.http.http, false, |execute| {
+                                execute
+                                    .username(player)
+                                    .avatar_url(avatar_url)
+                                    .content(&piece)
+                            })
+                        })
+                        .await?;
+                    }
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// Returns whether the bridge should keep running.
+    async fn handle_exited(
+        &mut self,
+        status: process::ExitStatus,
+        stopped: bool,
+    ) -> anyhow::Result<bool> {
+        self.metrics.running.store(false, Ordering::Relaxed);
+        self.store.data.online.clear();
+        self.backends.clear();
+        self.playtime.quit_all(&mut self.store.data);
+        self.save();
+        self.update_presence();
+        self.flush_verbose().await?;
+        if self.backup == Backup::Saving {
+            self.backup = Backup::Idle;
+        }
+        if stopped {
+            let message = self.settings.templates.stopped.clone();
+            self.announce(&message).await;
+        }
+
+        if self.restoring.is_some() {
+            self.restarting = false;
+            self.stopping = false;
+            self.restore();
+            return Ok(true);
+        }
+
+        if mem::take(&mut self.restarting) {
+            self.launch().await?;
+            return Ok(true);
+        }
+
+        if mem::take(&mut self.stopping) {
+            tracing::info!("Server stopped from Discord with {}", status);
+            return Ok(true);
+        }
+
+        if stopped {
+            tracing::info!("Server exited with {}", status);
+            return Ok(false);
+        }
+
+        if self.launched.elapsed().unwrap_or_default() > STABLE {
+            self.crashes = 0;
+        }
+
+        let delay = BACKOFF
+            .checked_mul(1 << self.crashes.min(16))
+            .unwrap_or(BACKOFF_MAX)
+            .min(BACKOFF_MAX);
+
+        let message = if self.settings.crash.restart {
+            format!(
+                "Server crashed ({}). Restarting in {} seconds...",
+                status,
+                delay.as_secs(),
+            )
+        } else {
+            format!("Server crashed ({}).", status)
+        };
+
+        tracing::error!("{}", message);
+
+        // Relaunch the server even if Discord can't be told.
+        let general_channel = id::ChannelId::from(self.settings.general_id);
+        if let Err(error) = self.say(general_channel, &message).await {
+            tracing::warn!("Failed to post crash alert: {:?}", error);
+        }
+        self.upload_crash(&message).await;
+
+        if !self.settings.crash.restart {
+            return Ok(false);
+        }
+
+        self.crashes += 1;
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            time::sleep(delay).await;
+            tx.send(Event::Launch).await.unwrap_or(());
+        });
+
+        Ok(true)
+    }
+
+    /// Post the crash report and the end of the server's log for admins, if
+    /// enabled, to the crash channel, error channel, or verbose channel.
+    async fn upload_crash(&mut self, message: &str) {
+        let crash = &self.settings.crash;
+        let directory = &self.settings.server_directory;
+        let mut files = Vec::new();
+
+        let report = if crash.upload_report {
+            minecraft::crash_report(directory, self.launched)
+        } else {
+            None
+        };
+        if let Some(report) = report {
+            let name = report
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| String::from("crash-report.txt"));
+            match std::fs::read(&report) {
+                Ok(data) => files.push((name, data)),
+                Err(error) => tracing::warn!("Failed to read crash report: {}", error),
+            }
+        }
+
+        if let Some(lines) = crash.upload_log.filter(|lines| *lines > 0) {
+            match minecraft::log_tail(directory, lines) {
+                Ok(tail) => files.push((String::from("latest.log"), tail.into_bytes())),
+                Err(error) => tracing::warn!("Failed to read server log: {:?}", error),
+            }
+        }
+
+        if files.is_empty() {
+            return;
+        }
+
+        let channel = crash
+            .channel
+            .or(self.settings.error_id)
+            .unwrap_or(self.settings.verbose_id);
+        self.post(Outgoing::Files {
+            channel: id::ChannelId::from(channel),
+            text: message.to_owned(),
+            files,
+        })
+        .await;
+    }
+
+    async fn launch(&mut self) -> anyhow::Result<()> {
+        self.metrics.restarts.fetch_add(1, Ordering::Relaxed);
+        self.launched = SystemTime::now();
+        self.gametime = None;
+        self.usage = None;
+        self.cpu = None;
+        self.last_output = Instant::now();
+        self.metrics.started.store(false, Ordering::Relaxed);
+        self.metrics.responsive.store(true, Ordering::Relaxed);
+        self.console.launch().await?;
+        self.metrics.running.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Ask the server for its TPS, if lag alerts are enabled. Servers may pause
+    /// while empty, so samples are only taken while someone is online.
+    async fn sample_tps(&mut self) -> anyhow::Result<()> {
+        let source = match &self.settings.lag {
+            None => return Ok(()),
+            Some(lag) => lag.source,
+        };
+
+        if !self.metrics.started.load(Ordering::Relaxed) || self.store.data.online.is_empty() {
+            self.gametime = None;
+            return Ok(());
+        }
+
+        match source {
+            config::TpsSource::Paper => {
+                if let Some(response) = self.console.send("/tps").await? {
+                    if let Some(tps) = events::parse_tps(&response) {
+                        self.record_tps(tps).await;
+                    }
+                }
+            }
+            config::TpsSource::Gametime => {
+                if let Some(response) = self.console.send("/time query gametime").await? {
+                    if let Some(ticks) = events::parse_gametime(&response) {
+                        self.record_gametime(ticks).await;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Estimate TPS from how far the game time advanced since the last sample.
+    async fn record_gametime(&mut self, ticks: u64) {
+        let now = Instant::now();
+        let (last, then) = match self.gametime.replace((ticks, now)) {
+            None => return,
+            Some(last) => last,
+        };
+        let elapsed = now.duration_since(then).as_secs_f64();
+        if ticks >= last && elapsed > 0.0 {
+            let tps = (ticks - last) as f64 / elapsed;
+            self.record_tps(tps.min(20.0)).await;
+        }
+    }
+
+    /// Remember the latest TPS, and alert when it's stayed below the threshold
+    /// for enough samples in a row, or recovered since.
+    async fn record_tps(&mut self, tps: f64) {
+        self.tps = Some(tps);
+
+        let (threshold, samples) = match &self.settings.lag {
+            None => return,
+            Some(lag) => (lag.threshold, lag.samples),
+        };
+
+        let template = if tps < threshold {
+            self.lag_samples += 1;
+            if self.lag_samples != samples {
+                return;
+            }
+            &self.settings.templates.lag
+        } else {
+            let lagging = self.lag_samples >= samples;
+            self.lag_samples = 0;
+            if !lagging {
+                return;
+            }
+            &self.settings.templates.lag_recovered
+        };
+
+        let message = template::render(template, &[("tps", &format!("{:.1}", tps))]);
+        self.announce(&message).await;
+    }
+
+    /// Measure the server process's memory and CPU usage, if enabled, and
+    /// alert when either is too high.
+    async fn sample_usage(&mut self) {
+        let resources = match &self.settings.resources {
+            None => return,
+            Some(resources) => resources.clone(),
+        };
+
+        let pid = match self.console.pid() {
+            None => {
+                self.usage = None;
+                self.cpu = None;
+                return;
+            }
+            Some(pid) => pid,
+        };
+
+        let usage = match tokio::task::spawn_blocking(move || usage::sample(pid)).await {
+            Ok(Ok(usage)) => usage,
+            Ok(Err(error)) => {
+                tracing::warn!("Failed to measure server process: {}", error);
+                return;
+            }
+            Err(error) => {
+                tracing::warn!("Failed to measure server process: {}", error);
+                return;
+            }
+        };
+
+        let now = Instant::now();
+        self.cpu = self
+            .usage
+            .filter(|(last, _)| last.cpu <= usage.cpu)
+            .map(|(last, then)| {
+                let elapsed = now.duration_since(then).as_secs_f64();
+                (usage.cpu - last.cpu).as_secs_f64() / elapsed * 100.0
+            })
+            .filter(|percent| percent.is_finite());
+        self.usage = Some((usage, now));
+        self.metrics.memory.store(usage.memory, Ordering::Relaxed);
+        self.metrics
+            .cpu
+            .store(usage.cpu.as_secs(), Ordering::Relaxed);
+
+        let limit = resources.memory_limit.or(usage.heap);
+        let restart = self
+            .settings
+            .watchdog
+            .as_ref()
+            .and_then(|watchdog| Some((watchdog.memory?, watchdog.warning)));
+        if let (Some((threshold, minutes)), Some(limit)) = (restart, limit) {
+            let percent = usage.memory as f64 / limit as f64 * 100.0;
+            // Already on its way down.
+            let stopping = self.counting_down || self.restarting || self.stopping;
+            if percent >= threshold && !stopping && self.console.is_owned() {
+                tracing::warn!("Restarting server using {:.0}% of its memory", percent);
+                let message = template::render(
+                    &self.settings.templates.memory_restart,
+                    &[
+                        ("percent", &format!("{:.0}%", percent)),
+                        ("memory", &template::size(usage.memory)),
+                        ("limit", &template::size(limit)),
+                    ],
+                );
+                self.announce(&message).await;
+                let reply = self.handle_server(commands::Server::Restart { minutes });
+                tracing::info!("{}", reply);
+            }
+        }
+
+        if let (Some(alert), Some(limit)) = (resources.memory_alert, limit) {
+            let percent = usage.memory as f64 / limit as f64 * 100.0;
+            if percent < alert {
+                self.memory_alerted = false;
+            } else if !mem::replace(&mut self.memory_alerted, true) {
+                let message = template::render(
+                    &self.settings.templates.memory_alert,
+                    &[
+                        ("percent", &format!("{:.0}%", percent)),
+                        ("memory", &template::size(usage.memory)),
+                        ("limit", &template::size(limit)),
+                    ],
+                );
+                self.alert(&message).await;
+            }
+        }
+
+        if let (Some(alert), Some(cpu)) = (resources.cpu_alert, self.cpu) {
+            if cpu < alert {
+                self.cpu_samples = 0;
+            } else {
+                self.cpu_samples += 1;
+                if self.cpu_samples == resources.samples {
+                    let message = template::render(
+                        &self.settings.templates.cpu_alert,
+                        &[("percent", &format!("{:.0}%", cpu))],
+                    );
+                    self.alert(&message).await;
+                }
+            }
+        }
+    }
+
+    async fn handle_announcement(&mut self, index: usize) -> anyhow::Result<()> {
+        let announcement = match self.settings.announcements.get(index) {
+            None => return Ok(()),
+            Some(announcement) => announcement.clone(),
+        };
+
+        if announcement.minecraft && self.metrics.started.load(Ordering::Relaxed) {
+            // A line break would let the message run a second command.
+            for line in announcement.message.lines() {
+                if self.settings.tellraw.enabled {
+                    let tellraw = json!({ "text": line, "color": announcement.color });
+                    self.console
+                        .send(&format!("/tellraw @a {}", tellraw))
+                        .await?;
+                } else {
+                    self.console.send(&format!("/say {}", line)).await?;
+                }
+            }
+        }
+
+        if announcement.discord {
+            self.announce(&announcement.message).await;
+        }
+        Ok(())
+    }
+
+    /// Restart the server if it hasn't logged anything for too long, or kill
+    /// it if it was already asked to stop. Since an idle server may have
+    /// nothing to say, it's prompted with `list` halfway there.
+    async fn watchdog(&mut self) -> anyhow::Result<()> {
+        let (silence, kill_after) = match &self.settings.watchdog {
+            Some(watchdog) => match watchdog.silence {
+                None => return Ok(()),
+                Some(silence) => (silence, watchdog.kill_after),
+            },
+            None => return Ok(()),
+        };
+
+        if !self.console.is_owned() || !self.metrics.running.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let quiet = self.last_output.elapsed();
+        if quiet < silence / 2 || self.counting_down {
+            return Ok(());
+        }
+
+        if self.restarting || self.stopping {
+            if quiet >= silence {
+                tracing::error!("Server hung while stopping");
+                self.console.kill().await;
+            }
+            return Ok(());
+        }
+
+        if quiet < silence {
+            return self.list().await;
+        }
+
+        let minutes = silence.as_secs() / 60;
+        tracing::error!("Server hasn't logged anything in {} minutes", minutes);
+        let message = template::render(
+            &self.settings.templates.hung,
+            &[("minutes", &minutes.to_string())],
+        );
+        self.announce(&message).await;
+
+        self.console.send("/stop").await?;
+        self.restarting = true;
+
+        let launch = self.metrics.restarts.load(Ordering::Relaxed);
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            time::sleep(kill_after).await;
+            tx.send(Event::Kill(launch)).await.unwrap_or(());
+        });
+        Ok(())
+    }
+
+    /// Post a warning for admins to the error channel, or the verbose channel.
+    async fn alert(&mut self, message: &str) {
+        if message.is_empty() {
+            return;
+        }
+        let channel =
+            id::ChannelId::from(self.settings.error_id.unwrap_or(self.settings.verbose_id));
+        self.post(Outgoing::Text {
+            channel,
+            text: message.to_owned(),
+        })
+        .await;
+    }
+
+    /// Restore the world from the pending backup in the background, while the
+    /// server is stopped.
+    fn restore(&mut self) {
+        let (backup, path) = match (self.settings.backup.clone(), self.restoring.clone()) {
+            (Some(backup), Some(path)) => (backup, path),
+            _ => {
+                self.restoring = None;
+                return;
+            }
+        };
+
+        let tx = self.tx.clone();
+        tokio::spawn(
+            async move {
+                let result = match tokio::task::spawn_blocking(move || backup.restore(&path)).await
+                {
+                    Ok(Ok(safety)) => Ok(safety),
+                    Ok(Err(error)) => Err(format!("{:#}", error)),
+                    Err(error) => Err(error.to_string()),
+                };
+                tx.send(Event::Restored(result)).await.unwrap_or(());
+            }
+            .in_current_span(),
+        );
+    }
+
+    /// Announce how the restore went, and start the server again either way,
+    /// since a failed restore puts the old world back.
+    async fn handle_restored(&mut self, result: Result<PathBuf, String>) -> anyhow::Result<()> {
+        let restored = self.restoring.take();
+        let name = restored
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let message = match result {
+            Ok(safety) => {
+                tracing::info!(
+                    "Restored world from `{}`, keeping the old world in `{}`",
+                    name,
+                    safety.display(),
+                );
+                format!(
+                    "Restored the world from `{}`. The previous world was kept as `{}`.",
+                    name,
+                    safety.display(),
+                )
+            }
+            Err(error) => {
+                tracing::error!("Failed to restore world: {}", error);
+                format!("⚠️ Failed to restore the world: {}", error)
+            }
+        };
+        self.post_audit("Restore", message.clone()).await?;
+        self.announce(&message).await;
+        self.launch().await
+    }
+
+    async fn handle_backup(&mut self) -> anyhow::Result<()> {
+        if self.restoring.is_some() {
+            tracing::warn!("Skipping backup: restoring a backup");
+            return Ok(());
+        }
+        if self.backup != Backup::Idle {
+            tracing::warn!("Skipping backup: previous backup is still running");
+            return Ok(());
+        }
+
+        // Nothing is writing to the world, and nothing would answer a save.
+        if !self.console.is_running().await {
+            self.archive();
+            return Ok(());
+        }
+
+        self.console.send("/save-off").await?;
+        self.backup = Backup::Saving;
+        self.backups += 1;
+
+        // Over RCON, the command only returns once the world is saved.
+        // Otherwise, wait for the server to log that it's done.
+        if self.console.send("/save-all flush").await?.is_some() {
+            self.archive();
+            return Ok(());
+        }
+
+        let backup = self.backups;
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            time::sleep(SAVE_TIMEOUT).await;
+            tx.send(Event::SaveTimeout(backup)).await.unwrap_or(());
+        });
+        Ok(())
+    }
+
+    /// Skip the backup if the server never said it saved the world, and turn
+    /// autosave back on.
+    async fn handle_save_timeout(&mut self, backup: u64) -> anyhow::Result<()> {
+        if self.backup != Backup::Saving || backup != self.backups {
+            return Ok(());
+        }
+        tracing::error!("Skipping backup: the server didn't save the world in time");
+        self.backup = Backup::Idle;
+        self.console.send("/save-on").await?;
+        let message = format!(
+            "⚠️ Skipped a backup: the server didn't save the world within {} minutes.",
+            SAVE_TIMEOUT.as_secs() / 60,
+        );
+        self.alert(&message).await;
+        Ok(())
+    }
+
+    fn archive(&mut self) {
+        let backup = self.settings.backup.clone();
+        let tx = self.tx.clone();
+        self.backup = Backup::Archiving;
+        tokio::spawn(async move {
+            let backup = match backup {
+                Some(backup) => backup,
+                None => {
+                    let error = String::from("backups were disabled");
+                    return tx.send(Event::BackedUp(Err(error))).await.unwrap_or(());
+                }
+            };
+
+            let result = match tokio::task::spawn_blocking(move || backup.run()).await {
+                Ok(Ok(path)) => Ok(path),
+                Ok(Err(error)) => Err(format!("{:#}", error)),
+                Err(error) => Err(error.to_string()),
+            };
+            tx.send(Event::BackedUp(result)).await.unwrap_or(());
+        });
+    }
+
+    async fn handle_backed_up(&mut self, result: Result<PathBuf, String>) -> anyhow::Result<()> {
+        self.backup = Backup::Idle;
+        self.console.send("/save-on").await?;
+        if mem::take(&mut self.launch_after_backup) {
+            self.launch().await?;
+        }
+
+        match result {
+            Ok(path) => {
+                tracing::info!("Backed up world to `{}`", path.display());
+                self.upload(path).await;
+            }
+            Err(error) => {
+                tracing::error!("Failed to back up world: {}", error);
+                let channel = id::ChannelId::from(self.settings.verbose_id);
+                let message = format!("Failed to back up world: {}", error);
+                self.say(channel, &message).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Upload a new archive to each remote in turn, in the background.
+    async fn upload(&mut self, archive: PathBuf) {
+        let remotes = match &self.settings.backup {
+            Some(backup) if !backup.remotes.is_empty() => backup.remotes.clone(),
+            _ => return,
+        };
+
+        let name = archive
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let size = std::fs::metadata(&archive)
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+        let message = format!(
+            "Uploading backup `{}` ({}) to {} remote{}...",
+            name,
+            template::size(size),
+            remotes.len(),
+            if remotes.len() == 1 { "" } else { "s" },
+        );
+        self.alert(&message).await;
+
+        let tx = self.tx.clone();
+        tokio::spawn(
+            async move {
+                for remote in remotes {
+                    let start = Instant::now();
+                    let result = remote
+                        .upload(&archive)
+                        .await
+                        .map(|()| start.elapsed())
+                        .map_err(|error| format!("{:#}", error));
+                    let remote = remote.to_string();
+                    tx.send(Event::Uploaded { remote, result })
+                        .await
+                        .unwrap_or(());
+                }
+            }
+            .in_current_span(),
+        );
+    }
+
+    async fn handle_uploaded(&mut self, remote: String, result: Result<Duration, String>) {
+        let message = match result {
+            Ok(elapsed) => {
+                tracing::info!("Uploaded backup to `{}`", remote);
+                format!(
+                    "Uploaded backup to `{}` in {} seconds.",
+                    remote,
+                    elapsed.as_secs()
+                )
+            }
+            Err(error) => {
+                tracing::error!("Failed to upload backup to `{}`: {}", remote, error);
+                format!("⚠️ Failed to upload backup to `{}`: {}", remote, error)
+            }
+        };
+        self.alert(&message).await;
+    }
+
+    /// Ask the server who's online. Over RCON, the response is handled
+    /// immediately; otherwise, it's parsed from the server log.
+    async fn list(&mut self) -> anyhow::Result<()> {
+        match self.console.send("/list").await? {
+            None => self.listing = true,
+            Some(response) => {
+                if let Some((max, players)) = events::parse_list(&response) {
+                    self.reconcile(max, players);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Update who's online from a status ping, falling back to `list` if the
+    /// server only sent some of them.
+    async fn handle_pinged(&mut self, result: Result<ping::Status, String>) -> anyhow::Result<()> {
+        let status = match result {
+            Ok(status) => status,
+            // A server that's starting or stopping isn't expected to answer.
+            Err(error) => {
+                if self.metrics.started.load(Ordering::Relaxed)
+                    && !self.restarting
+                    && !self.stopping
+                {
+                    tracing::warn!("{}", error);
+                    self.metrics.responsive.store(false, Ordering::Relaxed);
+                }
+                return Ok(());
+            }
+        };
+
+        self.metrics.responsive.store(true, Ordering::Relaxed);
+        if self.version.is_none() && !status.version.is_empty() {
+            self.version = Some(status.version.clone());
+        }
+        if status.complete() {
+            self.reconcile(status.max, status.sample);
+        } else if self.settings.query_address.is_some() {
+            self.max_players = Some(status.max);
+            self.query();
+        } else {
+            self.max_players = Some(status.max);
+            self.list().await?;
+        }
+        Ok(())
+    }
+
+    /// Ask for the server's full status in the background.
+    fn query(&self) {
+        let address = match self.settings.query_address.clone() {
+            Some(address) => address,
+            None => return,
+        };
+        let tx = self.tx.clone();
+        tokio::spawn(
+            async move {
+                let result = query::query(&address)
+                    .await
+                    .map_err(|error| format!("{:#}", error));
+                tx.send(Event::Queried(result)).await.unwrap_or(());
+            }
+            .in_current_span(),
+        );
+    }
+
+    /// Update who's online from a query, falling back to `list` if the server
+    /// didn't answer.
+    async fn handle_queried(
+        &mut self,
+        result: Result<query::Status, String>,
+    ) -> anyhow::Result<()> {
+        let status = match result {
+            Ok(status) => status,
+            Err(error) => {
+                if self.metrics.started.load(Ordering::Relaxed)
+                    && !self.restarting
+                    && !self.stopping
+                {
+                    tracing::warn!("{}", error);
+                    self.list().await?;
+                }
+                return Ok(());
+            }
+        };
+
+        if self.version.is_none() && !status.version.is_empty() {
+            self.version = Some(status.version.clone());
+        }
+        self.plugins = status.plugins;
+        self.reconcile(status.max, status.players);
+        Ok(())
+    }
+
+    /// Replace the online players with the server's own list.
+    fn reconcile(&mut self, max: usize, players: Vec<String>) {
+        self.max_players = Some(max);
+        let players = players.into_iter().collect::<BTreeSet<_>>();
+        let joined = players
+            .difference(&self.store.data.online)
+            .cloned()
+            .collect::<Vec<_>>();
+        let quit = self
+            .store
+            .data
+            .online
+            .difference(&players)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for player in &joined {
+            tracing::warn!("{} is online, but their join was missed", player);
+            self.playtime.join(player);
+        }
+        for player in &quit {
+            tracing::warn!("{} is offline, but their quit was missed", player);
+            self.playtime.quit(player, &mut self.store.data);
+        }
+
+        self.backends.retain(|player, _| players.contains(player));
+        self.store.data.online = players;
+        self.save();
+        self.update_presence();
+    }
+
+    /// Names of the online players, with the server each is on behind a
+    /// proxy, and whether they're AFK.
+    fn online(&self) -> String {
+        self.store
+            .data
+            .online
+            .iter()
+            .map(|player| {
+                let mut notes = Vec::new();
+                if let Some(server) = self.backends.get(player) {
+                    notes.push(server.as_str());
+                }
+                if self.is_afk(player) {
+                    notes.push("AFK");
+                }
+                match notes.is_empty() {
+                    true => player.clone(),
+                    false => format!("{} ({})", player, notes.join(", ")),
+                }
+            })
+            .join_with(", ")
+            .to_string()
+    }
+
+    /// How many players are online, and how many of them are AFK, e.g.
+    /// `3 online (1 AFK)`.
+    fn online_count(&self) -> String {
+        let online = &self.store.data.online;
+        match online.iter().filter(|player| self.is_afk(player)).count() {
+            0 => format!("{} online", online.len()),
+            afk => format!("{} online ({} AFK)", online.len(), afk),
+        }
+    }
+
+    /// Note that the player in `log`, if any, isn't AFK.
+    fn active(&mut self, log: &Log) {
+        let player = match log {
+            Log::Join { player }
+            | Log::Switch { player, .. }
+            | Log::Achievement { player, .. }
+            | Log::Death { player, .. }
+            | Log::Message { player, .. }
+            | Log::Sleeping { player }
+            | Log::Activity { player } => player,
+            _ => return,
+        };
+        self.activity
+            .insert(player.to_lowercase(), (Instant::now(), false));
+    }
+
+    /// How long `player` has gone without activity, counting from when the
+    /// bridge first saw them online.
+    fn inactive(&self, player: &str) -> Duration {
+        self.activity
+            .get(&player.to_lowercase())
+            .map(|(last, _)| last.elapsed())
+            .unwrap_or_default()
+    }
+
+    fn is_afk(&self, player: &str) -> bool {
+        let timeout = self.settings.afk.timeout;
+        timeout > 0 && self.inactive(player) >= Duration::from_secs(timeout)
+    }
+
+    /// Start tracking players who were online before the bridge saw them
+    /// join, and post in the status channel about those who've been AFK for
+    /// long, once each time.
+    async fn check_afk(&mut self) {
+        let online = self
+            .store
+            .data
+            .online
+            .iter()
+            .map(|player| player.to_lowercase())
+            .collect::<BTreeSet<_>>();
+        self.activity.retain(|player, _| online.contains(player));
+        for player in online {
+            self.activity
+                .entry(player)
+                .or_insert_with(|| (Instant::now(), false));
+        }
+
+        let announce = self.settings.afk.announce;
+        let channel = match &self.settings.status_channel {
+            Some(status_channel) if announce > 0 && !self.settings.templates.afk.is_empty() => {
+                id::ChannelId::from(status_channel.id)
+            }
+            _ => return,
+        };
+        let players = self.store.data.online.iter().cloned().collect::<Vec<_>>();
+        for player in players {
+            let inactive = self.inactive(&player);
+            if inactive < Duration::from_secs(announce) {
+                continue;
+            }
+            match self.activity.get_mut(&player.to_lowercase()) {
+                Some((_, announced)) if !*announced => *announced = true,
+                _ => continue,
+            }
+            let text = template::render(
+                &self.settings.templates.afk,
+                &[
+                    ("player", &markdown::escape(&player)),
+                    ("time", &template::duration(inactive)),
+                ],
+            );
+            self.post(Outgoing::Text { channel, text }).await;
+        }
+    }
+
+    /// Update everything that shows the online player count, now or once
+    /// their throttles allow it.
+    fn update_presence(&mut self) {
+        self.metrics
+            .online
+            .store(self.store.data.online.len() as u64, Ordering::Relaxed);
+        if let Some(players) = &self.players {
+            players.lock().unwrap().clone_from(&self.store.data.online);
+        }
+        let interval = Duration::from_secs(self.settings.presence.throttle);
+        if self.presence.poll(interval, Event::Presence, &self.tx) {
+            self.set_presence();
+        }
+        if self
+            .status_channel
+            .poll(STATUS_CHANNEL_THROTTLE, Event::StatusChannel, &self.tx)
+        {
+            self.set_status_channel();
+        }
+    }
+
+    fn set_presence(&mut self) {
+        self.presence.updated();
+
+        let shard = match &self.shard {
+            None => return,
+            Some(shard) => shard,
+        };
+
+        if !self.settings.presence.enabled {
+            shard.set_activity(None);
+            return;
+        }
+
+        let activity = self.render_count(&self.settings.presence.format);
+        shard.set_activity(Some(gateway::Activity::playing(&activity)));
+    }
+
+    /// Rename the status channel, if configured and changed. Renames are
+    /// heavily rate limited, so they run in the background.
+    fn set_status_channel(&mut self) {
+        self.status_channel.updated();
+
+        let status_channel = match &self.settings.status_channel {
+            Some(status_channel) if self.settings.discord => status_channel,
+            _ => return,
+        };
+
+        let name = self.render_count(&status_channel.format);
+        if self.status_channel_name.as_ref() == Some(&name) {
+            return;
+        }
+
+        self.status_channel_name = Some(name.clone());
+
+        let channel_id = id::ChannelId::from(status_channel.id);
+        let http = Arc::clone(&self.http);
+        tokio::spawn(
+            async move {
+                if let Err(error) = channel_id
+                    .edit(&http.http, |channel| channel.name(name))
+                    .await
+                {
+                    tracing::warn!("Failed to rename status channel: {}", error);
+                }
+            }
+            .in_current_span(),
+        );
+    }
+
+    /// Show the server's status in the general channel's topic, if changed.
+    /// Topics share the channel rename rate limit, so updates that come too
+    /// soon are skipped until the next scheduled one.
+    fn set_topic(&mut self) {
+        let topic = match &self.settings.topic {
+            Some(topic) if self.settings.discord => topic,
+            _ => return,
+        };
+
+        if !self.topic.ready(STATUS_CHANNEL_THROTTLE) {
+            return;
+        }
+
+        let unknown = || String::from("?");
+        let version = self.version.clone().unwrap_or_else(unknown);
+        let tps = self
+            .tps
+            .map(|tps| format!("{:.1}", tps))
+            .unwrap_or_else(unknown);
+        let uptime = template::duration(self.launched.elapsed().unwrap_or_default());
+        let text = template::render(
+            &self.render_count(&topic.format),
+            &[("version", &version), ("tps", &tps), ("uptime", &uptime)],
+        );
+
+        if self.topic_text.as_ref() == Some(&text) {
+            return;
+        }
+
+        self.topic.updated();
+        self.topic_text = Some(text.clone());
+
+        let channel_id = id::ChannelId::from(self.settings.general_id);
+        let http = Arc::clone(&self.http);
+        tokio::spawn(
+            async move {
+                if let Err(error) = channel_id
+                    .edit(&http.http, |channel| channel.topic(text))
+                    .await
+                {
+                    tracing::warn!("Failed to update channel topic: {}", error);
+                }
+            }
+            .in_current_span(),
+        );
+    }
+
+    /// Substitute the online and maximum player counts, and the latest TPS,
+    /// into `format`.
+    fn render_count(&self, format: &str) -> String {
+        let online = self.store.data.online.len().to_string();
+        let max = self
+            .max_players
+            .map(|max| max.to_string())
+            .unwrap_or_else(|| String::from("?"));
+        let tps = self
+            .tps
+            .map(|tps| format!("{:.1}", tps))
+            .unwrap_or_else(|| String::from("?"));
+        template::render(format, &[("online", &online), ("max", &max), ("tps", &tps)])
+    }
+
+    fn save(&self) {
+        if let Err(error) = self.store.save() {
+            tracing::error!("{:?}", error);
+        }
+    }
+
+    fn reload(&mut self) -> anyhow::Result<()> {
+        let reloaded = self
+            .opt
+            .load()?
+            .into_iter()
+            .find(|settings| settings.name == self.settings.name)
+            .ok_or_else(|| anyhow!("Server is no longer in the config file"))?;
+        self.settings.reload(reloaded);
+        if self
+            .sinks
+            .iter()
+            .map(sink::Sink::config)
+            .ne(self.settings.sinks.iter())
+        {
+            self.sinks = sinks(&self.settings)?;
+        }
+        if self.journal.as_ref().map(journal::Recorder::journal) != self.settings.journal.as_ref() {
+            self.journal = journal(&self.settings)?;
+        }
+        // The player role may have changed, so check everyone again.
+        self.player_roles.clear();
+        Ok(())
+    }
+
+    /// Give the player role to members whose linked account was whitelisted,
+    /// and take it from those who were unwhitelisted or unlinked. Only changes
+    /// since the last sync are sent to Discord, in the background.
+    async fn sync_roles(&mut self) {
+        let role_id = match self.settings.player_role {
+            None => return,
+            Some(role_id) => id::RoleId(role_id),
+        };
+
+        let guild_id = match self.guild_id().await {
+            None => return,
+            Some(guild_id) => guild_id,
+        };
+
+        let whitelist = match minecraft::whitelist(&self.settings.server_directory) {
+            Ok(whitelist) => whitelist,
+            Err(error) => {
+                tracing::error!("{:?}", error);
+                return;
+            }
+        };
+
+        let verified = roles::verified(&self.store.data.links, &whitelist);
+        let mut holders = roles::holders(&self.http, guild_id, role_id).await;
+        holders.extend(mem::replace(&mut self.player_roles, verified.clone()));
+
+        let add = verified.difference(&holders).copied().collect::<Vec<_>>();
+        let remove = holders.difference(&verified).copied().collect::<Vec<_>>();
+        if add.is_empty() && remove.is_empty() {
+            return;
+        }
+
+        let http = Arc::clone(&self.http);
+        tokio::spawn(
+            async move {
+                roles::update(&http, guild_id, role_id, add, remove).await;
+            }
+            .in_current_span(),
+        );
+    }
+
+    /// The guild containing the general channel, if cached.
+    async fn guild_id(&self) -> Option<id::GuildId> {
+        match id::ChannelId::from(self.settings.general_id)
+            .to_channel_cached(&self.http.cache)
+            .await?
+        {
+            channel::Channel::Guild(channel) => Some(channel.guild_id),
+            _ => None,
+        }
+    }
+
+    /// Fetch the chat webhook, if configured, whenever its URL changes.
+    async fn webhook(&mut self) -> anyhow::Result<Option<webhook::Webhook>> {
+        let url = match &self.settings.webhook_url {
+            Some(url) if self.settings.discord => url,
+            _ => return Ok(None),
+        };
+
+        match &self.webhook {
+            Some((cached, webhook)) if cached == url => Ok(Some(webhook.clone())),
+            _ => {
+                let webhook = self.http.http.get_webhook_from_url(url).await?;
+                self.webhook = Some((url.clone(), webhook.clone()));
+                Ok(Some(webhook))
+            }
+        }
+    }
+}
+
+/// Captured output in a code block, shortened to fit in a message.
+fn code_block(lines: &[String]) -> String {
+    // Keep the output from closing the code block early.
+    let output = lines.join("\n").replace("```", "`\u{200B}``");
+    format!(
+        "```\n{}\n```",
+        template::truncate(&output, DISCORD_LIMIT - 10)
+    )
+}
+
+/// Show the result of a server command, in green or red.
+fn result_embed<'a>(
+    embed: &'a mut builder::CreateEmbed,
+    title: &str,
+    result: &Result<String, String>,
+) -> &'a mut builder::CreateEmbed {
+    let (colour, message) = match result {
+        Ok(message) => (utils::Colour::DARK_GREEN, message),
+        Err(error) => (utils::Colour::RED, error),
+    };
+    embed
+        .title(title)
+        .colour(colour)
+        .description(markdown::escape(message))
+}
+
+/// Check that a waypoint's name is short and easy to type.
+fn check_waypoint(name: &str) -> Result<&str, &'static str> {
+    let valid = (1..=32).contains(&name.len())
+        && name
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || char == '_' || char == '-');
+    if valid {
+        Ok(name)
+    } else {
+        Err("Waypoint names are up to 32 letters, digits, `_`, and `-`.")
+    }
+}
+
+/// Where a waypoint is, e.g. `12, 64, -45 in the Overworld`.
+fn describe_waypoint(waypoint: &store::Waypoint) -> String {
+    let [x, y, z] = waypoint.position;
+    format!(
+        "{}, {}, {} in {}",
+        x,
+        y,
+        z,
+        dimension_name(&waypoint.dimension)
+    )
+}
+
+/// Name of a dimension in English, e.g. `the Nether` for `the_nether`.
+fn dimension_name(dimension: &str) -> String {
+    match dimension {
+        "overworld" => String::from("the Overworld"),
+        "the_nether" => String::from("the Nether"),
+        "the_end" => String::from("the End"),
+        other => stats::name(other),
+    }
+}
+
+/// Show a history entry on one line, with a timestamp Discord localizes.
+fn history_line(entry: &history::Entry) -> String {
+    let player = markdown::escape(&entry.player);
+    let message = entry.message.as_deref().unwrap_or_default();
+    let message = markdown::escape(&template::truncate(message, HISTORY_EXCERPT));
+    let event = match entry.kind {
+        history::Kind::Join => format!("{} joined", player),
+        history::Kind::Quit => format!("{} left", player),
+        history::Kind::Chat => format!("<{}> {}", player, message),
+        history::Kind::Death => format!("{} {}", player, message),
+        history::Kind::Advancement => format!("{} made the advancement [{}]", player, message),
+        history::Kind::Command => format!("{} ran /{}", player, message.trim_start_matches('/')),
+    };
+    format!("<t:{}:f> {}", entry.time, event)
+}
+
+/// Start delivering to each of the configured HTTP endpoints.
+fn sinks(settings: &config::Settings) -> anyhow::Result<Vec<sink::Sink>> {
+    settings
+        .sinks
+        .iter()
+        .map(|config| sink::Sink::new(config.clone(), settings.name.clone()))
+        .collect()
+}
+
+/// Start recording to the journal, if configured.
+fn journal(settings: &config::Settings) -> anyhow::Result<Option<journal::Recorder>> {
+    settings
+        .journal
+        .clone()
+        .map(|journal| journal::Recorder::new(journal, settings.name.as_deref()))
+        .transpose()
+}
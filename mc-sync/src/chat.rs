@@ -0,0 +1,30 @@
+use futures::future::BoxFuture;
+use tokio::sync::mpsc;
+
+use crate::events::Event;
+
+/// A secondary chat platform, like Matrix or Slack, that the bridge relays
+/// Minecraft chat and server events to, and messages from. Backends are
+/// compiled in behind features of the same name.
+///
+/// Discord doesn't go through this: the bridge uses it directly, for slash
+/// commands, embeds, webhooks, and everything else the others don't have.
+pub trait ChatBridge: Send + Sync {
+    /// Shown in Minecraft before relayed messages, and in logs, e.g. `Matrix`.
+    fn name(&self) -> &str;
+
+    /// Relay a player's message from Minecraft chat.
+    fn send_message<'a>(
+        &'a self,
+        player: &'a str,
+        message: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    /// Relay a server event, like a join or death, as rendered from its
+    /// template, in Discord's Markdown.
+    fn send_event<'a>(&'a self, text: &'a str) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    /// Receive messages until the connection is lost, sending each to the
+    /// bridge as [`Event::Chat`].
+    fn receive(&self, tx: mpsc::Sender<Event>) -> BoxFuture<'_, anyhow::Result<()>>;
+}
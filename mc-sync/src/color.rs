@@ -0,0 +1,141 @@
+use joinery::JoinableIterator;
+
+/// Remove `§` formatting codes and ANSI escape sequences from a line of server
+/// output.
+pub fn strip(line: &str) -> String {
+    convert(line, |_| None, |_| None)
+}
+
+/// Translate `§` formatting codes to ANSI escape sequences, for a terminal.
+/// ANSI escape sequences are kept as they are.
+pub fn to_terminal(line: &str) -> String {
+    convert(line, terminal, |parameters| Some(parameters.to_owned()))
+}
+
+/// Translate `§` formatting codes and ANSI escape sequences to the few that
+/// Discord shows in `ansi` code blocks: bold, underline, and eight colors.
+pub fn to_discord(line: &str) -> String {
+    convert(line, discord, |parameters| {
+        let mut kept = Vec::new();
+        for parameter in parameters.split(';') {
+            match parameter.parse::<u8>().unwrap_or(0) {
+                parameter @ (0 | 1 | 4 | 30..=37 | 40..=47) => kept.push(parameter),
+                // Bright colors, which Discord doesn't have.
+                parameter @ (90..=97 | 100..=107) => kept.push(parameter - 60),
+                // 256 and 24-bit colors, which take the remaining parameters.
+                38 | 48 => break,
+                _ => (),
+            }
+        }
+        Some(kept.iter().join_with(';').to_string()).filter(|kept| !kept.is_empty())
+    })
+}
+
+/// Replace each `§` code with the SGR parameters from `code`, and each SGR
+/// sequence with the parameters from `sgr`, dropping those without any. Other
+/// escape sequences, like cursor movement, are dropped. Any formatting is
+/// reset at the end, like Minecraft does.
+fn convert(
+    line: &str,
+    code: fn(char) -> Option<&'static str>,
+    sgr: fn(&str) -> Option<String>,
+) -> String {
+    let mut converted = String::with_capacity(line.len());
+    let mut formatted = false;
+    let mut chars = line.chars();
+    while let Some(char) = chars.next() {
+        let parameters = match char {
+            // Hex colors are spelled out as `§x§R§R§G§G§B§B`.
+            '§' => match chars.next() {
+                Some('x') | Some('X') => {
+                    chars.nth(11);
+                    None
+                }
+                Some(char) => code(char.to_ascii_lowercase()).map(String::from),
+                None => None,
+            },
+            '\x1b' => match chars.next() {
+                // Control Sequence Introducer: parameters, then a final byte.
+                Some('[') => {
+                    let mut parameters = String::new();
+                    let end = chars.by_ref().find(|char| match char {
+                        '\x40'..='\x7e' => true,
+                        char => {
+                            parameters.push(*char);
+                            false
+                        }
+                    });
+                    match end {
+                        Some('m') => sgr(&parameters),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            },
+            char => {
+                converted.push(char);
+                continue;
+            }
+        };
+        if let Some(parameters) = parameters {
+            formatted = true;
+            converted.push_str("\x1b[");
+            converted.push_str(&parameters);
+            converted.push('m');
+        }
+    }
+    if formatted {
+        converted.push_str("\x1b[0m");
+    }
+    converted
+}
+
+/// SGR parameters for a `§` code in a terminal.
+fn terminal(code: char) -> Option<&'static str> {
+    let parameters = match code {
+        '0' => "0;30",
+        '1' => "0;34",
+        '2' => "0;32",
+        '3' => "0;36",
+        '4' => "0;31",
+        '5' => "0;35",
+        '6' => "0;33",
+        '7' => "0;37",
+        '8' => "0;90",
+        '9' => "0;94",
+        'a' => "0;92",
+        'b' => "0;96",
+        'c' => "0;91",
+        'd' => "0;95",
+        'e' => "0;93",
+        'f' => "0;97",
+        'k' => "8",
+        'l' => "1",
+        'm' => "9",
+        'n' => "4",
+        'o' => "3",
+        'r' => "0",
+        _ => return None,
+    };
+    Some(parameters)
+}
+
+/// SGR parameters for a `§` code in a Discord `ansi` code block, which has no
+/// bright colors, italics, or strikethrough.
+fn discord(code: char) -> Option<&'static str> {
+    let parameters = match code {
+        '0' | '8' => "0;30",
+        '1' | '9' => "0;34",
+        '2' | 'a' => "0;32",
+        '3' | 'b' => "0;36",
+        '4' | 'c' => "0;31",
+        '5' | 'd' => "0;35",
+        '6' | 'e' => "0;33",
+        '7' | 'f' => "0;37",
+        'l' => "1",
+        'n' => "4",
+        'r' => "0",
+        _ => return None,
+    };
+    Some(parameters)
+}
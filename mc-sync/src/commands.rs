@@ -0,0 +1,784 @@
+use serde_json::Value;
+use serenity::builder;
+use serenity::model::interactions::application_command as command;
+use serenity::model::interactions::message_component;
+
+use crate::bedrock;
+use crate::events::Flavor;
+use crate::permissions::Capability;
+
+/// Discord application (slash) commands understood by the bridge.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Command {
+    /// List the players currently on the server.
+    Online,
+
+    /// Show the server's version, seed, difficulty, and other settings.
+    ServerInfo,
+
+    /// Broadcast a message in Minecraft chat.
+    Say { message: String },
+
+    /// Run a command in the server console.
+    Command { command: String },
+
+    /// Re-read the config file.
+    Reload,
+
+    /// Show a player's total time played, or the leaderboard.
+    Playtime { player: Option<String> },
+
+    /// Show how many times a player has died, or the leaderboard.
+    Deaths { player: Option<String> },
+
+    /// Show when a player was last online, and for how long.
+    Seen { player: String },
+
+    /// Show a player's statistics, as the server counts them.
+    Stats { player: String },
+
+    /// Show a player's advancement progress, or the leaderboard.
+    Advancements { player: Option<String> },
+
+    /// Show where a player was when the server last saved.
+    Whereis { player: String },
+
+    /// Show what a player was carrying when the server last saved.
+    Inventory { player: String },
+
+    /// List the waypoints players have saved in game.
+    Waypoints,
+
+    /// Show the top scores for a published objective, or list them.
+    Scoreboard { objective: Option<String> },
+
+    /// Change or show the server's whitelist.
+    Whitelist { action: Whitelist },
+
+    /// Ban, pardon, or kick a player.
+    Moderate { action: Moderation },
+
+    /// Start linking the user's Discord and Minecraft accounts.
+    Link,
+
+    /// Forget the user's linked Minecraft account.
+    Unlink,
+
+    /// Start, stop, or restart the server.
+    Server { action: Server },
+
+    /// Read or change a setting in `server.properties`.
+    Property { action: Property },
+
+    /// List backups, or restore the world from one.
+    Backup { action: Backup },
+
+    /// Show a player's most recent chat and events.
+    History { player: String, count: u64 },
+
+    /// Find the most recent chat and events containing some text.
+    Search { text: String, count: u64 },
+}
+
+/// Subcommands of `/backup`, where each backup is identified by when it was
+/// taken, e.g. `2021-06-01T12-00-00`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Backup {
+    List,
+    Restore { id: String },
+}
+
+/// Subcommands of `/property`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Property {
+    Get { key: String },
+    Set { key: String, value: String },
+}
+
+/// Subcommands of `/server`, with how many minutes to warn players before
+/// stopping or restarting.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Server {
+    Start,
+    Stop { minutes: u64 },
+    Restart { minutes: u64 },
+}
+
+/// Minutes to warn players before `/server stop` or `/server restart`, by
+/// default, or before restarting from the control panel.
+pub static SERVER_DELAY: u64 = 1;
+
+/// Entries to show from `/history` and `/search`, by default, and at most.
+pub static HISTORY_COUNT: u64 = 10;
+pub static HISTORY_MAX: u64 = 25;
+
+/// Subcommands of `/whitelist`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Whitelist {
+    Add { player: String },
+    Remove { player: String },
+    List,
+}
+
+impl Whitelist {
+    /// The server command to run, or why it can't be run.
+    pub fn command(&self, flavor: Flavor) -> Result<String, &'static str> {
+        match self {
+            Whitelist::Add { player } => {
+                Ok(format!("/whitelist add {}", check_player(player, flavor)?))
+            }
+            Whitelist::Remove { player } => Ok(format!(
+                "/whitelist remove {}",
+                check_player(player, flavor)?
+            )),
+            Whitelist::List => Ok(String::from("/whitelist list")),
+        }
+    }
+}
+
+/// The `/ban`, `/pardon`, and `/kick` commands.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Moderation {
+    Ban {
+        player: String,
+        reason: Option<String>,
+    },
+    Pardon {
+        player: String,
+    },
+    Kick {
+        player: String,
+        reason: Option<String>,
+    },
+}
+
+impl Moderation {
+    /// The server command to run, or why it can't be run.
+    pub fn command(&self, flavor: Flavor) -> Result<String, &'static str> {
+        let (command, player, reason) = match self {
+            Moderation::Ban { player, reason } => ("ban", player, reason.as_deref()),
+            Moderation::Pardon { player } => ("pardon", player, None),
+            Moderation::Kick { player, reason } => ("kick", player, reason.as_deref()),
+        };
+
+        // The reason follows the name, so this can't wait for the Bedrock
+        // translation to quote it.
+        let player = bedrock::target(check_player(player, flavor)?);
+
+        // A line break would let the reason run a second command.
+        match reason {
+            None => Ok(format!("/{} {}", command, player)),
+            Some(reason) if reason.contains(char::is_control) => {
+                Err("The reason can't contain line breaks.")
+            }
+            Some(reason) => Ok(format!("/{} {} {}", command, player, reason)),
+        }
+    }
+}
+
+fn player(
+    option: &mut builder::CreateApplicationCommandOption,
+) -> &mut builder::CreateApplicationCommandOption {
+    option
+        .name("player")
+        .description("Minecraft username")
+        .kind(command::ApplicationCommandOptionType::String)
+        .required(true)
+        .set_autocomplete(true)
+}
+
+fn minutes(
+    option: &mut builder::CreateApplicationCommandOption,
+) -> &mut builder::CreateApplicationCommandOption {
+    option
+        .name("minutes")
+        .description("Minutes to warn players beforehand (default: 1)")
+        .kind(command::ApplicationCommandOptionType::Integer)
+        .min_int_value(0)
+        .max_int_value(60)
+        .required(false)
+}
+
+fn count(
+    option: &mut builder::CreateApplicationCommandOption,
+) -> &mut builder::CreateApplicationCommandOption {
+    option
+        .name("count")
+        .description("Number of entries to show (default: 10)")
+        .kind(command::ApplicationCommandOptionType::Integer)
+        .min_int_value(1)
+        .max_int_value(HISTORY_MAX as i32)
+        .required(false)
+}
+
+fn key(
+    option: &mut builder::CreateApplicationCommandOption,
+) -> &mut builder::CreateApplicationCommandOption {
+    option
+        .name("key")
+        .description("Property name, e.g. `view-distance`")
+        .kind(command::ApplicationCommandOptionType::String)
+        .required(true)
+}
+
+fn reason(
+    option: &mut builder::CreateApplicationCommandOption,
+) -> &mut builder::CreateApplicationCommandOption {
+    option
+        .name("reason")
+        .description("Reason shown to the player")
+        .kind(command::ApplicationCommandOptionType::String)
+        .required(false)
+}
+
+/// Reject anything that isn't a valid Minecraft username, so it can't smuggle
+/// extra arguments into the server command. Bedrock gamertags can also have
+/// spaces between words, which are quoted when the command is translated.
+pub fn check_player(name: &str, flavor: Flavor) -> Result<&str, &'static str> {
+    let space = |char| char == ' ' && flavor == Flavor::Bedrock;
+    let valid = (1..=16).contains(&name.len())
+        && name.trim() == name
+        && !name.contains("  ")
+        && name
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || char == '_' || space(char));
+    if valid {
+        Ok(name)
+    } else {
+        Err("That isn't a valid Minecraft username.")
+    }
+}
+
+impl Command {
+    /// Describe every command to Discord.
+    pub fn register(
+        commands: &mut builder::CreateApplicationCommands,
+    ) -> &mut builder::CreateApplicationCommands {
+        commands
+            .create_application_command(|command| {
+                command
+                    .name("online")
+                    .description("List players currently on the server")
+            })
+            .create_application_command(|command| {
+                command
+                    .name("serverinfo")
+                    .description("Show the server's version, seed, difficulty, and world size")
+            })
+            .create_application_command(|command| {
+                command
+                    .name("say")
+                    .description("Send a message to Minecraft chat")
+                    .create_option(|option| {
+                        option
+                            .name("message")
+                            .description("Message to send")
+                            .kind(command::ApplicationCommandOptionType::String)
+                            .required(true)
+                    })
+            })
+            .create_application_command(|command| {
+                command
+                    .name("command")
+                    .description("Run a command in the server console")
+                    .create_option(|option| {
+                        option
+                            .name("command")
+                            .description("Command to run, e.g. `time set day`")
+                            .kind(command::ApplicationCommandOptionType::String)
+                            .required(true)
+                    })
+            })
+            .create_application_command(|command| {
+                command
+                    .name("reload")
+                    .description("Re-read the bot's config file")
+            })
+            .create_application_command(|command| {
+                command
+                    .name("link")
+                    .description("Link your Minecraft account to your Discord account")
+            })
+            .create_application_command(|command| {
+                command
+                    .name("unlink")
+                    .description("Unlink your Minecraft account")
+            })
+            .create_application_command(|command| {
+                command
+                    .name("playtime")
+                    .description("Show time played on the server")
+                    .create_option(|option| {
+                        option
+                            .name("player")
+                            .description("Player to look up (default: top 10 players)")
+                            .kind(command::ApplicationCommandOptionType::String)
+                            .required(false)
+                            .set_autocomplete(true)
+                    })
+            })
+            .create_application_command(|command| {
+                command
+                    .name("deaths")
+                    .description("Show how many times players have died")
+                    .create_option(|option| {
+                        option
+                            .name("player")
+                            .description("Player to look up (default: top 10 players)")
+                            .kind(command::ApplicationCommandOptionType::String)
+                            .required(false)
+                            .set_autocomplete(true)
+                    })
+            })
+            .create_application_command(|command| {
+                command
+                    .name("seen")
+                    .description("Show when a player was last online, and for how long")
+                    .create_option(player)
+            })
+            .create_application_command(|command| {
+                command
+                    .name("stats")
+                    .description("Show a player's blocks mined, kills, deaths, and more")
+                    .create_option(player)
+            })
+            .create_application_command(|command| {
+                command
+                    .name("whereis")
+                    .description("Show where a player was when the server last saved")
+                    .create_option(player)
+            })
+            .create_application_command(|command| {
+                command
+                    .name("inventory")
+                    .description("Show what a player was carrying when the server last saved")
+                    .create_option(player)
+            })
+            .create_application_command(|command| {
+                command
+                    .name("waypoints")
+                    .description("List the waypoints players have saved in game")
+            })
+            .create_application_command(|command| {
+                command
+                    .name("scoreboard")
+                    .description("Show the top scores for a scoreboard objective")
+                    .create_option(|option| {
+                        option
+                            .name("objective")
+                            .description("Objective to show (default: list them)")
+                            .kind(command::ApplicationCommandOptionType::String)
+                            .required(false)
+                    })
+            })
+            .create_application_command(|command| {
+                command
+                    .name("advancements")
+                    .description("Show advancement progress")
+                    .create_option(|option| {
+                        option
+                            .name("player")
+                            .description("Player to look up (default: top 10 players)")
+                            .kind(command::ApplicationCommandOptionType::String)
+                            .required(false)
+                            .set_autocomplete(true)
+                    })
+            })
+            .create_application_command(|command| {
+                command
+                    .name("whitelist")
+                    .description("Manage the server's whitelist")
+                    .create_option(|option| {
+                        option
+                            .name("add")
+                            .description("Allow a player to join the server")
+                            .kind(command::ApplicationCommandOptionType::SubCommand)
+                            .create_sub_option(player)
+                    })
+                    .create_option(|option| {
+                        option
+                            .name("remove")
+                            .description("Stop a player from joining the server")
+                            .kind(command::ApplicationCommandOptionType::SubCommand)
+                            .create_sub_option(player)
+                    })
+                    .create_option(|option| {
+                        option
+                            .name("list")
+                            .description("List whitelisted players")
+                            .kind(command::ApplicationCommandOptionType::SubCommand)
+                    })
+            })
+            .create_application_command(|command| {
+                command
+                    .name("ban")
+                    .description("Ban a player from the server")
+                    .create_option(player)
+                    .create_option(reason)
+            })
+            .create_application_command(|command| {
+                command
+                    .name("pardon")
+                    .description("Unban a player")
+                    .create_option(player)
+            })
+            .create_application_command(|command| {
+                command
+                    .name("kick")
+                    .description("Disconnect a player from the server")
+                    .create_option(player)
+                    .create_option(reason)
+            })
+            .create_application_command(|command| {
+                command
+                    .name("server")
+                    .description("Start, stop, or restart the server")
+                    .create_option(|option| {
+                        option
+                            .name("start")
+                            .description("Start the server after it was stopped")
+                            .kind(command::ApplicationCommandOptionType::SubCommand)
+                    })
+                    .create_option(|option| {
+                        option
+                            .name("stop")
+                            .description("Warn players, then stop the server")
+                            .kind(command::ApplicationCommandOptionType::SubCommand)
+                            .create_sub_option(minutes)
+                    })
+                    .create_option(|option| {
+                        option
+                            .name("restart")
+                            .description("Warn players, then restart the server")
+                            .kind(command::ApplicationCommandOptionType::SubCommand)
+                            .create_sub_option(minutes)
+                    })
+            })
+            .create_application_command(|command| {
+                command
+                    .name("property")
+                    .description("Read or change a setting in server.properties")
+                    .create_option(|option| {
+                        option
+                            .name("get")
+                            .description("Show a setting's value")
+                            .kind(command::ApplicationCommandOptionType::SubCommand)
+                            .create_sub_option(key)
+                    })
+                    .create_option(|option| {
+                        option
+                            .name("set")
+                            .description("Change a setting, taking effect after a restart")
+                            .kind(command::ApplicationCommandOptionType::SubCommand)
+                            .create_sub_option(key)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("value")
+                                    .description("New value")
+                                    .kind(command::ApplicationCommandOptionType::String)
+                                    .required(true)
+                            })
+                    })
+            })
+            .create_application_command(|command| {
+                command
+                    .name("backup")
+                    .description("List world backups, or restore one")
+                    .create_option(|option| {
+                        option
+                            .name("list")
+                            .description("List the most recent backups")
+                            .kind(command::ApplicationCommandOptionType::SubCommand)
+                    })
+                    .create_option(|option| {
+                        option
+                            .name("restore")
+                            .description(
+                                "Stop the server, restore the world from a backup, and restart",
+                            )
+                            .kind(command::ApplicationCommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("id")
+                                    .description("Backup to restore, from `/backup list`")
+                                    .kind(command::ApplicationCommandOptionType::String)
+                                    .required(true)
+                            })
+                    })
+            })
+            .create_application_command(|command| {
+                command
+                    .name("history")
+                    .description("Show a player's recent chat and events")
+                    .create_option(player)
+                    .create_option(count)
+            })
+            .create_application_command(|command| {
+                command
+                    .name("search")
+                    .description("Find recent chat and events containing some text")
+                    .create_option(|option| {
+                        option
+                            .name("text")
+                            .description("Text to look for, ignoring case")
+                            .kind(command::ApplicationCommandOptionType::String)
+                            .required(true)
+                    })
+                    .create_option(count)
+            })
+    }
+
+    pub fn parse(data: &command::ApplicationCommandInteractionData) -> Option<Self> {
+        let string = |options: &[command::ApplicationCommandInteractionDataOption], name: &str| {
+            options
+                .iter()
+                .find(|option| option.name == name)
+                .and_then(|option| option.value.as_ref())
+                .and_then(Value::as_str)
+                .map(String::from)
+        };
+
+        let count = data
+            .options
+            .iter()
+            .find(|option| option.name == "count")
+            .and_then(|option| option.value.as_ref())
+            .and_then(Value::as_u64)
+            .unwrap_or(HISTORY_COUNT)
+            .clamp(1, HISTORY_MAX);
+
+        match data.name.as_str() {
+            "online" => Some(Command::Online),
+            "serverinfo" => Some(Command::ServerInfo),
+            "say" => string(&data.options, "message").map(|message| Command::Say { message }),
+            "command" => {
+                string(&data.options, "command").map(|command| Command::Command { command })
+            }
+            "reload" => Some(Command::Reload),
+            "link" => Some(Command::Link),
+            "unlink" => Some(Command::Unlink),
+            "playtime" => Some(Command::Playtime {
+                player: string(&data.options, "player"),
+            }),
+            "deaths" => Some(Command::Deaths {
+                player: string(&data.options, "player"),
+            }),
+            "seen" => Some(Command::Seen {
+                player: string(&data.options, "player")?,
+            }),
+            "stats" => Some(Command::Stats {
+                player: string(&data.options, "player")?,
+            }),
+            "advancements" => Some(Command::Advancements {
+                player: string(&data.options, "player"),
+            }),
+            "waypoints" => Some(Command::Waypoints),
+            "scoreboard" => Some(Command::Scoreboard {
+                objective: string(&data.options, "objective"),
+            }),
+            "whereis" => Some(Command::Whereis {
+                player: string(&data.options, "player")?,
+            }),
+            "inventory" => Some(Command::Inventory {
+                player: string(&data.options, "player")?,
+            }),
+            "history" => Some(Command::History {
+                player: string(&data.options, "player")?,
+                count,
+            }),
+            "search" => Some(Command::Search {
+                text: string(&data.options, "text")?,
+                count,
+            }),
+            "whitelist" => {
+                let subcommand = data.options.first()?;
+                let player = || string(&subcommand.options, "player");
+                let action = match subcommand.name.as_str() {
+                    "add" => Whitelist::Add { player: player()? },
+                    "remove" => Whitelist::Remove { player: player()? },
+                    "list" => Whitelist::List,
+                    _ => return None,
+                };
+                Some(Command::Whitelist { action })
+            }
+            "ban" | "pardon" | "kick" => {
+                let player = string(&data.options, "player")?;
+                let reason = string(&data.options, "reason");
+                let action = match data.name.as_str() {
+                    "ban" => Moderation::Ban { player, reason },
+                    "pardon" => Moderation::Pardon { player },
+                    _ => Moderation::Kick { player, reason },
+                };
+                Some(Command::Moderate { action })
+            }
+            "property" => {
+                let subcommand = data.options.first()?;
+                let key = string(&subcommand.options, "key")?;
+                let action = match subcommand.name.as_str() {
+                    "get" => Property::Get { key },
+                    "set" => Property::Set {
+                        key,
+                        value: string(&subcommand.options, "value")?,
+                    },
+                    _ => return None,
+                };
+                Some(Command::Property { action })
+            }
+            "backup" => {
+                let subcommand = data.options.first()?;
+                let action = match subcommand.name.as_str() {
+                    "list" => Backup::List,
+                    "restore" => Backup::Restore {
+                        id: string(&subcommand.options, "id")?,
+                    },
+                    _ => return None,
+                };
+                Some(Command::Backup { action })
+            }
+            "server" => {
+                let subcommand = data.options.first()?;
+                let minutes = subcommand
+                    .options
+                    .iter()
+                    .find(|option| option.name == "minutes")
+                    .and_then(|option| option.value.as_ref())
+                    .and_then(Value::as_u64)
+                    .unwrap_or(SERVER_DELAY);
+                let action = match subcommand.name.as_str() {
+                    "start" => Server::Start,
+                    "stop" => Server::Stop { minutes },
+                    "restart" => Server::Restart { minutes },
+                    _ => return None,
+                };
+                Some(Command::Server { action })
+            }
+            _ => None,
+        }
+    }
+
+    /// What the user has typed so far into the option they're filling in, if
+    /// it's a player name.
+    pub fn partial_player(data: &command::ApplicationCommandInteractionData) -> Option<&str> {
+        let mut options = &data.options;
+        loop {
+            match options.iter().find(|option| option.focused) {
+                Some(option) if option.name == "player" => {
+                    return option.value.as_ref().and_then(Value::as_str);
+                }
+                Some(_) => return None,
+                // Look inside subcommands, like `/whitelist remove`.
+                None => options = &options.first()?.options,
+            }
+        }
+    }
+
+    /// What the user needs to be allowed to do to run this command.
+    pub fn capability(&self) -> Capability {
+        match self {
+            Command::Online
+            | Command::ServerInfo
+            | Command::Playtime { .. }
+            | Command::Deaths { .. }
+            | Command::Seen { .. }
+            | Command::Stats { .. }
+            | Command::Advancements { .. }
+            | Command::Scoreboard { .. }
+            | Command::Waypoints => Capability::Online,
+            Command::Say { .. } | Command::Link | Command::Unlink => Capability::Chat,
+            Command::Command { .. } | Command::Property { .. } => Capability::Console,
+            Command::Reload => Capability::Reload,
+            Command::History { .. } | Command::Search { .. } => Capability::History,
+            Command::Whereis { .. } | Command::Inventory { .. } => Capability::Inspect,
+            Command::Whitelist { .. } => Capability::Whitelist,
+            Command::Moderate { .. } => Capability::Moderate,
+            Command::Server { .. } => Capability::Restart,
+            Command::Backup {
+                action: Backup::List,
+            } => Capability::Backup,
+            Command::Backup {
+                action: Backup::Restore { .. },
+            } => Capability::Restore,
+        }
+    }
+}
+
+/// Buttons on the control panel.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Button {
+    /// Warn players, then restart the server.
+    Restart,
+
+    /// Back up the world now.
+    Backup,
+
+    /// List whitelisted players.
+    Whitelist,
+
+    /// List the players currently on the server.
+    Players,
+}
+
+impl Button {
+    const ALL: [Button; 4] = [
+        Button::Restart,
+        Button::Backup,
+        Button::Whitelist,
+        Button::Players,
+    ];
+
+    /// Add a row with every button to the control panel message.
+    pub fn panel(components: &mut builder::CreateComponents) -> &mut builder::CreateComponents {
+        components.create_action_row(|row| {
+            for button in Self::ALL.iter() {
+                row.create_button(|create| {
+                    create
+                        .custom_id(button.id())
+                        .label(button.label())
+                        .style(match button {
+                            Button::Restart => message_component::ButtonStyle::Danger,
+                            Button::Backup => message_component::ButtonStyle::Primary,
+                            Button::Whitelist | Button::Players => {
+                                message_component::ButtonStyle::Secondary
+                            }
+                        })
+                });
+            }
+            row
+        })
+    }
+
+    pub fn parse(data: &message_component::MessageComponentInteractionData) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|button| button.id() == data.custom_id)
+    }
+
+    fn id(&self) -> &'static str {
+        match self {
+            Button::Restart => "panel-restart",
+            Button::Backup => "panel-backup",
+            Button::Whitelist => "panel-whitelist",
+            Button::Players => "panel-players",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Button::Restart => "Restart",
+            Button::Backup => "Backup now",
+            Button::Whitelist => "Whitelist",
+            Button::Players => "Players",
+        }
+    }
+
+    /// What the user needs to be allowed to do to press this button.
+    pub fn capability(&self) -> Capability {
+        match self {
+            Button::Restart => Capability::Restart,
+            Button::Backup => Capability::Backup,
+            Button::Whitelist => Capability::Whitelist,
+            Button::Players => Capability::Online,
+        }
+    }
+}
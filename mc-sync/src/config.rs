@@ -0,0 +1,1663 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::Context as _;
+use joinery::JoinableIterator;
+use regex::Regex;
+use serde::Deserialize;
+use structopt::StructOpt;
+use tracing::level_filters::LevelFilter;
+
+use crate::api;
+use crate::backup;
+use crate::events;
+use crate::journal;
+use crate::locale;
+use crate::logging;
+use crate::permissions;
+use crate::schedule::Schedule;
+use crate::sink;
+use crate::tag;
+use crate::template::Templates;
+use crate::usage;
+
+/// Wrap a Minecraft server and synchronize the chat with Discord.
+#[derive(Clone, Debug, Default, StructOpt)]
+pub struct Opt {
+    /// TOML file to read settings from (overridden by flags and environment variables)
+    #[structopt(long, env = "MC_SYNC_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Discord bot application token (optional if Matrix or Slack is configured)
+    #[structopt(long, env = "DISCORD_TOKEN")]
+    pub token: Option<String>,
+
+    /// Forward interesting server events
+    #[structopt(long, env = "DISCORD_GENERAL_CHANNEL_ID")]
+    pub general_id: Option<u64>,
+
+    /// Forward all server logs
+    #[structopt(long, env = "DISCORD_VERBOSE_CHANNEL_ID")]
+    pub verbose_id: Option<u64>,
+
+    /// Mirror the server console, and accept commands from authorized users
+    #[structopt(long, env = "DISCORD_CONSOLE_CHANNEL_ID")]
+    pub console_id: Option<u64>,
+
+    /// Record moderation commands run from Discord
+    #[structopt(long, env = "DISCORD_AUDIT_CHANNEL_ID")]
+    pub audit_id: Option<u64>,
+
+    /// Report errors the bot runs into (default: verbose channel)
+    #[structopt(long, env = "DISCORD_ERROR_CHANNEL_ID")]
+    pub error_id: Option<u64>,
+
+    /// Post a pinned control panel with buttons for common admin actions
+    #[structopt(long, env = "DISCORD_PANEL_CHANNEL_ID")]
+    pub panel_id: Option<u64>,
+
+    /// Relay Minecraft chat through this webhook, using player names and skins
+    #[structopt(long, env = "DISCORD_WEBHOOK_URL")]
+    pub webhook_url: Option<String>,
+
+    /// Shutdown port
+    #[structopt(long, env = "MINECRAFT_SERVER_PORT")]
+    pub server_port: Option<u16>,
+
+    /// Attach to a running server over RCON instead of launching one
+    #[structopt(long, env = "MINECRAFT_RCON_ADDRESS")]
+    pub rcon_address: Option<String>,
+
+    /// RCON password, from `rcon.password` in server.properties
+    #[structopt(long, env = "MINECRAFT_RCON_PASSWORD")]
+    pub rcon_password: Option<String>,
+
+    /// Follow the server's `logs/latest.log` for its output in RCON mode
+    #[structopt(long)]
+    pub tail_log: bool,
+
+    /// Language the server logs in, if it's translated, e.g. `de_de`
+    #[structopt(long, env = "MINECRAFT_SERVER_LOCALE")]
+    pub server_locale: Option<String>,
+
+    /// Log level: `error`, `warn`, `info`, `debug`, `trace`, or `off`
+    #[structopt(long, env = "MC_SYNC_LOG")]
+    pub log_level: Option<String>,
+
+    /// Path to Minecraft server.jar or script
+    pub command: Option<String>,
+}
+
+impl Opt {
+    /// Merge command-line arguments with the config file, if any, for each
+    /// server to bridge. The first server is the primary one.
+    pub fn load(&self) -> anyhow::Result<Vec<Settings>> {
+        let configs = match &self.config {
+            None => vec![Config::default()],
+            Some(path) => Config::read(path)?,
+        };
+
+        if configs.len() > 1 {
+            let mut names = BTreeSet::new();
+            for config in &configs {
+                let name = config
+                    .name
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("Each of `servers` needs a `name`"))?;
+                if !names.insert(name) {
+                    return Err(anyhow!("Duplicate server name `{}`", name));
+                }
+            }
+        }
+
+        let mut settings = configs
+            .into_iter()
+            .map(|config| self.merge(config))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        // The Discord connection is shared, so it's all or nothing.
+        if settings
+            .iter()
+            .any(|other| other.discord != settings[0].discord)
+        {
+            return Err(anyhow!(
+                "Either every server or none of them can use Discord"
+            ));
+        }
+
+        // Each server takes messages and commands from its own channels.
+        let claimed = settings.iter().map(Settings::channels).collect::<Vec<_>>();
+        for (index, settings) in settings.iter_mut().enumerate() {
+            settings.primary = index == 0;
+            settings.others = claimed
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != index)
+                .flat_map(|(_, channels)| channels.iter().copied())
+                .collect();
+        }
+
+        Ok(settings)
+    }
+
+    fn merge(&self, config: Config) -> anyhow::Result<Settings> {
+        let data_file = match &config.name {
+            None => PathBuf::from("mc-sync.json"),
+            Some(name) => PathBuf::from(format!("mc-sync-{}.json", name)),
+        };
+        let flavor = config.log_flavor;
+
+        // Discord can be left out entirely when another platform stands in.
+        let token = self.token.clone().or(config.token);
+        let discord = token.is_some() || config.matrix.is_none() && config.slack.is_none();
+        let general_id = self.general_id.or(config.general_id);
+        let verbose_id = self.verbose_id.or(config.verbose_id);
+
+        Ok(Settings {
+            name: config.name,
+            primary: true,
+            others: BTreeSet::new(),
+            discord,
+            token: required_if(discord, "token", token)?,
+            general_id: required_if(discord, "general-id", general_id)?,
+            verbose_id: required_if(discord, "verbose-id", verbose_id)?,
+            console_id: self.console_id.or(config.console_id),
+            audit_id: self.audit_id.or(config.audit_id),
+            error_id: self.error_id.or(config.error_id),
+            panel_id: self.panel_id.or(config.panel_id),
+            routes: config
+                .routes
+                .into_iter()
+                .map(|(channel, route)| {
+                    channel
+                        .parse::<u64>()
+                        .map(|channel| (channel, route))
+                        .with_context(|| format!("Invalid channel ID `{}` in `routes`", channel))
+                })
+                .collect::<anyhow::Result<_>>()?,
+            player_role: config.player_role,
+            enforce_membership: config.enforce_membership,
+            permissions: config.permissions.load()?,
+            webhook_url: self.webhook_url.clone().or(config.webhook_url),
+            avatar_url: config
+                .avatar_url
+                .unwrap_or_else(|| String::from(AVATAR_URL)),
+            server_port: required("server-port", self.server_port.or(config.server_port))?,
+            mode: match self.rcon_address.clone().or(config.rcon_address) {
+                Some(address) => Mode::Rcon {
+                    address,
+                    password: required(
+                        "rcon-password",
+                        self.rcon_password.clone().or(config.rcon_password),
+                    )?,
+                    tail_log: self.tail_log || config.tail_log,
+                },
+                None => Mode::Spawn {
+                    command: required("command", self.command.clone().or(config.command))?,
+                },
+            },
+            server_directory: config
+                .server_directory
+                .unwrap_or_else(|| PathBuf::from(".")),
+            data_file: config.data_file.unwrap_or(data_file),
+            history_file: config.history_file,
+            crash: config.crash,
+            tellraw: config.tellraw,
+            presence: config.presence,
+            advancements: config.advancements,
+            announce: config.announce,
+            reconnects: config.reconnects,
+            afk: config.afk,
+            scoreboard: config.scoreboard,
+            in_game: config.in_game.load()?,
+            embeds: config.embeds,
+            emoji: config.emoji,
+            threads: config.threads,
+            ignore: config.ignore,
+            offline: config.offline,
+            verbose: config.verbose.load()?,
+            federate: config.federate,
+            hide_seed: config.hide_seed,
+            console_colors: config.console_colors,
+            mirror_tags: config.mirror_tags.map(tag::load).transpose()?,
+            http_address: config.http_address,
+            ping_address: config.ping_address,
+            query_address: config.query_address,
+            log_level: logging::level(
+                self.log_level
+                    .as_deref()
+                    .or(config.logging.level.as_deref())
+                    .unwrap_or("info"),
+            )?,
+            log_format: config.logging.format,
+            death_milestone: config.death_milestone.filter(|milestone| *milestone > 0),
+            status_channel: config.status_channel,
+            topic: config.topic.map(TopicConfig::load).transpose()?,
+            lag: config.lag.map(|lag| lag.load(flavor)).transpose()?,
+            resources: config.resources.map(ResourcesConfig::load).transpose()?,
+            watchdog: config.watchdog.map(WatchdogConfig::load).transpose()?,
+            announcements: config
+                .announcements
+                .into_iter()
+                .map(AnnouncementConfig::load)
+                .collect::<anyhow::Result<_>>()?,
+            matrix: config.matrix.map(Matrix::load).transpose()?,
+            slack: config.slack.map(Slack::load).transpose()?,
+            mqtt: config.mqtt.map(Mqtt::load).transpose()?,
+            api: config.api,
+            rcon_server: config.rcon_server,
+            sinks: config
+                .sinks
+                .into_iter()
+                .map(Sink::load)
+                .collect::<anyhow::Result<_>>()?,
+            list_schedule: Schedule::parse(
+                config.list_schedule.as_deref().unwrap_or(LIST_SCHEDULE),
+            )?,
+            restart_schedule: config
+                .restart_schedule
+                .as_deref()
+                .map(Schedule::parse)
+                .transpose()?,
+            backup: config.backup.map(BackupConfig::load).transpose()?,
+            journal: config.journal.map(JournalConfig::load).transpose()?,
+            flavor: config.log_flavor,
+            proxy_broadcast: config.proxy_broadcast,
+            parser: events::Parser::new(
+                config.log_flavor,
+                locale::Locale::parse(
+                    self.server_locale
+                        .as_deref()
+                        .or(config.server_locale.as_deref())
+                        .unwrap_or("en_us"),
+                )?,
+                config.regex,
+                config.rules,
+            )?,
+            templates: config.templates,
+        })
+    }
+}
+
+fn required<T>(name: &str, value: Option<T>) -> anyhow::Result<T> {
+    value.ok_or_else(|| {
+        anyhow!(
+            "Missing setting `{}`: pass it as an argument or add it to the config file",
+            name
+        )
+    })
+}
+
+/// Like `required`, but defaults to nothing unless `condition` holds.
+fn required_if<T: Default>(condition: bool, name: &str, value: Option<T>) -> anyhow::Result<T> {
+    match condition {
+        true => required(name, value),
+        false => Ok(value.unwrap_or_default()),
+    }
+}
+
+static AVATAR_URL: &str = "https://mc-heads.net/avatar/{player}";
+
+static LIST_SCHEDULE: &str = "*/5 * * * *";
+
+/// Settings read from a TOML file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Identifies the server when bridging more than one.
+    pub name: Option<String>,
+    pub token: Option<String>,
+    pub general_id: Option<u64>,
+    pub verbose_id: Option<u64>,
+    pub console_id: Option<u64>,
+    pub audit_id: Option<u64>,
+    pub error_id: Option<u64>,
+    pub panel_id: Option<u64>,
+    pub routes: BTreeMap<String, Route>,
+    pub player_role: Option<u64>,
+    pub enforce_membership: bool,
+    pub webhook_url: Option<String>,
+    pub avatar_url: Option<String>,
+    pub server_port: Option<u16>,
+    pub rcon_address: Option<String>,
+    pub rcon_password: Option<String>,
+    pub tail_log: bool,
+    pub command: Option<String>,
+    pub server_directory: Option<PathBuf>,
+    pub data_file: Option<PathBuf>,
+    pub history_file: Option<PathBuf>,
+    pub crash: Crash,
+    pub tellraw: Tellraw,
+    pub presence: Presence,
+    pub advancements: Advancements,
+    pub announce: Announce,
+    pub reconnects: Reconnects,
+    pub afk: Afk,
+    pub scoreboard: Scoreboard,
+    pub in_game: InGame,
+    pub embeds: Embeds,
+    pub emoji: Emoji,
+    pub threads: Threads,
+    pub ignore: Ignore,
+    pub offline: Offline,
+    pub verbose: VerboseConfig,
+    pub federate: bool,
+    pub hide_seed: bool,
+    pub console_colors: bool,
+    pub mirror_tags: Option<String>,
+    pub http_address: Option<String>,
+    pub ping_address: Option<String>,
+    pub query_address: Option<String>,
+    pub logging: LoggingConfig,
+    pub death_milestone: Option<u64>,
+    pub status_channel: Option<StatusChannel>,
+    pub topic: Option<TopicConfig>,
+    pub lag: Option<LagConfig>,
+    pub resources: Option<ResourcesConfig>,
+    pub watchdog: Option<WatchdogConfig>,
+    pub announcements: Vec<AnnouncementConfig>,
+    pub matrix: Option<Matrix>,
+    pub slack: Option<Slack>,
+    pub sinks: Vec<Sink>,
+    pub mqtt: Option<Mqtt>,
+    pub api: Option<Api>,
+    pub rcon_server: Option<RconServer>,
+    pub list_schedule: Option<String>,
+    pub restart_schedule: Option<String>,
+    pub backup: Option<BackupConfig>,
+    pub journal: Option<JournalConfig>,
+    pub log_flavor: events::Flavor,
+    pub server_locale: Option<String>,
+    pub proxy_broadcast: Option<String>,
+    pub permissions: permissions::PermissionsConfig,
+    pub regex: Patterns,
+    pub rules: Vec<Rule>,
+    pub templates: Templates,
+}
+
+impl Config {
+    /// Read the config for each server. Settings in a `[[servers]]` table
+    /// replace the shared settings at the top of the file.
+    pub fn read(path: &Path) -> anyhow::Result<Vec<Self>> {
+        let config = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file `{}`", path.display()))?;
+        let parse = || format!("Failed to parse config file `{}`", path.display());
+
+        let mut shared = toml::from_str::<toml::value::Table>(&config).with_context(parse)?;
+        let servers = match shared.remove("servers") {
+            None => vec![toml::value::Table::new()],
+            Some(servers) => servers
+                .try_into::<Vec<toml::value::Table>>()
+                .with_context(parse)?,
+        };
+
+        if servers.is_empty() {
+            return Err(anyhow!("No servers in config file `{}`", path.display()));
+        }
+
+        servers
+            .into_iter()
+            .map(|server| {
+                let mut config = shared.clone();
+                config.extend(server);
+                toml::Value::Table(config).try_into().with_context(parse)
+            })
+            .collect()
+    }
+}
+
+/// Overrides for the server log patterns.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Patterns {
+    pub join: Option<String>,
+    pub quit: Option<String>,
+    pub achievement: Option<String>,
+    pub death: Option<String>,
+    pub message: Option<String>,
+    pub saved: Option<String>,
+    pub list: Option<String>,
+    pub seed: Option<String>,
+    pub difficulty: Option<String>,
+    pub entity_data: Option<String>,
+    pub version: Option<String>,
+    pub done: Option<String>,
+    pub stopping: Option<String>,
+    pub sleeping: Option<String>,
+    pub night_skipped: Option<String>,
+    pub weather: Option<String>,
+    pub tps: Option<String>,
+    pub gametime: Option<String>,
+    pub whitelist: Option<String>,
+    pub moderation: Option<String>,
+    pub switch: Option<String>,
+    pub activity: Option<String>,
+}
+
+/// Extra log line to relay to Discord.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    pub pattern: String,
+    pub message: String,
+    #[serde(default)]
+    pub channel: Option<u64>,
+}
+
+/// What to do when the server exits without being stopped.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Crash {
+    /// Relaunch the server, waiting longer after each consecutive crash.
+    pub restart: bool,
+
+    /// Upload the server's crash report.
+    pub upload_report: bool,
+
+    /// Upload this many lines from the end of `logs/latest.log`.
+    pub upload_log: Option<usize>,
+
+    /// Channel to upload to, instead of the error channel.
+    pub channel: Option<u64>,
+}
+
+/// How Discord chat is shown in Minecraft.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Tellraw {
+    /// Use `tellraw` instead of `say`, which prefixes every message with `[Server]`.
+    pub enabled: bool,
+
+    /// Color of the Discord username, by name (e.g. `aqua`) or as `#RRGGBB`.
+    pub color: String,
+
+    /// Show the Discord username in bold.
+    pub bold: bool,
+
+    /// Show links by their file or host name instead of the full URL. Links
+    /// are clickable either way.
+    pub shorten_urls: bool,
+}
+
+impl Default for Tellraw {
+    fn default() -> Self {
+        Tellraw {
+            enabled: true,
+            color: String::from("aqua"),
+            bold: false,
+            shorten_urls: false,
+        }
+    }
+}
+
+/// The bot's Discord status, showing how many players are online.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Presence {
+    pub enabled: bool,
+
+    /// Activity text, with `{online}` and `{max}` players, and `{tps}`.
+    pub format: String,
+
+    /// Minimum seconds between updates.
+    pub throttle: u64,
+}
+
+impl Default for Presence {
+    fn default() -> Self {
+        Presence {
+            enabled: true,
+            format: String::from("{online}/{max} online"),
+            throttle: 15,
+        }
+    }
+}
+
+/// The bot's own log messages, written to stderr.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// `error`, `warn`, `info` (the default), `debug`, `trace`, or `off`.
+    pub level: Option<String>,
+    pub format: logging::Format,
+}
+
+/// Which server output is mirrored to the verbose channel, and how it's
+/// batched to stay clear of Discord's rate limits.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VerboseConfig {
+    /// Seconds to collect lines before posting them. `0` posts each line as
+    /// soon as it's logged.
+    pub interval: u64,
+
+    /// Post early once this many lines are collected.
+    pub lines: usize,
+
+    /// Only mirror lines matching one of these patterns, if any are given.
+    pub include: Vec<String>,
+
+    /// Never mirror lines matching one of these patterns.
+    pub exclude: Vec<String>,
+}
+
+impl Default for VerboseConfig {
+    fn default() -> Self {
+        VerboseConfig {
+            interval: 2,
+            lines: 20,
+            include: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+}
+
+impl VerboseConfig {
+    fn load(self) -> anyhow::Result<Verbose> {
+        let compile = |section: &str, patterns: Vec<String>| {
+            patterns
+                .into_iter()
+                .map(|pattern| {
+                    Regex::new(&pattern)
+                        .with_context(|| format!("Failed to compile `verbose.{}` pattern", section))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()
+        };
+
+        Ok(Verbose {
+            interval: self.interval,
+            lines: self.lines,
+            include: compile("include", self.include)?,
+            exclude: compile("exclude", self.exclude)?,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Verbose {
+    pub interval: u64,
+    pub lines: usize,
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+impl Verbose {
+    /// Whether `line` should be mirrored to the verbose channel.
+    pub fn mirrors(&self, line: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|regex| regex.is_match(line)))
+            && !self.exclude.iter().any(|regex| regex.is_match(line))
+    }
+}
+
+/// How advancements are announced in Discord.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Advancements {
+    /// Post an embed with the advancement's description, instead of the
+    /// `advancement` template.
+    pub embed: bool,
+
+    /// Thumbnail for the embed, with `{item}` replaced by the advancement's
+    /// icon, e.g. `diamond`. Only vanilla advancements have icons.
+    pub icon_url: Option<String>,
+}
+
+impl Default for Advancements {
+    fn default() -> Self {
+        Advancements {
+            embed: true,
+            icon_url: None,
+        }
+    }
+}
+
+/// Keep players with unstable connections from flooding Discord with joins
+/// and quits.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Reconnects {
+    /// Seconds to hold each quit announcement, dropping it (and the join)
+    /// if the player rejoins in the meantime. Quits are announced right away
+    /// if 0.
+    pub delay: u64,
+    /// Reconnects in a row before announcing `unstable`, once.
+    pub unstable: u32,
+    /// Seconds between reconnects for them to count as in a row.
+    pub window: u64,
+}
+
+impl Default for Reconnects {
+    fn default() -> Self {
+        Reconnects {
+            delay: 0,
+            unstable: 3,
+            window: 10 * 60,
+        }
+    }
+}
+
+/// Mark players AFK when the log shows nothing from them for a while: no
+/// chat, commands, deaths, advancements, or sleeping.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Afk {
+    /// Seconds without activity before a player is AFK, or never if 0.
+    pub timeout: u64,
+    /// Seconds without activity before posting the `afk` template in the
+    /// status channel, once, or never if 0.
+    pub announce: u64,
+}
+
+/// Scoreboard objectives to publish with `/scoreboard`.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Scoreboard {
+    /// Names of the objectives, as in the `/scoreboard` command in game.
+    pub objectives: Vec<String>,
+}
+
+/// Commands players type in Minecraft chat, like `!online`, which the
+/// bridge answers privately instead of relaying.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct InGame {
+    /// Start of each command. `!link` always works, with this prefix.
+    pub prefix: String,
+    /// Answer the commands besides `link`.
+    pub enabled: bool,
+    /// Invite link for `!discord`, which isn't available if unset.
+    pub discord: Option<String>,
+    /// Extra commands, each answered with its text: `{player}`.
+    pub replies: BTreeMap<String, String>,
+}
+
+impl Default for InGame {
+    fn default() -> Self {
+        InGame {
+            prefix: String::from("!"),
+            enabled: true,
+            discord: None,
+            replies: BTreeMap::new(),
+        }
+    }
+}
+
+impl InGame {
+    fn load(self) -> anyhow::Result<Self> {
+        if self.prefix.is_empty() || self.prefix.contains(char::is_whitespace) {
+            return Err(anyhow!("`in-game.prefix` can't be empty or contain spaces"));
+        }
+        if let Some(name) = self
+            .replies
+            .keys()
+            .find(|name| name.is_empty() || name.contains(char::is_whitespace))
+        {
+            return Err(anyhow!("Invalid command `{}` in `in-game.replies`", name));
+        }
+        Ok(self)
+    }
+}
+
+/// Which world events to announce in Discord. Players sleeping and weather
+/// changes can be frequent, so they're off by default.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Announce {
+    pub sleeping: bool,
+    pub night_skipped: bool,
+    pub weather: bool,
+}
+
+impl Default for Announce {
+    fn default() -> Self {
+        Announce {
+            sleeping: false,
+            night_skipped: true,
+            weather: false,
+        }
+    }
+}
+
+/// Relay chat, joins, quits, and deaths to Discord as colored embeds.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Embeds {
+    pub enabled: bool,
+    pub join: EmbedStyle,
+    pub quit: EmbedStyle,
+    pub death: EmbedStyle,
+    pub chat: EmbedStyle,
+}
+
+impl Default for Embeds {
+    fn default() -> Self {
+        let style = |color, emoji: &str| EmbedStyle {
+            color,
+            emoji: String::from(emoji),
+        };
+        Embeds {
+            enabled: false,
+            join: style(0x55FF55, "📥"),
+            quit: style(0xFF5555, "📤"),
+            death: style(0xAAAAAA, "💀"),
+            chat: style(0xFFFFFF, ""),
+        }
+    }
+}
+
+/// How emoji are translated between Discord and Minecraft.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Emoji {
+    /// Replace custom emotes and common emoji from Discord with `:shortcode:`
+    /// text, since Minecraft's font can't show most of them.
+    pub shortcodes: bool,
+
+    /// Replace emoticons like `:)` and `:shortcodes:` from Minecraft with emoji.
+    pub emoticons: bool,
+}
+
+impl Default for Emoji {
+    fn default() -> Self {
+        Emoji {
+            shortcodes: true,
+            emoticons: false,
+        }
+    }
+}
+
+/// Which mirrors post to a new thread each day, instead of their channel.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Threads {
+    pub console: bool,
+    pub verbose: bool,
+}
+
+/// Where messages from a Discord channel go.
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Route {
+    /// Broadcast in Minecraft chat.
+    Chat,
+
+    /// Run as server commands, for users allowed to use the console.
+    Console,
+}
+
+/// Discord messages not to relay, besides the bot's own.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Ignore {
+    /// Messages from other bots, such as `mc-boot`.
+    pub bots: bool,
+
+    /// Messages posted through webhooks, other than the bridge's own.
+    pub webhooks: bool,
+}
+
+impl Default for Ignore {
+    fn default() -> Self {
+        Ignore {
+            bots: true,
+            webhooks: false,
+        }
+    }
+}
+
+/// How messages for Discord are kept while it's unreachable.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Offline {
+    /// Messages to keep until Discord is reachable again.
+    pub limit: usize,
+
+    /// Which messages to drop once `limit` is reached.
+    pub overflow: Overflow,
+}
+
+impl Default for Offline {
+    fn default() -> Self {
+        Offline {
+            limit: 100,
+            overflow: Overflow::default(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Overflow {
+    /// Keep the most recent messages.
+    #[default]
+    DropOldest,
+
+    /// Keep the first messages.
+    DropNewest,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EmbedStyle {
+    /// Color of the embed's left border, as `0xRRGGBB`.
+    pub color: u32,
+
+    /// Prefix for the message, if not empty.
+    #[serde(default)]
+    pub emoji: String,
+}
+
+/// Channel (usually a locked voice channel) renamed to show how many players are online.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct StatusChannel {
+    pub id: u64,
+
+    /// Channel name, with `{online}` and `{max}` players, and `{tps}`.
+    #[serde(default = "StatusChannel::format")]
+    pub format: String,
+}
+
+impl StatusChannel {
+    fn format() -> String {
+        String::from("🟢 Online: {online}")
+    }
+}
+
+/// Periodically show the server's status in the general channel's topic.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct TopicConfig {
+    #[serde(default = "TopicConfig::schedule")]
+    pub schedule: String,
+    #[serde(default = "TopicConfig::format")]
+    pub format: String,
+}
+
+impl TopicConfig {
+    fn schedule() -> String {
+        String::from("*/10 * * * *")
+    }
+
+    fn format() -> String {
+        String::from("Minecraft {version} | {online}/{max} online | TPS {tps} | Up {uptime}")
+    }
+
+    fn load(self) -> anyhow::Result<Topic> {
+        Ok(Topic {
+            schedule: Schedule::parse(&self.schedule)?,
+            format: self.format,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Topic {
+    pub schedule: Schedule,
+
+    /// Topic text, with `{version}`, `{online}`, `{max}`, `{tps}`, and `{uptime}`.
+    pub format: String,
+}
+
+/// Periodically measure the server's TPS, and alert when it stays low.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LagConfig {
+    #[serde(default = "LagConfig::schedule")]
+    pub schedule: String,
+    #[serde(default)]
+    pub source: Option<TpsSource>,
+    #[serde(default = "LagConfig::threshold")]
+    pub threshold: f64,
+    #[serde(default = "LagConfig::samples")]
+    pub samples: u32,
+}
+
+impl LagConfig {
+    fn schedule() -> String {
+        String::from("* * * * *")
+    }
+
+    fn threshold() -> f64 {
+        15.0
+    }
+
+    fn samples() -> u32 {
+        3
+    }
+
+    fn load(self, flavor: events::Flavor) -> anyhow::Result<Lag> {
+        Ok(Lag {
+            schedule: Schedule::parse(&self.schedule)?,
+            source: self.source.unwrap_or(match flavor {
+                events::Flavor::Paper => TpsSource::Paper,
+                _ => TpsSource::Gametime,
+            }),
+            threshold: self.threshold,
+            samples: self.samples.max(1),
+        })
+    }
+}
+
+/// How to measure TPS.
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TpsSource {
+    /// Paper's `tps` command, averaged over the last minute.
+    Paper,
+
+    /// How far `time query gametime` advances between samples, which works on
+    /// any server.
+    Gametime,
+}
+
+#[derive(Clone, Debug)]
+pub struct Lag {
+    pub schedule: Schedule,
+    pub source: TpsSource,
+
+    /// Alert when TPS is below this...
+    pub threshold: f64,
+
+    /// ...for this many samples in a row.
+    pub samples: u32,
+}
+
+/// Periodically measure the server process's memory and CPU usage, and alert
+/// when either is too high.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ResourcesConfig {
+    #[serde(default = "ResourcesConfig::schedule")]
+    pub schedule: String,
+    #[serde(default)]
+    pub memory_limit: Option<String>,
+    #[serde(default)]
+    pub memory_alert: Option<f64>,
+    #[serde(default)]
+    pub cpu_alert: Option<f64>,
+    #[serde(default = "ResourcesConfig::samples")]
+    pub samples: u32,
+}
+
+impl ResourcesConfig {
+    fn schedule() -> String {
+        String::from("* * * * *")
+    }
+
+    fn samples() -> u32 {
+        3
+    }
+
+    fn load(self) -> anyhow::Result<Resources> {
+        let memory_limit = match self.memory_limit {
+            None => None,
+            Some(limit) => Some(usage::parse_size(&limit).with_context(|| {
+                format!("Invalid size `{}` in `resources.memory-limit`", limit)
+            })?),
+        };
+        Ok(Resources {
+            schedule: Schedule::parse(&self.schedule)?,
+            memory_limit,
+            memory_alert: self.memory_alert,
+            cpu_alert: self.cpu_alert,
+            samples: self.samples.max(1),
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Resources {
+    pub schedule: Schedule,
+
+    /// Memory the server may use, in bytes, instead of its `-Xmx` heap limit.
+    pub memory_limit: Option<u64>,
+
+    /// Alert when memory use reaches this percent of the limit.
+    pub memory_alert: Option<f64>,
+
+    /// Alert when CPU use, as a percent of one core, reaches this...
+    pub cpu_alert: Option<f64>,
+
+    /// ...for this many samples in a row.
+    pub samples: u32,
+}
+
+/// Restart the server when it stops logging anything, or uses too much memory.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct WatchdogConfig {
+    #[serde(default = "WatchdogConfig::schedule")]
+    pub schedule: String,
+    #[serde(default)]
+    pub silence: Option<u64>,
+    #[serde(default)]
+    pub memory: Option<f64>,
+    #[serde(default = "WatchdogConfig::warning")]
+    pub warning: u64,
+    #[serde(default = "WatchdogConfig::kill_after")]
+    pub kill_after: u64,
+}
+
+impl WatchdogConfig {
+    fn schedule() -> String {
+        String::from("* * * * *")
+    }
+
+    fn warning() -> u64 {
+        1
+    }
+
+    fn kill_after() -> u64 {
+        60
+    }
+
+    fn load(self) -> anyhow::Result<Watchdog> {
+        Ok(Watchdog {
+            schedule: Schedule::parse(&self.schedule)?,
+            silence: self
+                .silence
+                .filter(|minutes| *minutes > 0)
+                .map(|minutes| Duration::from_secs(minutes * 60)),
+            memory: self.memory,
+            warning: self.warning,
+            kill_after: Duration::from_secs(self.kill_after),
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Watchdog {
+    pub schedule: Schedule,
+
+    /// Restart the server when it hasn't logged anything for this long.
+    pub silence: Option<Duration>,
+
+    /// Restart the server when its memory use reaches this percent of the
+    /// `[resources]` limit.
+    pub memory: Option<f64>,
+
+    /// Minutes to warn players before a memory restart.
+    pub warning: u64,
+
+    /// Kill a hung server if it hasn't stopped this long after `/stop`.
+    pub kill_after: Duration,
+}
+
+/// A message posted in Minecraft chat, Discord, or both, on a schedule.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct AnnouncementConfig {
+    pub schedule: String,
+    pub message: String,
+    #[serde(default = "AnnouncementConfig::minecraft")]
+    pub minecraft: bool,
+    #[serde(default)]
+    pub discord: bool,
+    #[serde(default = "AnnouncementConfig::color")]
+    pub color: String,
+}
+
+impl AnnouncementConfig {
+    fn minecraft() -> bool {
+        true
+    }
+
+    fn color() -> String {
+        String::from("yellow")
+    }
+
+    fn load(self) -> anyhow::Result<Announcement> {
+        Ok(Announcement {
+            schedule: Schedule::parse(&self.schedule)?,
+            message: self.message,
+            minecraft: self.minecraft,
+            discord: self.discord,
+            color: self.color,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Announcement {
+    pub schedule: Schedule,
+    pub message: String,
+
+    /// Broadcast in Minecraft chat, with `tellraw` in this color if enabled.
+    pub minecraft: bool,
+    pub color: String,
+
+    /// Post in the general channel.
+    pub discord: bool,
+}
+
+/// Relay chat and server events to a Matrix room, as the account that owns
+/// the access token. The account needs to have joined the room already.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Matrix {
+    /// Base URL of the account's homeserver, like `https://matrix.org`.
+    pub homeserver: String,
+    pub access_token: String,
+    /// Internal ID of the room, like `!abcdefg:matrix.org`.
+    pub room_id: String,
+}
+
+impl Matrix {
+    fn load(self) -> anyhow::Result<Self> {
+        if cfg!(feature = "matrix") {
+            Ok(self)
+        } else {
+            Err(anyhow!(
+                "`matrix` needs mc-sync to be built with the `matrix` feature"
+            ))
+        }
+    }
+}
+
+/// Relay chat and server events to a Slack channel, as a bot. The bot needs
+/// to be a member of the channel.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Slack {
+    /// Bot token (`xoxb-...`), for posting messages and looking up users.
+    pub bot_token: String,
+    /// App-level token (`xapp-...`), for receiving messages over Socket Mode.
+    pub app_token: String,
+    /// ID of the channel, like `C0123456789`.
+    pub channel: String,
+}
+
+impl Slack {
+    fn load(self) -> anyhow::Result<Self> {
+        if cfg!(feature = "slack") {
+            Ok(self)
+        } else {
+            Err(anyhow!(
+                "`slack` needs mc-sync to be built with the `slack` feature"
+            ))
+        }
+    }
+}
+
+/// An HTTP endpoint that server events are POSTed to as JSON.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Sink {
+    pub url: String,
+    /// Sign each body with HMAC-SHA256, keyed by this secret.
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// Only send these kinds of event, or every kind if empty.
+    #[serde(default)]
+    pub events: Vec<sink::Kind>,
+}
+
+impl Sink {
+    fn load(self) -> anyhow::Result<Self> {
+        reqwest::Url::parse(&self.url)
+            .with_context(|| format!("Invalid URL `{}` in `sinks`", self.url))?;
+        Ok(self)
+    }
+}
+
+/// Publish server events to an MQTT broker, and optionally take console
+/// commands from it.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Mqtt {
+    pub host: String,
+    #[serde(default = "Mqtt::port")]
+    pub port: u16,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default = "Mqtt::client_id")]
+    pub client_id: String,
+    /// Topics are `<prefix>/<server>/<event>`.
+    #[serde(default = "Mqtt::prefix")]
+    pub prefix: String,
+    /// Run messages on `<prefix>/<server>/command` as console commands.
+    #[serde(default)]
+    pub commands: bool,
+}
+
+impl Mqtt {
+    fn port() -> u16 {
+        1883
+    }
+
+    fn client_id() -> String {
+        String::from("mc-sync")
+    }
+
+    fn prefix() -> String {
+        String::from("minecraft")
+    }
+
+    fn load(self) -> anyhow::Result<Self> {
+        if cfg!(feature = "mqtt") {
+            Ok(self)
+        } else {
+            Err(anyhow!(
+                "`mqtt` needs mc-sync to be built with the `mqtt` feature"
+            ))
+        }
+    }
+}
+
+/// HTTP API for controlling the bridge, authenticated by bearer token.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Api {
+    pub address: String,
+    #[serde(default)]
+    pub tokens: Vec<ApiToken>,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ApiToken {
+    /// Shown in the audit channel, and as the platform of chat messages.
+    pub name: String,
+    pub token: String,
+    pub permissions: Vec<api::Permission>,
+}
+
+/// RCON server that runs commands through the bridge, for tools that only
+/// speak RCON.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct RconServer {
+    pub address: String,
+    pub password: String,
+}
+
+/// Periodic world backups.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct BackupConfig {
+    pub schedule: String,
+    pub world: PathBuf,
+    pub directory: PathBuf,
+    #[serde(default)]
+    pub format: backup::Format,
+    #[serde(default)]
+    pub keep_last: Option<usize>,
+    #[serde(default)]
+    pub keep_daily: Option<usize>,
+    #[serde(default)]
+    pub keep_weekly: Option<usize>,
+    #[serde(default)]
+    pub remote: Vec<backup::Remote>,
+}
+
+impl BackupConfig {
+    fn load(self) -> anyhow::Result<backup::Backup> {
+        if self.format == backup::Format::Snapshot && !self.remote.is_empty() {
+            return Err(anyhow!("`backup.remote` only uploads `archive` backups"));
+        }
+        Ok(backup::Backup {
+            schedule: Schedule::parse(&self.schedule)?,
+            world: self.world,
+            directory: self.directory,
+            format: self.format,
+            retention: backup::Retention {
+                last: self.keep_last,
+                daily: self.keep_daily,
+                weekly: self.keep_weekly,
+            },
+            remotes: self.remote,
+        })
+    }
+}
+
+/// Rotating files recording what the bridge relays.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct JournalConfig {
+    pub directory: PathBuf,
+    #[serde(default)]
+    pub rotate: journal::Rotate,
+    #[serde(default)]
+    pub max_size: Option<String>,
+    #[serde(default)]
+    pub compress: bool,
+    #[serde(default)]
+    pub keep: Option<usize>,
+}
+
+impl JournalConfig {
+    fn load(self) -> anyhow::Result<journal::Journal> {
+        let max_size = match self.max_size {
+            None => None,
+            Some(size) => Some(
+                usage::parse_size(&size)
+                    .with_context(|| format!("Invalid size `{}` in `journal.max-size`", size))?,
+            ),
+        };
+        Ok(journal::Journal {
+            directory: self.directory,
+            rotate: self.rotate,
+            max_size,
+            compress: self.compress,
+            keep: self.keep,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Settings {
+    pub name: Option<String>,
+    /// Takes messages and commands from channels no other server claims.
+    pub primary: bool,
+    /// Channels claimed by other servers.
+    pub others: BTreeSet<u64>,
+    /// Bridges Discord, which can be left out when Matrix or Slack is
+    /// configured. Without it, `token` is empty and the channel IDs are 0.
+    pub discord: bool,
+    pub token: String,
+    pub general_id: u64,
+    pub verbose_id: u64,
+    pub console_id: Option<u64>,
+    pub audit_id: Option<u64>,
+    /// Where to report errors, instead of the verbose channel.
+    pub error_id: Option<u64>,
+    /// Where to post the control panel.
+    pub panel_id: Option<u64>,
+    /// Where messages from channels besides the general and console channels go.
+    pub routes: BTreeMap<u64, Route>,
+    /// Role for members whose linked Minecraft account is whitelisted.
+    pub player_role: Option<u64>,
+    /// Unwhitelist members' linked accounts when they leave the guild.
+    pub enforce_membership: bool,
+    pub permissions: permissions::Permissions,
+    pub webhook_url: Option<String>,
+    pub avatar_url: String,
+    pub server_port: u16,
+    pub mode: Mode,
+    pub server_directory: PathBuf,
+    pub data_file: PathBuf,
+    /// SQLite database to record server events and commands in.
+    pub history_file: Option<PathBuf>,
+    pub crash: Crash,
+    pub tellraw: Tellraw,
+    pub presence: Presence,
+    pub advancements: Advancements,
+    pub announce: Announce,
+    pub reconnects: Reconnects,
+    pub afk: Afk,
+    pub scoreboard: Scoreboard,
+    pub in_game: InGame,
+    pub embeds: Embeds,
+    pub emoji: Emoji,
+    pub threads: Threads,
+    pub ignore: Ignore,
+    pub offline: Offline,
+    pub verbose: Verbose,
+    /// Relay chat to and from other bridged servers that also federate.
+    pub federate: bool,
+    /// Leave the world seed out of `/serverinfo`.
+    pub hide_seed: bool,
+    /// Keep the server's colors in the console channel, with `ansi` code blocks.
+    pub console_colors: bool,
+    /// Prefix lines in the terminal and console channel with the time, in this
+    /// `strftime` format, and where they came from.
+    pub mirror_tags: Option<String>,
+    /// Serve Prometheus metrics at `/metrics`, and health at `/healthz`, on this
+    /// address.
+    pub http_address: Option<String>,
+    /// Check the server's status with the Server List Ping protocol at this
+    /// address, instead of only `list`.
+    pub ping_address: Option<String>,
+    /// Check the server's full status with the Query protocol at this address,
+    /// if a ping's sample of players is incomplete or `ping_address` is unset.
+    pub query_address: Option<String>,
+    pub log_level: LevelFilter,
+    pub log_format: logging::Format,
+    /// Announce every this many deaths.
+    pub death_milestone: Option<u64>,
+    pub status_channel: Option<StatusChannel>,
+    pub topic: Option<Topic>,
+    pub lag: Option<Lag>,
+    pub resources: Option<Resources>,
+    pub watchdog: Option<Watchdog>,
+    pub announcements: Vec<Announcement>,
+    pub matrix: Option<Matrix>,
+    pub slack: Option<Slack>,
+    pub sinks: Vec<Sink>,
+    pub mqtt: Option<Mqtt>,
+    /// Serve the HTTP API, using the first server's settings.
+    pub api: Option<Api>,
+    pub rcon_server: Option<RconServer>,
+    pub list_schedule: Schedule,
+    pub restart_schedule: Option<Schedule>,
+    pub backup: Option<backup::Backup>,
+    pub journal: Option<journal::Journal>,
+    pub flavor: events::Flavor,
+    /// Console command to broadcast chat on every server behind a proxy, with
+    /// `{message}` and `{json}` placeholders.
+    pub proxy_broadcast: Option<String>,
+    pub parser: events::Parser,
+    pub templates: Templates,
+}
+
+/// How to reach the Minecraft server.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Mode {
+    /// Launch the server as a child process and own its stdin and stdout.
+    Spawn { command: String },
+
+    /// Attach to an already-running server over RCON, and optionally follow
+    /// its log for output.
+    Rcon {
+        address: String,
+        password: String,
+        tail_log: bool,
+    },
+}
+
+impl Settings {
+    /// Channels whose messages and commands are meant for this server.
+    pub fn channels(&self) -> BTreeSet<u64> {
+        [
+            Some(self.general_id),
+            Some(self.verbose_id),
+            self.console_id,
+        ]
+        .iter()
+        .flatten()
+        .copied()
+        .chain(self.routes.keys().copied())
+        .collect()
+    }
+
+    /// Where messages from `channel` go, if anywhere.
+    pub fn route(&self, channel: u64) -> Option<Route> {
+        if let Some(route) = self.routes.get(&channel) {
+            Some(*route)
+        } else if Some(channel) == self.console_id {
+            Some(Route::Console)
+        } else if channel == self.general_id {
+            Some(Route::Chat)
+        } else {
+            None
+        }
+    }
+
+    /// Replace the settings that can change without restarting the server,
+    /// and warn about any that can't.
+    pub fn reload(&mut self, settings: Settings) {
+        let mut ignored = Vec::new();
+        if self.token != settings.token {
+            ignored.push("token");
+        }
+        if self.server_port != settings.server_port {
+            ignored.push("server-port");
+        }
+        if self.mode != settings.mode {
+            ignored.push("command or rcon-*");
+        }
+        if self.server_directory != settings.server_directory {
+            ignored.push("server-directory");
+        }
+        if self.data_file != settings.data_file {
+            ignored.push("data-file");
+        }
+        if self.history_file != settings.history_file {
+            ignored.push("history-file");
+        }
+        if self.enforce_membership != settings.enforce_membership {
+            ignored.push("enforce-membership");
+        }
+        if self.http_address != settings.http_address {
+            ignored.push("http-address");
+        }
+        if self.log_level != settings.log_level || self.log_format != settings.log_format {
+            ignored.push("logging");
+        }
+        if self.topic.as_ref().map(|topic| &topic.schedule)
+            != settings.topic.as_ref().map(|topic| &topic.schedule)
+        {
+            ignored.push("topic.schedule");
+        }
+        if self.lag.as_ref().map(|lag| &lag.schedule)
+            != settings.lag.as_ref().map(|lag| &lag.schedule)
+        {
+            ignored.push("lag.schedule");
+        }
+        if self.resources.as_ref().map(|resources| &resources.schedule)
+            != settings
+                .resources
+                .as_ref()
+                .map(|resources| &resources.schedule)
+        {
+            ignored.push("resources.schedule");
+        }
+        if self.watchdog.as_ref().map(|watchdog| &watchdog.schedule)
+            != settings
+                .watchdog
+                .as_ref()
+                .map(|watchdog| &watchdog.schedule)
+        {
+            ignored.push("watchdog.schedule");
+        }
+        if self
+            .announcements
+            .iter()
+            .map(|announcement| &announcement.schedule)
+            .ne(settings
+                .announcements
+                .iter()
+                .map(|announcement| &announcement.schedule))
+        {
+            ignored.push("announcements");
+        }
+        if self.matrix != settings.matrix {
+            ignored.push("matrix");
+        }
+        if self.slack != settings.slack {
+            ignored.push("slack");
+        }
+        if self.mqtt != settings.mqtt {
+            ignored.push("mqtt");
+        }
+        if self.api != settings.api {
+            ignored.push("api");
+        }
+        if self.rcon_server != settings.rcon_server {
+            ignored.push("rcon-server");
+        }
+        if self.list_schedule != settings.list_schedule {
+            ignored.push("list-schedule");
+        }
+        if self.restart_schedule != settings.restart_schedule {
+            ignored.push("restart-schedule");
+        }
+        if self.backup.as_ref().map(|backup| &backup.schedule)
+            != settings.backup.as_ref().map(|backup| &backup.schedule)
+        {
+            ignored.push("backup.schedule");
+        }
+        // Commands are translated for Bedrock and proxies by the running
+        // server's console.
+        let translated = |flavor| match flavor {
+            events::Flavor::Bedrock | events::Flavor::Velocity | events::Flavor::Bungeecord => {
+                Some(flavor)
+            }
+            _ => None,
+        };
+        let same_console = translated(self.flavor) == translated(settings.flavor);
+        if !same_console {
+            ignored.push("log-flavor");
+        }
+        if self.proxy_broadcast != settings.proxy_broadcast {
+            ignored.push("proxy-broadcast");
+        }
+        if !ignored.is_empty() {
+            tracing::warn!(
+                "Ignoring changes to {} until restart",
+                ignored
+                    .iter()
+                    .map(|name| format!("`{}`", name))
+                    .join_with(", "),
+            );
+        }
+
+        self.primary = settings.primary;
+        self.others = settings.others;
+        self.general_id = settings.general_id;
+        self.verbose_id = settings.verbose_id;
+        self.console_id = settings.console_id;
+        self.audit_id = settings.audit_id;
+        self.error_id = settings.error_id;
+        self.panel_id = settings.panel_id;
+        self.routes = settings.routes;
+        self.player_role = settings.player_role;
+        self.permissions = settings.permissions;
+        self.webhook_url = settings.webhook_url;
+        self.avatar_url = settings.avatar_url;
+        self.backup = settings.backup;
+        self.journal = settings.journal;
+        self.crash = settings.crash;
+        self.tellraw = settings.tellraw;
+        self.presence = settings.presence;
+        self.advancements = settings.advancements;
+        self.announce = settings.announce;
+        self.reconnects = settings.reconnects;
+        self.afk = settings.afk;
+        self.scoreboard = settings.scoreboard;
+        self.in_game = settings.in_game;
+        self.embeds = settings.embeds;
+        self.emoji = settings.emoji;
+        self.threads = settings.threads;
+        self.ignore = settings.ignore;
+        self.offline = settings.offline;
+        self.verbose = settings.verbose;
+        self.federate = settings.federate;
+        self.hide_seed = settings.hide_seed;
+        self.console_colors = settings.console_colors;
+        self.mirror_tags = settings.mirror_tags;
+        self.ping_address = settings.ping_address;
+        self.query_address = settings.query_address;
+        self.death_milestone = settings.death_milestone;
+        self.status_channel = settings.status_channel;
+        self.topic = settings.topic;
+        self.lag = settings.lag;
+        self.resources = settings.resources;
+        self.watchdog = settings.watchdog;
+        self.announcements = settings.announcements;
+        self.sinks = settings.sinks;
+        if same_console {
+            self.flavor = settings.flavor;
+            self.parser = settings.parser;
+        }
+        self.templates = settings.templates;
+    }
+}
@@ -0,0 +1,141 @@
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use serenity::client;
+use serenity::client::bridge::gateway::event as gateway_event;
+use serenity::gateway::ConnectionStage;
+use serenity::model::channel;
+use serenity::model::event;
+use serenity::model::gateway;
+use serenity::model::guild;
+use serenity::model::id;
+use serenity::model::interactions;
+use serenity::model::user;
+use tokio::sync::mpsc;
+
+use crate::commands::Command;
+use crate::events::Event;
+use crate::metrics::Gateway;
+
+/// Forward Discord messages and commands to the bridge for each server, which
+/// decides whether they're meant for it.
+pub struct Discord {
+    pub bridges: Vec<mpsc::Sender<Event>>,
+    pub gateway: Arc<Gateway>,
+}
+
+impl Discord {
+    async fn send(&self, event: impl Fn() -> Event) {
+        for tx in &self.bridges {
+            // The bridge stops once its server does, while others keep running.
+            if tx.send(event()).await.is_err() {
+                tracing::debug!("Dropping Discord event for a stopped server");
+            }
+        }
+    }
+}
+
+#[serenity::async_trait]
+impl client::EventHandler for Discord {
+    async fn ready(&self, context: client::Context, ready: gateway::Ready) {
+        self.gateway.connections.fetch_add(1, Ordering::Relaxed);
+        self.gateway.connected.store(true, Ordering::Relaxed);
+        self.send(|| Event::Ready(context.shard.clone())).await;
+
+        for guild in &ready.guilds {
+            if let Err(error) = guild
+                .id()
+                .set_application_commands(&context.http, Command::register)
+                .await
+            {
+                tracing::error!(
+                    "Failed to register commands in guild {}: {}",
+                    guild.id(),
+                    error
+                );
+            }
+        }
+    }
+
+    async fn resume(&self, _: client::Context, _: event::ResumedEvent) {
+        self.gateway.connections.fetch_add(1, Ordering::Relaxed);
+        self.gateway.connected.store(true, Ordering::Relaxed);
+        self.send(|| Event::Resumed).await;
+    }
+
+    async fn shard_stage_update(
+        &self,
+        _: client::Context,
+        update: gateway_event::ShardStageUpdateEvent,
+    ) {
+        self.gateway
+            .connected
+            .store(update.new == ConnectionStage::Connected, Ordering::Relaxed);
+    }
+
+    async fn message(&self, _: client::Context, message: channel::Message) {
+        self.send(|| Event::Discord(Box::new(message.clone())))
+            .await;
+    }
+
+    async fn message_update(
+        &self,
+        _: client::Context,
+        _: Option<channel::Message>,
+        _: Option<channel::Message>,
+        update: event::MessageUpdateEvent,
+    ) {
+        // Embeds for links are added by updating the message, without editing it.
+        if update.edited_timestamp.is_none() || update.content.is_none() {
+            return;
+        }
+        self.send(|| Event::Edited {
+            guild_id: update.guild_id,
+            channel_id: update.channel_id,
+            message_id: update.id,
+        })
+        .await;
+    }
+
+    async fn message_delete(
+        &self,
+        _: client::Context,
+        _: id::ChannelId,
+        message_id: id::MessageId,
+        _: Option<id::GuildId>,
+    ) {
+        self.send(|| Event::Deleted(message_id)).await;
+    }
+
+    async fn guild_member_removal(
+        &self,
+        _: client::Context,
+        guild_id: id::GuildId,
+        user: user::User,
+        _: Option<guild::Member>,
+    ) {
+        self.send(|| Event::Left {
+            guild_id,
+            user: user.id,
+        })
+        .await;
+    }
+
+    async fn interaction_create(&self, _: client::Context, interaction: interactions::Interaction) {
+        match interaction {
+            interactions::Interaction::ApplicationCommand(interaction) => {
+                self.send(|| Event::Command(Box::new(interaction.clone())))
+                    .await
+            }
+            interactions::Interaction::Autocomplete(interaction) => {
+                self.send(|| Event::Autocomplete(Box::new(interaction.clone())))
+                    .await
+            }
+            interactions::Interaction::MessageComponent(interaction) => {
+                self.send(|| Event::Component(Box::new(interaction.clone())))
+                    .await
+            }
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+
+use joinery::JoinableIterator;
+use once_cell::sync::Lazy;
+use regex::Captures;
+use regex::Regex;
+
+/// Custom Discord emotes, like `<:pog:1234>` or animated `<a:pog:1234>`.
+static CUSTOM: Lazy<Regex> = Lazy::new(|| Regex::new(r"<a?:([A-Za-z0-9_]+):[0-9]+>").unwrap());
+
+static UNICODE: Lazy<Regex> = Lazy::new(|| {
+    // Prefer the longest match, so sequences aren't split into their parts.
+    let mut emoji = EMOJI.iter().map(|(emoji, _)| *emoji).collect::<Vec<_>>();
+    emoji.sort_by_key(|emoji| std::cmp::Reverse(emoji.len()));
+    let pattern = emoji
+        .iter()
+        .map(|emoji| format!("{}\u{FE0F}?", regex::escape(emoji)))
+        .join_with("|")
+        .to_string();
+    Regex::new(&pattern).unwrap()
+});
+
+static SHORTCODE: Lazy<Regex> = Lazy::new(|| Regex::new(r":([a-z0-9_+-]+):").unwrap());
+
+static BY_EMOJI: Lazy<HashMap<&str, &str>> = Lazy::new(|| EMOJI.iter().copied().collect());
+
+static BY_SHORTCODE: Lazy<HashMap<&str, &str>> = Lazy::new(|| {
+    EMOJI
+        .iter()
+        .map(|(emoji, shortcode)| (*shortcode, *emoji))
+        .collect()
+});
+
+static WORD: Lazy<Regex> = Lazy::new(|| Regex::new(r"\S+").unwrap());
+
+/// Replace custom emotes and common Unicode emoji with `:shortcode:` text,
+/// which the Minecraft client can show.
+pub fn to_shortcodes(text: &str) -> String {
+    let text = CUSTOM.replace_all(text, ":$1:");
+    UNICODE
+        .replace_all(&text, |captures: &Captures| {
+            BY_EMOJI
+                .get(captures[0].trim_end_matches('\u{FE0F}'))
+                .map(|shortcode| format!(":{}:", shortcode))
+                .unwrap_or_else(|| captures[0].to_owned())
+        })
+        .into_owned()
+}
+
+/// Replace emoticons like `:)` and known `:shortcodes:` typed in Minecraft
+/// with Unicode emoji for Discord.
+pub fn from_emoticons(text: &str) -> String {
+    let text = WORD.replace_all(text, |captures: &Captures| {
+        EMOTICONS
+            .iter()
+            .find(|(emoticon, _)| *emoticon == &captures[0])
+            .map(|(_, emoji)| String::from(*emoji))
+            .unwrap_or_else(|| captures[0].to_owned())
+    });
+    SHORTCODE
+        .replace_all(&text, |captures: &Captures| {
+            BY_SHORTCODE
+                .get(&captures[1])
+                .map(|emoji| String::from(*emoji))
+                .unwrap_or_else(|| captures[0].to_owned())
+        })
+        .into_owned()
+}
+
+static EMOTICONS: &[(&str, &str)] = &[
+    (":)", "🙂"),
+    (":-)", "🙂"),
+    (":(", "🙁"),
+    (":-(", "🙁"),
+    (":D", "😄"),
+    (":-D", "😄"),
+    ("xD", "😆"),
+    ("XD", "😆"),
+    (";)", "😉"),
+    (";-)", "😉"),
+    (":P", "😛"),
+    (":p", "😛"),
+    (":O", "😮"),
+    (":o", "😮"),
+    (":'(", "😢"),
+    (":|", "😐"),
+    (":/", "😕"),
+    ("<3", "❤️"),
+    ("</3", "💔"),
+    ("B)", "😎"),
+];
+
+// Shortcodes follow Discord's names.
+static EMOJI: &[(&str, &str)] = &[
+    // Faces
+    ("😀", "grinning"),
+    ("😃", "smiley"),
+    ("😄", "smile"),
+    ("😁", "grin"),
+    ("😆", "laughing"),
+    ("😅", "sweat_smile"),
+    ("🤣", "rofl"),
+    ("😂", "joy"),
+    ("🙂", "slight_smile"),
+    ("🙃", "upside_down"),
+    ("😉", "wink"),
+    ("😊", "blush"),
+    ("😇", "innocent"),
+    ("🥰", "smiling_face_with_3_hearts"),
+    ("😍", "heart_eyes"),
+    ("🤩", "star_struck"),
+    ("😘", "kissing_heart"),
+    ("😋", "yum"),
+    ("😛", "stuck_out_tongue"),
+    ("😜", "stuck_out_tongue_winking_eye"),
+    ("🤪", "zany_face"),
+    ("🤑", "money_mouth"),
+    ("🤗", "hugging"),
+    ("🤭", "face_with_hand_over_mouth"),
+    ("🤫", "shushing_face"),
+    ("🤔", "thinking"),
+    ("🤐", "zipper_mouth"),
+    ("🤨", "face_with_raised_eyebrow"),
+    ("😐", "neutral_face"),
+    ("😑", "expressionless"),
+    ("😶", "no_mouth"),
+    ("😏", "smirk"),
+    ("😒", "unamused"),
+    ("🙄", "rolling_eyes"),
+    ("😬", "grimacing"),
+    ("😌", "relieved"),
+    ("😔", "pensive"),
+    ("😪", "sleepy"),
+    ("🤤", "drooling_face"),
+    ("😴", "sleeping"),
+    ("😷", "mask"),
+    ("🤒", "thermometer_face"),
+    ("🤢", "nauseated_face"),
+    ("🤮", "face_vomiting"),
+    ("🥵", "hot_face"),
+    ("🥶", "cold_face"),
+    ("😵", "dizzy_face"),
+    ("🤯", "exploding_head"),
+    ("🥳", "partying_face"),
+    ("😎", "sunglasses"),
+    ("🤓", "nerd"),
+    ("😕", "confused"),
+    ("😟", "worried"),
+    ("🙁", "slight_frown"),
+    ("😮", "open_mouth"),
+    ("😲", "astonished"),
+    ("😳", "flushed"),
+    ("🥺", "pleading_face"),
+    ("😨", "fearful"),
+    ("😰", "cold_sweat"),
+    ("😢", "cry"),
+    ("😭", "sob"),
+    ("😱", "scream"),
+    ("😖", "confounded"),
+    ("😞", "disappointed"),
+    ("😩", "weary"),
+    ("😫", "tired_face"),
+    ("🥱", "yawning_face"),
+    ("😤", "triumph"),
+    ("😡", "rage"),
+    ("😠", "angry"),
+    ("🤬", "face_with_symbols_over_mouth"),
+    ("😈", "smiling_imp"),
+    ("💀", "skull"),
+    ("💩", "poop"),
+    ("🤡", "clown"),
+    ("👻", "ghost"),
+    ("👽", "alien"),
+    ("🤖", "robot"),
+    // Hands and people
+    ("👋", "wave"),
+    ("👌", "ok_hand"),
+    ("✌", "v"),
+    ("🤞", "fingers_crossed"),
+    ("🤘", "metal"),
+    ("👈", "point_left"),
+    ("👉", "point_right"),
+    ("👆", "point_up_2"),
+    ("👇", "point_down"),
+    ("👍", "thumbsup"),
+    ("👎", "thumbsdown"),
+    ("✊", "fist"),
+    ("👊", "punch"),
+    ("👏", "clap"),
+    ("🙌", "raised_hands"),
+    ("🙏", "pray"),
+    ("💪", "muscle"),
+    ("👀", "eyes"),
+    ("🤷", "shrug"),
+    ("🤦", "face_palm"),
+    // Hearts and symbols
+    ("❤", "heart"),
+    ("🧡", "orange_heart"),
+    ("💛", "yellow_heart"),
+    ("💚", "green_heart"),
+    ("💙", "blue_heart"),
+    ("💜", "purple_heart"),
+    ("🖤", "black_heart"),
+    ("💔", "broken_heart"),
+    ("💯", "100"),
+    ("💥", "boom"),
+    ("💤", "zzz"),
+    ("✨", "sparkles"),
+    ("⭐", "star"),
+    ("🔥", "fire"),
+    ("⚡", "zap"),
+    ("✅", "white_check_mark"),
+    ("❌", "x"),
+    ("❓", "question"),
+    ("❗", "exclamation"),
+    ("⚠", "warning"),
+    ("🎉", "tada"),
+    ("🎁", "gift"),
+    ("🏆", "trophy"),
+    // Things from the game
+    ("⛏", "pick"),
+    ("🪓", "axe"),
+    ("🗡", "dagger"),
+    ("🏹", "bow_and_arrow"),
+    ("🛡", "shield"),
+    ("💎", "gem"),
+    ("🪙", "coin"),
+    ("🧱", "bricks"),
+    ("🪵", "wood"),
+    ("🌲", "evergreen_tree"),
+    ("🌳", "deciduous_tree"),
+    ("🌊", "ocean"),
+    ("🌋", "volcano"),
+    ("🏠", "house"),
+    ("🐷", "pig"),
+    ("🐄", "cow2"),
+    ("🐑", "sheep"),
+    ("🐔", "chicken"),
+    ("🐺", "wolf"),
+    ("🐱", "cat"),
+    ("🐝", "bee"),
+    ("🐟", "fish"),
+    ("🕷", "spider"),
+    ("🧟", "zombie"),
+    ("🍎", "apple"),
+    ("🍞", "bread"),
+    ("🍰", "cake"),
+    ("🍪", "cookie"),
+    ("☀", "sunny"),
+    ("🌙", "crescent_moon"),
+    ("🌧", "cloud_rain"),
+    ("⛈", "thunder_cloud_rain"),
+    ("❄", "snowflake"),
+];
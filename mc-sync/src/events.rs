@@ -0,0 +1,794 @@
+use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
+
+use anyhow::Context as _;
+use joinery::JoinableIterator;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+use serenity::client::bridge::gateway::ShardMessenger;
+use serenity::model::channel;
+use serenity::model::id;
+use serenity::model::interactions::application_command;
+use serenity::model::interactions::autocomplete;
+use serenity::model::interactions::message_component;
+
+use crate::advancements;
+use crate::api;
+use crate::bedrock;
+use crate::config;
+use crate::locale::Locale;
+use crate::markdown;
+use crate::ping;
+use crate::proxy;
+use crate::query;
+use crate::rcon;
+use crate::template;
+
+/// Input to the bridge from each long-running task.
+#[derive(Clone, Debug)]
+pub enum Event {
+    Discord(Box<channel::Message>),
+    Command(Box<application_command::ApplicationCommandInteraction>),
+    /// A user is typing an option that can be autocompleted.
+    Autocomplete(Box<autocomplete::AutocompleteInteraction>),
+    /// A button on the control panel was pressed.
+    Component(Box<message_component::MessageComponentInteraction>),
+    Minecraft(String),
+    Stdin(String),
+    /// A console command from the MQTT command topic.
+    Mqtt(String),
+    /// A console command from an RCON client, waiting for its output.
+    Rcon {
+        command: String,
+        reply: rcon::Reply,
+    },
+    /// Stop capturing output for the command with this ID.
+    Captured(u64),
+    /// Announce the quit held with this ID, unless the player has rejoined.
+    Held(u64),
+    /// A request from the HTTP API, made with the named token.
+    Api {
+        token: String,
+        request: api::Request,
+        reply: api::Reply,
+    },
+    /// A message from another chat platform, like Matrix.
+    Chat {
+        platform: String,
+        user: String,
+        message: String,
+    },
+    /// Chat from another bridged server.
+    Federated {
+        server: String,
+        player: String,
+        message: String,
+    },
+    Reload,
+    Restart,
+    /// Stop the server, and keep the bridge running until it's started again.
+    Stop,
+    /// The server process exited, and whether a `stop` command preceded it.
+    Exited {
+        status: process::ExitStatus,
+        stopped: bool,
+    },
+    /// Relaunch the server after a crash.
+    Launch,
+    Backup,
+    BackedUp(Result<PathBuf, String>),
+    /// Give up on the given backup if the server still hasn't saved the
+    /// world for it.
+    SaveTimeout(u64),
+    /// The world was restored from a backup, keeping the old world at this
+    /// path, or failed to be.
+    Restored(Result<PathBuf, String>),
+    /// An archive was uploaded to a remote, taking this long, or failed to be.
+    Uploaded {
+        remote: String,
+        result: Result<Duration, String>,
+    },
+    /// Check who's online with the `list` command, a ping, or a query.
+    List,
+    /// The server answered a status ping, or failed to.
+    Pinged(Result<ping::Status, String>),
+    /// The server answered a query, or failed to.
+    Queried(Result<query::Status, String>),
+    /// A member left, or was kicked or banned from, a guild.
+    Left {
+        guild_id: id::GuildId,
+        user: id::UserId,
+    },
+    /// Connected to Discord, and can update the bot's status.
+    Ready(ShardMessenger),
+    /// A Discord message was edited.
+    Edited {
+        guild_id: Option<id::GuildId>,
+        channel_id: id::ChannelId,
+        message_id: id::MessageId,
+    },
+    /// A Discord message was deleted.
+    Deleted(id::MessageId),
+    /// The Discord connection was reestablished.
+    Resumed,
+    /// Update the bot's status, after throttling.
+    Presence,
+    /// Rename the status channel, after throttling.
+    StatusChannel,
+    /// Measure the server's TPS.
+    SampleTps,
+    /// Measure the server process's memory and CPU usage.
+    SampleUsage,
+    /// Post the scheduled announcement at this index in the config file.
+    Announcement(usize),
+    /// Check whether the server has hung.
+    Watchdog,
+    /// Kill the server if it's still running the given launch, which a hung
+    /// server would be after being asked to stop.
+    Kill(u64),
+    /// Update the general channel's topic.
+    Topic,
+    /// Post buffered server output to the console channel.
+    FlushConsole,
+    /// Post buffered server output to the verbose channel.
+    FlushVerbose,
+    /// Reply to `/serverinfo`, once the server has had a chance to log its
+    /// seed and difficulty.
+    ServerInfo(Box<application_command::ApplicationCommandInteraction>),
+    /// Give up waiting for the server to respond to a slash command.
+    ResponseTimeout(id::InteractionId),
+}
+
+/// Interesting line from the server log.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Log {
+    Join {
+        player: String,
+    },
+    Quit {
+        player: String,
+    },
+    /// A player connected to a backend server behind a proxy, e.g. `survival`.
+    Switch {
+        player: String,
+        server: String,
+    },
+    Achievement {
+        player: String,
+        achievement: String,
+        frame: advancements::Frame,
+    },
+    Death {
+        player: String,
+        message: String,
+    },
+    Message {
+        player: String,
+        message: String,
+    },
+    Saved,
+    /// The server is starting.
+    Version {
+        version: String,
+    },
+    /// The server has finished starting, and how many seconds it took.
+    Done {
+        seconds: Option<f64>,
+    },
+    /// The server is shutting down.
+    Stopping,
+    /// A player got into bed.
+    Sleeping {
+        player: String,
+    },
+    /// Enough players slept to skip the night.
+    NightSkipped,
+    /// A player did something that isn't otherwise announced, like running
+    /// a command.
+    Activity {
+        player: String,
+    },
+    /// The weather changed, e.g. to `rain`.
+    Weather {
+        weather: String,
+    },
+    /// Response to Paper's `tps` command, averaged over the last minute.
+    Tps {
+        tps: f64,
+    },
+    /// Response to `time query gametime`: ticks since the world was created.
+    Gametime {
+        ticks: u64,
+    },
+    /// Response to the `list` command.
+    List {
+        max: usize,
+        players: Vec<String>,
+    },
+    /// Response to the `seed` command.
+    Seed {
+        seed: String,
+    },
+    /// Response to the `difficulty` command, e.g. `Normal`.
+    Difficulty {
+        difficulty: String,
+    },
+    /// Response to `data get entity`, as SNBT, e.g. `[12.5d, 64.0d, -3.5d]`
+    /// for a player's `Pos`.
+    EntityData {
+        player: String,
+        data: String,
+    },
+    /// Response to the `whitelist` command, and whether it succeeded.
+    Whitelist {
+        result: Result<String, String>,
+    },
+    /// Response to the `ban`, `pardon`, or `kick` command, and whether it succeeded.
+    Moderation {
+        result: Result<String, String>,
+    },
+    /// Matched a user-defined rule.
+    Custom {
+        channel: Option<u64>,
+        message: String,
+    },
+}
+
+/// Server software, which determines how each log line is prefixed.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Flavor {
+    /// Accept any of the prefixes below.
+    #[default]
+    Auto,
+
+    /// `[12:34:56] [Server thread/INFO]: `
+    Vanilla,
+
+    /// `[12:34:56 INFO]: `
+    #[serde(alias = "spigot")]
+    Paper,
+
+    /// `[12:34:56] [Server thread/INFO] (Minecraft) `
+    Fabric,
+
+    /// `[12:34:56] [Server thread/INFO] [minecraft/DedicatedServer]: `
+    Forge,
+
+    /// Bedrock Dedicated Server, whose log and commands differ entirely:
+    /// `[2024-01-01 12:34:56:789 INFO] `
+    Bedrock,
+
+    /// Velocity proxy, which logs joins and quits for every backend server,
+    /// and which server each player is on: `[12:34:56 INFO]: `
+    Velocity,
+
+    /// BungeeCord or Waterfall proxy, likewise: `12:34:56 [INFO] `
+    #[serde(alias = "bungee", alias = "waterfall")]
+    Bungeecord,
+}
+
+impl Flavor {
+    /// Start of each line from the server thread. Anything may come before
+    /// it, like a timestamp from a wrapper, but not chat, so players can't
+    /// fake a line by typing one.
+    fn prefix(self) -> &'static str {
+        match self {
+            Flavor::Auto => {
+                r"^[^<]*?(?:\[Server thread/INFO\](?: \[[^\]]*\]:| \([^)]*\)|:) |\[\d{2}:\d{2}:\d{2} INFO\]: )"
+            }
+            Flavor::Vanilla => r"^[^<]*?\[Server thread/INFO\]: ",
+            Flavor::Paper => r"^[^<]*?\[\d{2}:\d{2}:\d{2} INFO\]: ",
+            Flavor::Fabric => r"^[^<]*?\[Server thread/INFO\](?: \([^)]*\)|:) ",
+            Flavor::Forge => r"^[^<]*?\[Server thread/INFO\] \[[^\]]*\]: ",
+            Flavor::Bedrock => bedrock::PREFIX,
+            Flavor::Velocity => proxy::VELOCITY_PREFIX,
+            Flavor::Bungeecord => proxy::BUNGEECORD_PREFIX,
+        }
+    }
+
+    /// Whether this is a proxy in front of the actual servers.
+    pub fn is_proxy(self) -> bool {
+        matches!(self, Flavor::Velocity | Flavor::Bungeecord)
+    }
+}
+
+static JOIN: &str = r"(.*)\[[^\]]*\] logged in with entity id .* at .*";
+
+static QUIT: &str = r"(.*) left the game";
+
+// Only proxies log which server a player is on.
+static NEVER: &str = r"$.^";
+
+static ACHIEVEMENT: &str =
+    r"(.*) has (?:made the advancement|reached the goal|completed the challenge) \[(.*)\]";
+
+// https://minecraft.fandom.com/wiki/Death_messages
+static DEATHS: &[&str] = &[
+    "blew up",
+    "burned to death",
+    "didn't want to live in the same world as",
+    "died",
+    "discovered the floor was lava",
+    "drowned",
+    "experienced kinetic energy",
+    "fell",
+    "froze to death",
+    "hit the ground too hard",
+    "left the confines of this world",
+    "starved to death",
+    "suffocated in a wall",
+    "tried to swim in lava",
+    "walked into",
+    "was blown up by",
+    "was burnt to a crisp",
+    "was doomed to fall",
+    "was fireballed by",
+    "was frozen to death by",
+    "was impaled",
+    "was killed",
+    "was obliterated by a sonically-charged shriek",
+    "was poked to death by a sweet berry bush",
+    "was pricked to death",
+    "was pummeled by",
+    "was roasted in dragon's breath",
+    "was shot by",
+    "was skewered by a falling stalactite",
+    "was slain by",
+    "was smashed by",
+    "was squashed by",
+    "was squished too much",
+    "was stung to death",
+    "was struck by lightning",
+    "went off with a bang",
+    "went up in flames",
+    "withered away",
+];
+
+static DEATH: Lazy<String> = Lazy::new(|| {
+    let deaths = DEATHS
+        .iter()
+        .map(|death| regex::escape(death))
+        .join_with("|");
+    format!(r"([A-Za-z0-9_]{{1,16}}) ((?:{})\b.*)", deaths)
+});
+
+// Newer servers mark chat from clients that don't sign their messages.
+static MESSAGE: &str = r"(?:\[Not Secure\] )?<([^ \]]*)> (.*)";
+
+static SAVED: &str = r"Saved the game";
+
+static VERSION: &str = r"Starting minecraft server version (.*)";
+
+static DONE: &str = r#"Done \(([0-9.,]+)s\)! For help, type "help""#;
+
+static STOPPING: &str = r"Stopping (?:the )?server";
+
+// Vanilla doesn't log these, but Paper and sleep plugins commonly do.
+static SLEEPING: &str = r"([A-Za-z0-9_]{1,16}) (?:is now sleeping|went to bed)";
+
+// Paper and Spigot log the commands players run.
+static ACTIVITY: &str = r"([A-Za-z0-9_]{1,16}) issued server command: ";
+
+static NIGHT_SKIPPED: &str =
+    r"(?:Skipping the night|Sleeping through this night|The night has been skipped)";
+
+// Feedback from the `weather` command, e.g. `[Steve: Set the weather to rain]`.
+static WEATHER: &str = r"(?:\[[^\]:]*: )?Set the weather to ([a-z &]+?)\]?$";
+
+static TPS: &str = r"TPS from last 1m, 5m, 15m: (?:§.)?\*?([0-9.]+)";
+
+static TPS_RESPONSE: Lazy<Regex> = Lazy::new(|| Regex::new(TPS).unwrap());
+
+static GAMETIME: &str = r"The time is (\d+)";
+
+static GAMETIME_RESPONSE: Lazy<Regex> = Lazy::new(|| Regex::new(GAMETIME).unwrap());
+
+// Since 1.13. Player names follow the colon, separated by commas.
+static LIST: &str = r"There are \d+ of a max(?: of)? (\d+) players online:(.*)";
+
+static LIST_RESPONSE: Lazy<Regex> = Lazy::new(|| Regex::new(LIST).unwrap());
+
+static SEED: &str = r"Seed: \[(-?\d+)\]";
+
+static SEED_RESPONSE: Lazy<Regex> = Lazy::new(|| Regex::new(SEED).unwrap());
+
+static DIFFICULTY: &str = r"The difficulty (?:is|has been set to) (\w+)";
+
+static DIFFICULTY_RESPONSE: Lazy<Regex> = Lazy::new(|| Regex::new(DIFFICULTY).unwrap());
+
+static ENTITY_DATA: &str = r"([A-Za-z0-9_]{1,16}) has the following entity data: (.*)";
+
+static ENTITY_DATA_RESPONSE: Lazy<Regex> = Lazy::new(|| Regex::new(ENTITY_DATA).unwrap());
+
+// SNBT list of three doubles, e.g. `[12.5d, 64.0d, -3.5d]`.
+static POSITION: Lazy<Regex> = Lazy::new(|| {
+    let double = r"(-?[0-9.]+(?:E-?[0-9]+)?)d";
+    Regex::new(&format!(r"^\[{0}, {0}, {0}\]$", double)).unwrap()
+});
+
+// The first group matches when the command succeeded, and the second when it failed.
+static WHITELIST: &str = r"(?:((?:Added|Removed) [A-Za-z0-9_]+ (?:to|from) the whitelist|There are \d+ whitelisted players?(?:\(s\))?:.*|There are no whitelisted players)|(Player is already whitelisted|Player is not whitelisted|That player does not exist))";
+
+static WHITELIST_RESPONSE: Lazy<Regex> = Lazy::new(|| Regex::new(WHITELIST).unwrap());
+
+// Likewise, the first group matches when the command succeeded. Kicking an
+// offline player fails to find them, and banning an unknown player fails
+// like the `whitelist` command above.
+static MODERATION: &str = r"(?:((?:Banned|Kicked) [A-Za-z0-9_]+: .*|Unbanned [A-Za-z0-9_]+)|(Nothing changed\. The player is already banned|Nothing changed\. The player isn't banned|No player was found))";
+
+static MODERATION_RESPONSE: Lazy<Regex> = Lazy::new(|| Regex::new(MODERATION).unwrap());
+
+/// Server log patterns, falling back to the defaults above.
+#[derive(Debug)]
+pub struct Parser {
+    /// Start of every line logged by the server thread.
+    prefix: Regex,
+    join: Regex,
+    quit: Regex,
+    switch: Regex,
+    achievement: Regex,
+    death: Regex,
+    message: Regex,
+    saved: Regex,
+    list: Regex,
+    seed: Regex,
+    difficulty: Regex,
+    entity_data: Regex,
+    version: Regex,
+    done: Regex,
+    stopping: Regex,
+    sleeping: Regex,
+    night_skipped: Regex,
+    weather: Regex,
+    tps: Regex,
+    gametime: Regex,
+    whitelist: Regex,
+    moderation: Regex,
+    activity: Regex,
+    /// Language of the server's log, which advancements are announced in.
+    locale: Locale,
+    rules: Vec<Rule>,
+}
+
+/// User-defined pattern, and the message to post when it matches.
+#[derive(Debug)]
+struct Rule {
+    regex: Regex,
+    message: String,
+    channel: Option<u64>,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Parser::new(
+            Flavor::default(),
+            Locale::default(),
+            config::Patterns::default(),
+            Vec::new(),
+        )
+        .expect("[INTERNAL ERROR]: default patterns should compile")
+    }
+}
+
+impl Parser {
+    /// Prefix the default patterns for `flavor`, translated for `locale`.
+    /// Overrides in `patterns` and `rules` are used verbatim.
+    pub fn new(
+        flavor: Flavor,
+        locale: Locale,
+        patterns: config::Patterns,
+        rules: Vec<config::Rule>,
+    ) -> anyhow::Result<Self> {
+        let compile = |name: &str, pattern: Option<String>, default: &str| {
+            let localized = locale.pattern(name);
+            let default = match flavor {
+                Flavor::Bedrock => bedrock::pattern(name),
+                Flavor::Velocity | Flavor::Bungeecord => proxy::pattern(flavor, name),
+                _ => localized.as_deref().unwrap_or(default),
+            };
+            let pattern = pattern.unwrap_or_else(|| format!("{}{}", flavor.prefix(), default));
+            Regex::new(&pattern)
+                .with_context(|| format!("Failed to compile `regex.{}` pattern", name))
+        };
+
+        Ok(Parser {
+            prefix: Regex::new(flavor.prefix())?,
+            join: compile("join", patterns.join, JOIN)?,
+            quit: compile("quit", patterns.quit, QUIT)?,
+            switch: compile("switch", patterns.switch, NEVER)?,
+            achievement: compile("achievement", patterns.achievement, ACHIEVEMENT)?,
+            death: compile("death", patterns.death, &DEATH)?,
+            message: compile("message", patterns.message, MESSAGE)?,
+            saved: compile("saved", patterns.saved, SAVED)?,
+            list: compile("list", patterns.list, LIST)?,
+            seed: compile("seed", patterns.seed, SEED)?,
+            difficulty: compile("difficulty", patterns.difficulty, DIFFICULTY)?,
+            entity_data: compile("entity-data", patterns.entity_data, ENTITY_DATA)?,
+            version: compile("version", patterns.version, VERSION)?,
+            done: compile("done", patterns.done, DONE)?,
+            stopping: compile("stopping", patterns.stopping, STOPPING)?,
+            sleeping: compile("sleeping", patterns.sleeping, SLEEPING)?,
+            night_skipped: compile("night-skipped", patterns.night_skipped, NIGHT_SKIPPED)?,
+            weather: compile("weather", patterns.weather, WEATHER)?,
+            tps: compile("tps", patterns.tps, TPS)?,
+            gametime: compile("gametime", patterns.gametime, GAMETIME)?,
+            whitelist: compile("whitelist", patterns.whitelist, WHITELIST)?,
+            moderation: compile("moderation", patterns.moderation, MODERATION)?,
+            activity: compile("activity", patterns.activity, ACTIVITY)?,
+            locale,
+            rules: rules
+                .into_iter()
+                .enumerate()
+                .map(|(index, rule)| {
+                    Ok(Rule {
+                        regex: Regex::new(&rule.pattern).with_context(|| {
+                            format!("Failed to compile `rules[{}].pattern`", index)
+                        })?,
+                        message: rule.message,
+                        channel: rule.channel,
+                    })
+                })
+                .collect::<anyhow::Result<_>>()?,
+        })
+    }
+
+    /// What the server thread logged in `line`, without the prefix, or `None`
+    /// for lines from other threads and levels.
+    pub fn message<'a>(&self, line: &'a str) -> Option<&'a str> {
+        self.prefix.find(line).map(|prefix| &line[prefix.end()..])
+    }
+
+    pub fn parse(&self, line: &str) -> Option<Log> {
+        // Chat first, since players can type anything the others look for.
+        if let Some(captures) = self.message.captures(line) {
+            Some(Log::Message {
+                player: captures[1].to_owned(),
+                message: captures[2].to_owned(),
+            })
+        } else if let Some(captures) = self.join.captures(line) {
+            Some(Log::Join {
+                player: captures[1].to_owned(),
+            })
+        } else if let Some(captures) = self.quit.captures(line) {
+            Some(Log::Quit {
+                player: captures[1].to_owned(),
+            })
+        } else if let Some(captures) = self.switch.captures(line) {
+            Some(Log::Switch {
+                player: captures[1].to_owned(),
+                server: captures[2].to_owned(),
+            })
+        } else if let Some(captures) = self.achievement.captures(line) {
+            Some(Log::Achievement {
+                player: captures[1].to_owned(),
+                achievement: captures[2].to_owned(),
+                frame: self.locale.frame(&captures[0]),
+            })
+        } else if let Some(captures) = self.death.captures(line) {
+            Some(Log::Death {
+                player: captures[1].to_owned(),
+                message: captures[2].to_owned(),
+            })
+        } else if self.saved.is_match(line) {
+            Some(Log::Saved)
+        } else if let Some(captures) = self.list.captures(line) {
+            Some(Log::List {
+                max: captures[1].parse().ok()?,
+                players: players(&captures[2]),
+            })
+        } else if let Some(captures) = self.seed.captures(line) {
+            Some(Log::Seed {
+                seed: captures[1].to_owned(),
+            })
+        } else if let Some(captures) = self.difficulty.captures(line) {
+            Some(Log::Difficulty {
+                difficulty: captures[1].to_owned(),
+            })
+        } else if let Some(captures) = self.entity_data.captures(line) {
+            Some(Log::EntityData {
+                player: captures[1].to_owned(),
+                data: captures[2].to_owned(),
+            })
+        } else if let Some(captures) = self.version.captures(line) {
+            Some(Log::Version {
+                version: captures[1].to_owned(),
+            })
+        } else if let Some(captures) = self.done.captures(line) {
+            // Some locales use a decimal comma.
+            Some(Log::Done {
+                seconds: captures
+                    .get(1)
+                    .and_then(|seconds| seconds.as_str().replace(',', ".").parse().ok()),
+            })
+        } else if self.stopping.is_match(line) {
+            Some(Log::Stopping)
+        } else if let Some(captures) = self.sleeping.captures(line) {
+            Some(Log::Sleeping {
+                player: captures[1].to_owned(),
+            })
+        } else if self.night_skipped.is_match(line) {
+            Some(Log::NightSkipped)
+        } else if let Some(captures) = self.weather.captures(line) {
+            Some(Log::Weather {
+                weather: captures[1].to_owned(),
+            })
+        } else if let Some(captures) = self.tps.captures(line) {
+            Some(Log::Tps {
+                tps: captures[1].parse().ok()?,
+            })
+        } else if let Some(captures) = self.gametime.captures(line) {
+            Some(Log::Gametime {
+                ticks: captures[1].parse().ok()?,
+            })
+        } else if let Some(captures) = self.whitelist.captures(line) {
+            Some(Log::Whitelist {
+                result: outcome(&captures),
+            })
+        } else if let Some(captures) = self.moderation.captures(line) {
+            Some(Log::Moderation {
+                result: outcome(&captures),
+            })
+        } else if let Some(log) = self.rules.iter().find_map(|rule| rule.apply(line)) {
+            Some(log)
+        } else {
+            self.activity.captures(line).map(|captures| Log::Activity {
+                player: captures[1].to_owned(),
+            })
+        }
+    }
+}
+
+/// Parse the `list` command's response when it's returned directly, e.g. over RCON.
+pub fn parse_list(response: &str) -> Option<(usize, Vec<String>)> {
+    let captures = LIST_RESPONSE.captures(response)?;
+    Some((captures[1].parse().ok()?, players(&captures[2])))
+}
+
+/// Parse Paper's `tps` command's response when it's returned directly, e.g. over RCON.
+pub fn parse_tps(response: &str) -> Option<f64> {
+    TPS_RESPONSE.captures(response)?[1].parse().ok()
+}
+
+/// Parse the `time query gametime` command's response when it's returned
+/// directly, e.g. over RCON.
+pub fn parse_gametime(response: &str) -> Option<u64> {
+    GAMETIME_RESPONSE.captures(response)?[1].parse().ok()
+}
+
+/// Parse the `seed` command's response when it's returned directly, e.g. over RCON.
+pub fn parse_seed(response: &str) -> Option<String> {
+    SEED_RESPONSE
+        .captures(response)
+        .map(|captures| captures[1].to_owned())
+}
+
+/// Parse the `difficulty` command's response when it's returned directly, e.g. over RCON.
+pub fn parse_difficulty(response: &str) -> Option<String> {
+    DIFFICULTY_RESPONSE
+        .captures(response)
+        .map(|captures| captures[1].to_owned())
+}
+
+/// Parse the `data get entity` command's response when it's returned
+/// directly, e.g. over RCON.
+pub fn parse_entity_data(response: &str) -> Option<(String, String)> {
+    ENTITY_DATA_RESPONSE
+        .captures(response)
+        .map(|captures| (captures[1].to_owned(), captures[2].to_owned()))
+}
+
+/// Parse a player's `Pos` from `data get entity`.
+pub fn parse_position(data: &str) -> Option<[f64; 3]> {
+    let captures = POSITION.captures(data.trim())?;
+    Some([
+        captures[1].parse().ok()?,
+        captures[2].parse().ok()?,
+        captures[3].parse().ok()?,
+    ])
+}
+
+/// Parse the `whitelist` command's response when it's returned directly, e.g. over RCON.
+pub fn parse_whitelist(response: &str) -> Option<Result<String, String>> {
+    WHITELIST_RESPONSE
+        .captures(response)
+        .map(|captures| outcome(&captures))
+}
+
+/// Parse the `ban`, `pardon`, or `kick` command's response when it's returned
+/// directly, e.g. over RCON.
+pub fn parse_moderation(response: &str) -> Option<Result<String, String>> {
+    MODERATION_RESPONSE
+        .captures(response)
+        .map(|captures| outcome(&captures))
+}
+
+/// The first group matches a successful response, and the second a failure.
+fn outcome(captures: &regex::Captures) -> Result<String, String> {
+    match captures.get(1) {
+        Some(success) => Ok(success.as_str().to_owned()),
+        None => Err(captures[2].to_owned()),
+    }
+}
+
+/// Player names separated by commas, as listed by the `list` command.
+pub fn players(names: &str) -> Vec<String> {
+    names
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+impl Rule {
+    /// Substitute numbered (`{1}`) and named (`{name}`) capture groups, escaped
+    /// for Discord, into the message.
+    fn apply(&self, line: &str) -> Option<Log> {
+        let captures = self.regex.captures(line)?;
+        let numbered = (0..captures.len()).filter_map(|index| {
+            Some((
+                index.to_string(),
+                markdown::escape(captures.get(index)?.as_str()),
+            ))
+        });
+        let named = self.regex.capture_names().flatten().filter_map(|name| {
+            Some((
+                name.to_owned(),
+                markdown::escape(captures.name(name)?.as_str()),
+            ))
+        });
+        let values = numbered.chain(named).collect::<Vec<_>>();
+        let values = values
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect::<Vec<_>>();
+        Some(Log::Custom {
+            channel: self.channel,
+            message: template::render(&self.message, &values),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chat_cannot_fake_death() {
+        let parser = Parser::default();
+        let line = "[12:34:56] [Server thread/INFO]: <Mallory> [Server thread/INFO]: Steve died";
+        assert_eq!(
+            parser.parse(line),
+            Some(Log::Message {
+                player: String::from("Mallory"),
+                message: String::from("[Server thread/INFO]: Steve died"),
+            }),
+        );
+    }
+
+    #[test]
+    fn chat_cannot_impersonate() {
+        let parser = Parser::default();
+        let line = "[12:34:56] [Server thread/INFO]: <Mallory> [Server thread/INFO]: <Steve> hi";
+        assert!(matches!(
+            parser.parse(line),
+            Some(Log::Message { player, .. }) if player == "Mallory",
+        ));
+    }
+
+    #[test]
+    fn death() {
+        let parser = Parser::default();
+        let line = "[12:34:56] [Server thread/INFO]: Steve was slain by Zombie";
+        assert_eq!(
+            parser.parse(line),
+            Some(Log::Death {
+                player: String::from("Steve"),
+                message: String::from("was slain by Zombie"),
+            }),
+        );
+    }
+}
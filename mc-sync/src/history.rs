@@ -0,0 +1,303 @@
+use std::path::Path;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use anyhow::anyhow;
+use anyhow::Context as _;
+use rusqlite::params;
+use rusqlite::Connection;
+use rusqlite::OptionalExtension as _;
+
+use crate::events::Log;
+
+/// Schema, by `user_version`. Each step upgrades from the one before it.
+static MIGRATIONS: &[&str] = &[r"
+    CREATE TABLE events (
+        id INTEGER PRIMARY KEY,
+        time INTEGER NOT NULL,
+        server TEXT,
+        kind TEXT NOT NULL,
+        player TEXT COLLATE NOCASE,
+        message TEXT
+    );
+    CREATE INDEX events_player ON events (server, player, kind, time);
+    CREATE INDEX events_kind ON events (server, kind, time);
+"];
+
+/// Kinds of event kept in the history.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Kind {
+    Join,
+    Quit,
+    Chat,
+    Death,
+    Advancement,
+    /// A console command, run by `player`: a Discord user, or where it came
+    /// from, like `terminal`.
+    Command,
+}
+
+impl Kind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Kind::Join => "join",
+            Kind::Quit => "quit",
+            Kind::Chat => "chat",
+            Kind::Death => "death",
+            Kind::Advancement => "advancement",
+            Kind::Command => "command",
+        }
+    }
+
+    fn parse(kind: &str) -> Option<Self> {
+        let kind = match kind {
+            "join" => Kind::Join,
+            "quit" => Kind::Quit,
+            "chat" => Kind::Chat,
+            "death" => Kind::Death,
+            "advancement" => Kind::Advancement,
+            "command" => Kind::Command,
+            _ => return None,
+        };
+        Some(kind)
+    }
+}
+
+/// An event, as kept in the history.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Entry {
+    /// Seconds since the Unix epoch.
+    pub time: i64,
+    pub kind: Kind,
+    pub player: String,
+    /// Chat and death messages, advancements, and commands.
+    pub message: Option<String>,
+}
+
+impl Entry {
+    /// The entry for a server event, if it's kept.
+    pub fn new(log: &Log) -> Option<Self> {
+        let (kind, player, message) = match log {
+            Log::Join { player } => (Kind::Join, player, None),
+            Log::Quit { player } => (Kind::Quit, player, None),
+            Log::Message { player, message } => (Kind::Chat, player, Some(message)),
+            Log::Death { player, message } => (Kind::Death, player, Some(message)),
+            Log::Achievement {
+                player,
+                achievement,
+                ..
+            } => (Kind::Advancement, player, Some(achievement)),
+            _ => return None,
+        };
+        Some(Entry {
+            time: now(),
+            kind,
+            player: player.clone(),
+            message: message.cloned(),
+        })
+    }
+
+    /// A console command run by `by`.
+    pub fn command(by: &str, command: &str) -> Self {
+        Entry {
+            time: now(),
+            kind: Kind::Command,
+            player: by.to_owned(),
+            message: Some(command.to_owned()),
+        }
+    }
+}
+
+/// Which entries to look up. Each field that's set narrows it down.
+#[derive(Clone, Debug, Default)]
+pub struct Filter<'a> {
+    pub kinds: &'a [Kind],
+    pub player: Option<&'a str>,
+    /// Substring of the message, ignoring ASCII case.
+    pub message: Option<&'a str>,
+    /// Only entries at or after this time.
+    pub since: Option<i64>,
+}
+
+/// Every event the bridge has seen on one server, in an SQLite database.
+/// Several servers can share a database, but each only sees its own events.
+///
+/// Queries are quick enough to run on the bridge's thread.
+pub struct History {
+    connection: Connection,
+    server: Option<String>,
+}
+
+impl History {
+    pub fn open(path: &Path, server: Option<&str>) -> anyhow::Result<Self> {
+        let mut connection = Connection::open(path)
+            .with_context(|| format!("Failed to open history `{}`", path.display()))?;
+        // Writing doesn't block reading, e.g. from `sqlite3`.
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        // Another server's bridge may be writing.
+        connection.busy_timeout(Duration::from_secs(5))?;
+
+        let version =
+            connection.pragma_query_value(None, "user_version", |row| row.get::<_, usize>(0))?;
+        if version > MIGRATIONS.len() {
+            return Err(anyhow!(
+                "History `{}` is from a newer version of mc-sync",
+                path.display(),
+            ));
+        }
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let transaction = connection.transaction()?;
+            transaction.execute_batch(migration)?;
+            transaction.pragma_update(None, "user_version", index + 1)?;
+            transaction.commit()?;
+        }
+
+        Ok(History {
+            connection,
+            server: server.map(String::from),
+        })
+    }
+
+    pub fn record(&self, entry: &Entry) -> anyhow::Result<()> {
+        self.connection
+            .prepare_cached(
+                "INSERT INTO events (time, server, kind, player, message)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?
+            .execute(params![
+                entry.time,
+                self.server,
+                entry.kind.as_str(),
+                entry.player,
+                entry.message,
+            ])?;
+        Ok(())
+    }
+
+    /// The most recent `limit` entries matching `filter`, newest first.
+    pub fn recent(&self, filter: &Filter, limit: usize) -> anyhow::Result<Vec<Entry>> {
+        let (conditions, mut values) = self.conditions(filter);
+        values.push(Box::new(limit as i64));
+        let query = format!(
+            "SELECT time, kind, player, message FROM events WHERE {}
+             ORDER BY time DESC, id DESC LIMIT ?",
+            conditions,
+        );
+        self.entries(&query, values)
+    }
+
+    /// The first entry matching `filter`, if any.
+    pub fn first(&self, filter: &Filter) -> anyhow::Result<Option<Entry>> {
+        let (conditions, values) = self.conditions(filter);
+        let query = format!(
+            "SELECT time, kind, player, message FROM events WHERE {}
+             ORDER BY time, id LIMIT 1",
+            conditions,
+        );
+        Ok(self.entries(&query, values)?.into_iter().next())
+    }
+
+    /// Number of entries matching `filter` for each player, most first, e.g.
+    /// for a leaderboard.
+    pub fn count(&self, filter: &Filter, limit: usize) -> anyhow::Result<Vec<(String, u64)>> {
+        let (conditions, mut values) = self.conditions(filter);
+        values.push(Box::new(limit as i64));
+        let query = format!(
+            "SELECT player, COUNT(*) AS count FROM events WHERE {}
+             GROUP BY player ORDER BY count DESC, player LIMIT ?",
+            conditions,
+        );
+        let mut statement = self.connection.prepare_cached(&query)?;
+        let counts = statement
+            .query_map(rusqlite::params_from_iter(values), |row| {
+                Ok((row.get(0)?, row.get::<_, i64>(1)? as u64))
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(counts)
+    }
+
+    /// Whether anything has been recorded for `player`.
+    pub fn knows(&self, player: &str) -> anyhow::Result<bool> {
+        let known = self
+            .connection
+            .prepare_cached("SELECT 1 FROM events WHERE server IS ?1 AND player = ?2 LIMIT 1")?
+            .query_row(params![self.server, player], |_| Ok(()))
+            .optional()?;
+        Ok(known.is_some())
+    }
+
+    fn conditions(&self, filter: &Filter) -> (String, Vec<Box<dyn rusqlite::ToSql>>) {
+        let mut conditions = vec![String::from("server IS ?")];
+        let mut values = vec![Box::new(self.server.clone()) as Box<dyn rusqlite::ToSql>];
+        if !filter.kinds.is_empty() {
+            conditions.push(format!(
+                "kind IN ({})",
+                vec!["?"; filter.kinds.len()].join(", "),
+            ));
+            values.extend(
+                filter
+                    .kinds
+                    .iter()
+                    .map(|kind| Box::new(kind.as_str()) as Box<dyn rusqlite::ToSql>),
+            );
+        }
+        if let Some(player) = filter.player {
+            conditions.push(String::from("player = ?"));
+            values.push(Box::new(player.to_owned()));
+        }
+        if let Some(message) = filter.message {
+            conditions.push(String::from(r"message LIKE ? ESCAPE '\'"));
+            let escaped = message
+                .replace('\\', r"\\")
+                .replace('%', r"\%")
+                .replace('_', r"\_");
+            values.push(Box::new(format!("%{}%", escaped)));
+        }
+        if let Some(since) = filter.since {
+            conditions.push(String::from("time >= ?"));
+            values.push(Box::new(since));
+        }
+        (conditions.join(" AND "), values)
+    }
+
+    fn entries(
+        &self,
+        query: &str,
+        values: Vec<Box<dyn rusqlite::ToSql>>,
+    ) -> anyhow::Result<Vec<Entry>> {
+        let mut statement = self.connection.prepare_cached(query)?;
+        let rows = statement.query_map(rusqlite::params_from_iter(values), |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+            ))
+        })?;
+
+        let mut entries = Vec::new();
+        for row in rows {
+            let (time, kind, player, message) = row?;
+            // From a newer version of mc-sync.
+            let kind = match Kind::parse(&kind) {
+                None => continue,
+                Some(kind) => kind,
+            };
+            entries.push(Entry {
+                time,
+                kind,
+                player,
+                message,
+            });
+        }
+        Ok(entries)
+    }
+}
+
+/// Seconds since the Unix epoch.
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64)
+}
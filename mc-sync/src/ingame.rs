@@ -0,0 +1,114 @@
+use crate::config;
+
+/// Command typed in Minecraft chat, e.g. `!seen Steve`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Command {
+    /// List the commands.
+    Help,
+    Online,
+    /// Show the Discord invite link.
+    Discord,
+    /// Time played by another player, or the one asking.
+    Playtime {
+        player: Option<String>,
+    },
+    Seen {
+        player: String,
+    },
+    Waypoints,
+    SetWaypoint {
+        name: String,
+    },
+    DeleteWaypoint {
+        name: String,
+    },
+    /// Redeem a code from `/link`.
+    Link {
+        code: String,
+    },
+    /// One of the configured `replies`, with its text.
+    Reply {
+        text: String,
+    },
+    /// A known command, used wrong, and how to use it.
+    Usage(String),
+}
+
+/// The command in a chat message, or `None` if the message should be relayed
+/// as usual.
+pub fn parse(config: &config::InGame, message: &str) -> Option<Command> {
+    let message = message.trim().strip_prefix(&config.prefix)?;
+    let (name, argument) = match message.split_once(char::is_whitespace) {
+        Some((name, argument)) => (
+            name,
+            Some(argument.trim()).filter(|argument| !argument.is_empty()),
+        ),
+        None => (message, None),
+    };
+    let name = name.to_lowercase();
+    let usage =
+        |arguments: &str| Command::Usage(format!("Usage: {}{} {}", config.prefix, name, arguments));
+
+    // Codes are only six digits, and so are easy to tell from chat.
+    if name == "link" {
+        let code = argument?;
+        let valid = code.len() == 6 && code.chars().all(|char| char.is_ascii_digit());
+        return valid.then(|| Command::Link {
+            code: code.to_owned(),
+        });
+    }
+    if !config.enabled {
+        return None;
+    }
+
+    let command = match (name.as_str(), argument) {
+        ("help", _) => Command::Help,
+        ("online", _) => Command::Online,
+        ("discord", _) if config.discord.is_some() => Command::Discord,
+        ("playtime", player) => Command::Playtime {
+            player: player.map(String::from),
+        },
+        ("seen", Some(player)) => Command::Seen {
+            player: player.to_owned(),
+        },
+        ("seen", None) => usage("<player>"),
+        ("waypoints", _) => Command::Waypoints,
+        ("setwaypoint", Some(name)) => Command::SetWaypoint {
+            name: name.to_owned(),
+        },
+        ("delwaypoint", Some(name)) => Command::DeleteWaypoint {
+            name: name.to_owned(),
+        },
+        ("setwaypoint", None) | ("delwaypoint", None) => usage("<name>"),
+        (name, _) => {
+            let text = config
+                .replies
+                .iter()
+                .find(|(reply, _)| reply.eq_ignore_ascii_case(name))
+                .map(|(_, text)| text.clone())?;
+            Command::Reply { text }
+        }
+    };
+    Some(command)
+}
+
+/// Every command available with `config`, for `help`.
+pub fn help(config: &config::InGame) -> String {
+    let mut commands = vec![
+        "online",
+        "playtime [player]",
+        "seen <player>",
+        "waypoints",
+        "setwaypoint <name>",
+        "delwaypoint <name>",
+    ];
+    if config.discord.is_some() {
+        commands.push("discord");
+    }
+    commands.extend(config.replies.keys().map(String::as_str));
+    let commands = commands
+        .iter()
+        .map(|command| format!("{}{}", config.prefix, command))
+        .collect::<Vec<_>>();
+    format!("Commands: {}", commands.join(", "))
+}
@@ -0,0 +1,250 @@
+use std::fs;
+use std::io;
+use std::io::Write as _;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+
+use anyhow::Context as _;
+use chrono::DateTime;
+use chrono::Local;
+use chrono::NaiveDateTime;
+use flate2::write::GzEncoder;
+use serde::Deserialize;
+
+use crate::tag;
+
+/// Appended to rotated files, which sort by when they were rotated.
+static TIMESTAMP: &str = "%Y-%m-%dT%H-%M-%S";
+
+/// Lines to hold while the disk is slow, before dropping more.
+static QUEUE: usize = 1000;
+
+/// Record of what the bridge sees and relays, in files of its own: the
+/// server's output, chat from Discord, and everything posted to Discord.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Journal {
+    pub directory: PathBuf,
+    pub rotate: Rotate,
+    /// Rotate before the file grows past this many bytes.
+    pub max_size: Option<u64>,
+    /// Compress rotated files with gzip.
+    pub compress: bool,
+    /// Rotated files to keep, or all of them.
+    pub keep: Option<usize>,
+}
+
+/// When to start a new file, besides when it's too big.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rotate {
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
+impl Rotate {
+    /// Format of the period a line belongs to, which changes when it's time
+    /// to rotate.
+    fn period(self) -> &'static str {
+        match self {
+            Rotate::Hourly => "%Y-%m-%d %H",
+            Rotate::Daily => "%Y-%m-%d",
+            Rotate::Never => "",
+        }
+    }
+}
+
+/// Writes to the journal on its own thread, so the bridge never waits on
+/// the disk.
+pub struct Recorder {
+    journal: Journal,
+    tx: mpsc::SyncSender<String>,
+}
+
+impl Recorder {
+    /// Start recording to `<server>.log` in the journal's directory, or
+    /// `mc-sync.log` for an unnamed server.
+    pub fn new(journal: Journal, server: Option<&str>) -> anyhow::Result<Self> {
+        let mut file = File::open(journal.clone(), server.unwrap_or("mc-sync"))?;
+        let (tx, rx) = mpsc::sync_channel::<String>(QUEUE);
+        thread::spawn(move || {
+            // Ends when the recorder is dropped, e.g. on reload.
+            for line in rx {
+                if let Err(error) = file.write(&line) {
+                    tracing::warn!("Failed to write to the journal: {:?}", error);
+                }
+            }
+        });
+        Ok(Recorder { journal, tx })
+    }
+
+    pub fn journal(&self) -> &Journal {
+        &self.journal
+    }
+
+    /// Record a line from `source`, with the time.
+    pub fn record(&self, source: tag::Source, line: &str) {
+        let line = format!(
+            "{} {} {}\n",
+            Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+            source.tag(),
+            line,
+        );
+        if self.tx.try_send(line).is_err() {
+            tracing::debug!("Journal is behind, dropping a line");
+        }
+    }
+}
+
+/// The file currently being written, and when it was started.
+struct File {
+    journal: Journal,
+    name: String,
+    file: fs::File,
+    size: u64,
+    period: String,
+}
+
+impl File {
+    fn open(journal: Journal, name: &str) -> anyhow::Result<Self> {
+        fs::create_dir_all(&journal.directory).with_context(|| {
+            format!(
+                "Failed to create journal directory `{}`",
+                journal.directory.display(),
+            )
+        })?;
+        let path = journal.directory.join(format!("{}.log", name));
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open journal `{}`", path.display()))?;
+
+        // Pick up where an earlier run left off.
+        let metadata = file.metadata()?;
+        let modified = metadata
+            .modified()
+            .map(DateTime::<Local>::from)
+            .unwrap_or_else(|_| Local::now());
+        Ok(File {
+            period: modified.format(journal.rotate.period()).to_string(),
+            size: metadata.len(),
+            journal,
+            name: name.to_owned(),
+            file,
+        })
+    }
+
+    fn path(&self) -> PathBuf {
+        self.journal.directory.join(format!("{}.log", self.name))
+    }
+
+    fn write(&mut self, line: &str) -> anyhow::Result<()> {
+        let now = Local::now();
+        let period = now.format(self.journal.rotate.period()).to_string();
+        let full = self
+            .journal
+            .max_size
+            .is_some_and(|max| self.size + line.len() as u64 > max);
+        if self.size > 0 && (period != self.period || full) {
+            self.rotate(now)?;
+        }
+        self.period = period;
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Move the current file aside, compress it if configured, and start a
+    /// new one.
+    fn rotate(&mut self, now: DateTime<Local>) -> anyhow::Result<()> {
+        let current = self.path();
+        let mut rotated =
+            self.journal
+                .directory
+                .join(format!("{}-{}.log", self.name, now.format(TIMESTAMP)));
+        // Rotated twice in a second, by size.
+        let mut count = 1;
+        while rotated.exists() || rotated.with_extension("log.gz").exists() {
+            rotated = self.journal.directory.join(format!(
+                "{}-{}.{}.log",
+                self.name,
+                now.format(TIMESTAMP),
+                count,
+            ));
+            count += 1;
+        }
+
+        fs::rename(&current, &rotated)
+            .with_context(|| format!("Failed to rotate journal `{}`", current.display()))?;
+        self.file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&current)
+            .with_context(|| format!("Failed to open journal `{}`", current.display()))?;
+        self.size = 0;
+
+        if self.journal.compress {
+            compress(&rotated)?;
+        }
+        self.prune()
+    }
+
+    /// Delete the oldest rotated files beyond the number to keep.
+    fn prune(&self) -> anyhow::Result<()> {
+        let keep = match self.journal.keep {
+            None => return Ok(()),
+            Some(keep) => keep,
+        };
+
+        let mut rotated = fs::read_dir(&self.journal.directory)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| self.is_rotated(path))
+            .collect::<Vec<_>>();
+        rotated.sort();
+
+        let excess = rotated.len().saturating_sub(keep);
+        for path in &rotated[..excess] {
+            fs::remove_file(path)
+                .with_context(|| format!("Failed to delete journal `{}`", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Whether `path` was rotated from this file, and not another server's
+    /// whose name starts the same.
+    fn is_rotated(&self, path: &Path) -> bool {
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            None => return false,
+            Some(name) => name,
+        };
+        let timestamp = name
+            .strip_suffix(".gz")
+            .unwrap_or(name)
+            .strip_suffix(".log")
+            .and_then(|name| name.strip_prefix(&self.name))
+            .and_then(|name| name.strip_prefix('-'))
+            .and_then(|name| name.get(..19));
+        match timestamp {
+            None => false,
+            Some(timestamp) => NaiveDateTime::parse_from_str(timestamp, TIMESTAMP).is_ok(),
+        }
+    }
+}
+
+/// Replace `path` with a gzipped `<path>.gz`.
+fn compress(path: &Path) -> anyhow::Result<()> {
+    let compressed = path.with_extension("log.gz");
+    let mut input = fs::File::open(path)?;
+    let output = fs::File::create(&compressed)
+        .with_context(|| format!("Failed to create `{}`", compressed.display()))?;
+    let mut encoder = GzEncoder::new(output, flate2::Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+    fs::remove_file(path)?;
+    Ok(())
+}
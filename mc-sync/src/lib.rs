@@ -0,0 +1,55 @@
+//! Wrap a Minecraft server and synchronize the chat with Discord.
+
+pub mod advancements;
+pub mod api;
+pub mod attachments;
+pub mod backup;
+pub mod bedrock;
+pub mod bridge;
+pub mod chat;
+pub mod color;
+pub mod commands;
+pub mod config;
+pub mod discord;
+pub mod emoji;
+pub mod events;
+pub mod history;
+pub mod ingame;
+pub mod journal;
+pub mod link;
+pub mod locale;
+pub mod logging;
+pub mod markdown;
+#[cfg(feature = "matrix")]
+pub mod matrix;
+pub mod mentions;
+pub mod metrics;
+pub mod minecraft;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+pub mod nbt;
+pub mod permissions;
+pub mod ping;
+pub mod playerdata;
+pub mod playtime;
+pub mod properties;
+pub mod proxy;
+pub mod query;
+pub mod rcon;
+pub mod restart;
+pub mod retry;
+pub mod roles;
+pub mod schedule;
+pub mod scoreboard;
+pub mod signal;
+pub mod sink;
+#[cfg(feature = "slack")]
+pub mod slack;
+pub mod stats;
+pub mod stdin;
+pub mod store;
+pub mod systemd;
+pub mod tag;
+pub mod tail;
+pub mod template;
+pub mod usage;
@@ -0,0 +1,41 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use std::time::Instant;
+
+use rand::Rng as _;
+use serenity::model::id;
+
+/// How long a code from `/link` stays valid.
+pub static EXPIRY: Duration = Duration::from_secs(10 * 60);
+
+/// One-time codes handed out by `/link`, waiting to be typed in Minecraft chat.
+#[derive(Debug, Default)]
+pub struct Linking {
+    codes: HashMap<String, (id::UserId, Instant)>,
+}
+
+impl Linking {
+    /// Hand out a new code to `user`, replacing any they had before.
+    pub fn issue(&mut self, user: id::UserId) -> String {
+        self.codes
+            .retain(|_, (issued_to, issued)| *issued_to != user && issued.elapsed() < EXPIRY);
+
+        let mut rng = rand::thread_rng();
+        loop {
+            let code = format!("{:06}", rng.gen_range(0, 1_000_000));
+            if !self.codes.contains_key(&code) {
+                self.codes.insert(code.clone(), (user, Instant::now()));
+                return code;
+            }
+        }
+    }
+
+    /// The user who was issued `code`, if it hasn't expired. Each code can only
+    /// be redeemed once.
+    pub fn redeem(&mut self, code: &str) -> Option<id::UserId> {
+        match self.codes.remove(code)? {
+            (user, issued) if issued.elapsed() < EXPIRY => Some(user),
+            _ => None,
+        }
+    }
+}
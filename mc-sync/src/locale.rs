@@ -0,0 +1,223 @@
+use anyhow::anyhow;
+use joinery::JoinableIterator;
+
+use crate::advancements;
+
+/// Language the server logs in, for servers (usually modded) that translate
+/// their log. Vanilla servers always log in English.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Locale {
+    #[default]
+    EnUs,
+    DeDe,
+    EsEs,
+    FrFr,
+    PtBr,
+    RuRu,
+    ZhCn,
+}
+
+/// Translated log lines, from the game's language files.
+struct Pack {
+    /// `chat.type.advancement.task`, `.goal`, and `.challenge`, as one
+    /// pattern capturing the player and the advancement.
+    advancement: &'static str,
+    /// Part of a goal's announcement, and likewise a challenge's.
+    goal: &'static str,
+    challenge: &'static str,
+    /// `commands.list.players`, capturing the max players and the names.
+    list: &'static str,
+    /// Start of each death message after the player's name, e.g. `ertrank`.
+    deaths: &'static [&'static str],
+    /// Separates the player's name from a death message.
+    space: &'static str,
+}
+
+static DE_DE: Pack = Pack {
+    advancement: r"(.*) hat (?:den Fortschritt|das Ziel|die Aufgabe) \[(.*)\] (?:erzielt|erreicht|gemeistert)",
+    goal: " das Ziel [",
+    challenge: " die Aufgabe [",
+    list: r"Es sind \d+ von maximal (\d+) Spielern online:(.*)",
+    deaths: &[
+        "starb",
+        "ertrank",
+        "erstickte",
+        "erfror",
+        "verbrannte",
+        "verhungerte",
+        "fiel",
+        "ging in Flammen auf",
+        "hat versucht, in Lava zu schwimmen",
+        "ist zu hart aufgeschlagen",
+        "wurde",
+    ],
+    space: " ",
+};
+
+static ES_ES: Pack = Pack {
+    advancement: r"(.*) ha (?:conseguido el progreso|alcanzado el objetivo|completado el desafío) \[(.*)\]",
+    goal: " el objetivo [",
+    challenge: " el desafío [",
+    list: r"Hay \d+ de un máximo de (\d+) jugadores conectados:(.*)",
+    deaths: &[
+        "murió",
+        "se ahogó",
+        "se asfixió",
+        "se congeló",
+        "se quemó",
+        "ardió",
+        "explotó",
+        "cayó",
+        "intentó nadar en lava",
+        "fue",
+    ],
+    space: " ",
+};
+
+static FR_FR: Pack = Pack {
+    advancement: r"(.*) a (?:accompli le progrès|atteint l'objectif|relevé le défi) \[(.*)\]",
+    goal: " l'objectif [",
+    challenge: " le défi [",
+    list: r"Il y a \d+ joueurs en ligne sur un maximum de (\d+) ?:(.*)",
+    deaths: &[
+        "est mort",
+        "s'est noyé",
+        "a suffoqué",
+        "est mort de froid",
+        "a brûlé",
+        "est parti en fumée",
+        "a explosé",
+        "est tombé",
+        "a essayé de nager dans la lave",
+        "a été",
+    ],
+    space: " ",
+};
+
+static PT_BR: Pack = Pack {
+    advancement: r"(.*) (?:conquistou o progresso|alcançou o objetivo|completou o desafio) \[(.*)\]",
+    goal: " o objetivo [",
+    challenge: " o desafio [",
+    list: r"Há \d+ de um máximo de (\d+) jogadores online:(.*)",
+    deaths: &[
+        "morreu",
+        "se afogou",
+        "sufocou",
+        "congelou",
+        "queimou",
+        "explodiu",
+        "caiu",
+        "tentou nadar na lava",
+        "foi",
+    ],
+    space: " ",
+};
+
+static RU_RU: Pack = Pack {
+    advancement: r"(.*) (?:получил достижение|достиг цели|выполнил испытание) \[(.*)\]",
+    goal: " достиг цели [",
+    challenge: " выполнил испытание [",
+    list: r"На сервере \d+ из (\d+) игроков:(.*)",
+    deaths: &[
+        "умер",
+        "утонул",
+        "задохнулся",
+        "замёрз",
+        "сгорел",
+        "взорвался",
+        "разбился",
+        "упал",
+        "пытался",
+        "был",
+    ],
+    space: " ",
+};
+
+static ZH_CN: Pack = Pack {
+    advancement: r"(.*)(?:取得了进度|达成了目标|完成了挑战)\[(.*)\]",
+    goal: "达成了目标[",
+    challenge: "完成了挑战[",
+    list: r"当前共有\d+名玩家在线（最大玩家数为(\d+)）：(.*)",
+    deaths: &[
+        "死了",
+        "淹死了",
+        "饿死了",
+        "冻死了",
+        "爆炸了",
+        "被",
+        "从高处摔了下来",
+        "落地过猛",
+        "浴火焚身",
+        "试图在熔岩里游泳",
+        "掉出了这个世界",
+    ],
+    space: "",
+};
+
+// These lines aren't translated, so they're the same in every language.
+// Quitting is also logged as `left the game`, but that is translated.
+static QUIT: &str = r"([A-Za-z0-9_]{1,16}) lost connection: .*";
+
+// Logged by `save-all flush`, which is how the bridge saves before backups.
+static SAVED: &str = r"ThreadedAnvilChunkStorage: All dimensions are saved";
+
+impl Locale {
+    /// Parse a locale like `de_de`, `de-DE`, or just `de`.
+    pub fn parse(locale: &str) -> anyhow::Result<Self> {
+        let locale = match locale.to_lowercase().replace('-', "_").as_str() {
+            "en" | "en_us" | "en_gb" => Locale::EnUs,
+            "de" | "de_de" => Locale::DeDe,
+            "es" | "es_es" => Locale::EsEs,
+            "fr" | "fr_fr" => Locale::FrFr,
+            "pt" | "pt_br" => Locale::PtBr,
+            "ru" | "ru_ru" => Locale::RuRu,
+            "zh" | "zh_cn" => Locale::ZhCn,
+            _ => return Err(anyhow!("Unsupported server locale `{}`", locale)),
+        };
+        Ok(locale)
+    }
+
+    fn pack(self) -> Option<&'static Pack> {
+        match self {
+            Locale::EnUs => None,
+            Locale::DeDe => Some(&DE_DE),
+            Locale::EsEs => Some(&ES_ES),
+            Locale::FrFr => Some(&FR_FR),
+            Locale::PtBr => Some(&PT_BR),
+            Locale::RuRu => Some(&RU_RU),
+            Locale::ZhCn => Some(&ZH_CN),
+        }
+    }
+
+    /// Default pattern for `regex.<name>` in this language, or `None` if
+    /// the English one applies.
+    pub fn pattern(self, name: &str) -> Option<String> {
+        let pack = self.pack()?;
+        let pattern = match name {
+            "quit" => String::from(QUIT),
+            "saved" => String::from(SAVED),
+            "list" => String::from(pack.list),
+            "achievement" => String::from(pack.advancement),
+            "death" => format!(
+                r"([A-Za-z0-9_]{{1,16}}){}((?:{}).*)",
+                pack.space,
+                pack.deaths
+                    .iter()
+                    .map(|death| regex::escape(death))
+                    .join_with("|"),
+            ),
+            _ => return None,
+        };
+        Some(pattern)
+    }
+
+    /// Determine an advancement's frame from the server's announcement.
+    pub fn frame(self, announcement: &str) -> advancements::Frame {
+        match self.pack() {
+            None => advancements::Frame::of(announcement),
+            Some(pack) if announcement.contains(pack.challenge) => advancements::Frame::Challenge,
+            Some(pack) if announcement.contains(pack.goal) => advancements::Frame::Goal,
+            Some(_) => advancements::Frame::Task,
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+
+use joinery::JoinableIterator;
+use serde::Deserialize;
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+use tracing::field;
+use tracing::level_filters::LevelFilter;
+use tracing::span;
+use tracing::Metadata;
+
+/// How log messages are written to stderr.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Format {
+    /// One line of text per message, with its spans and fields.
+    #[default]
+    Text,
+
+    /// One JSON object per message, for log aggregators.
+    Json,
+}
+
+thread_local! {
+    /// Spans entered on this thread, innermost last.
+    static STACK: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Write log messages from this crate at `level` and above, and from
+/// dependencies at warnings and above, to stderr.
+pub struct Logger {
+    level: LevelFilter,
+    format: Format,
+    next: AtomicU64,
+    spans: Mutex<HashMap<u64, Span>>,
+}
+
+struct Span {
+    name: &'static str,
+    fields: Map<String, Value>,
+    references: usize,
+}
+
+impl Logger {
+    pub fn new(level: LevelFilter, format: Format) -> Self {
+        Logger {
+            level,
+            format,
+            next: AtomicU64::new(1),
+            spans: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn install(self) -> anyhow::Result<()> {
+        tracing::subscriber::set_global_default(self)?;
+        Ok(())
+    }
+
+    fn spans(&self) -> std::sync::MutexGuard<'_, HashMap<u64, Span>> {
+        self.spans
+            .lock()
+            .expect("[INTERNAL ERROR]: logging panicked")
+    }
+}
+
+impl tracing::Subscriber for Logger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        if metadata.target().starts_with(env!("CARGO_CRATE_NAME")) {
+            *metadata.level() <= self.level
+        } else {
+            *metadata.level() <= self.level.min(LevelFilter::WARN)
+        }
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(self.level)
+    }
+
+    fn new_span(&self, attributes: &span::Attributes<'_>) -> span::Id {
+        let id = self.next.fetch_add(1, Ordering::Relaxed);
+        let mut fields = Map::new();
+        attributes.record(&mut Fields(&mut fields));
+        self.spans().insert(
+            id,
+            Span {
+                name: attributes.metadata().name(),
+                fields,
+                references: 1,
+            },
+        );
+        span::Id::from_u64(id)
+    }
+
+    fn record(&self, span: &span::Id, values: &span::Record<'_>) {
+        if let Some(span) = self.spans().get_mut(&span.into_u64()) {
+            values.record(&mut Fields(&mut span.fields));
+        }
+    }
+
+    fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+    fn event(&self, event: &tracing::Event<'_>) {
+        let mut fields = Map::new();
+        event.record(&mut Fields(&mut fields));
+        let message = fields.remove("message").map(text).unwrap_or_default();
+
+        let metadata = event.metadata();
+        let timestamp = chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        let stack = STACK.with(|stack| stack.borrow().clone());
+        let spans = self.spans();
+        let spans = stack.iter().filter_map(|id| spans.get(id));
+
+        let line = match self.format {
+            Format::Json => json!({
+                "timestamp": timestamp,
+                "level": metadata.level().as_str(),
+                "target": metadata.target(),
+                "spans": spans
+                    .map(|span| json!({ "name": span.name, "fields": span.fields }))
+                    .collect::<Vec<_>>(),
+                "message": message,
+                "fields": fields,
+            })
+            .to_string(),
+            Format::Text => {
+                let spans = spans
+                    .map(|span| {
+                        if span.fields.is_empty() {
+                            span.name.to_owned()
+                        } else {
+                            format!("{}{{{}}}", span.name, pairs(&span.fields))
+                        }
+                    })
+                    .join_with(":")
+                    .to_string();
+                let mut line = format!("{} {:>5} ", timestamp, metadata.level());
+                if !spans.is_empty() {
+                    line.push_str(&spans);
+                    line.push_str(": ");
+                }
+                line.push_str(&message);
+                if !fields.is_empty() {
+                    line.push(' ');
+                    line.push_str(&pairs(&fields));
+                }
+                line
+            }
+        };
+
+        eprintln!("{}", line);
+    }
+
+    fn enter(&self, span: &span::Id) {
+        STACK.with(|stack| stack.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, span: &span::Id) {
+        STACK.with(|stack| {
+            let mut stack = stack.borrow_mut();
+            if let Some(index) = stack.iter().rposition(|id| *id == span.into_u64()) {
+                stack.remove(index);
+            }
+        });
+    }
+
+    fn clone_span(&self, span: &span::Id) -> span::Id {
+        if let Some(span) = self.spans().get_mut(&span.into_u64()) {
+            span.references += 1;
+        }
+        span.clone()
+    }
+
+    fn try_close(&self, span: span::Id) -> bool {
+        let mut spans = self.spans();
+        let closed = match spans.get_mut(&span.into_u64()) {
+            None => return false,
+            Some(span) => {
+                span.references -= 1;
+                span.references == 0
+            }
+        };
+        if closed {
+            spans.remove(&span.into_u64());
+        }
+        closed
+    }
+}
+
+/// Parse a level like `info` or `debug`, or `off`.
+pub fn level(level: &str) -> anyhow::Result<LevelFilter> {
+    level
+        .parse::<LevelFilter>()
+        .map_err(|_| anyhow::anyhow!("Invalid log level `{}`", level))
+}
+
+/// Collect fields as JSON values.
+struct Fields<'a>(&'a mut Map<String, Value>);
+
+impl field::Visit for Fields<'_> {
+    fn record_debug(&mut self, field: &field::Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_owned(), Value::from(format!("{:?}", value)));
+    }
+
+    fn record_str(&mut self, field: &field::Field, value: &str) {
+        self.0.insert(field.name().to_owned(), Value::from(value));
+    }
+
+    fn record_i64(&mut self, field: &field::Field, value: i64) {
+        self.0.insert(field.name().to_owned(), Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &field::Field, value: u64) {
+        self.0.insert(field.name().to_owned(), Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &field::Field, value: bool) {
+        self.0.insert(field.name().to_owned(), Value::from(value));
+    }
+}
+
+fn pairs(fields: &Map<String, Value>) -> String {
+    fields
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, text(value.clone())))
+        .join_with(" ")
+        .to_string()
+}
+
+fn text(value: Value) -> String {
+    match value {
+        Value::String(string) => string,
+        value => value.to_string(),
+    }
+}
@@ -1,50 +1,74 @@
-use std::collections::HashSet;
-use std::net::IpAddr;
-use std::net::Ipv4Addr;
+use std::future::Future;
 use std::sync::Arc;
-
-use joinery::JoinableIterator;
-use once_cell::sync::Lazy;
-use regex::Regex;
-use serenity::client;
+use std::time::Duration;
+
+use futures::future::FutureExt as _;
+use futures::future::LocalBoxFuture;
+use futures::stream::FuturesUnordered;
+use futures::stream::StreamExt as _;
+use mc_sync::bridge::Bridge;
+use mc_sync::chat::ChatBridge;
+use mc_sync::config::Mode;
+use mc_sync::config::Opt;
+use mc_sync::discord::Discord;
+use mc_sync::events::Event;
+use mc_sync::logging::Logger;
+#[cfg(feature = "matrix")]
+use mc_sync::matrix::Matrix;
+use mc_sync::metrics;
+use mc_sync::metrics::Gateway;
+use mc_sync::metrics::Registry;
+use mc_sync::minecraft::Console;
+use mc_sync::minecraft::Minecraft;
+#[cfg(feature = "mqtt")]
+use mc_sync::mqtt::Mqtt;
+use mc_sync::rcon;
+use mc_sync::restart::Restart;
+use mc_sync::schedule::Timer;
+use mc_sync::signal::Reload;
+use mc_sync::signal::Shutdown;
+use mc_sync::signal::Terminate;
+#[cfg(feature = "slack")]
+use mc_sync::slack::Slack;
+use mc_sync::stdin::Stdin;
+use mc_sync::systemd;
+use mc_sync::tail::Tail;
+use serenity::client::bridge::gateway::GatewayIntents;
 use serenity::framework;
-use serenity::model::channel;
-use serenity::model::id;
+use serenity::http;
 use structopt::StructOpt;
-use tokio::io;
-use tokio::io::AsyncBufReadExt as _;
-use tokio::io::AsyncWriteExt as _;
-use tokio::net;
-use tokio::process;
 use tokio::runtime;
+use tokio::sync::broadcast;
 use tokio::sync::mpsc;
-use tokio::sync::Mutex;
-
-/// Wrap a Minecraft server and synchronize the chat with Discord.
-#[derive(Debug, StructOpt)]
-struct Opt {
-    /// Discord bot application token
-    #[structopt(long, env = "DISCORD_TOKEN")]
-    token: String,
-
-    /// Forward interesting server events
-    #[structopt(long, env = "DISCORD_GENERAL_CHANNEL_ID")]
-    general_id: u64,
-
-    /// Forward all server logs
-    #[structopt(long, env = "DISCORD_VERBOSE_CHANNEL_ID")]
-    verbose_id: u64,
-
-    /// Shutdown port
-    #[structopt(long, env = "MINECRAFT_SERVER_PORT")]
-    server_port: u16,
-
-    /// Path to Minecraft server.jar or script
-    command: String,
+use tokio::time;
+use tracing::Instrument as _;
+
+/// How long to wait for the server to save and exit after asking it to stop.
+static STOP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Events and log lines to hold for each API subscriber that falls behind.
+static EVENT_STREAM: usize = 256;
+
+/// Long-running task, which stops every server (even those attached over RCON)
+/// when it finishes if `true`, or only those it launched if `false`.
+type Task = LocalBoxFuture<'static, (bool, anyhow::Result<()>)>;
+
+/// Box a long-running task, which runs in `span`.
+fn task(
+    future: impl Future<Output = anyhow::Result<()>> + 'static,
+    stop: bool,
+    span: &tracing::Span,
+) -> Task {
+    future
+        .instrument(span.clone())
+        .map(move |finished| (stop, finished))
+        .boxed_local()
 }
 
 fn main() -> anyhow::Result<()> {
     let opt = Opt::from_args();
+    let servers = opt.load()?;
+    Logger::new(servers[0].log_level, servers[0].log_format).install()?;
 
     let runtime = runtime::Builder::new_current_thread()
         .enable_all()
@@ -52,222 +76,343 @@ fn main() -> anyhow::Result<()> {
 
     let _guard = runtime.enter();
 
-    let (event_tx, event_rx) = mpsc::channel(10);
-
-    let shutdown = runtime.block_on(Shutdown::new(opt.server_port))?;
-    let (child_stdin, mut child, minecraft) = Minecraft::new(&opt.command, event_tx.clone());
-    let (stdout, stdin) = Stdin::new(event_tx.clone());
-    let mut discord = runtime.block_on({
-        serenity::Client::builder(&opt.token)
-            .event_handler(Discord(event_tx))
-            .framework(framework::StandardFramework::default())
-    })?;
-
-    let http = Arc::clone(&discord.cache_and_http);
-    let general_channel = id::ChannelId::from(opt.general_id);
-    let verbose_channel = id::ChannelId::from(opt.verbose_id);
-
-    // If any long-running task returns or errors unexpectedly, try to shut down
-    // the Minecraft server gracefully.
-    runtime.spawn(async move {
-        let child_stdin = Mutex::new(child_stdin);
-
-        let finished = tokio::select! {
-            finished = shutdown.start() => finished,
-            finished = discord.start() => finished.map_err(anyhow::Error::from),
-            finished = minecraft.start() => finished,
-            finished = stdin.start() => finished,
-            finished = process(
-                event_rx,
-                &child_stdin,
-                stdout,
-                http,
-                general_channel,
-                verbose_channel,
-            ) => finished,
+    // The Discord connection is shared, so it uses the primary server's settings.
+    let primary = &servers[0];
+    let application =
+        match primary.discord {
+            false => None,
+            true => Some(runtime.block_on(
+                http::Http::new_with_token(&primary.token).get_current_application_info(),
+            )?),
         };
-
-        let mut child_stdin = child_stdin.lock().await;
-        child_stdin.write_all(b"/stop\n").await?;
-        child_stdin.flush().await?;
-        finished
-    });
-
-    runtime.block_on(child.wait())?;
-    runtime.shutdown_background();
-
-    Ok(())
-}
-
-async fn process(
-    mut event_rx: mpsc::Receiver<Event>,
-    child_stdin: &Mutex<io::BufWriter<process::ChildStdin>>,
-    mut stdout: io::BufWriter<io::Stdout>,
-    http: Arc<serenity::CacheAndHttp>,
-    general_channel: id::ChannelId,
-    verbose_channel: id::ChannelId,
-) -> anyhow::Result<()> {
-    let mut online = HashSet::<String>::new();
-
-    while let Some(event) = event_rx.recv().await {
-        match event {
-            Event::Discord(message) => {
-                if message.author.name == "mc-boot" || message.author.name == "mc-sync" {
-                    continue;
-                }
-
-                if message.content.trim() == "!online" {
-                    let online =
-                        format!("{} online: {}", online.len(), online.iter().join_with(", "));
-                    message.channel_id.say(&http.http, online).await?;
-                    continue;
-                }
-
-                let say = format!("/say [{}]: {}\n", message.author.name, message.content);
-                let mut child_stdin = child_stdin.lock().await;
-                child_stdin.write_all(say.as_bytes()).await?;
-                child_stdin.flush().await?;
-            }
-            Event::Minecraft(message) => {
-                stdout.write_all(message.as_bytes()).await?;
-                stdout.write_all(b"\n").await?;
-                stdout.flush().await?;
-
-                verbose_channel.say(&http.http, &message).await?;
-
-                let message = if let Some(captures) = JOIN.captures(&message) {
-                    online.insert(captures[1].to_owned());
-                    format!("{} joined the server!", &captures[1])
-                } else if let Some(captures) = QUIT.captures(&message) {
-                    online.remove(&captures[1]);
-                    format!("{} left the server.", &captures[1])
-                } else if let Some(captures) = ACHIEVEMENT.captures(&message) {
-                    format!("{} unlocked achievement [{}]!", &captures[1], &captures[2])
-                } else if let Some(captures) = MESSAGE.captures(&message) {
-                    format!("[{}]: {}", &captures[1], &captures[2])
-                } else {
-                    continue;
-                };
-
-                general_channel.say(&http.http, message).await?;
-            }
-            Event::Stdin(message) => {
-                let mut child_stdin = child_stdin.lock().await;
-                child_stdin.write_all(message.as_bytes()).await?;
-                child_stdin.write_all(b"\n").await?;
-                child_stdin.flush().await?;
+    // Members leaving is only visible with the privileged members intent,
+    // which has to be enabled on the bot's application page.
+    let intents = if servers.iter().any(|settings| settings.enforce_membership) {
+        GatewayIntents::non_privileged() | GatewayIntents::GUILD_MEMBERS
+    } else {
+        GatewayIntents::non_privileged()
+    };
+
+    let channels = servers
+        .iter()
+        .map(|_| mpsc::channel(10))
+        .collect::<Vec<_>>();
+    let senders = channels
+        .iter()
+        .map(|(event_tx, _)| event_tx.clone())
+        .collect::<Vec<_>>();
+
+    // Commands typed into the terminal go to the primary server.
+    let mut stdin = Stdin::new(senders[0].clone())?;
+    let _restore = stdin.restore();
+
+    let gateway = Arc::new(Gateway::default());
+    let http_address = primary.http_address.clone();
+    let api = primary.api.clone();
+    let discord = application
+        .as_ref()
+        .map(|application| {
+            runtime.block_on({
+                serenity::Client::builder(&primary.token)
+                    .application_id(application.id.0)
+                    .intents(intents)
+                    .event_handler(Discord {
+                        bridges: senders.clone(),
+                        gateway: Arc::clone(&gateway),
+                    })
+                    .framework(framework::StandardFramework::default())
+            })
+        })
+        .transpose()?;
+    // Without Discord, the bridge is handed an unused client and skips
+    // anything that would reach Discord.
+    let http = match &discord {
+        None => Arc::new(serenity::CacheAndHttp::default()),
+        Some(discord) => Arc::clone(&discord.cache_and_http),
+    };
+    let owner = application
+        .map(|application| application.owner.id)
+        .unwrap_or_default();
+
+    let mut tasks: Vec<Task> = Vec::new();
+    let mut bridges = Vec::new();
+    let mut consoles = Vec::new();
+    let mut registry = Registry {
+        servers: Vec::new(),
+        gateway,
+    };
+
+    for (settings, (event_tx, event_rx)) in servers.into_iter().zip(channels) {
+        let shutdown = runtime.block_on(Shutdown::new(settings.server_port))?;
+        let console = match &settings.mode {
+            Mode::Spawn { command } => Console::Child(Minecraft::new(
+                command,
+                &settings.server_directory,
+                settings.flavor,
+                settings.proxy_broadcast.clone(),
+                event_tx.clone(),
+            )),
+            Mode::Rcon {
+                address, password, ..
+            } => runtime.block_on(Console::rcon(address, password))?,
+        };
+        let restart = settings
+            .restart_schedule
+            .clone()
+            .map(|schedule| Restart::new(schedule, console.clone(), event_tx.clone()));
+        let backup = settings
+            .backup
+            .as_ref()
+            .map(|backup| Timer::new(backup.schedule.clone(), Event::Backup, event_tx.clone()));
+        let topic = settings
+            .topic
+            .as_ref()
+            .map(|topic| Timer::new(topic.schedule.clone(), Event::Topic, event_tx.clone()));
+        let lag = settings
+            .lag
+            .as_ref()
+            .map(|lag| Timer::new(lag.schedule.clone(), Event::SampleTps, event_tx.clone()));
+        let resources = settings.resources.as_ref().map(|resources| {
+            Timer::new(
+                resources.schedule.clone(),
+                Event::SampleUsage,
+                event_tx.clone(),
+            )
+        });
+        let watchdog = settings.watchdog.as_ref().map(|watchdog| {
+            Timer::new(watchdog.schedule.clone(), Event::Watchdog, event_tx.clone())
+        });
+        let tail = match &settings.mode {
+            Mode::Rcon { tail_log: true, .. } => {
+                Some(Tail::new(&settings.server_directory, event_tx.clone()))
             }
+            _ => None,
+        };
+        let announcements = settings
+            .announcements
+            .iter()
+            .enumerate()
+            .map(|(index, announcement)| {
+                Timer::new(
+                    announcement.schedule.clone(),
+                    Event::Announcement(index),
+                    event_tx.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let list = Timer::new(
+            settings.list_schedule.clone(),
+            Event::List,
+            event_tx.clone(),
+        );
+        let reload = Reload::new(event_tx.clone())?;
+        let name = settings
+            .name
+            .clone()
+            .unwrap_or_else(|| String::from("default"));
+        let span = tracing::info_span!("server", name = %name);
+
+        tasks.push(task(shutdown.start(), true, &span));
+        tasks.push(task(list.start(), false, &span));
+        tasks.push(task(reload.start(), false, &span));
+        if let Some(topic) = topic {
+            tasks.push(task(topic.start(), false, &span));
+        }
+        if let Some(lag) = lag {
+            tasks.push(task(lag.start(), false, &span));
+        }
+        if let Some(resources) = resources {
+            tasks.push(task(resources.start(), false, &span));
+        }
+        for announcement in announcements {
+            tasks.push(task(announcement.start(), false, &span));
+        }
+        if let Some(tail) = tail {
+            tasks.push(task(tail.start(), false, &span));
+        }
+        if let Some(watchdog) = watchdog {
+            tasks.push(task(watchdog.start(), false, &span));
+        }
+        if let Some(restart) = restart {
+            tasks.push(task(restart.start(), false, &span));
+        }
+        if let Some(backup) = backup {
+            tasks.push(task(backup.start(), false, &span));
         }
-    }
 
-    Ok(())
-}
+        #[allow(unused_mut)]
+        let mut chats: Vec<Arc<dyn ChatBridge>> = Vec::new();
+        #[cfg(feature = "matrix")]
+        if let Some(config) = &settings.matrix {
+            chats.push(Arc::new(Matrix::new(config)?));
+        }
+        #[cfg(feature = "slack")]
+        if let Some(config) = &settings.slack {
+            chats.push(Arc::new(Slack::new(config)?));
+        }
+        #[cfg(feature = "mqtt")]
+        let mqtt = settings.mqtt.as_ref().map(|config| {
+            let (mqtt, publisher) = Mqtt::new(config, settings.name.as_deref(), event_tx.clone());
+            tasks.push(task(mqtt.start(), false, &span));
+            publisher
+        });
+        if let Some(config) = &settings.rcon_server {
+            let server = runtime.block_on(rcon::Server::new(config, event_tx.clone()))?;
+            tasks.push(task(server.start(), false, &span));
+        }
+        for chat in &chats {
+            let chat = Arc::clone(chat);
+            let tx = event_tx.clone();
+            tasks.push(task(async move { chat.receive(tx).await }, false, &span));
+        }
 
-#[derive(Clone, Debug)]
-enum Event {
-    Discord(channel::Message),
-    Minecraft(String),
-    Stdin(String),
-}
+        let mut bridge = Bridge::new(
+            event_tx.clone(),
+            event_rx,
+            console.clone(),
+            Arc::clone(&http),
+            owner,
+            opt.clone(),
+            settings,
+        )?;
+        for chat in chats {
+            bridge.add_chat(chat);
+        }
+        if let Some(printer) = stdin.printer()? {
+            bridge.set_printer(printer);
+        }
+        #[cfg(feature = "mqtt")]
+        if let Some(mqtt) = mqtt {
+            bridge.set_mqtt(mqtt);
+        }
 
-struct Discord(mpsc::Sender<Event>);
+        registry
+            .servers
+            .push((name, bridge.metrics(), event_tx.clone()));
 
-#[serenity::async_trait]
-impl client::EventHandler for Discord {
-    async fn message(&self, _: client::Context, message: channel::Message) {
-        self.0
-            .send(Event::Discord(message))
-            .await
-            .expect("[INTERNAL ERROR]: `rx` dropped");
+        runtime.block_on(console.launch().instrument(span.clone()))?;
+        bridges.push((console.is_owned(), span, bridge));
+        consoles.push(console);
     }
-}
-
-static JOIN: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r".*\[Server thread/INFO\]: (.*)\[[^\]]*\] logged in with entity id .* at .*")
-        .unwrap()
-});
-
-static QUIT: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r".*\[Server thread/INFO\]: (.*) left the game").unwrap());
 
-static ACHIEVEMENT: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r".*\[Server thread/INFO\]: (.*) has made the advancement \[(.*)\]").unwrap()
-});
-
-static MESSAGE: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r".*\[Server thread/INFO\]: <([^ \]]*)> (.*)").unwrap());
-
-struct Minecraft {
-    stdout: io::BufReader<process::ChildStdout>,
-    tx: mpsc::Sender<Event>,
-}
+    if let Some(players) = stdin.players() {
+        bridges[0].2.set_players(players);
+    }
 
-impl Minecraft {
-    fn new(
-        command: &str,
-        tx: mpsc::Sender<Event>,
-    ) -> (io::BufWriter<process::ChildStdin>, process::Child, Self) {
-        let mut child = process::Command::new(command)
-            .stdin(std::process::Stdio::piped())
-            .stdout(std::process::Stdio::piped())
-            .kill_on_drop(true)
-            .spawn()
-            .expect("Failed to launch server");
-        let stdout = child
-            .stdout
-            .take()
-            .map(io::BufReader::new)
-            .expect("[IMPOSSIBLE]: stdout is piped");
-        let stdin = child
-            .stdin
-            .take()
-            .map(io::BufWriter::new)
-            .expect("[IMPOSSIBLE]: stdin is piped");
-        (stdin, child, Minecraft { stdout, tx })
+    for (index, (_, _, bridge)) in bridges.iter_mut().enumerate() {
+        let peers = senders
+            .iter()
+            .enumerate()
+            .filter(|(peer, _)| *peer != index)
+            .map(|(_, tx)| tx.clone())
+            .collect();
+        bridge.set_peers(peers);
     }
 
-    async fn start(self) -> anyhow::Result<()> {
-        let mut lines = self.stdout.lines();
-        while let Some(line) = lines.next_line().await? {
-            self.tx.send(Event::Minecraft(line)).await?;
+    let registry = Arc::new(registry);
+    if let Some(address) = http_address {
+        let server = runtime.block_on(metrics::Server::new(&address, Arc::clone(&registry)))?;
+        tasks.push(task(server.start(), false, &tracing::info_span!("http")));
+    }
+    if let Some(api) = api {
+        let (events, _) = broadcast::channel(EVENT_STREAM);
+        for (_, _, bridge) in &mut bridges {
+            bridge.set_stream(events.clone());
         }
-        Ok(())
+        let server = runtime.block_on(mc_sync::api::Server::new(
+            api,
+            Arc::clone(&registry),
+            events,
+        ))?;
+        tasks.push(task(server.start(), false, &tracing::info_span!("api")));
     }
-}
-
-struct Stdin {
-    stdin: io::BufReader<io::Stdin>,
-    tx: mpsc::Sender<Event>,
-}
-
-impl Stdin {
-    fn new(tx: mpsc::Sender<Event>) -> (io::BufWriter<io::Stdout>, Self) {
-        let stdin = io::BufReader::new(io::stdin());
-        let stdout = io::BufWriter::new(io::stdout());
-        (stdout, Stdin { stdin, tx })
+    if let Some(notify) = systemd::Notify::new(registry)? {
+        tasks.push(task(notify.start(), false, &tracing::info_span!("systemd")));
     }
 
-    async fn start(self) -> anyhow::Result<()> {
-        let mut lines = self.stdin.lines();
-        while let Some(line) = lines.next_line().await? {
-            self.tx.send(Event::Stdin(line)).await?;
-        }
-        Ok(())
+    let terminate = Terminate::new()?;
+    tasks.push(task(stdin.start(), false, &tracing::info_span!("stdin")));
+    tasks.push(task(terminate.start(), false, &tracing::Span::none()));
+    if let Some(mut discord) = discord {
+        tasks.push(task(
+            async move { discord.start().await.map_err(anyhow::Error::from) },
+            false,
+            &tracing::info_span!("discord"),
+        ));
     }
-}
 
-struct Shutdown(net::TcpListener);
+    // Keep running until every server exits without being restarted. If any
+    // other long-running task returns or errors unexpectedly, try to shut down
+    // the Minecraft servers gracefully, and keep relaying until they exit. When
+    // attached over RCON, the server belongs to someone else, so only stop it
+    // when explicitly asked to.
+    let finished = runtime.block_on(async move {
+        let mut bridges = bridges
+            .into_iter()
+            .map(|(owned, span, bridge)| {
+                bridge
+                    .start()
+                    .instrument(span)
+                    .map(move |finished| (owned, finished))
+            })
+            .collect::<FuturesUnordered<_>>();
+        let mut tasks = tasks.into_iter().collect::<FuturesUnordered<_>>();
+
+        let (stop, finished) = loop {
+            tokio::select! {
+                Some((_, finished)) = bridges.next() => {
+                    if bridges.is_empty() {
+                        return finished;
+                    }
+                    if let Err(error) = finished {
+                        tracing::error!("{:?}", error);
+                    }
+                }
+                Some(finished) = tasks.next() => break finished,
+            }
+        };
 
-impl Shutdown {
-    async fn new(port: u16) -> anyhow::Result<Self> {
-        net::TcpListener::bind((IpAddr::V4(Ipv4Addr::UNSPECIFIED), port))
-            .await
-            .map(Self)
-            .map_err(anyhow::Error::from)
-    }
+        systemd::stopping();
+        let mut owned = 0;
+        for console in &consoles {
+            if !stop && !console.is_owned() {
+                continue;
+            }
+            // Servers stopped from Discord, or waiting to relaunch after a
+            // crash, have nothing to wait for.
+            if !console.is_running().await {
+                continue;
+            }
+            if console.is_owned() {
+                owned += 1;
+            }
+            // The server may have already received SIGINT from the terminal.
+            if let Err(error) = console.send("/stop").await {
+                tracing::error!("Failed to stop server: {:?}", error);
+            }
+        }
 
-    async fn start(self) -> anyhow::Result<()> {
-        let (_, _) = self.0.accept().await?;
-        Ok(())
-    }
+        let stopped = async {
+            while owned > 0 {
+                match bridges.next().await {
+                    None => break,
+                    Some((was_owned, finished)) => {
+                        finished?;
+                        if was_owned {
+                            owned -= 1;
+                        }
+                    }
+                }
+            }
+            Ok::<_, anyhow::Error>(())
+        };
+        match time::timeout(STOP_TIMEOUT, stopped).await {
+            Ok(result) => result?,
+            Err(_) => tracing::warn!("Timed out waiting for server to stop"),
+        }
+        finished
+    });
+
+    runtime.shutdown_background();
+    finished
 }
@@ -0,0 +1,54 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+pub static URL: Lazy<Regex> = Lazy::new(|| Regex::new(r"https?://\S+").unwrap());
+
+/// Characters with special meaning in Discord markdown.
+static SPECIAL: &[char] = &[
+    '\\', '*', '_', '~', '`', '|', '>', '#', '-', '[', ']', '(', ')',
+];
+
+/// Escape Discord markdown and defuse mentions in text from Minecraft, so
+/// players can't format messages or ping anyone. URLs are left intact so they
+/// remain clickable.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut last = 0;
+    for url in URL.find_iter(text) {
+        escape_into(&text[last..url.start()], &mut escaped);
+        escaped.push_str(url.as_str());
+        last = url.end();
+    }
+    escape_into(&text[last..], &mut escaped);
+
+    escaped
+        .replace("@everyone", "@\u{200B}everyone")
+        .replace("@here", "@\u{200B}here")
+        .replace("<@", "<\u{200B}@")
+}
+
+/// Remove the escapes added by [`escape`], for platforms that don't use
+/// Discord markdown.
+pub fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(char) = chars.next() {
+        match (char, chars.clone().next()) {
+            ('\\', Some(next)) if SPECIAL.contains(&next) => {
+                unescaped.push(next);
+                chars.next();
+            }
+            _ => unescaped.push(char),
+        }
+    }
+    unescaped.replace('\u{200B}', "")
+}
+
+fn escape_into(text: &str, escaped: &mut String) {
+    for char in text.chars() {
+        if SPECIAL.contains(&char) {
+            escaped.push('\\');
+        }
+        escaped.push(char);
+    }
+}
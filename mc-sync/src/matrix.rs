@@ -0,0 +1,216 @@
+use std::sync::atomic;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use anyhow::anyhow;
+use anyhow::Context as _;
+use futures::future::BoxFuture;
+use futures::future::FutureExt as _;
+use serde_json::json;
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tokio::time;
+
+use crate::chat::ChatBridge;
+use crate::config;
+use crate::events::Event;
+use crate::markdown;
+
+/// How long the homeserver may hold each `/sync` open waiting for messages.
+static SYNC_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Delay before trying again after the homeserver can't be reached.
+static RETRY: Duration = Duration::from_secs(10);
+
+/// Relay to a Matrix room over the client-server API, as the user whose
+/// access token is configured.
+pub struct Matrix {
+    client: reqwest::Client,
+    homeserver: reqwest::Url,
+    access_token: String,
+    room_id: String,
+    /// Distinguishes transaction IDs from those of earlier runs, which the
+    /// homeserver would otherwise treat as retries and drop.
+    session: u64,
+    transaction: atomic::AtomicU64,
+}
+
+impl Matrix {
+    pub fn new(config: &config::Matrix) -> anyhow::Result<Self> {
+        let homeserver = reqwest::Url::parse(&config.homeserver)
+            .with_context(|| format!("Invalid Matrix homeserver `{}`", config.homeserver))?;
+        let session = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        Ok(Matrix {
+            client: reqwest::Client::builder()
+                .timeout(SYNC_TIMEOUT * 2)
+                .build()?,
+            homeserver,
+            access_token: config.access_token.clone(),
+            room_id: config.room_id.clone(),
+            session,
+            transaction: atomic::AtomicU64::new(0),
+        })
+    }
+
+    /// The client-server API endpoint at `path`, with each segment escaped.
+    fn url(&self, path: &[&str]) -> anyhow::Result<reqwest::Url> {
+        let mut url = self.homeserver.clone();
+        url.path_segments_mut()
+            .map_err(|()| anyhow!("Invalid Matrix homeserver `{}`", self.homeserver))?
+            .pop_if_empty()
+            .extend(["_matrix", "client", "v3"].iter().chain(path));
+        Ok(url)
+    }
+
+    async fn send(&self, msgtype: &str, body: &str) -> anyhow::Result<()> {
+        let transaction = format!(
+            "mc-sync-{}-{}",
+            self.session,
+            self.transaction.fetch_add(1, atomic::Ordering::Relaxed),
+        );
+        let url = self.url(&[
+            "rooms",
+            &self.room_id,
+            "send",
+            "m.room.message",
+            &transaction,
+        ])?;
+        self.client
+            .put(url)
+            .bearer_auth(&self.access_token)
+            .json(&json!({ "msgtype": msgtype, "body": body }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    async fn get(&self, path: &[&str], query: &[(&str, &str)]) -> anyhow::Result<Value> {
+        let response = self
+            .client
+            .get(self.url(path)?)
+            .bearer_auth(&self.access_token)
+            .query(query)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(response)
+    }
+
+    /// Forward messages from the room until the bridge stops. Messages sent
+    /// before the bridge started, or by the bridge itself, are skipped.
+    async fn sync(&self, tx: mpsc::Sender<Event>) -> anyhow::Result<()> {
+        let user_id = loop {
+            match self.get(&["account", "whoami"], &[]).await {
+                Ok(whoami) => match whoami["user_id"].as_str() {
+                    Some(user_id) => break user_id.to_owned(),
+                    None => return Err(anyhow!("Matrix homeserver didn't return a user ID")),
+                },
+                Err(error) => {
+                    tracing::warn!("Failed to connect to Matrix: {:?}", error);
+                    time::sleep(RETRY).await;
+                }
+            }
+        };
+
+        let filter = json!({
+            "room": {
+                "rooms": [self.room_id],
+                "timeline": { "types": ["m.room.message"] },
+                "state": { "types": [] },
+                "ephemeral": { "types": [] },
+                "account_data": { "types": [] },
+            },
+            "presence": { "types": [] },
+            "account_data": { "types": [] },
+        })
+        .to_string();
+        let timeout = SYNC_TIMEOUT.as_millis().to_string();
+
+        let mut since: Option<String> = None;
+        loop {
+            let mut query = vec![("filter", filter.as_str()), ("timeout", timeout.as_str())];
+            if let Some(since) = &since {
+                query.push(("since", since));
+            }
+
+            let sync = match self.get(&["sync"], &query).await {
+                Ok(sync) => sync,
+                Err(error) => {
+                    tracing::warn!("Failed to sync with Matrix: {:?}", error);
+                    time::sleep(RETRY).await;
+                    continue;
+                }
+            };
+
+            let next_batch = match sync["next_batch"].as_str() {
+                Some(next_batch) => next_batch.to_owned(),
+                None => {
+                    tracing::warn!("Matrix homeserver didn't return a sync token");
+                    time::sleep(RETRY).await;
+                    continue;
+                }
+            };
+            if since.replace(next_batch).is_none() {
+                continue;
+            }
+
+            let events = sync["rooms"]["join"][&self.room_id]["timeline"]["events"].as_array();
+            for event in events.into_iter().flatten() {
+                let sender = event["sender"].as_str().unwrap_or_default();
+                let content = &event["content"];
+                let body = match (content["msgtype"].as_str(), content["body"].as_str()) {
+                    (Some("m.text"), Some(body)) | (Some("m.emote"), Some(body)) => body,
+                    _ => continue,
+                };
+                if sender == user_id || body.is_empty() {
+                    continue;
+                }
+
+                let event = Event::Chat {
+                    platform: self.name().to_owned(),
+                    user: localpart(sender).to_owned(),
+                    message: body.to_owned(),
+                };
+                tx.send(event).await?;
+            }
+        }
+    }
+}
+
+/// The name part of a user ID like `@alice:example.org`.
+fn localpart(user_id: &str) -> &str {
+    let user_id = user_id.trim_start_matches('@');
+    user_id.split(':').next().unwrap_or(user_id)
+}
+
+impl ChatBridge for Matrix {
+    fn name(&self) -> &str {
+        "Matrix"
+    }
+
+    fn send_message<'a>(
+        &'a self,
+        player: &'a str,
+        message: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            self.send("m.text", &format!("<{}> {}", player, message))
+                .await
+        }
+        .boxed()
+    }
+
+    fn send_event<'a>(&'a self, text: &'a str) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move { self.send("m.notice", &markdown::unescape(text)).await }.boxed()
+    }
+
+    fn receive(&self, tx: mpsc::Sender<Event>) -> BoxFuture<'_, anyhow::Result<()>> {
+        self.sync(tx).boxed()
+    }
+}
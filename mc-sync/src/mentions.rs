@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use regex::Captures;
+use regex::Regex;
+use serenity::model::channel;
+use serenity::model::guild;
+use serenity::model::id;
+use serenity::prelude::Mentionable as _;
+
+use crate::markdown;
+
+static MENTION: Lazy<Regex> = Lazy::new(|| Regex::new(r"(^|\s)@([A-Za-z0-9_.]{2,32})").unwrap());
+
+static CHANNEL: Lazy<Regex> = Lazy::new(|| Regex::new(r"<#([0-9]+)>").unwrap());
+
+/// Replace `@name` with a mention of the guild member with that username or
+/// nickname, and escape everything else.
+pub async fn to_discord(
+    http: &serenity::CacheAndHttp,
+    guild_id: Option<id::GuildId>,
+    text: &str,
+) -> String {
+    let mut resolved = HashMap::new();
+    if let Some(guild_id) = guild_id {
+        for captures in MENTION.captures_iter(text) {
+            let name = &captures[2];
+            if resolved.contains_key(name) {
+                continue;
+            }
+            if let Some(user_id) = find(http, guild_id, name).await {
+                resolved.insert(name.to_owned(), user_id);
+            }
+        }
+    }
+
+    let mut message = String::with_capacity(text.len());
+    let mut last = 0;
+    for captures in MENTION.captures_iter(text) {
+        let name = captures
+            .get(2)
+            .expect("[IMPOSSIBLE]: name group always matches");
+        if let Some(user_id) = resolved.get(name.as_str()) {
+            // Skip the `@` before the name.
+            message.push_str(&markdown::escape(&text[last..name.start() - 1]));
+            message.push_str(&user_id.mention().to_string());
+            last = name.end();
+        }
+    }
+    message.push_str(&markdown::escape(&text[last..]));
+    message
+}
+
+async fn find(
+    http: &serenity::CacheAndHttp,
+    guild_id: id::GuildId,
+    name: &str,
+) -> Option<id::UserId> {
+    let matches = |member: &guild::Member| {
+        member.user.name.eq_ignore_ascii_case(name)
+            || member
+                .nick
+                .as_deref()
+                .is_some_and(|nick| nick.eq_ignore_ascii_case(name))
+    };
+
+    let cached = http
+        .cache
+        .guild_field(guild_id, |guild| {
+            guild
+                .members
+                .values()
+                .find(|member| matches(member))
+                .map(|member| member.user.id)
+        })
+        .await
+        .flatten();
+
+    if cached.is_some() {
+        return cached;
+    }
+
+    // Only a subset of members are cached without the privileged members intent.
+    guild_id
+        .search_members(&http.http, name, Some(10))
+        .await
+        .ok()?
+        .into_iter()
+        .find(|member| matches(member))
+        .map(|member| member.user.id)
+}
+
+/// Replace user, role, and channel mentions with readable names.
+pub async fn to_minecraft(http: &serenity::CacheAndHttp, message: &channel::Message) -> String {
+    let mut content = message.content.clone();
+
+    for user in &message.mentions {
+        let name = match message.guild_id {
+            Some(guild_id) => user.nick_in(http, guild_id).await,
+            None => None,
+        }
+        .unwrap_or_else(|| user.name.clone());
+        let name = format!("@{}", name);
+        content = content
+            .replace(&format!("<@{}>", user.id), &name)
+            .replace(&format!("<@!{}>", user.id), &name);
+    }
+
+    for role_id in &message.mention_roles {
+        let name = match role_id.to_role_cached(&http.cache).await {
+            Some(role) => format!("@{}", role.name),
+            None => String::from("@deleted-role"),
+        };
+        content = content.replace(&format!("<@&{}>", role_id), &name);
+    }
+
+    let mut channels = HashMap::new();
+    for captures in CHANNEL.captures_iter(&content) {
+        let channel_id = match captures[1].parse::<u64>() {
+            Ok(channel_id) => id::ChannelId(channel_id),
+            Err(_) => continue,
+        };
+        if let Some(name) = channel_id.name(&http.cache).await {
+            channels.insert(captures[0].to_owned(), format!("#{}", name));
+        }
+    }
+
+    CHANNEL
+        .replace_all(&content, |captures: &Captures| {
+            channels
+                .get(&captures[0])
+                .cloned()
+                .unwrap_or_else(|| captures[0].to_owned())
+        })
+        .into_owned()
+}
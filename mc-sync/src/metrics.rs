@@ -0,0 +1,288 @@
+use std::fmt::Write as _;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use serde_json::json;
+use serde_json::Map;
+
+use tokio::io::AsyncReadExt as _;
+use tokio::io::AsyncWriteExt as _;
+use tokio::net;
+use tokio::sync::mpsc;
+
+use crate::events::Event;
+
+/// How long the bridge may take to handle an event, while others wait, before
+/// it's considered stalled.
+static STALLED: Duration = Duration::from_secs(60);
+
+/// Counters and gauges for one bridged server.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    pub online: AtomicU64,
+    pub to_discord: AtomicU64,
+    pub to_minecraft: AtomicU64,
+    pub restarts: AtomicU64,
+    pub parse_failures: AtomicU64,
+    pub errors: AtomicU64,
+
+    /// Resident memory of the server process, in bytes, if measured.
+    pub memory: AtomicU64,
+
+    /// CPU time used by the server process since launch, in seconds, if measured.
+    pub cpu: AtomicU64,
+
+    /// Whether the server is running, or attached over RCON.
+    pub running: AtomicBool,
+
+    /// Whether the server has finished starting since it was last launched.
+    pub started: AtomicBool,
+
+    /// Whether the server answered its last status ping, if pinged.
+    pub responsive: AtomicBool,
+
+    /// When the bridge last started handling an event, in seconds since the
+    /// Unix epoch.
+    pub handled: AtomicU64,
+}
+
+impl Metrics {
+    /// Record that the bridge is handling another event.
+    pub fn handle(&self) {
+        self.handled.store(now(), Ordering::Relaxed);
+    }
+}
+
+/// State of the shared Discord connection.
+#[derive(Debug, Default)]
+pub struct Gateway {
+    /// Sessions started or resumed.
+    pub connections: AtomicU64,
+    pub connected: AtomicBool,
+}
+
+/// Metrics for each server, and for the shared Discord connection.
+#[derive(Debug)]
+pub struct Registry {
+    /// Name, metrics, and event channel of each server.
+    pub servers: Vec<(String, Arc<Metrics>, mpsc::Sender<Event>)>,
+    pub gateway: Arc<Gateway>,
+}
+
+impl Registry {
+    /// Whether every server has finished starting.
+    pub fn started(&self) -> bool {
+        self.servers
+            .iter()
+            .all(|(_, metrics, _)| metrics.started.load(Ordering::Relaxed))
+    }
+
+    /// Whether any bridge is stuck with events waiting.
+    pub fn stalled(&self) -> bool {
+        self.servers
+            .iter()
+            .any(|(_, metrics, tx)| stalled(metrics, tx))
+    }
+
+    /// Whether every server is running and answering pings, Discord is
+    /// connected, and no bridge is stuck with events waiting, as a JSON report.
+    fn health(&self) -> (bool, String) {
+        let connected = self.gateway.connected.load(Ordering::Relaxed);
+        let mut healthy = connected;
+        let mut servers = Map::new();
+        for (server, metrics, tx) in &self.servers {
+            let running = metrics.running.load(Ordering::Relaxed);
+            let stalled = stalled(metrics, tx);
+            let responsive = metrics.responsive.load(Ordering::Relaxed);
+            healthy &= running && !stalled && responsive;
+            servers.insert(
+                server.clone(),
+                json!({ "running": running, "stalled": stalled, "responsive": responsive }),
+            );
+        }
+        let report = json!({
+            "healthy": healthy,
+            "discord": connected,
+            "servers": servers,
+        });
+        (healthy, report.to_string())
+    }
+
+    /// Render in the Prometheus text format.
+    fn render(&self) -> String {
+        let mut text = String::new();
+
+        let mut family =
+            |name: &str,
+             kind: &str,
+             help: &str,
+             value: &dyn Fn(&Metrics, &mpsc::Sender<Event>) -> u64| {
+                writeln!(text, "# HELP {} {}", name, help).ok();
+                writeln!(text, "# TYPE {} {}", name, kind).ok();
+                for (server, metrics, tx) in &self.servers {
+                    writeln!(
+                        text,
+                        "{}{{server=\"{}\"}} {}",
+                        name,
+                        escape(server),
+                        value(metrics, tx),
+                    )
+                    .ok();
+                }
+            };
+
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        family(
+            "mc_sync_online_players",
+            "gauge",
+            "Players online.",
+            &|metrics, _| load(&metrics.online),
+        );
+        family(
+            "mc_sync_messages_to_discord_total",
+            "counter",
+            "Chat messages relayed from Minecraft to Discord.",
+            &|metrics, _| load(&metrics.to_discord),
+        );
+        family(
+            "mc_sync_messages_to_minecraft_total",
+            "counter",
+            "Chat messages relayed from Discord to Minecraft.",
+            &|metrics, _| load(&metrics.to_minecraft),
+        );
+        family(
+            "mc_sync_server_restarts_total",
+            "counter",
+            "Server relaunches after a restart or crash.",
+            &|metrics, _| load(&metrics.restarts),
+        );
+        family(
+            "mc_sync_parse_failures_total",
+            "counter",
+            "Server log lines that matched no pattern.",
+            &|metrics, _| load(&metrics.parse_failures),
+        );
+        family(
+            "mc_sync_errors_total",
+            "counter",
+            "Errors while handling an event, which the bridge skipped.",
+            &|metrics, _| load(&metrics.errors),
+        );
+        family(
+            "mc_sync_server_memory_bytes",
+            "gauge",
+            "Resident memory of the server process, if `[resources]` is enabled.",
+            &|metrics, _| load(&metrics.memory),
+        );
+        family(
+            "mc_sync_server_cpu_seconds_total",
+            "counter",
+            "CPU time used by the server process since launch, if `[resources]` is enabled.",
+            &|metrics, _| load(&metrics.cpu),
+        );
+        family(
+            "mc_sync_event_queue_depth",
+            "gauge",
+            "Events waiting to be handled by the bridge.",
+            &|_, tx| (tx.max_capacity() - tx.capacity()) as u64,
+        );
+
+        let connections = load(&self.gateway.connections);
+        writeln!(
+            text,
+            "# HELP mc_sync_discord_reconnects_total Times the Discord connection was reestablished."
+        )
+        .ok();
+        writeln!(text, "# TYPE mc_sync_discord_reconnects_total counter").ok();
+        writeln!(
+            text,
+            "mc_sync_discord_reconnects_total {}",
+            connections.saturating_sub(1),
+        )
+        .ok();
+
+        text
+    }
+}
+
+fn stalled(metrics: &Metrics, tx: &mpsc::Sender<Event>) -> bool {
+    let waiting = tx.max_capacity() - tx.capacity();
+    let handled = metrics.handled.load(Ordering::Relaxed);
+    waiting > 0 && now().saturating_sub(handled) > STALLED.as_secs()
+}
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Serve metrics over HTTP at `/metrics`, for Prometheus to scrape, and health
+/// at `/healthz`, for liveness probes.
+pub struct Server {
+    listener: net::TcpListener,
+    registry: Arc<Registry>,
+}
+
+impl Server {
+    pub async fn new(address: &str, registry: Arc<Registry>) -> anyhow::Result<Self> {
+        let listener = net::TcpListener::bind(address).await?;
+        Ok(Server { listener, registry })
+    }
+
+    pub async fn start(self) -> anyhow::Result<()> {
+        loop {
+            let (stream, _) = self.listener.accept().await?;
+            let registry = Arc::clone(&self.registry);
+            tokio::spawn(async move {
+                if let Err(error) = serve(stream, &registry).await {
+                    tracing::warn!("Failed to serve metrics: {}", error);
+                }
+            });
+        }
+    }
+}
+
+async fn serve(mut stream: net::TcpStream, registry: &Registry) -> anyhow::Result<()> {
+    // Only the request line matters, which fits in the first read.
+    let mut request = [0; 1024];
+    let read = stream.read(&mut request).await?;
+    let request = String::from_utf8_lossy(&request[..read]);
+
+    let response = match request.lines().next() {
+        Some(line) if line.starts_with("GET /metrics ") => {
+            response("200 OK", "text/plain; version=0.0.4", &registry.render())
+        }
+        Some(line) if line.starts_with("GET /healthz ") => match registry.health() {
+            (true, report) => response("200 OK", "application/json", &report),
+            (false, report) => response("503 Service Unavailable", "application/json", &report),
+        },
+        _ => response("404 Not Found", "text/plain", ""),
+    };
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+fn response(status: &str, content_type: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body,
+    )
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,409 @@
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use anyhow::anyhow;
+use anyhow::Context as _;
+use serde::Deserialize;
+use tokio::io;
+use tokio::io::AsyncBufReadExt as _;
+use tokio::io::AsyncWriteExt as _;
+use tokio::process;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+use tokio::sync::Mutex;
+use tracing::Instrument as _;
+
+use crate::bedrock;
+use crate::color;
+use crate::events::Event;
+use crate::events::Flavor;
+use crate::properties::Properties;
+use crate::proxy;
+use crate::rcon;
+
+/// Launch the server as a child process, and relaunch it on request.
+#[derive(Clone)]
+pub struct Minecraft {
+    command: String,
+    directory: PathBuf,
+    /// Translate commands for Bedrock Dedicated Server and proxies.
+    flavor: Flavor,
+    /// Proxy command to broadcast chat with.
+    broadcast: Option<String>,
+    stdin: Arc<Mutex<Option<io::BufWriter<process::ChildStdin>>>>,
+    stopping: Arc<atomic::AtomicBool>,
+    /// Process ID of the running server, or zero.
+    pid: Arc<atomic::AtomicU32>,
+    /// Asks the running server's output task to kill it.
+    kill: Arc<Mutex<Option<oneshot::Sender<()>>>>,
+    tx: mpsc::Sender<Event>,
+}
+
+impl Minecraft {
+    pub fn new(
+        command: &str,
+        directory: &Path,
+        flavor: Flavor,
+        broadcast: Option<String>,
+        tx: mpsc::Sender<Event>,
+    ) -> Self {
+        Minecraft {
+            command: command.to_owned(),
+            directory: directory.to_owned(),
+            flavor,
+            broadcast,
+            stdin: Arc::new(Mutex::new(None)),
+            stopping: Arc::new(atomic::AtomicBool::new(false)),
+            pid: Arc::new(atomic::AtomicU32::new(0)),
+            kill: Arc::new(Mutex::new(None)),
+            tx,
+        }
+    }
+
+    /// Launch the server, forwarding each line of its output and then its
+    /// exit status to the bridge.
+    pub async fn launch(&self) -> anyhow::Result<()> {
+        let mut child = process::Command::new(&self.command)
+            .current_dir(&self.directory)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to launch server `{}`", self.command))?;
+        let stdout = child
+            .stdout
+            .take()
+            .map(io::BufReader::new)
+            .expect("[IMPOSSIBLE]: stdout is piped");
+        let stdin = child
+            .stdin
+            .take()
+            .map(io::BufWriter::new)
+            .expect("[IMPOSSIBLE]: stdin is piped");
+
+        let (kill, killed) = oneshot::channel();
+        *self.stdin.lock().await = Some(stdin);
+        *self.kill.lock().await = Some(kill);
+        self.stopping.store(false, atomic::Ordering::SeqCst);
+        self.pid
+            .store(child.id().unwrap_or(0), atomic::Ordering::SeqCst);
+
+        tokio::spawn(
+            forward(
+                child,
+                stdout,
+                killed,
+                Arc::clone(&self.stdin),
+                Arc::clone(&self.stopping),
+                Arc::clone(&self.pid),
+                self.tx.clone(),
+            )
+            .instrument(tracing::info_span!("minecraft")),
+        );
+
+        Ok(())
+    }
+
+    /// Kill the server without waiting for it to stop, e.g. if it's hung.
+    async fn kill(&self) {
+        if let Some(kill) = self.kill.lock().await.take() {
+            kill.send(()).unwrap_or(());
+        }
+    }
+
+    async fn send(&self, command: &str) -> anyhow::Result<()> {
+        // Each line written to stdin is a command of its own.
+        if command.contains(['\r', '\n']) {
+            return Err(anyhow!(
+                "Refusing to send a command with a line break: {:?}",
+                command
+            ));
+        }
+        let stop = command.trim().trim_start_matches('/') == "stop";
+        let translated = match self.flavor {
+            Flavor::Bedrock => bedrock::command(command),
+            Flavor::Velocity | Flavor::Bungeecord => {
+                proxy::command(self.flavor, command, self.broadcast.as_deref())
+            }
+            _ => Some(command.to_owned()),
+        };
+        let command = match translated {
+            Some(command) => command,
+            None => {
+                tracing::debug!("Skipping command the server doesn't have: {}", command);
+                return Ok(());
+            }
+        };
+
+        let mut stdin = self.stdin.lock().await;
+        let stdin = match &mut *stdin {
+            Some(stdin) => stdin,
+            None => {
+                tracing::warn!("Dropping command while server is stopped: {}", command);
+                return Ok(());
+            }
+        };
+
+        // Mark the exit as expected even if the write fails, since the server
+        // may already be shutting down on its own (e.g. after Ctrl-C).
+        if stop {
+            self.stopping.store(true, atomic::Ordering::SeqCst);
+        }
+
+        stdin.write_all(command.as_bytes()).await?;
+        stdin.write_all(b"\n").await?;
+        stdin.flush().await?;
+
+        Ok(())
+    }
+}
+
+async fn forward(
+    mut child: process::Child,
+    stdout: io::BufReader<process::ChildStdout>,
+    mut killed: oneshot::Receiver<()>,
+    stdin: Arc<Mutex<Option<io::BufWriter<process::ChildStdin>>>>,
+    stopping: Arc<atomic::AtomicBool>,
+    pid: Arc<atomic::AtomicU32>,
+    tx: mpsc::Sender<Event>,
+) {
+    let mut lines = stdout.lines();
+    loop {
+        let line = tokio::select! {
+            line = lines.next_line() => line,
+            Ok(()) = &mut killed => {
+                tracing::warn!("Killing server");
+                if let Err(error) = child.start_kill() {
+                    tracing::error!("Failed to kill server: {}", error);
+                }
+                // A launch script's own children may still hold stdout open.
+                break;
+            }
+        };
+        match line {
+            Ok(Some(line)) => {
+                if tx.send(Event::Minecraft(line)).await.is_err() {
+                    return;
+                }
+            }
+            Ok(None) => break,
+            Err(error) => {
+                tracing::error!("Failed to read server output: {}", error);
+                break;
+            }
+        }
+    }
+
+    stdin.lock().await.take();
+
+    let status = child.wait().await;
+    pid.store(0, atomic::Ordering::SeqCst);
+    match status {
+        Ok(status) => {
+            let stopped = stopping.load(atomic::Ordering::SeqCst);
+            tx.send(Event::Exited { status, stopped })
+                .await
+                .unwrap_or(())
+        }
+        Err(error) => tracing::error!("Failed to wait for server: {}", error),
+    }
+}
+
+/// The newest crash report written by the server since `since`, if any.
+pub fn crash_report(directory: &Path, since: SystemTime) -> Option<PathBuf> {
+    fs::read_dir(directory.join("crash-reports"))
+        .ok()?
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((modified, entry.path())).filter(|(modified, _)| *modified >= since)
+        })
+        .max()
+        .map(|(_, path)| path)
+}
+
+/// The last `lines` lines of the server's current log, `logs/latest.log`.
+pub fn log_tail(directory: &Path, lines: usize) -> anyhow::Result<String> {
+    let path = directory.join("logs").join("latest.log");
+    let log =
+        fs::read(&path).with_context(|| format!("Failed to read log `{}`", path.display()))?;
+    let log = String::from_utf8_lossy(&log);
+    let start = log
+        .trim_end()
+        .rmatch_indices('\n')
+        .nth(lines.saturating_sub(1))
+        .map_or(0, |(index, _)| index + 1);
+    Ok(log[start..].to_owned())
+}
+
+/// Directories holding the world named `level` (the `level-name` property),
+/// including the Nether and End where servers like Paper keep them separately.
+pub fn worlds(directory: &Path, level: &str) -> Vec<PathBuf> {
+    ["", "_nether", "_the_end"]
+        .iter()
+        .map(|suffix| directory.join(format!("{}{}", level, suffix)))
+        .filter(|path| path.is_dir())
+        .collect()
+}
+
+/// Total size in bytes of the files under `path`.
+pub fn disk_usage(path: &Path) -> std::io::Result<u64> {
+    let metadata = fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+    fs::read_dir(path)?.try_fold(0, |total, entry| Ok(total + disk_usage(&entry?.path())?))
+}
+
+/// Lowercase names of the players on the server's whitelist.
+pub fn whitelist(directory: &Path) -> anyhow::Result<BTreeSet<String>> {
+    #[derive(Deserialize)]
+    struct Entry {
+        name: String,
+    }
+
+    let path = directory.join("whitelist.json");
+    let whitelist = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read whitelist `{}`", path.display()))?;
+    let entries = serde_json::from_str::<Vec<Entry>>(&whitelist)
+        .with_context(|| format!("Failed to parse whitelist `{}`", path.display()))?;
+    Ok(entries
+        .into_iter()
+        .map(|entry| entry.name.to_lowercase())
+        .collect())
+}
+
+/// Player in the server's `usercache.json`.
+#[derive(Deserialize)]
+struct CachedUser {
+    name: String,
+    uuid: String,
+}
+
+fn read_usercache(directory: &Path) -> anyhow::Result<Vec<CachedUser>> {
+    let path = directory.join("usercache.json");
+    let usercache = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read user cache `{}`", path.display()))?;
+    serde_json::from_str(&usercache)
+        .with_context(|| format!("Failed to parse user cache `{}`", path.display()))
+}
+
+/// Lowercase names of every player the server has cached, which includes
+/// everyone who has joined recently (for a month, by default).
+pub fn usercache(directory: &Path) -> anyhow::Result<BTreeSet<String>> {
+    Ok(read_usercache(directory)?
+        .into_iter()
+        .map(|user| user.name.to_lowercase())
+        .collect())
+}
+
+/// The name of `player` as the server has cached it, and their UUID, which
+/// names the files the world keeps about them.
+pub fn uuid(directory: &Path, player: &str) -> anyhow::Result<Option<(String, String)>> {
+    Ok(read_usercache(directory)?
+        .into_iter()
+        .find(|user| user.name.eq_ignore_ascii_case(player))
+        .map(|user| (user.name, user.uuid)))
+}
+
+/// Names of the players the server has cached, by UUID.
+pub fn names(directory: &Path) -> anyhow::Result<HashMap<String, String>> {
+    Ok(read_usercache(directory)?
+        .into_iter()
+        .map(|user| (user.uuid, user.name))
+        .collect())
+}
+
+/// Directory of the main world, from the `level-name` property.
+pub fn world(directory: &Path) -> PathBuf {
+    let level = match Properties::read(directory) {
+        Ok(properties) => properties
+            .get("level-name")
+            .filter(|level| !level.is_empty())
+            .map(String::from),
+        Err(error) => {
+            tracing::debug!("Failed to read server properties: {:?}", error);
+            None
+        }
+    };
+    directory.join(level.as_deref().unwrap_or("world"))
+}
+
+/// Shared handle to the server's console.
+#[derive(Clone)]
+pub enum Console {
+    Child(Minecraft),
+    Rcon(Arc<Mutex<rcon::Rcon>>),
+}
+
+impl Console {
+    pub async fn rcon(address: &str, password: &str) -> anyhow::Result<Self> {
+        rcon::Rcon::connect(address, password)
+            .await
+            .map(Mutex::new)
+            .map(Arc::new)
+            .map(Console::Rcon)
+    }
+
+    /// Whether the bridge launched the server, and so is responsible for stopping it.
+    pub fn is_owned(&self) -> bool {
+        match self {
+            Console::Child(_) => true,
+            Console::Rcon(_) => false,
+        }
+    }
+
+    /// Launch the server, unless it's managed by someone else.
+    pub async fn launch(&self) -> anyhow::Result<()> {
+        match self {
+            Console::Child(minecraft) => minecraft.launch().await,
+            Console::Rcon(_) => Ok(()),
+        }
+    }
+
+    /// Whether the server is running, which is always assumed over RCON.
+    pub async fn is_running(&self) -> bool {
+        match self {
+            Console::Child(minecraft) => minecraft.stdin.lock().await.is_some(),
+            Console::Rcon(_) => true,
+        }
+    }
+
+    /// Process ID of the server, unless it's stopped or managed by someone else.
+    pub fn pid(&self) -> Option<u32> {
+        match self {
+            Console::Child(minecraft) => {
+                Some(minecraft.pid.load(atomic::Ordering::SeqCst)).filter(|pid| *pid != 0)
+            }
+            Console::Rcon(_) => None,
+        }
+    }
+
+    /// Kill the server, unless it's managed by someone else.
+    pub async fn kill(&self) {
+        match self {
+            Console::Child(minecraft) => minecraft.kill().await,
+            Console::Rcon(_) => (),
+        }
+    }
+
+    /// Write a single command to the server. Over RCON, the server's response
+    /// is returned directly instead of appearing in its stdout.
+    pub async fn send(&self, command: &str) -> anyhow::Result<Option<String>> {
+        match self {
+            Console::Child(minecraft) => minecraft.send(command).await.map(|()| None),
+            Console::Rcon(rcon) => rcon
+                .lock()
+                .await
+                .command(command)
+                .await
+                .map(|response| Some(color::strip(&response))),
+        }
+    }
+}
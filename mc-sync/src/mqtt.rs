@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+use rumqttc::AsyncClient;
+use rumqttc::EventLoop;
+use rumqttc::MqttOptions;
+use rumqttc::Packet;
+use rumqttc::QoS;
+use tokio::sync::mpsc;
+use tokio::time;
+
+use crate::config;
+use crate::events::Event;
+use crate::sink;
+
+/// Delay before reconnecting after the broker can't be reached.
+static RETRY: Duration = Duration::from_secs(10);
+
+/// Messages to queue for the broker while it's unreachable.
+static QUEUE: usize = 100;
+
+/// Publishes server events to an MQTT broker, under
+/// `<prefix>/<server>/<event>`.
+#[derive(Clone)]
+pub struct Publisher {
+    client: AsyncClient,
+    topic: String,
+    server: Option<String>,
+}
+
+impl Publisher {
+    /// Queue `payload` for the broker, as the same JSON sent to `[[sinks]]`.
+    pub fn publish(&self, payload: &sink::Payload) {
+        let kind = payload.kind().as_str();
+        let body = match payload.to_json(self.server.as_deref()) {
+            Ok(body) => body,
+            Err(error) => {
+                tracing::error!("Failed to serialize event: {}", error);
+                return;
+            }
+        };
+
+        let topic = format!("{}/{}", self.topic, kind);
+        if let Err(error) = self
+            .client
+            .try_publish(topic, QoS::AtLeastOnce, false, body)
+        {
+            tracing::warn!("Dropping {} event for MQTT: {}", kind, error);
+        }
+    }
+}
+
+/// The connection to the broker, which delivers queued events, and receives
+/// console commands on `<prefix>/<server>/command` if enabled.
+pub struct Mqtt {
+    client: AsyncClient,
+    eventloop: EventLoop,
+    /// Topic to receive console commands on, if enabled.
+    command: Option<String>,
+    tx: mpsc::Sender<Event>,
+}
+
+impl Mqtt {
+    pub fn new(
+        config: &config::Mqtt,
+        server: Option<&str>,
+        tx: mpsc::Sender<Event>,
+    ) -> (Self, Publisher) {
+        let topic = format!(
+            "{}/{}",
+            config.prefix.trim_end_matches('/'),
+            server.unwrap_or("default"),
+        );
+
+        let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let Some(username) = &config.username {
+            options.set_credentials(username, config.password.as_deref().unwrap_or(""));
+        }
+
+        let (client, eventloop) = AsyncClient::new(options, QUEUE);
+        let publisher = Publisher {
+            client: client.clone(),
+            topic: topic.clone(),
+            server: server.map(String::from),
+        };
+        let mqtt = Mqtt {
+            client,
+            eventloop,
+            command: Some(format!("{}/command", topic)).filter(|_| config.commands),
+            tx,
+        };
+        (mqtt, publisher)
+    }
+
+    pub async fn start(mut self) -> anyhow::Result<()> {
+        loop {
+            let packet = match self.eventloop.poll().await {
+                Ok(rumqttc::Event::Incoming(packet)) => packet,
+                Ok(rumqttc::Event::Outgoing(_)) => continue,
+                Err(error) => {
+                    tracing::warn!("Lost connection to MQTT broker: {}", error);
+                    time::sleep(RETRY).await;
+                    continue;
+                }
+            };
+
+            match packet {
+                // Subscriptions don't survive reconnecting with a clean session.
+                Packet::ConnAck(_) => {
+                    tracing::info!("Connected to MQTT broker");
+                    if let Some(command) = &self.command {
+                        if let Err(error) = self.client.try_subscribe(command, QoS::AtLeastOnce) {
+                            tracing::error!("Failed to subscribe to `{}`: {}", command, error);
+                        }
+                    }
+                }
+                Packet::Publish(publish) if Some(&publish.topic) == self.command.as_ref() => {
+                    let command = String::from_utf8_lossy(&publish.payload).trim().to_owned();
+                    if !command.is_empty() {
+                        self.tx.send(Event::Mqtt(command)).await?;
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::convert::TryFrom as _;
+use std::fs;
+use std::io;
+use std::io::Read as _;
+use std::path::Path;
+
+use anyhow::anyhow;
+use anyhow::Context as _;
+use flate2::read::GzDecoder;
+
+/// Deepest nesting of lists and compounds to follow, so a corrupt file can't
+/// overflow the stack.
+static MAX_DEPTH: usize = 512;
+
+/// Value in Minecraft's Named Binary Tag format, which the server saves
+/// player and world data in.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(HashMap<String, Tag>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    /// The value of `key`, if this is a compound that has it.
+    pub fn get(&self, key: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(compound) => compound.get(key),
+            _ => None,
+        }
+    }
+
+    /// Any integer, widened.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Tag::Byte(value) => Some(value.into()),
+            Tag::Short(value) => Some(value.into()),
+            Tag::Int(value) => Some(value.into()),
+            Tag::Long(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Any number, widened.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Tag::Float(value) => Some(value.into()),
+            Tag::Double(value) => Some(value),
+            _ => self.as_i64().map(|value| value as f64),
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Tag]> {
+        match self {
+            Tag::List(list) => Some(list),
+            _ => None,
+        }
+    }
+}
+
+/// Read the root compound from a file, which is usually gzipped.
+pub fn read(path: &Path) -> anyhow::Result<Tag> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read `{}`", path.display()))?;
+    let bytes = if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut decompressed = Vec::new();
+        GzDecoder::new(&bytes[..])
+            .read_to_end(&mut decompressed)
+            .with_context(|| format!("Failed to decompress `{}`", path.display()))?;
+        decompressed
+    } else {
+        bytes
+    };
+    parse(&bytes).with_context(|| format!("Failed to parse `{}`", path.display()))
+}
+
+/// Parse the root compound, ignoring its name.
+pub fn parse(bytes: &[u8]) -> anyhow::Result<Tag> {
+    let mut reader = Reader(bytes);
+    match reader.u8()? {
+        10 => {
+            reader.string()?;
+            reader.payload(10, 0)
+        }
+        kind => Err(anyhow!(
+            "Expected a compound at the root, but found tag {}",
+            kind
+        )),
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> anyhow::Result<&[u8]> {
+        if self.0.len() < len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> anyhow::Result<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    /// Length of an array or list, which can't be negative.
+    fn len(&mut self) -> anyhow::Result<usize> {
+        let len = i32::from_be_bytes(self.array()?);
+        usize::try_from(len).map_err(|_| anyhow!("Negative length {}", len))
+    }
+
+    /// Strings are in Java's modified UTF-8, which only differs from UTF-8
+    /// for null and characters outside the Basic Multilingual Plane.
+    fn string(&mut self) -> anyhow::Result<String> {
+        let len = u16::from_be_bytes(self.array()?);
+        Ok(String::from_utf8_lossy(self.take(len.into())?).into_owned())
+    }
+
+    fn payload(&mut self, kind: u8, depth: usize) -> anyhow::Result<Tag> {
+        if depth > MAX_DEPTH {
+            return Err(anyhow!("Nested more than {} deep", MAX_DEPTH));
+        }
+        let tag = match kind {
+            1 => Tag::Byte(i8::from_be_bytes(self.array()?)),
+            2 => Tag::Short(i16::from_be_bytes(self.array()?)),
+            3 => Tag::Int(i32::from_be_bytes(self.array()?)),
+            4 => Tag::Long(i64::from_be_bytes(self.array()?)),
+            5 => Tag::Float(f32::from_be_bytes(self.array()?)),
+            6 => Tag::Double(f64::from_be_bytes(self.array()?)),
+            7 => {
+                let len = self.len()?;
+                Tag::ByteArray(self.take(len)?.iter().map(|byte| *byte as i8).collect())
+            }
+            8 => Tag::String(self.string()?),
+            9 => {
+                let kind = self.u8()?;
+                let len = self.len()?;
+                // Empty lists may be of the end tag.
+                if kind == 0 && len > 0 {
+                    return Err(anyhow!("List of end tags"));
+                }
+                let mut list = Vec::with_capacity(len.min(self.0.len()));
+                for _ in 0..len {
+                    list.push(self.payload(kind, depth + 1)?);
+                }
+                Tag::List(list)
+            }
+            10 => {
+                let mut compound = HashMap::new();
+                loop {
+                    let kind = self.u8()?;
+                    if kind == 0 {
+                        break;
+                    }
+                    let name = self.string()?;
+                    compound.insert(name, self.payload(kind, depth + 1)?);
+                }
+                Tag::Compound(compound)
+            }
+            11 => {
+                let len = self.len()?;
+                let mut array = Vec::with_capacity(len.min(self.0.len() / 4));
+                for _ in 0..len {
+                    array.push(i32::from_be_bytes(self.array()?));
+                }
+                Tag::IntArray(array)
+            }
+            12 => {
+                let len = self.len()?;
+                let mut array = Vec::with_capacity(len.min(self.0.len() / 8));
+                for _ in 0..len {
+                    array.push(i64::from_be_bytes(self.array()?));
+                }
+                Tag::LongArray(array)
+            }
+            kind => return Err(anyhow!("Unknown tag {}", kind)),
+        };
+        Ok(tag)
+    }
+}
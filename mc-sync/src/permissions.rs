@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use serenity::model::id;
+
+/// Something a Discord user may be allowed to do.
+#[derive(Copy, Clone, Debug, Deserialize, Eq, Hash, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Capability {
+    /// Relay messages to Minecraft chat, and use `/say`.
+    Chat,
+
+    /// Look up server information, like `/online` and `/playtime`.
+    Online,
+
+    /// Run arbitrary server commands, with `/command` or in the console channel.
+    Console,
+
+    /// Manage the server's whitelist.
+    Whitelist,
+
+    /// Ban, pardon, and kick players.
+    Moderate,
+
+    /// Start, stop, or restart the server.
+    Restart,
+
+    /// Back up the world from the control panel, and list backups.
+    Backup,
+
+    /// Restore the world from a backup, replacing the current one.
+    Restore,
+
+    /// Re-read the config file.
+    Reload,
+
+    /// Look up players' chat and events, with `/history` and `/search`.
+    History,
+
+    /// Look up where players are and what they carry, with `/whereis` and
+    /// `/inventory`.
+    Inspect,
+}
+
+/// Capabilities granted to everyone, and to specific roles and users, as
+/// written in the config file.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PermissionsConfig {
+    pub everyone: HashSet<Capability>,
+    pub roles: HashMap<String, HashSet<Capability>>,
+    pub users: HashMap<String, HashSet<Capability>>,
+}
+
+impl Default for PermissionsConfig {
+    fn default() -> Self {
+        PermissionsConfig {
+            everyone: [Capability::Chat, Capability::Online]
+                .iter()
+                .copied()
+                .collect(),
+            roles: HashMap::new(),
+            users: HashMap::new(),
+        }
+    }
+}
+
+impl PermissionsConfig {
+    pub fn load(self) -> anyhow::Result<Permissions> {
+        let parse = |section: &str, grants: HashMap<String, HashSet<Capability>>| {
+            grants
+                .into_iter()
+                .map(|(id, capabilities)| {
+                    id.parse::<u64>()
+                        .map(|id| (id, capabilities))
+                        .with_context(|| {
+                            format!("Invalid ID `{}` in `permissions.{}`", id, section)
+                        })
+                })
+                .collect::<anyhow::Result<HashMap<_, _>>>()
+        };
+
+        Ok(Permissions {
+            everyone: self.everyone,
+            roles: parse("roles", self.roles)?,
+            users: parse("users", self.users)?,
+        })
+    }
+}
+
+/// Who may do what. The bot's owner may always do everything.
+#[derive(Clone, Debug)]
+pub struct Permissions {
+    everyone: HashSet<Capability>,
+    roles: HashMap<u64, HashSet<Capability>>,
+    users: HashMap<u64, HashSet<Capability>>,
+}
+
+impl Permissions {
+    /// Whether `user`, who has `roles` in the guild, may do `capability`.
+    pub fn allows(&self, user: id::UserId, roles: &[id::RoleId], capability: Capability) -> bool {
+        let grants = |capabilities: Option<&HashSet<Capability>>| {
+            capabilities.is_some_and(|capabilities| capabilities.contains(&capability))
+        };
+
+        self.everyone.contains(&capability)
+            || grants(self.users.get(&user.0))
+            || roles.iter().any(|role| grants(self.roles.get(&role.0)))
+    }
+}
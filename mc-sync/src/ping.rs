@@ -0,0 +1,181 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::Context as _;
+use serde_json::Value;
+use tokio::io::AsyncReadExt as _;
+use tokio::io::AsyncWriteExt as _;
+use tokio::net;
+use tokio::time;
+
+/// How long to wait for the server to answer a ping.
+static TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest status response to accept.
+static RESPONSE_LIMIT: usize = 256 * 1024;
+
+/// The server's status, as shown in the multiplayer server list.
+#[derive(Clone, Debug, Default)]
+pub struct Status {
+    /// Version name, e.g. `Paper 1.20.4`.
+    pub version: String,
+    pub online: usize,
+    pub max: usize,
+    /// Some of the players online, or all of them if there are few enough.
+    /// Servers that hide their players send none.
+    pub sample: Vec<String>,
+    /// Message of the day, without formatting codes.
+    pub motd: String,
+}
+
+impl Status {
+    /// Whether `sample` lists everyone online.
+    pub fn complete(&self) -> bool {
+        self.sample.len() == self.online
+    }
+}
+
+/// Ask the server at `address` (`host:port`) for its status with the Server
+/// List Ping protocol, as the multiplayer menu does.
+pub async fn ping(address: &str) -> anyhow::Result<Status> {
+    time::timeout(TIMEOUT, query(address))
+        .await
+        .map_err(|_| anyhow!("Timed out pinging `{}`", address))?
+        .with_context(|| format!("Failed to ping `{}`", address))
+}
+
+async fn query(address: &str) -> anyhow::Result<Status> {
+    let (host, port) = match address.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse::<u16>()?),
+        None => (address, 25565),
+    };
+    let mut stream = net::TcpStream::connect((host, port)).await?;
+
+    // Handshake: unknown protocol version, then the address as typed, and
+    // the status state.
+    let mut handshake = Vec::new();
+    write_varint(&mut handshake, 0x00);
+    write_varint(&mut handshake, -1);
+    write_varint(&mut handshake, host.len() as i32);
+    handshake.extend_from_slice(host.as_bytes());
+    handshake.extend_from_slice(&port.to_be_bytes());
+    write_varint(&mut handshake, 1);
+
+    let mut request = Vec::new();
+    write_varint(&mut request, handshake.len() as i32);
+    request.extend_from_slice(&handshake);
+    // Status request: an empty packet.
+    request.extend_from_slice(&[0x01, 0x00]);
+    stream.write_all(&request).await?;
+
+    let length = read_varint(&mut stream).await? as usize;
+    if length > RESPONSE_LIMIT {
+        return Err(anyhow!("Status response is too long: {} bytes", length));
+    }
+    let mut packet = vec![0; length];
+    stream.read_exact(&mut packet).await?;
+
+    let mut packet = packet.as_slice();
+    if read_varint(&mut packet).await? != 0x00 {
+        return Err(anyhow!("Unexpected packet in response to status request"));
+    }
+    let length = read_varint(&mut packet).await? as usize;
+    let json = packet
+        .get(..length)
+        .ok_or_else(|| anyhow!("Status response was cut off"))?;
+    parse(&serde_json::from_slice(json)?)
+}
+
+fn parse(status: &Value) -> anyhow::Result<Status> {
+    let players = &status["players"];
+    let count = |key: &str| {
+        players[key]
+            .as_u64()
+            .map(|count| count as usize)
+            .ok_or_else(|| anyhow!("Status response is missing `players.{}`", key))
+    };
+
+    // Players hidden by the server are sent as "Anonymous Player" with an
+    // all-zero UUID.
+    let sample = players["sample"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|player| player["id"].as_str() != Some("00000000-0000-0000-0000-000000000000"))
+        .filter_map(|player| player["name"].as_str())
+        .map(String::from)
+        .collect();
+
+    let mut motd = String::new();
+    flatten(&status["description"], &mut motd);
+
+    Ok(Status {
+        version: status["version"]["name"]
+            .as_str()
+            .unwrap_or_default()
+            .to_owned(),
+        online: count("online")?,
+        max: count("max")?,
+        sample,
+        motd: strip_formatting(&motd),
+    })
+}
+
+/// Append the text of a chat component, which is a string, an array of
+/// components, or an object with `text` and `extra` components.
+fn flatten(component: &Value, text: &mut String) {
+    match component {
+        Value::String(string) => text.push_str(string),
+        Value::Array(components) => components
+            .iter()
+            .for_each(|component| flatten(component, text)),
+        Value::Object(object) => {
+            if let Some(Value::String(string)) = object.get("text") {
+                text.push_str(string);
+            }
+            if let Some(extra) = object.get("extra") {
+                flatten(extra, text);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Remove `§` formatting codes, like `§a` for green.
+fn strip_formatting(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(char) = chars.next() {
+        match char {
+            '§' => {
+                chars.next();
+            }
+            _ => stripped.push(char),
+        }
+    }
+    stripped
+}
+
+fn write_varint(buffer: &mut Vec<u8>, value: i32) {
+    let mut value = value as u32;
+    loop {
+        if value & !0x7F == 0 {
+            buffer.push(value as u8);
+            return;
+        }
+        buffer.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+}
+
+async fn read_varint<R: tokio::io::AsyncRead + Unpin>(reader: &mut R) -> anyhow::Result<i32> {
+    let mut value = 0u32;
+    for position in 0..5 {
+        let byte = reader.read_u8().await?;
+        value |= ((byte & 0x7F) as u32) << (7 * position);
+        if byte & 0x80 == 0 {
+            return Ok(value as i32);
+        }
+    }
+    Err(anyhow!("VarInt is too long"))
+}
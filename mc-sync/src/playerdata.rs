@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+use crate::nbt;
+use crate::nbt::Tag;
+use crate::stats;
+
+/// What the server saved about a player in `<world>/playerdata/<uuid>.dat`,
+/// as of the last time it saved: when they quit, and every few minutes
+/// while they're online.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PlayerData {
+    /// Block coordinates.
+    pub position: Option<[f64; 3]>,
+    /// e.g. `overworld` or `the_nether`.
+    pub dimension: Option<String>,
+    pub health: Option<f64>,
+    pub food: Option<i64>,
+    pub xp_level: Option<i64>,
+    pub inventory: Vec<Item>,
+    pub ender_chest: Vec<Item>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Item {
+    pub slot: i64,
+    /// e.g. `minecraft:diamond_sword`.
+    pub id: String,
+    pub count: i64,
+}
+
+impl Item {
+    /// Readable name, e.g. `diamond sword`.
+    pub fn name(&self) -> String {
+        stats::name(&self.id)
+    }
+}
+
+impl PlayerData {
+    /// Total count of each item across stacks, by readable name, most first.
+    pub fn totals(items: &[Item]) -> Vec<(String, i64)> {
+        let mut totals = BTreeMap::<String, i64>::new();
+        for item in items {
+            *totals.entry(item.name()).or_default() += item.count;
+        }
+        let mut totals = totals.into_iter().collect::<Vec<_>>();
+        totals.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+        totals
+    }
+}
+
+/// Read the player with `uuid` from `world`, or `None` if they haven't
+/// played in it.
+pub fn read(world: &Path, uuid: &str) -> anyhow::Result<Option<PlayerData>> {
+    let path = world.join("playerdata").join(format!("{}.dat", uuid));
+    let root = match nbt::read(&path) {
+        Ok(root) => root,
+        Err(error)
+            if error
+                .downcast_ref::<io::Error>()
+                .is_some_and(|error| error.kind() == io::ErrorKind::NotFound) =>
+        {
+            return Ok(None)
+        }
+        Err(error) => return Err(error),
+    };
+
+    let position = root.get("Pos").and_then(Tag::as_list).and_then(|position| {
+        match position
+            .iter()
+            .map(Tag::as_f64)
+            .collect::<Option<Vec<_>>>()?[..]
+        {
+            [x, y, z] => Some([x, y, z]),
+            _ => None,
+        }
+    });
+    // Before 1.16, dimensions were numbered.
+    let dimension = root.get("Dimension").and_then(|dimension| match dimension {
+        Tag::String(dimension) => Some(
+            dimension
+                .strip_prefix("minecraft:")
+                .unwrap_or(dimension)
+                .to_owned(),
+        ),
+        _ => match dimension.as_i64()? {
+            -1 => Some(String::from("the_nether")),
+            0 => Some(String::from("overworld")),
+            1 => Some(String::from("the_end")),
+            _ => None,
+        },
+    });
+
+    Ok(Some(PlayerData {
+        position,
+        dimension,
+        health: root.get("Health").and_then(Tag::as_f64),
+        food: root.get("foodLevel").and_then(Tag::as_i64),
+        xp_level: root.get("XpLevel").and_then(Tag::as_i64),
+        inventory: items(root.get("Inventory")),
+        ender_chest: items(root.get("EnderItems")),
+    }))
+}
+
+fn items(list: Option<&Tag>) -> Vec<Item> {
+    list.and_then(Tag::as_list)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|item| {
+            Some(Item {
+                slot: item.get("Slot").and_then(Tag::as_i64).unwrap_or(0),
+                id: item.get("id")?.as_str()?.to_owned(),
+                // Renamed from `Count` in 1.20.5.
+                count: item
+                    .get("count")
+                    .or_else(|| item.get("Count"))
+                    .and_then(Tag::as_i64)
+                    .unwrap_or(1),
+            })
+        })
+        .collect()
+}
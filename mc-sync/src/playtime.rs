@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use crate::store;
+
+/// Sessions in progress, credited to each player's total when they end.
+#[derive(Debug, Default)]
+pub struct Playtime {
+    sessions: HashMap<String, SystemTime>,
+}
+
+impl Playtime {
+    pub fn join(&mut self, player: &str) {
+        self.sessions
+            .entry(player.to_owned())
+            .or_insert_with(SystemTime::now);
+    }
+
+    pub fn quit(&mut self, player: &str, data: &mut store::Data) {
+        if let Some(joined) = self.sessions.remove(player) {
+            let elapsed = joined.elapsed().unwrap_or_default().as_secs();
+            *data.playtime.entry(player.to_owned()).or_default() += elapsed;
+        }
+    }
+
+    /// End every session, e.g. when the server stops.
+    pub fn quit_all(&mut self, data: &mut store::Data) {
+        let players = self.sessions.keys().cloned().collect::<Vec<_>>();
+        for player in players {
+            self.quit(&player, data);
+        }
+    }
+
+    /// Total time played, including the current session.
+    pub fn total(&self, player: &str, data: &store::Data) -> Duration {
+        let saved = data.playtime.get(player).copied().unwrap_or(0);
+        let current = self
+            .sessions
+            .get(player)
+            .and_then(|joined| joined.elapsed().ok())
+            .unwrap_or_default();
+        Duration::from_secs(saved) + current
+    }
+
+    /// Players with the most time played, in descending order.
+    pub fn leaderboard(&self, data: &store::Data, count: usize) -> Vec<(String, Duration)> {
+        let mut totals = data
+            .playtime
+            .keys()
+            .chain(self.sessions.keys())
+            .map(|player| (player.clone(), self.total(player, data)))
+            .collect::<HashMap<_, _>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        totals.sort_by(|(a, a_total), (b, b_total)| b_total.cmp(a_total).then(a.cmp(b)));
+        totals.truncate(count);
+        totals
+    }
+}
@@ -0,0 +1,144 @@
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+
+/// The server's `server.properties`, kept line by line so that comments and
+/// ordering survive being written back.
+#[derive(Clone, Debug)]
+pub struct Properties {
+    path: PathBuf,
+    lines: Vec<Line>,
+}
+
+#[derive(Clone, Debug)]
+enum Line {
+    /// A comment or blank line, kept verbatim.
+    Other(String),
+    Entry {
+        key: String,
+        value: String,
+    },
+}
+
+impl Properties {
+    pub fn read(directory: &Path) -> anyhow::Result<Self> {
+        let path = directory.join("server.properties");
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read properties `{}`", path.display()))?;
+        let lines = text
+            .lines()
+            .map(|line| {
+                let trimmed = line.trim_start();
+                if trimmed.starts_with('#') || trimmed.starts_with('!') {
+                    return Line::Other(line.to_owned());
+                }
+                match split(trimmed) {
+                    None => Line::Other(line.to_owned()),
+                    Some((key, value)) => Line::Entry {
+                        key: unescape(key),
+                        value: unescape(value),
+                    },
+                }
+            })
+            .collect();
+        Ok(Properties { path, lines })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.lines.iter().find_map(|line| match line {
+            Line::Entry { key: name, value } if name == key => Some(value.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Replace the value of an existing property, returning the old value, or
+    /// `None` if there's no such property.
+    pub fn set(&mut self, key: &str, value: &str) -> Option<String> {
+        self.lines.iter_mut().find_map(|line| match line {
+            Line::Entry {
+                key: name,
+                value: old,
+            } if name == key => Some(std::mem::replace(old, value.to_owned())),
+            _ => None,
+        })
+    }
+
+    pub fn write(&self) -> anyhow::Result<()> {
+        let mut text = String::new();
+        for line in &self.lines {
+            match line {
+                Line::Other(line) => text.push_str(line),
+                Line::Entry { key, value } => {
+                    text.push_str(&escape(key, true));
+                    text.push('=');
+                    text.push_str(&escape(value, false));
+                }
+            }
+            text.push('\n');
+        }
+        fs::write(&self.path, text)
+            .with_context(|| format!("Failed to write properties `{}`", self.path.display()))
+    }
+}
+
+/// Split a line at its first unescaped `=` or `:`.
+fn split(line: &str) -> Option<(&str, &str)> {
+    let mut escaped = false;
+    for (index, char) in line.char_indices() {
+        match char {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '=' | ':' => return Some((line[..index].trim_end(), line[index + 1..].trim_start())),
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Undo Java's escapes, like `\:` and `\u00A7` in values like `motd`.
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            unescaped.push(char);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('u') => {
+                let hex = chars.by_ref().take(4).collect::<String>();
+                unescaped.extend(u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32));
+            }
+            escaped => unescaped.extend(escaped),
+        }
+    }
+    unescaped
+}
+
+/// Escape text the way the server writes it, so it reads back the same.
+fn escape(text: &str, key: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for (index, char) in text.chars().enumerate() {
+        match char {
+            '\\' | '=' | ':' | '#' | '!' => {
+                escaped.push('\\');
+                escaped.push(char);
+            }
+            ' ' if key || index == 0 => escaped.push_str("\\ "),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            ' '..='~' => escaped.push(char),
+            _ => {
+                let mut units = [0; 2];
+                for unit in char.encode_utf16(&mut units) {
+                    escaped.push_str(&format!("\\u{:04X}", unit));
+                }
+            }
+        }
+    }
+    escaped
+}
@@ -0,0 +1,126 @@
+use serde_json::json;
+use serde_json::Value;
+
+use crate::events::Flavor;
+use crate::template;
+
+/// Start of each line logged by Velocity, e.g. `[12:34:56 INFO]: `.
+pub static VELOCITY_PREFIX: &str = r"^[^<]*?\[\d{2}:\d{2}:\d{2} INFO\]: ";
+
+/// Start of each line logged by BungeeCord, e.g. `12:34:56 [INFO] `, or by
+/// Waterfall, like Velocity.
+pub static BUNGEECORD_PREFIX: &str =
+    r"^[^<]*?(?:\[\d{2}:\d{2}:\d{2} INFO\]:|\d{2}:\d{2}:\d{2} \[INFO\]) ";
+
+/// Matches nothing, for events proxies don't log, like deaths.
+static NEVER: &str = r"$.^";
+
+/// Default pattern for `regex.<name>`, in place of the server one.
+pub fn pattern(flavor: Flavor, name: &str) -> &'static str {
+    match (flavor, name) {
+        // e.g. `[connected player] Steve (/127.0.0.1:51234) has connected`
+        (Flavor::Velocity, "join") => r"\[connected player\] (\S+) \([^)]*\) has connected",
+        (Flavor::Velocity, "quit") => r"\[connected player\] (\S+) \([^)]*\) has disconnected",
+        // e.g. `[server connection] Steve -> survival has connected`
+        (Flavor::Velocity, "switch") => r"\[server connection\] (\S+) -> (\S+) has connected",
+        (Flavor::Velocity, "version") => r"Booting up Velocity (\S+)",
+        (Flavor::Velocity, "done") => r"Done \(([0-9.,]+)s\)!",
+        (Flavor::Velocity, "stopping") => r"Shutting down the proxy",
+        // e.g. `[Steve|/127.0.0.1:51234] <-> InitialHandler has connected`,
+        // where older versions put the address first, or separate with a comma.
+        (Flavor::Bungeecord, "join") => {
+            r"\[(?:/[^|\]]*\|)?([A-Za-z0-9_]{1,16})[^\]]*\] <-> InitialHandler has connected"
+        }
+        (Flavor::Bungeecord, "quit") => {
+            r"\[(?:/[^|\]]*\|)?([A-Za-z0-9_]{1,16})[^\]]*\] -> UpstreamBridge has disconnected"
+        }
+        // e.g. `[Steve] <-> ServerConnector [survival] has connected`
+        (Flavor::Bungeecord, "switch") => {
+            r"\[(?:/[^|\]]*\|)?([A-Za-z0-9_]{1,16})[^\]]*\] <-> ServerConnector \[([^\]]+)\] has connected"
+        }
+        (Flavor::Bungeecord, "version") => r"Enabled (?:BungeeCord|Waterfall) version (\S+)",
+        (Flavor::Bungeecord, "done") => r"Listening on /",
+        (Flavor::Bungeecord, "stopping") => r"Closing listener",
+        _ => NEVER,
+    }
+}
+
+/// Translate a command, as the bridge sends them, for the proxy's console, or
+/// `None` if it doesn't apply to a proxy. Broadcasts use the `broadcast`
+/// command, with `{message}` (plain text) and `{json}` (a chat component).
+pub fn command(flavor: Flavor, command: &str, broadcast: Option<&str>) -> Option<String> {
+    let command = command.trim().trim_start_matches('/');
+    let (name, arguments) = match command.split_once(' ') {
+        Some((name, arguments)) => (name, arguments.trim()),
+        None => (command, ""),
+    };
+
+    let command = match (name, arguments) {
+        ("stop", _) => match flavor {
+            Flavor::Velocity => String::from("shutdown"),
+            _ => String::from("end"),
+        },
+        ("say", message) => {
+            let component = json!({ "text": message });
+            self::broadcast(flavor, broadcast, message, &component)?
+        }
+        // Messages to a single player can't be sent from the console.
+        ("tellraw", arguments) => {
+            let json = arguments.strip_prefix("@a ")?;
+            let component = serde_json::from_str::<Value>(json).ok()?;
+            let mut message = String::new();
+            text(&component, &mut message);
+            self::broadcast(flavor, broadcast, &message, &component)?
+        }
+        // The world belongs to the backend servers.
+        ("save-off", _)
+        | ("save-on", _)
+        | ("save-all", _)
+        | ("seed", _)
+        | ("tps", _)
+        | ("difficulty", _)
+        | ("time", _)
+        | ("list", _)
+        | ("whitelist", _) => return None,
+        _ => command.to_owned(),
+    };
+    Some(command)
+}
+
+/// Broadcast on every backend server. BungeeCord has a command for this, but
+/// Velocity needs one from a plugin.
+fn broadcast(
+    flavor: Flavor,
+    broadcast: Option<&str>,
+    message: &str,
+    component: &Value,
+) -> Option<String> {
+    let broadcast = match (broadcast, flavor) {
+        (Some(broadcast), _) => broadcast,
+        (None, Flavor::Bungeecord) => "alertraw {json}",
+        (None, _) => return None,
+    };
+    Some(template::render(
+        broadcast,
+        &[("message", message), ("json", &component.to_string())],
+    ))
+}
+
+/// Append the text of a chat component, without its formatting.
+fn text(component: &Value, text: &mut String) {
+    match component {
+        Value::String(string) => text.push_str(string),
+        Value::Array(components) => components
+            .iter()
+            .for_each(|component| self::text(component, text)),
+        Value::Object(object) => {
+            if let Some(Value::String(string)) = object.get("text") {
+                text.push_str(string);
+            }
+            if let Some(extra) = object.get("extra") {
+                self::text(extra, text);
+            }
+        }
+        _ => (),
+    }
+}
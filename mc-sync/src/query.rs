@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::Context as _;
+use tokio::net;
+use tokio::time;
+
+/// How long to wait for the server to answer a query.
+static TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Every packet to the server starts with these bytes.
+static MAGIC: [u8; 2] = [0xFE, 0xFD];
+
+static HANDSHAKE: u8 = 0x09;
+static STAT: u8 = 0x00;
+
+/// The server's full status, from the Query protocol (`enable-query` in
+/// `server.properties`).
+#[derive(Clone, Debug, Default)]
+pub struct Status {
+    /// Version number, e.g. `1.20.4`.
+    pub version: String,
+    /// Server software and plugins, e.g. `Paper on 1.20.4: EssentialsX 2.20.1`,
+    /// if the server reports any.
+    pub plugins: Option<String>,
+    pub online: usize,
+    pub max: usize,
+    /// Everyone online, unlike a status ping's sample.
+    pub players: Vec<String>,
+    /// Message of the day.
+    pub motd: String,
+}
+
+/// Ask the server at `address` (`host:port`) for its full status with the
+/// UDP Query protocol.
+pub async fn query(address: &str) -> anyhow::Result<Status> {
+    time::timeout(TIMEOUT, full_stat(address))
+        .await
+        .map_err(|_| anyhow!("Timed out querying `{}`", address))?
+        .with_context(|| format!("Failed to query `{}`", address))
+}
+
+async fn full_stat(address: &str) -> anyhow::Result<Status> {
+    let (host, port) = match address.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse::<u16>()?),
+        None => (address, 25565),
+    };
+    let address = net::lookup_host((host, port))
+        .await?
+        .next()
+        .ok_or_else(|| anyhow!("No address found for `{}`", host))?;
+    let socket = match address.is_ipv4() {
+        true => net::UdpSocket::bind("0.0.0.0:0").await?,
+        false => net::UdpSocket::bind("[::]:0").await?,
+    };
+    socket.connect(address).await?;
+
+    // The server only reads the low four bits of each byte.
+    let session = rand::random::<u32>() & 0x0F0F_0F0F;
+
+    let mut request = MAGIC.to_vec();
+    request.push(HANDSHAKE);
+    request.extend_from_slice(&session.to_be_bytes());
+    socket.send(&request).await?;
+
+    let response = receive(&socket, HANDSHAKE, session).await?;
+    let token = null_terminated(&mut response.as_slice())
+        .and_then(|token| token.parse::<i32>().ok())
+        .ok_or_else(|| anyhow!("Invalid challenge token"))?;
+
+    // A full stat request is padded to tell it apart from a basic one.
+    let mut request = MAGIC.to_vec();
+    request.push(STAT);
+    request.extend_from_slice(&session.to_be_bytes());
+    request.extend_from_slice(&token.to_be_bytes());
+    request.extend_from_slice(&[0; 4]);
+    socket.send(&request).await?;
+
+    let response = receive(&socket, STAT, session).await?;
+    parse(&response)
+}
+
+/// Receive the body of the server's response to a `kind` request.
+async fn receive(socket: &net::UdpSocket, kind: u8, session: u32) -> anyhow::Result<Vec<u8>> {
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let length = socket.recv(&mut buffer).await?;
+        match buffer[..length] {
+            [header, a, b, c, d, ..]
+                if header == kind && u32::from_be_bytes([a, b, c, d]) == session =>
+            {
+                return Ok(buffer[5..length].to_vec())
+            }
+            // Stale responses to an earlier session.
+            _ => continue,
+        }
+    }
+}
+
+fn parse(response: &[u8]) -> anyhow::Result<Status> {
+    // Keys and values, after 11 bytes of constant padding, end with an empty
+    // key. Then 10 more bytes of padding, and a name per player, ending with
+    // an empty name.
+    let mut body = response
+        .get(11..)
+        .ok_or_else(|| anyhow!("Query response was cut off"))?;
+
+    let mut values = HashMap::new();
+    loop {
+        let key =
+            null_terminated(&mut body).ok_or_else(|| anyhow!("Query response was cut off"))?;
+        if key.is_empty() {
+            break;
+        }
+        let value =
+            null_terminated(&mut body).ok_or_else(|| anyhow!("Query response was cut off"))?;
+        values.insert(key, value);
+    }
+
+    let mut players = Vec::new();
+    if let Some(mut names) = body.get(10..) {
+        while let Some(name) = null_terminated(&mut names).filter(|name| !name.is_empty()) {
+            players.push(name);
+        }
+    }
+
+    let count = |key: &str| {
+        values
+            .get(key)
+            .and_then(|count| count.parse::<usize>().ok())
+            .ok_or_else(|| anyhow!("Query response is missing `{}`", key))
+    };
+
+    Ok(Status {
+        version: values.get("version").cloned().unwrap_or_default(),
+        plugins: values
+            .get("plugins")
+            .filter(|plugins| !plugins.is_empty())
+            .cloned(),
+        online: count("numplayers")?,
+        max: count("maxplayers")?,
+        players,
+        motd: values.get("hostname").cloned().unwrap_or_default(),
+    })
+}
+
+/// Take a null-terminated string from the front of `bytes`.
+fn null_terminated(bytes: &mut &[u8]) -> Option<String> {
+    let end = bytes.iter().position(|byte| *byte == 0)?;
+    let string = String::from_utf8_lossy(&bytes[..end]).into_owned();
+    *bytes = &bytes[end + 1..];
+    Some(string)
+}
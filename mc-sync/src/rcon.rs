@@ -0,0 +1,282 @@
+use std::fmt;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::Context as _;
+use tokio::io;
+use tokio::io::AsyncReadExt as _;
+use tokio::io::AsyncWriteExt as _;
+use tokio::net;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+use tokio::time;
+use tracing::Instrument as _;
+
+use crate::api;
+use crate::config;
+use crate::events::Event;
+
+// https://wiki.vg/RCON#Packets
+const RESPONSE: i32 = 0;
+const COMMAND: i32 = 2;
+const AUTH_RESPONSE: i32 = 2;
+const LOGIN: i32 = 3;
+
+/// Longest request body to accept, as the vanilla server does.
+static REQUEST_LIMIT: usize = 1446;
+
+/// Longest response body to send in one packet. Longer responses are split.
+static RESPONSE_LIMIT: usize = 4096;
+
+/// How long to wait for the bridge to run a command.
+static TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long a client has to log in after connecting.
+static LOGIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a logged in client can go between commands before it's
+/// disconnected.
+static IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Most connections to serve at once. More wait to be accepted.
+static CONNECTION_LIMIT: usize = 16;
+
+/// Minecraft server remote console client.
+pub struct Rcon {
+    address: String,
+    password: String,
+    stream: io::BufStream<net::TcpStream>,
+    next: i32,
+}
+
+impl Rcon {
+    pub async fn connect(address: &str, password: &str) -> anyhow::Result<Self> {
+        let stream = net::TcpStream::connect(address)
+            .await
+            .map(io::BufStream::new)
+            .with_context(|| format!("Failed to connect to RCON at `{}`", address))?;
+
+        let mut rcon = Rcon {
+            address: address.to_owned(),
+            password: password.to_owned(),
+            stream,
+            next: 0,
+        };
+
+        let id = rcon.write(LOGIN, password).await?;
+        match read(&mut rcon.stream, usize::MAX).await? {
+            (response, _, _) if response == id => Ok(rcon),
+            _ => Err(anyhow!("Incorrect RCON password for `{}`", address)),
+        }
+    }
+
+    /// Run a command, reconnecting once if the connection was dropped.
+    pub async fn command(&mut self, command: &str) -> anyhow::Result<String> {
+        match self.command_once(command).await {
+            Ok(response) => Ok(response),
+            Err(_) => {
+                *self = Rcon::connect(&self.address, &self.password).await?;
+                self.command_once(command).await
+            }
+        }
+    }
+
+    async fn command_once(&mut self, command: &str) -> anyhow::Result<String> {
+        // Long responses are split across several packets, so send an invalid
+        // packet afterward and read until the server complains about it.
+        let id = self.write(COMMAND, command.trim_start_matches('/')).await?;
+        let end = self.write(RESPONSE, "").await?;
+
+        let mut response = String::new();
+        loop {
+            match read(&mut self.stream, usize::MAX).await? {
+                (response_id, _, body) if response_id == id => response.push_str(&body),
+                (response_id, _, _) if response_id == end => return Ok(response),
+                (response_id, _, _) => {
+                    return Err(anyhow!("Unexpected RCON response ID {}", response_id))
+                }
+            }
+        }
+    }
+
+    async fn write(&mut self, kind: i32, body: &str) -> anyhow::Result<i32> {
+        let id = self.next;
+        self.next = self.next.wrapping_add(1) & i32::MAX;
+        write(&mut self.stream, id, kind, body).await?;
+        Ok(id)
+    }
+}
+
+/// Where the bridge sends a command's output. Only the first is sent.
+#[derive(Clone)]
+pub struct Reply(Arc<Mutex<Option<oneshot::Sender<String>>>>);
+
+impl Reply {
+    pub fn send(&self, output: String) {
+        if let Some(tx) = self.0.lock().unwrap().take() {
+            tx.send(output).ok();
+        }
+    }
+}
+
+impl fmt::Debug for Reply {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str("Reply")
+    }
+}
+
+/// Accepts RCON connections as the Minecraft server would, and runs their
+/// commands through the bridge, so tools that only speak RCON can manage a
+/// server whose console mc-sync owns.
+pub struct Server {
+    listener: net::TcpListener,
+    password: Arc<str>,
+    tx: mpsc::Sender<Event>,
+    connections: Arc<Semaphore>,
+}
+
+impl Server {
+    pub async fn new(config: &config::RconServer, tx: mpsc::Sender<Event>) -> anyhow::Result<Self> {
+        let listener = net::TcpListener::bind(&config.address)
+            .await
+            .with_context(|| format!("Failed to listen for RCON on `{}`", config.address))?;
+        Ok(Server {
+            listener,
+            password: Arc::from(config.password.as_str()),
+            tx,
+            connections: Arc::new(Semaphore::new(CONNECTION_LIMIT)),
+        })
+    }
+
+    pub async fn start(self) -> anyhow::Result<()> {
+        loop {
+            let permit = Arc::clone(&self.connections).acquire_owned().await?;
+            let (stream, peer) = self.listener.accept().await?;
+            let password = Arc::clone(&self.password);
+            let tx = self.tx.clone();
+            tokio::spawn(
+                async move {
+                    if let Err(error) = serve(stream, permit, &password, tx).await {
+                        tracing::debug!("RCON connection closed: {}", error);
+                    }
+                }
+                .instrument(tracing::info_span!("rcon", %peer)),
+            );
+        }
+    }
+}
+
+/// Serve one client until it disconnects, goes quiet, or gets the password
+/// wrong. The connection counts against the limit until `permit` is dropped.
+async fn serve(
+    stream: net::TcpStream,
+    _permit: OwnedSemaphorePermit,
+    password: &str,
+    tx: mpsc::Sender<Event>,
+) -> anyhow::Result<()> {
+    let mut stream = io::BufStream::new(stream);
+    let mut authenticated = false;
+    loop {
+        let timeout = match authenticated {
+            true => IDLE_TIMEOUT,
+            false => LOGIN_TIMEOUT,
+        };
+        let (id, kind, body) = time::timeout(timeout, read(&mut stream, REQUEST_LIMIT))
+            .await
+            .map_err(|_| anyhow!("Timed out waiting for a request"))??;
+        match kind {
+            LOGIN if api::equal(body.as_bytes(), password.as_bytes()) => {
+                authenticated = true;
+                write(&mut stream, id, AUTH_RESPONSE, "").await?;
+            }
+            // Make each guess cost a new connection.
+            LOGIN => {
+                tracing::warn!("Incorrect RCON password");
+                write(&mut stream, -1, AUTH_RESPONSE, "").await?;
+                return Err(anyhow!("Incorrect password"));
+            }
+            COMMAND if !authenticated => write(&mut stream, -1, AUTH_RESPONSE, "").await?,
+            COMMAND => {
+                let (reply, rx) = oneshot::channel();
+                let event = Event::Rcon {
+                    command: body.trim_start_matches('/').to_owned(),
+                    reply: Reply(Arc::new(Mutex::new(Some(reply)))),
+                };
+                tx.send(event)
+                    .await
+                    .map_err(|_| anyhow!("The bridge has stopped"))?;
+                let output = time::timeout(TIMEOUT, rx)
+                    .await
+                    .ok()
+                    .and_then(Result::ok)
+                    .unwrap_or_default();
+                for chunk in chunks(&output) {
+                    write(&mut stream, id, RESPONSE, chunk).await?;
+                }
+            }
+            // Clients rely on this to find the end of a split response.
+            kind => {
+                let message = format!("Unknown request {:x}", kind);
+                write(&mut stream, id, RESPONSE, &message).await?;
+            }
+        }
+    }
+}
+
+/// Split `output` into packet bodies, without splitting characters. Empty
+/// output is still sent as one packet.
+fn chunks(mut output: &str) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    while output.len() > RESPONSE_LIMIT {
+        let mut end = RESPONSE_LIMIT;
+        while !output.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (chunk, rest) = output.split_at(end);
+        chunks.push(chunk);
+        output = rest;
+    }
+    chunks.push(output);
+    chunks
+}
+
+async fn write<S: io::AsyncWrite + Unpin>(
+    stream: &mut S,
+    id: i32,
+    kind: i32,
+    body: &str,
+) -> anyhow::Result<()> {
+    let length = 4 + 4 + body.len() + 2;
+    stream.write_i32_le(length as i32).await?;
+    stream.write_i32_le(id).await?;
+    stream.write_i32_le(kind).await?;
+    stream.write_all(body.as_bytes()).await?;
+    stream.write_all(&[0, 0]).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+/// Read a packet with a body of at most `limit` bytes.
+async fn read<S: io::AsyncRead + Unpin>(
+    stream: &mut S,
+    limit: usize,
+) -> anyhow::Result<(i32, i32, String)> {
+    let length = stream.read_i32_le().await?;
+    if length < 10 || length as usize - 10 > limit {
+        return Err(anyhow!("Invalid RCON packet length {}", length));
+    }
+    let id = stream.read_i32_le().await?;
+    let kind = stream.read_i32_le().await?;
+
+    let mut body = vec![0; (length as usize).saturating_sub(8)];
+    stream.read_exact(&mut body).await?;
+    while body.last() == Some(&0) {
+        body.pop();
+    }
+
+    Ok((id, kind, String::from_utf8_lossy(&body).into_owned()))
+}
@@ -0,0 +1,75 @@
+use std::future;
+use std::time::Duration;
+
+use chrono::Local;
+use tokio::sync::mpsc;
+use tokio::time;
+
+use crate::events::Event;
+use crate::minecraft::Console;
+use crate::schedule;
+use crate::schedule::Schedule;
+
+/// Minutes before a scheduled restart to warn players.
+static WARNINGS: &[i64] = &[15, 5, 1];
+
+/// Warn players about upcoming restarts, then ask the bridge to restart the server.
+pub struct Restart {
+    schedule: Schedule,
+    console: Console,
+    tx: mpsc::Sender<Event>,
+}
+
+impl Restart {
+    pub fn new(schedule: Schedule, console: Console, tx: mpsc::Sender<Event>) -> Self {
+        Restart {
+            schedule,
+            console,
+            tx,
+        }
+    }
+
+    pub async fn start(self) -> anyhow::Result<()> {
+        while let Some(next) = self.schedule.next() {
+            for minutes in WARNINGS {
+                let warning = next - chrono::Duration::minutes(*minutes);
+                if warning < Local::now() {
+                    continue;
+                }
+
+                schedule::sleep_until(warning).await;
+                let plural = if *minutes == 1 { "" } else { "s" };
+                let say = format!("/say Server restarting in {} minute{}.", minutes, plural);
+                self.console.send(&say).await?;
+            }
+
+            schedule::sleep_until(next).await;
+            self.tx.send(Event::Restart).await?;
+        }
+
+        future::pending().await
+    }
+}
+
+/// Warn players every so often for the next `minutes`, as in `Server
+/// restarting in 5 minutes.` for `action` "restarting".
+pub async fn countdown(console: &Console, action: &str, minutes: u64) -> anyhow::Result<()> {
+    let mut warnings = WARNINGS
+        .iter()
+        .map(|minutes| *minutes as u64)
+        .filter(|warning| *warning < minutes)
+        .collect::<Vec<_>>();
+    if minutes > 0 {
+        warnings.insert(0, minutes);
+    }
+
+    for (index, remaining) in warnings.iter().enumerate() {
+        let plural = if *remaining == 1 { "" } else { "s" };
+        let say = format!("/say Server {} in {} minute{}.", action, remaining, plural);
+        console.send(&say).await?;
+        let next = warnings.get(index + 1).copied().unwrap_or(0);
+        time::sleep(Duration::from_secs((remaining - next) * 60)).await;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use std::future::Future;
+use std::time::Duration;
+
+use serenity::http::error::Error as HttpError;
+use tokio::time;
+
+/// Attempts at a Discord request before giving up.
+static ATTEMPTS: u32 = 5;
+
+/// Delay before the first retry, doubled after each failure.
+static BACKOFF: Duration = Duration::from_millis(500);
+
+/// Least wait after a rate limit, since its `Retry-After` isn't known.
+static RATE_LIMIT: Duration = Duration::from_secs(2);
+
+/// Send a Discord request, retrying rate limits, server errors, and network
+/// failures with exponential backoff.
+///
+/// Serenity sleeps for the `Retry-After` of a rate limit and sends the
+/// request again itself, so the ones that get through came without it.
+/// Serenity doesn't keep the `retry_after` in their body either, so these
+/// wait at least [`RATE_LIMIT`] instead of the usual backoff.
+pub async fn discord<T, F, R>(mut request: F) -> serenity::Result<T>
+where
+    F: FnMut() -> R,
+    R: Future<Output = serenity::Result<T>>,
+{
+    let mut delay = BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match request().await {
+            Ok(response) => return Ok(response),
+            Err(error) if attempt < ATTEMPTS && transient(&error) => {
+                let wait = if rate_limited(&error) {
+                    delay.max(RATE_LIMIT)
+                } else {
+                    delay
+                };
+                tracing::warn!(
+                    "Discord request failed (attempt {} of {}), retrying in {:?}: {}",
+                    attempt,
+                    ATTEMPTS,
+                    wait,
+                    error,
+                );
+                time::sleep(wait).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+fn rate_limited(error: &serenity::Error) -> bool {
+    match error {
+        serenity::Error::Http(error) => matches!(
+            &**error,
+            HttpError::UnsuccessfulRequest(response) if response.status_code.as_u16() == 429
+        ),
+        _ => false,
+    }
+}
+
+/// Whether the request might succeed if sent again.
+fn transient(error: &serenity::Error) -> bool {
+    match error {
+        serenity::Error::Http(error) => match &**error {
+            HttpError::UnsuccessfulRequest(response) => {
+                response.status_code.as_u16() == 429 || response.status_code.is_server_error()
+            }
+            HttpError::Request(error) => {
+                error.is_timeout() || error.is_connect() || error.is_request()
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
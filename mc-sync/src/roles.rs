@@ -0,0 +1,59 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use serenity::model::id;
+
+/// Users whose linked Minecraft account is on the `whitelist`, which holds
+/// lowercase names.
+pub fn verified(links: &BTreeMap<u64, String>, whitelist: &BTreeSet<String>) -> BTreeSet<u64> {
+    links
+        .iter()
+        .filter(|(_, player)| whitelist.contains(&player.to_lowercase()))
+        .map(|(user, _)| *user)
+        .collect()
+}
+
+/// Cached members who have `role_id`. Without the privileged members intent,
+/// this is only a subset of them.
+pub async fn holders(
+    http: &serenity::CacheAndHttp,
+    guild_id: id::GuildId,
+    role_id: id::RoleId,
+) -> BTreeSet<u64> {
+    http.cache
+        .guild_field(guild_id, |guild| {
+            guild
+                .members
+                .values()
+                .filter(|member| member.roles.contains(&role_id))
+                .map(|member| member.user.id.0)
+                .collect()
+        })
+        .await
+        .unwrap_or_default()
+}
+
+/// Give `role_id` to each user in `add`, and take it from each user in `remove`.
+pub async fn update(
+    http: &serenity::CacheAndHttp,
+    guild_id: id::GuildId,
+    role_id: id::RoleId,
+    add: Vec<u64>,
+    remove: Vec<u64>,
+) {
+    for user in add {
+        if let Err(error) = http.http.add_member_role(guild_id.0, user, role_id.0).await {
+            tracing::warn!("Failed to give player role to user {}: {}", user, error);
+        }
+    }
+
+    for user in remove {
+        if let Err(error) = http
+            .http
+            .remove_member_role(guild_id.0, user, role_id.0)
+            .await
+        {
+            tracing::warn!("Failed to take player role from user {}: {}", user, error);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::future;
+use std::str::FromStr as _;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Context as _;
+use chrono::DateTime;
+use chrono::Local;
+use tokio::sync::mpsc;
+use tokio::time;
+
+use crate::events::Event;
+
+/// Cron expression in the host's local time zone, with an optional leading seconds field.
+#[derive(Clone, Debug)]
+pub struct Schedule {
+    expression: String,
+    schedule: cron::Schedule,
+}
+
+impl Schedule {
+    pub fn parse(expression: &str) -> anyhow::Result<Self> {
+        // The `cron` crate requires a seconds field, which most people leave out.
+        let normalized = match expression.split_whitespace().count() {
+            5 => format!("0 {}", expression),
+            _ => expression.to_owned(),
+        };
+
+        cron::Schedule::from_str(&normalized)
+            .map(|schedule| Schedule {
+                expression: expression.to_owned(),
+                schedule,
+            })
+            .with_context(|| format!("Failed to parse cron expression `{}`", expression))
+    }
+
+    /// The next scheduled time after now.
+    pub fn next(&self) -> Option<DateTime<Local>> {
+        self.schedule.upcoming(Local).next()
+    }
+}
+
+impl PartialEq for Schedule {
+    fn eq(&self, other: &Self) -> bool {
+        self.expression == other.expression
+    }
+}
+
+impl Eq for Schedule {}
+
+/// Send an event to the bridge on a schedule.
+pub struct Timer {
+    schedule: Schedule,
+    event: Event,
+    tx: mpsc::Sender<Event>,
+}
+
+impl Timer {
+    pub fn new(schedule: Schedule, event: Event, tx: mpsc::Sender<Event>) -> Self {
+        Timer {
+            schedule,
+            event,
+            tx,
+        }
+    }
+
+    pub async fn start(self) -> anyhow::Result<()> {
+        while let Some(next) = self.schedule.next() {
+            sleep_until(next).await;
+            self.tx.send(self.event.clone()).await?;
+        }
+
+        future::pending().await
+    }
+}
+
+pub async fn sleep_until(time: DateTime<Local>) {
+    if let Ok(duration) = (time - Local::now()).to_std() {
+        time::sleep(duration).await;
+    }
+}
+
+/// Limit how often something is updated, deferring the latest update instead
+/// of dropping it.
+#[derive(Debug, Default)]
+pub struct Throttle {
+    updated: Option<Instant>,
+    pending: bool,
+}
+
+impl Throttle {
+    /// Whether to update now. Otherwise, `event` is sent to the bridge once
+    /// `interval` has passed since the last update.
+    pub fn poll(&mut self, interval: Duration, event: Event, tx: &mpsc::Sender<Event>) -> bool {
+        if self.pending {
+            return false;
+        }
+
+        let elapsed = self
+            .updated
+            .map(|updated| updated.elapsed())
+            .unwrap_or(interval);
+
+        if elapsed >= interval {
+            return true;
+        }
+
+        self.pending = true;
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            time::sleep(interval - elapsed).await;
+            tx.send(event).await.unwrap_or(());
+        });
+        false
+    }
+
+    /// Whether `interval` has passed since the last update.
+    pub fn ready(&self, interval: Duration) -> bool {
+        self.updated
+            .is_none_or(|updated| updated.elapsed() >= interval)
+    }
+
+    /// Record an update, including one deferred by `poll`.
+    pub fn updated(&mut self) {
+        self.updated = Some(Instant::now());
+        self.pending = false;
+    }
+}
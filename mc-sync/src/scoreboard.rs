@@ -0,0 +1,89 @@
+use std::path::Path;
+
+use crate::nbt;
+use crate::nbt::Tag;
+
+/// Scores for one objective in `<world>/data/scoreboard.dat`, as of the
+/// server's last save.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Objective {
+    pub name: String,
+    /// As shown in game, without formatting.
+    pub display_name: String,
+    /// Highest first. Fake players, whose names start with `#`, are left
+    /// out, like in game.
+    pub scores: Vec<(String, i64)>,
+}
+
+/// Read `objective` from `world`, or `None` if there's no such objective.
+pub fn read(world: &Path, objective: &str) -> anyhow::Result<Option<Objective>> {
+    let path = world.join("data").join("scoreboard.dat");
+    let root = nbt::read(&path)?;
+    let data = match root.get("data") {
+        None => return Ok(None),
+        Some(data) => data,
+    };
+
+    let list = |key: &str| data.get(key).and_then(Tag::as_list).unwrap_or_default();
+    let display_name = list("Objectives").iter().find_map(|candidate| {
+        if candidate.get("Name")?.as_str()? != objective {
+            return None;
+        }
+        let display_name = candidate.get("DisplayName").and_then(Tag::as_str);
+        Some(
+            display_name
+                .map(plain)
+                .unwrap_or_else(|| objective.to_owned()),
+        )
+    });
+    let display_name = match display_name {
+        None => return Ok(None),
+        Some(display_name) => display_name,
+    };
+
+    let mut scores = list("PlayerScores")
+        .iter()
+        .filter(|score| score.get("Objective").and_then(Tag::as_str) == Some(objective))
+        .filter_map(|score| {
+            let name = score.get("Name")?.as_str()?;
+            Some((name.to_owned(), score.get("Score")?.as_i64()?))
+        })
+        .filter(|(name, _)| !name.starts_with('#'))
+        .collect::<Vec<_>>();
+    scores.sort_by(|(a, a_score), (b, b_score)| b_score.cmp(a_score).then_with(|| a.cmp(b)));
+
+    Ok(Some(Objective {
+        name: objective.to_owned(),
+        display_name,
+        scores,
+    }))
+}
+
+/// Text of a JSON text component, e.g. `{"text":"Kills","color":"red"}`,
+/// or the component as-is if it isn't JSON.
+fn plain(component: &str) -> String {
+    fn text(value: &serde_json::Value, plain: &mut String) {
+        match value {
+            serde_json::Value::String(string) => plain.push_str(string),
+            serde_json::Value::Array(values) => values.iter().for_each(|value| text(value, plain)),
+            serde_json::Value::Object(object) => {
+                if let Some(value) = object.get("text") {
+                    text(value, plain);
+                }
+                if let Some(value) = object.get("extra") {
+                    text(value, plain);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    match serde_json::from_str::<serde_json::Value>(component) {
+        Err(_) => component.to_owned(),
+        Ok(value) => {
+            let mut plain = String::new();
+            text(&value, &mut plain);
+            plain
+        }
+    }
+}
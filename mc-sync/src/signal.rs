@@ -0,0 +1,70 @@
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+
+use tokio::net;
+use tokio::signal::unix as signal;
+use tokio::sync::mpsc;
+
+use crate::events::Event;
+
+/// Reload the config file on SIGHUP.
+pub struct Reload {
+    hangup: signal::Signal,
+    tx: mpsc::Sender<Event>,
+}
+
+impl Reload {
+    pub fn new(tx: mpsc::Sender<Event>) -> anyhow::Result<Self> {
+        let hangup = signal::signal(signal::SignalKind::hangup())?;
+        Ok(Reload { hangup, tx })
+    }
+
+    pub async fn start(mut self) -> anyhow::Result<()> {
+        while let Some(()) = self.hangup.recv().await {
+            self.tx.send(Event::Reload).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Stop the server on SIGTERM or SIGINT.
+pub struct Terminate {
+    terminate: signal::Signal,
+    interrupt: signal::Signal,
+}
+
+impl Terminate {
+    pub fn new() -> anyhow::Result<Self> {
+        let terminate = signal::signal(signal::SignalKind::terminate())?;
+        let interrupt = signal::signal(signal::SignalKind::interrupt())?;
+        Ok(Terminate {
+            terminate,
+            interrupt,
+        })
+    }
+
+    pub async fn start(mut self) -> anyhow::Result<()> {
+        tokio::select! {
+            _ = self.terminate.recv() => (),
+            _ = self.interrupt.recv() => (),
+        }
+        Ok(())
+    }
+}
+
+/// Stop the server when anything connects to the shutdown port.
+pub struct Shutdown(net::TcpListener);
+
+impl Shutdown {
+    pub async fn new(port: u16) -> anyhow::Result<Self> {
+        net::TcpListener::bind((IpAddr::V4(Ipv4Addr::UNSPECIFIED), port))
+            .await
+            .map(Self)
+            .map_err(anyhow::Error::from)
+    }
+
+    pub async fn start(self) -> anyhow::Result<()> {
+        let (_, _) = self.0.accept().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+use std::time::Duration;
+
+use hmac::Mac as _;
+use hmac::NewMac as _;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio::time;
+use tracing::Instrument as _;
+
+use crate::advancements;
+use crate::config;
+use crate::events::Log;
+
+/// Attempts at delivering each event before giving up.
+static ATTEMPTS: u32 = 5;
+
+/// Delay before the first retry, doubled after each failure.
+static BACKOFF: Duration = Duration::from_secs(1);
+
+/// Events to hold for an endpoint that's slow or down, before dropping more.
+static QUEUE: usize = 100;
+
+/// Header with the kind of event, e.g. `join`.
+static EVENT_HEADER: &str = "X-MC-Sync-Event";
+
+/// Header with the HMAC-SHA256 of the body, keyed by the endpoint's secret.
+static SIGNATURE_HEADER: &str = "X-MC-Sync-Signature";
+
+/// Kinds of event sent to HTTP endpoints and MQTT.
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Join,
+    Quit,
+    Chat,
+    Death,
+    Advancement,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 5] = [
+        Kind::Join,
+        Kind::Quit,
+        Kind::Chat,
+        Kind::Death,
+        Kind::Advancement,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Kind::Join => "join",
+            Kind::Quit => "quit",
+            Kind::Chat => "chat",
+            Kind::Death => "death",
+            Kind::Advancement => "advancement",
+        }
+    }
+}
+
+/// A server event, as sent to HTTP endpoints and MQTT.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum Payload {
+    Join {
+        player: String,
+    },
+    Quit {
+        player: String,
+    },
+    Chat {
+        player: String,
+        message: String,
+    },
+    Death {
+        player: String,
+        message: String,
+    },
+    Advancement {
+        player: String,
+        advancement: String,
+        frame: advancements::Frame,
+    },
+}
+
+impl Payload {
+    pub fn new(log: &Log) -> Option<Self> {
+        let payload = match log.clone() {
+            Log::Join { player } => Payload::Join { player },
+            Log::Quit { player } => Payload::Quit { player },
+            Log::Message { player, message } => Payload::Chat { player, message },
+            Log::Death { player, message } => Payload::Death { player, message },
+            Log::Achievement {
+                player,
+                achievement,
+                frame,
+            } => Payload::Advancement {
+                player,
+                advancement: achievement,
+                frame,
+            },
+            _ => return None,
+        };
+        Some(payload)
+    }
+
+    pub fn kind(&self) -> Kind {
+        match self {
+            Payload::Join { .. } => Kind::Join,
+            Payload::Quit { .. } => Kind::Quit,
+            Payload::Chat { .. } => Kind::Chat,
+            Payload::Death { .. } => Kind::Death,
+            Payload::Advancement { .. } => Kind::Advancement,
+        }
+    }
+
+    /// Encode as JSON, with the name of the server and the current time.
+    pub fn to_json(&self, server: Option<&str>) -> serde_json::Result<Vec<u8>> {
+        serde_json::to_vec(&Body {
+            server,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            payload: self,
+        })
+    }
+}
+
+/// The JSON body of each request.
+#[derive(Serialize)]
+struct Body<'a> {
+    /// Name of the server, when bridging more than one.
+    server: Option<&'a str>,
+    /// When the event was logged, in RFC 3339.
+    timestamp: String,
+    #[serde(flatten)]
+    payload: &'a Payload,
+}
+
+/// An HTTP endpoint that each server event is POSTed to as JSON. Events are
+/// queued and delivered in order, in the background.
+pub struct Sink {
+    config: config::Sink,
+    server: Option<String>,
+    tx: mpsc::Sender<(Kind, Vec<u8>)>,
+}
+
+impl Sink {
+    /// Start delivering to the endpoint, until the sink is dropped.
+    pub fn new(config: config::Sink, server: Option<String>) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()?;
+        let (tx, rx) = mpsc::channel(QUEUE);
+        tokio::spawn(deliver(client, config.clone(), rx).in_current_span());
+        Ok(Sink { config, server, tx })
+    }
+
+    pub fn config(&self) -> &config::Sink {
+        &self.config
+    }
+
+    /// Queue `payload` for delivery, if the endpoint wants it.
+    pub fn send(&self, payload: &Payload) {
+        let kind = payload.kind();
+        if !self.config.events.is_empty() && !self.config.events.contains(&kind) {
+            return;
+        }
+
+        let body = match payload.to_json(self.server.as_deref()) {
+            Ok(body) => body,
+            Err(error) => {
+                tracing::error!("Failed to serialize event: {}", error);
+                return;
+            }
+        };
+
+        if self.tx.try_send((kind, body)).is_err() {
+            tracing::warn!(
+                "Dropping {} event for `{}`, which is falling behind",
+                kind.as_str(),
+                self.config.url,
+            );
+        }
+    }
+}
+
+async fn deliver(
+    client: reqwest::Client,
+    config: config::Sink,
+    mut rx: mpsc::Receiver<(Kind, Vec<u8>)>,
+) {
+    while let Some((kind, body)) = rx.recv().await {
+        let mut delay = BACKOFF;
+        let mut attempt = 1;
+        loop {
+            match post(&client, &config, kind, &body).await {
+                Ok(()) => break,
+                Err((error, true)) if attempt < ATTEMPTS => {
+                    tracing::warn!(
+                        "Failed to send {} event to `{}` (attempt {} of {}), retrying in {:?}: {}",
+                        kind.as_str(),
+                        config.url,
+                        attempt,
+                        ATTEMPTS,
+                        delay,
+                        error,
+                    );
+                    time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err((error, _)) => {
+                    tracing::error!(
+                        "Failed to send {} event to `{}`: {}",
+                        kind.as_str(),
+                        config.url,
+                        error,
+                    );
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Send one event, or fail with whether it's worth trying again.
+async fn post(
+    client: &reqwest::Client,
+    config: &config::Sink,
+    kind: Kind,
+    body: &[u8],
+) -> Result<(), (reqwest::Error, bool)> {
+    let mut request = client
+        .post(&config.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(EVENT_HEADER, kind.as_str());
+
+    if let Some(secret) = &config.secret {
+        let mut mac = hmac::Hmac::<sha2::Sha256>::new_varkey(secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(body);
+        let signature = hex::encode(mac.finalize().into_bytes());
+        request = request.header(SIGNATURE_HEADER, format!("sha256={}", signature));
+    }
+
+    request
+        .body(body.to_vec())
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map(drop)
+        .map_err(|error| {
+            let transient = match error.status() {
+                Some(status) => status.as_u16() == 429 || status.is_server_error(),
+                None => error.is_timeout() || error.is_connect() || error.is_request(),
+            };
+            (error, transient)
+        })
+}
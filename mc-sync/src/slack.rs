@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_tungstenite::tungstenite::Message;
+use futures::future::BoxFuture;
+use futures::future::FutureExt as _;
+use futures::sink::SinkExt as _;
+use futures::stream::StreamExt as _;
+use serde_json::json;
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tokio::time;
+
+use crate::chat::ChatBridge;
+use crate::config;
+use crate::events::Event;
+use crate::markdown;
+
+static API: &str = "https://slack.com/api";
+
+/// Delay before reconnecting after Slack can't be reached, or drops the
+/// connection without asking to reconnect.
+static RETRY: Duration = Duration::from_secs(10);
+
+/// Relay to a Slack channel as a bot, receiving messages over Socket Mode so
+/// no public URL is needed.
+pub struct Slack {
+    client: reqwest::Client,
+    bot_token: String,
+    app_token: String,
+    channel: String,
+    /// Display names of users seen so far, by ID.
+    names: Mutex<HashMap<String, String>>,
+}
+
+impl Slack {
+    pub fn new(config: &config::Slack) -> anyhow::Result<Self> {
+        Ok(Slack {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(30))
+                .build()?,
+            bot_token: config.bot_token.clone(),
+            app_token: config.app_token.clone(),
+            channel: config.channel.clone(),
+            names: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Call a Web API method, which reports errors in the body rather than the
+    /// status code.
+    async fn call(&self, method: &str, token: &str, body: Value) -> anyhow::Result<Value> {
+        let response = self
+            .client
+            .post(format!("{}/{}", API, method))
+            .bearer_auth(token)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Value>()
+            .await?;
+        if response["ok"].as_bool() != Some(true) {
+            return Err(anyhow!(
+                "Slack `{}` failed: {}",
+                method,
+                response["error"].as_str().unwrap_or("unknown error"),
+            ));
+        }
+        Ok(response)
+    }
+
+    async fn post(&self, text: String) -> anyhow::Result<()> {
+        let body = json!({ "channel": self.channel, "text": text });
+        self.call("chat.postMessage", &self.bot_token, body).await?;
+        Ok(())
+    }
+
+    /// The name `user` goes by in Slack, or their ID if it can't be found.
+    async fn display_name(&self, user: &str) -> String {
+        if let Some(name) = self.names.lock().unwrap().get(user) {
+            return name.clone();
+        }
+
+        // `users.info` takes query parameters rather than JSON.
+        let response = self
+            .client
+            .get(format!("{}/users.info", API))
+            .bearer_auth(&self.bot_token)
+            .query(&[("user", user)])
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        let info = match response {
+            Ok(response) => response.json::<Value>().await.ok(),
+            Err(error) => {
+                tracing::warn!("Failed to look up Slack user `{}`: {}", user, error);
+                None
+            }
+        };
+
+        let name = info.and_then(|info| {
+            let profile = &info["user"]["profile"];
+            [&profile["display_name"], &profile["real_name"]]
+                .iter()
+                .filter_map(|name| name.as_str())
+                .find(|name| !name.is_empty())
+                .map(String::from)
+        });
+        match name {
+            Some(name) => {
+                self.names
+                    .lock()
+                    .unwrap()
+                    .insert(user.to_owned(), name.clone());
+                name
+            }
+            None => user.to_owned(),
+        }
+    }
+
+    /// Forward messages from the channel, reconnecting whenever Slack drops
+    /// the connection. Messages from bots, including this one, are skipped.
+    async fn socket(&self, tx: mpsc::Sender<Event>) -> anyhow::Result<()> {
+        loop {
+            match self.connect(&tx).await {
+                Ok(()) => tracing::info!("Reconnecting to Slack"),
+                Err(error) => {
+                    tracing::warn!("Lost connection to Slack: {:?}", error);
+                    time::sleep(RETRY).await;
+                }
+            }
+        }
+    }
+
+    /// Receive events over one Socket Mode connection, until Slack asks for
+    /// a new one.
+    async fn connect(&self, tx: &mpsc::Sender<Event>) -> anyhow::Result<()> {
+        let open = self
+            .call("apps.connections.open", &self.app_token, json!({}))
+            .await?;
+        let url = open["url"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Slack didn't return a Socket Mode URL"))?;
+        let (mut socket, _) = async_tungstenite::tokio::connect_async(url).await?;
+        tracing::info!("Connected to Slack");
+
+        while let Some(message) = socket.next().await {
+            let envelope = match message? {
+                Message::Text(text) => serde_json::from_str::<Value>(&text)?,
+                Message::Close(_) => return Ok(()),
+                _ => continue,
+            };
+
+            // Events are redelivered unless acknowledged.
+            if let Some(id) = envelope["envelope_id"].as_str() {
+                let ack = json!({ "envelope_id": id }).to_string();
+                socket.send(Message::Text(ack)).await?;
+            }
+
+            match envelope["type"].as_str() {
+                Some("disconnect") => return Ok(()),
+                Some("events_api") => (),
+                _ => continue,
+            }
+
+            let event = &envelope["payload"]["event"];
+            if event["type"].as_str() != Some("message")
+                || event["channel"].as_str() != Some(&self.channel)
+                || event.get("subtype").is_some()
+                || event.get("bot_id").is_some()
+            {
+                continue;
+            }
+
+            let (user, text) = match (event["user"].as_str(), event["text"].as_str()) {
+                (Some(user), Some(text)) if !text.is_empty() => (user, unescape(text)),
+                _ => continue,
+            };
+            let event = Event::Chat {
+                platform: self.name().to_owned(),
+                user: self.display_name(user).await,
+                message: text,
+            };
+            tx.send(event).await?;
+        }
+
+        Err(anyhow!("Slack closed the connection"))
+    }
+}
+
+/// Slack treats `&`, `<`, and `>` as control characters in message text.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+impl ChatBridge for Slack {
+    fn name(&self) -> &str {
+        "Slack"
+    }
+
+    fn send_message<'a>(
+        &'a self,
+        player: &'a str,
+        message: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            self.post(format!("*{}*: {}", escape(player), escape(message)))
+                .await
+        }
+        .boxed()
+    }
+
+    fn send_event<'a>(&'a self, text: &'a str) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move { self.post(escape(&markdown::unescape(text))).await }.boxed()
+    }
+
+    fn receive(&self, tx: mpsc::Sender<Event>) -> BoxFuture<'_, anyhow::Result<()>> {
+        self.socket(tx).boxed()
+    }
+}
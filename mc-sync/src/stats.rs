@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Context as _;
+use serde::Deserialize;
+
+/// Ticks per second, which `play_time` is counted in.
+static TICKS: u64 = 20;
+
+/// What the server counts for each player, in `<world>/stats/<uuid>.json`,
+/// as of the last time it saved.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Stats {
+    pub play_time: Duration,
+    pub blocks_mined: u64,
+    /// The block mined most, and how many times.
+    pub most_mined: Option<(String, u64)>,
+    pub mob_kills: u64,
+    /// The mob killed most, and how many times.
+    pub most_killed: Option<(String, u64)>,
+    pub player_kills: u64,
+    pub deaths: u64,
+    /// Centimeters traveled in every way, including by boat, horse, and elytra.
+    pub distance: u64,
+    pub jumps: u64,
+    pub items_crafted: u64,
+}
+
+/// Counters by category, e.g. `minecraft:mined`, and then by what was
+/// counted, e.g. `minecraft:stone`.
+#[derive(Deserialize)]
+struct File {
+    stats: HashMap<String, HashMap<String, u64>>,
+}
+
+/// Read the stats of the player with `uuid` from `world`, or `None` if they
+/// haven't played in it.
+pub fn read(world: &Path, uuid: &str) -> anyhow::Result<Option<Stats>> {
+    let path = world.join("stats").join(format!("{}.json", uuid));
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => {
+            return Err(error).with_context(|| format!("Failed to read stats `{}`", path.display()))
+        }
+    };
+    // Servers before 1.13 use a flat format, which isn't supported.
+    let file = serde_json::from_str::<File>(&text)
+        .with_context(|| format!("Failed to parse stats `{}`", path.display()))?;
+
+    let empty = HashMap::new();
+    let category = |name: &str| {
+        file.stats
+            .get(&format!("minecraft:{}", name))
+            .unwrap_or(&empty)
+    };
+    let custom = category("custom");
+    let custom = |name: &str| custom.get(&format!("minecraft:{}", name)).copied();
+    let most = |counts: &HashMap<String, u64>| {
+        counts
+            .iter()
+            .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then(b.cmp(a)))
+            .map(|(id, count)| (name(id), *count))
+    };
+
+    Ok(Some(Stats {
+        // Renamed from `play_one_minute` in 1.17, though it was always ticks.
+        play_time: Duration::from_secs(
+            custom("play_time")
+                .or_else(|| custom("play_one_minute"))
+                .unwrap_or(0)
+                / TICKS,
+        ),
+        blocks_mined: category("mined").values().sum(),
+        most_mined: most(category("mined")),
+        mob_kills: custom("mob_kills").unwrap_or(0),
+        most_killed: most(category("killed")),
+        player_kills: custom("player_kills").unwrap_or(0),
+        deaths: custom("deaths").unwrap_or(0),
+        distance: category("custom")
+            .iter()
+            .filter(|(id, _)| id.ends_with("_one_cm"))
+            .map(|(_, count)| count)
+            .sum(),
+        jumps: custom("jump").unwrap_or(0),
+        items_crafted: category("crafted").values().sum(),
+    }))
+}
+
+/// Readable name of a block, item, or mob, e.g. `diamond ore` for
+/// `minecraft:diamond_ore`.
+pub fn name(id: &str) -> String {
+    let id = id.strip_prefix("minecraft:").unwrap_or(id);
+    id.replace('_', " ")
+}
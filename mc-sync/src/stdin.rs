@@ -0,0 +1,243 @@
+use std::collections::BTreeSet;
+use std::io::IsTerminal as _;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+
+use anyhow::Context as _;
+use nix::sys::termios;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::FileHistory;
+use rustyline::validate::Validator;
+use rustyline::CompletionType;
+use rustyline::Editor;
+use rustyline::ExternalPrinter;
+use tokio::io;
+use tokio::io::AsyncBufReadExt as _;
+use tokio::sync::mpsc;
+
+use crate::events::Event;
+
+/// Commands typed into the terminal, kept across runs.
+static HISTORY: &str = "mc-sync-history.txt";
+
+static PROMPT: &str = "> ";
+
+/// Server commands to complete, as the server itself would.
+static COMMANDS: &[&str] = &[
+    "advancement",
+    "ban",
+    "ban-ip",
+    "banlist",
+    "clear",
+    "deop",
+    "difficulty",
+    "effect",
+    "enchant",
+    "execute",
+    "experience",
+    "gamemode",
+    "gamerule",
+    "give",
+    "help",
+    "kick",
+    "kill",
+    "list",
+    "locate",
+    "msg",
+    "op",
+    "pardon",
+    "pardon-ip",
+    "reload",
+    "save-all",
+    "save-off",
+    "save-on",
+    "say",
+    "seed",
+    "setworldspawn",
+    "spawnpoint",
+    "stop",
+    "summon",
+    "teleport",
+    "tell",
+    "tellraw",
+    "time",
+    "title",
+    "tp",
+    "weather",
+    "whitelist",
+    "worldborder",
+];
+
+/// Players online on the primary server, kept up to date by its bridge.
+pub type Players = Arc<Mutex<BTreeSet<String>>>;
+
+/// Prints server output above the prompt while a command is being typed.
+pub type Printer = Box<dyn ExternalPrinter + Send>;
+
+/// Read commands typed into the terminal. When it's interactive, they can be
+/// edited like in a shell, with history and Ctrl-R search; otherwise, stdin is
+/// read line by line.
+pub struct Stdin {
+    editor: Option<Editor<Completions, FileHistory>>,
+    mode: Option<termios::Termios>,
+    players: Players,
+    tx: mpsc::Sender<Event>,
+}
+
+impl Stdin {
+    pub fn new(tx: mpsc::Sender<Event>) -> anyhow::Result<Self> {
+        let players = Players::default();
+        if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+            return Ok(Stdin {
+                editor: None,
+                mode: None,
+                players,
+                tx,
+            });
+        }
+
+        // Keep Ctrl-C and Ctrl-Z working as they do without line editing.
+        let config = rustyline::Config::builder()
+            .enable_signals(true)
+            .auto_add_history(true)
+            .completion_type(CompletionType::List)
+            .build();
+        let mut editor = Editor::with_config(config)
+            .context("Failed to set up line editing for the terminal")?;
+        editor.set_helper(Some(Completions(Arc::clone(&players))));
+        if let Err(error) = editor.load_history(HISTORY) {
+            tracing::debug!("Starting a new history: {}", error);
+        }
+
+        Ok(Stdin {
+            editor: Some(editor),
+            mode: termios::tcgetattr(std::io::stdin()).ok(),
+            players,
+            tx,
+        })
+    }
+
+    /// Online players to complete, if the terminal is interactive.
+    pub fn players(&self) -> Option<Players> {
+        self.editor.as_ref().map(|_| Arc::clone(&self.players))
+    }
+
+    /// Where to print server output, if the terminal is interactive.
+    pub fn printer(&mut self) -> anyhow::Result<Option<Printer>> {
+        match &mut self.editor {
+            None => Ok(None),
+            Some(editor) => {
+                let printer = editor
+                    .create_external_printer()
+                    .context("Failed to print above the prompt")?;
+                Ok(Some(Box::new(printer)))
+            }
+        }
+    }
+
+    /// Restore the terminal when dropped, since the process may exit while a
+    /// command is being typed.
+    pub fn restore(&mut self) -> Option<Restore> {
+        self.mode.take().map(Restore)
+    }
+
+    pub async fn start(self) -> anyhow::Result<()> {
+        let mut editor = match self.editor {
+            Some(editor) => editor,
+            None => {
+                let mut lines = io::BufReader::new(io::stdin()).lines();
+                while let Some(line) = lines.next_line().await? {
+                    self.tx.send(Event::Stdin(line)).await?;
+                }
+                return Ok(());
+            }
+        };
+
+        // Editing blocks, so it gets its own thread.
+        let (line_tx, mut line_rx) = mpsc::unbounded_channel();
+        thread::spawn(move || loop {
+            match editor.readline(PROMPT) {
+                Ok(line) => {
+                    if let Err(error) = editor.save_history(HISTORY) {
+                        tracing::warn!("Failed to save history to `{}`: {}", HISTORY, error);
+                    }
+                    if line_tx.send(line).is_err() {
+                        return;
+                    }
+                }
+                // Ctrl-D, like closing stdin.
+                Err(ReadlineError::Eof) | Err(ReadlineError::Interrupted) => return,
+                Err(error) => {
+                    tracing::error!("Failed to read from the terminal: {}", error);
+                    return;
+                }
+            }
+        });
+
+        while let Some(line) = line_rx.recv().await {
+            self.tx.send(Event::Stdin(line)).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Completes the command, and then the names of online players.
+struct Completions(Players);
+
+impl Completer for Completions {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        position: usize,
+        _: &rustyline::Context,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let line = &line[..position];
+        let start = line.rfind(' ').map_or(0, |space| space + 1);
+        let word = &line[start..];
+
+        let candidates = if start == 0 {
+            let slash = if word.starts_with('/') { "/" } else { "" };
+            let word = word.trim_start_matches('/');
+            COMMANDS
+                .iter()
+                .filter(|command| command.starts_with(word))
+                .map(|command| format!("{}{} ", slash, command))
+                .collect()
+        } else {
+            let word = word.to_lowercase();
+            self.0
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|player| player.to_lowercase().starts_with(&word))
+                .map(|player| format!("{} ", player))
+                .collect()
+        };
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for Completions {
+    type Hint = String;
+}
+
+impl Highlighter for Completions {}
+
+impl Validator for Completions {}
+
+impl rustyline::Helper for Completions {}
+
+/// The terminal's mode from before line editing.
+pub struct Restore(termios::Termios);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        termios::tcsetattr(std::io::stdin(), termios::SetArg::TCSADRAIN, &self.0).ok();
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// State that should survive restarts.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Data {
+    /// Total seconds played, by player name.
+    pub playtime: BTreeMap<String, u64>,
+
+    /// Players on the server, as of the last join, quit, or `list`.
+    pub online: BTreeSet<String>,
+
+    /// Minecraft player linked to each Discord user, by user ID.
+    pub links: BTreeMap<u64, String>,
+
+    /// Number of deaths, by player name.
+    pub deaths: BTreeMap<String, u64>,
+
+    /// Today's thread for mirrored output, by parent channel ID.
+    pub threads: BTreeMap<u64, Thread>,
+
+    /// The control panel message, once posted.
+    pub panel: Option<Panel>,
+
+    /// Places players have saved with `!setwaypoint`, by lowercase name.
+    pub waypoints: BTreeMap<String, Waypoint>,
+}
+
+/// Thread that server output is mirrored to for one day.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Thread {
+    pub id: u64,
+    /// Local date, as `YYYY-MM-DD`.
+    pub date: String,
+}
+
+/// Named place in the world, shared by a player.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Waypoint {
+    /// As the player typed it.
+    pub name: String,
+    /// Who saved it, and may replace or delete it.
+    pub player: String,
+    /// Block coordinates.
+    pub position: [i64; 3],
+    /// e.g. `overworld`.
+    pub dimension: String,
+}
+
+/// Pinned message with buttons for common admin actions.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Panel {
+    pub channel_id: u64,
+    pub message_id: u64,
+}
+
+impl Data {
+    /// The Discord user linked to `player`, if any.
+    pub fn linked_user(&self, player: &str) -> Option<u64> {
+        self.links
+            .iter()
+            .find(|(_, linked)| linked.eq_ignore_ascii_case(player))
+            .map(|(user, _)| *user)
+    }
+
+    /// Every player seen online, killed, or linked, in any case.
+    pub fn known_players(&self) -> BTreeSet<&str> {
+        self.online
+            .iter()
+            .chain(self.playtime.keys())
+            .chain(self.deaths.keys())
+            .chain(self.links.values())
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Players who have died the most, in descending order.
+    pub fn most_deaths(&self, count: usize) -> Vec<(&str, u64)> {
+        let mut deaths = self
+            .deaths
+            .iter()
+            .map(|(player, deaths)| (player.as_str(), *deaths))
+            .collect::<Vec<_>>();
+        deaths.sort_by(|(a, a_deaths), (b, b_deaths)| b_deaths.cmp(a_deaths).then(a.cmp(b)));
+        deaths.truncate(count);
+        deaths
+    }
+}
+
+/// JSON file holding the bot's persistent state.
+#[derive(Debug)]
+pub struct Store {
+    path: PathBuf,
+    pub data: Data,
+}
+
+impl Store {
+    /// Read the store at `path`, or start empty if it doesn't exist yet.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let data = match fs::read_to_string(path) {
+            Ok(data) => serde_json::from_str(&data)
+                .with_context(|| format!("Failed to parse data file `{}`", path.display()))?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Data::default(),
+            Err(error) => {
+                return Err(error)
+                    .with_context(|| format!("Failed to read data file `{}`", path.display()))
+            }
+        };
+
+        Ok(Store {
+            path: path.to_owned(),
+            data,
+        })
+    }
+
+    /// Write the store to disk, replacing the previous file atomically.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let partial = self.path.with_extension("partial");
+        let data = serde_json::to_string_pretty(&self.data)?;
+        fs::write(&partial, data)
+            .and_then(|()| fs::rename(&partial, &self.path))
+            .with_context(|| format!("Failed to write data file `{}`", self.path.display()))
+    }
+}
@@ -0,0 +1,94 @@
+use std::env;
+#[cfg(target_os = "linux")]
+use std::os::linux::net::SocketAddrExt as _;
+use std::os::unix::net::UnixDatagram;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::time;
+
+use crate::metrics::Registry;
+
+/// How often to check whether every server has started.
+static POLL: Duration = Duration::from_secs(1);
+
+/// Report to systemd when running as a `Type=notify` service: ready once every
+/// server has started, and alive while no bridge is stalled.
+pub struct Notify {
+    socket: UnixDatagram,
+    path: String,
+    watchdog: Option<Duration>,
+    registry: Arc<Registry>,
+}
+
+impl Notify {
+    /// Returns `None` unless systemd is listening.
+    pub fn new(registry: Arc<Registry>) -> anyhow::Result<Option<Self>> {
+        let path = match env::var("NOTIFY_SOCKET") {
+            Ok(path) => path,
+            Err(_) => return Ok(None),
+        };
+
+        // Keepalives are due every `WATCHDOG_USEC`, so send them twice as often.
+        let watchdog = env::var("WATCHDOG_USEC")
+            .ok()
+            .and_then(|usec| usec.parse::<u64>().ok())
+            .map(|usec| Duration::from_micros(usec) / 2);
+
+        Ok(Some(Notify {
+            socket: UnixDatagram::unbound()?,
+            path,
+            watchdog,
+            registry,
+        }))
+    }
+
+    pub async fn start(self) -> anyhow::Result<()> {
+        let period = self.watchdog.map_or(POLL, |watchdog| watchdog.min(POLL));
+        let mut interval = time::interval(period);
+        let mut ready = false;
+        loop {
+            interval.tick().await;
+            if !ready && self.registry.started() {
+                ready = true;
+                self.send("READY=1\nSTATUS=Relaying chat")?;
+            }
+            if self.watchdog.is_some() && !self.registry.stalled() {
+                self.send("WATCHDOG=1")?;
+            }
+        }
+    }
+
+    fn send(&self, state: &str) -> anyhow::Result<()> {
+        send(&self.socket, &self.path, state)
+    }
+}
+
+/// Tell systemd the bot is shutting down, if it's listening.
+pub fn stopping() {
+    let path = match env::var("NOTIFY_SOCKET") {
+        Ok(path) => path,
+        Err(_) => return,
+    };
+    let sent = UnixDatagram::unbound()
+        .map_err(anyhow::Error::from)
+        .and_then(|socket| send(&socket, &path, "STOPPING=1"));
+    if let Err(error) = sent {
+        tracing::warn!("Failed to notify systemd: {:?}", error);
+    }
+}
+
+fn send(socket: &UnixDatagram, path: &str, state: &str) -> anyhow::Result<()> {
+    // Paths starting with `@` name sockets in the abstract namespace.
+    match path.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            let address = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            socket.send_to_addr(state.as_bytes(), &address)?;
+        }
+        _ => {
+            socket.send_to(state.as_bytes(), path)?;
+        }
+    }
+    Ok(())
+}
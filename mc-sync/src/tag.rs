@@ -0,0 +1,45 @@
+use anyhow::anyhow;
+use chrono::format::Item;
+use chrono::format::StrftimeItems;
+use chrono::Local;
+
+/// Where a line in the terminal, console channel, or journal came from.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Source {
+    /// The server's output.
+    Minecraft,
+    /// Chat relayed from Discord to the server.
+    Discord,
+    /// The bot's own announcements, like the server starting.
+    System,
+    /// What the bot posted to Discord, which is only journaled.
+    Relay,
+}
+
+impl Source {
+    pub fn tag(self) -> &'static str {
+        match self {
+            Source::Minecraft => "[MC]",
+            Source::Discord => "[DISCORD]",
+            Source::System => "[SYS]",
+            Source::Relay => "[RELAY]",
+        }
+    }
+}
+
+/// Check a `strftime` format for timestamps, since formatting with an invalid
+/// one panics.
+pub fn load(format: String) -> anyhow::Result<String> {
+    if StrftimeItems::new(&format).any(|item| item == Item::Error) {
+        return Err(anyhow!(
+            "Invalid timestamp format `{}` in `mirror-tags`",
+            format
+        ));
+    }
+    Ok(format)
+}
+
+/// Prefix `line` with the current time, in `format`, and its source.
+pub fn tag(format: &str, source: Source, line: &str) -> String {
+    format!("{} {} {}", Local::now().format(format), source.tag(), line)
+}
@@ -0,0 +1,111 @@
+use std::fs;
+use std::io;
+use std::io::BufRead as _;
+use std::io::Seek as _;
+use std::os::unix::fs::MetadataExt as _;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+use tokio::time;
+
+use crate::events::Event;
+
+/// How often to check the log for new lines.
+static POLL: Duration = Duration::from_millis(250);
+
+/// Follow the server's `logs/latest.log`, forwarding each line to the bridge
+/// as if it were the server's output. For servers the bridge didn't launch,
+/// like ones running under `screen` or a hosting panel.
+pub struct Tail {
+    path: PathBuf,
+    tx: mpsc::Sender<Event>,
+}
+
+/// The open log, and its inode, to notice when the server replaces it.
+struct Log {
+    reader: io::BufReader<fs::File>,
+    inode: u64,
+}
+
+impl Tail {
+    pub fn new(directory: &Path, tx: mpsc::Sender<Event>) -> Self {
+        Tail {
+            path: directory.join("logs").join("latest.log"),
+            tx,
+        }
+    }
+
+    pub async fn start(self) -> anyhow::Result<()> {
+        // Skip whatever was logged before the bridge started.
+        let mut log = self.open(io::SeekFrom::End(0));
+        let mut line = Vec::new();
+
+        loop {
+            if let Some(open) = &mut log {
+                if let Err(error) = self.forward(open, &mut line).await {
+                    tracing::warn!("Failed to read server log: {}", error);
+                    log = None;
+                }
+            }
+
+            // The server compresses the old log when it starts, and begins a
+            // new one, which is read from the start.
+            if let Ok(metadata) = fs::metadata(&self.path) {
+                let replaced = match &mut log {
+                    None => true,
+                    Some(open) => {
+                        let position = open.reader.stream_position();
+                        open.inode != metadata.ino()
+                            || matches!(position, Ok(position) if position > metadata.len())
+                    }
+                };
+                if replaced {
+                    if let Some(open) = &mut log {
+                        self.forward(open, &mut line).await.unwrap_or(());
+                        tracing::info!("Following new server log");
+                    }
+                    line.clear();
+                    log = self.open(io::SeekFrom::Start(0));
+                }
+            }
+
+            time::sleep(POLL).await;
+        }
+    }
+
+    fn open(&self, from: io::SeekFrom) -> Option<Log> {
+        let mut file = match fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(error) => {
+                tracing::debug!("Failed to open `{}`: {}", self.path.display(), error);
+                return None;
+            }
+        };
+        let inode = file.metadata().ok()?.ino();
+        file.seek(from).ok()?;
+        Some(Log {
+            reader: io::BufReader::new(file),
+            inode,
+        })
+    }
+
+    /// Send each complete line written since the last call, keeping any
+    /// partial line in `line` until the rest is written.
+    async fn forward(&self, log: &mut Log, line: &mut Vec<u8>) -> io::Result<()> {
+        while log.reader.read_until(b'\n', line)? > 0 {
+            if line.last() != Some(&b'\n') {
+                break;
+            }
+            let text = String::from_utf8_lossy(line)
+                .trim_end_matches(&['\r', '\n'][..])
+                .to_owned();
+            line.clear();
+            if self.tx.send(Event::Minecraft(text)).await.is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
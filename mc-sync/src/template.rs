@@ -0,0 +1,326 @@
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use regex::Captures;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::json;
+use serde_json::Value;
+
+use crate::markdown;
+
+static PLACEHOLDER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{([A-Za-z0-9_]+)\}").unwrap());
+
+/// Formats for relayed messages, with `{name}` placeholders.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Templates {
+    /// Posted to Discord when a player joins: `{player}`.
+    pub join: String,
+
+    /// Posted to Discord instead of `join` the first time a player joins the
+    /// server: `{player}`. Only `join` is posted if empty.
+    pub first_join: String,
+
+    /// Sent to a player in Minecraft the first time they join: `{player}`.
+    /// Nothing is sent if empty.
+    pub welcome: String,
+
+    /// Posted to Discord when a player leaves: `{player}`.
+    pub quit: String,
+
+    /// Posted to Discord when a player keeps reconnecting, per `[reconnects]`:
+    /// `{player}`. Not announced if empty.
+    pub unstable: String,
+
+    /// Posted in the status channel when a player has been AFK for long, per
+    /// `[afk]`: `{player}`, `{time}`.
+    pub afk: String,
+
+    /// Posted to Discord when a player moves to another server behind a
+    /// proxy: `{player}`, `{server}`. Moves aren't announced if empty.
+    pub switch: String,
+
+    /// Posted to Discord when a player makes an advancement: `{player}`, `{advancement}`.
+    pub advancement: String,
+
+    /// Posted to Discord when a player dies: `{player}`, `{message}`.
+    pub death: String,
+
+    /// Posted to Discord after `death` when a player reaches a multiple of
+    /// `death-milestone` deaths: `{player}`, `{count}` (e.g. `100th`).
+    pub death_milestone: String,
+
+    /// Posted to Discord when a player chats, unless relayed through a webhook:
+    /// `{player}`, `{message}`.
+    pub chat: String,
+
+    /// Broadcast in Minecraft when someone chats on Discord: `{user}`, `{player}`
+    /// (their linked Minecraft name, or `{user}` if not linked), `{message}`.
+    pub discord: String,
+
+    /// Broadcast in Minecraft before a Discord reply: `{user}` and `{message}`
+    /// (an excerpt) of the message being replied to.
+    pub reply: String,
+
+    /// Broadcast in Minecraft when a player chats on another bridged server:
+    /// `{server}`, `{player}`, `{message}`.
+    pub federated: String,
+
+    /// Broadcast in Minecraft, and posted to Discord, when someone chats on
+    /// another platform, like Matrix: `{platform}`, `{user}`, `{message}`.
+    pub platform: String,
+
+    /// Broadcast in Minecraft when someone edits a relayed Discord message,
+    /// with the same placeholders as `discord`. Edits aren't relayed if empty.
+    pub edited: String,
+
+    /// Broadcast in Minecraft when someone deletes a relayed Discord message:
+    /// `{user}`. Deletions aren't announced if empty.
+    pub deleted: String,
+
+    /// Posted to Discord when the server starts launching: `{version}`. This
+    /// and the other lifecycle announcements are skipped when empty.
+    pub starting: String,
+
+    /// Posted to Discord when the server has finished starting: `{time}`
+    /// (how long it took, e.g. `12.3s`).
+    pub online: String,
+
+    /// Posted to Discord when the server starts shutting down.
+    pub stopping: String,
+
+    /// Posted to Discord when the server has stopped without crashing.
+    pub stopped: String,
+
+    /// Posted to Discord when TPS stays below the `[lag]` threshold: `{tps}`.
+    pub lag: String,
+
+    /// Posted to Discord when TPS recovers after a `lag` alert: `{tps}`.
+    pub lag_recovered: String,
+
+    /// Posted to the error channel when the server's memory use reaches
+    /// `[resources]`'s `memory-alert`: `{percent}`, `{memory}`, `{limit}`.
+    pub memory_alert: String,
+
+    /// Posted to the error channel when the server's CPU use stays above
+    /// `[resources]`'s `cpu-alert`: `{percent}`.
+    pub cpu_alert: String,
+
+    /// Posted to Discord when `[watchdog]` restarts a server that hasn't
+    /// logged anything for too long: `{minutes}`.
+    pub hung: String,
+
+    /// Posted to Discord when `[watchdog]` restarts a server for using too
+    /// much memory: `{percent}`, `{memory}`, `{limit}`.
+    pub memory_restart: String,
+
+    /// Posted to Discord when a player gets into bed, if enabled: `{player}`.
+    pub sleeping: String,
+
+    /// Posted to Discord when the night is skipped, if enabled.
+    pub night_skipped: String,
+
+    /// Posted to Discord when the weather changes, if enabled: `{weather}`.
+    pub weather: String,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Templates {
+            join: String::from("{player} joined the server!"),
+            first_join: String::from("🎉 Welcome {player}, first time on the server!"),
+            welcome: String::new(),
+            quit: String::from("{player} left the server."),
+            unstable: String::from("⚠️ {player} is having connection issues."),
+            afk: String::from("💤 {player} has been AFK for {time}."),
+            switch: String::from("{player} moved to {server}."),
+            advancement: String::from("{player} unlocked achievement [{advancement}]!"),
+            death: String::from("{player} {message}"),
+            death_milestone: String::from("💀 {player} died for the {count} time!"),
+            chat: String::from("[{player}]: {message}"),
+            discord: String::from("[{user}]: {message}"),
+            reply: String::from("↪ replying to {user}: {message}"),
+            federated: String::from("[{server}] <{player}> {message}"),
+            platform: String::from("[{platform}] <{user}> {message}"),
+            edited: String::from("[{user}] (edited): {message}"),
+            deleted: String::new(),
+            starting: String::from("⏳ Server starting..."),
+            online: String::from("✅ Server online! (started in {time})"),
+            stopping: String::from("Server stopping..."),
+            stopped: String::from("🛑 Server stopped."),
+            lag: String::from("⚠️ The server is lagging: {tps} TPS"),
+            lag_recovered: String::from("✅ The server has recovered: {tps} TPS"),
+            memory_alert: String::from(
+                "⚠️ The server is using {percent} of its {limit} memory limit ({memory}). Consider restarting it.",
+            ),
+            cpu_alert: String::from("⚠️ The server has been using {percent} CPU."),
+            hung: String::from(
+                "⚠️ The server hasn't responded in {minutes} minutes. Restarting it...",
+            ),
+            memory_restart: String::from(
+                "⚠️ The server is using {percent} of its {limit} memory limit. Restarting it soon...",
+            ),
+            sleeping: String::from("💤 {player} went to bed."),
+            night_skipped: String::from("🌅 The night was skipped."),
+            weather: String::from("The weather is now {weather}."),
+        }
+    }
+}
+
+/// Substitute each `{name}` in `template` with its value. Unknown placeholders
+/// are left as-is, and substituted values are never expanded again.
+pub fn render(template: &str, values: &[(&str, &str)]) -> String {
+    PLACEHOLDER
+        .replace_all(template, |captures: &Captures| {
+            values
+                .iter()
+                .find(|(name, _)| *name == &captures[1])
+                .map(|(_, value)| String::from(*value))
+                .unwrap_or_else(|| String::from(&captures[0]))
+        })
+        .into_owned()
+}
+
+/// Like `render`, but build a JSON text component for `tellraw`, so each value
+/// can carry its own formatting.
+pub fn render_json(template: &str, values: &[(&str, Value)]) -> Value {
+    // Later components inherit the style of the first, so start with a blank one.
+    let mut components = vec![Value::from("")];
+    let mut last = 0;
+    for captures in PLACEHOLDER.captures_iter(template) {
+        let placeholder = captures
+            .get(0)
+            .expect("[IMPOSSIBLE]: group 0 always matches");
+        if placeholder.start() > last {
+            components.push(Value::from(&template[last..placeholder.start()]));
+        }
+        components.push(
+            values
+                .iter()
+                .find(|(name, _)| *name == &captures[1])
+                .map(|(_, value)| value.clone())
+                .unwrap_or_else(|| Value::from(placeholder.as_str())),
+        );
+        last = placeholder.end();
+    }
+    if last < template.len() {
+        components.push(Value::from(&template[last..]));
+    }
+    Value::Array(components)
+}
+
+/// Build a `tellraw` component for `text` with clickable links, shown by
+/// their file or host name if `shorten` is set.
+pub fn linkify(text: &str, shorten: bool) -> Value {
+    let mut components = vec![Value::from("")];
+    let mut last = 0;
+    for url in markdown::URL.find_iter(text) {
+        if url.start() > last {
+            components.push(Value::from(&text[last..url.start()]));
+        }
+        let label = if shorten {
+            self::shorten(url.as_str())
+        } else {
+            url.as_str().to_owned()
+        };
+        components.push(json!({
+            "text": label,
+            "underlined": true,
+            "hoverEvent": {
+                "action": "show_text",
+                "contents": url.as_str(),
+            },
+            "clickEvent": {
+                "action": "open_url",
+                "value": url.as_str(),
+            },
+        }));
+        last = url.end();
+    }
+    if last < text.len() {
+        components.push(Value::from(&text[last..]));
+    }
+    Value::Array(components)
+}
+
+/// Show `https://cdn.discordapp.com/attachments/1/2/cat.png?ex=1` as
+/// `[cat.png]`, and `https://example.com/some/page` as `[example.com]`.
+fn shorten(url: &str) -> String {
+    let url = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let path = url.split(&['?', '#'][..]).next().unwrap_or(url);
+    let host = path.split('/').next().unwrap_or(path);
+    let file = path
+        .rsplit('/')
+        .next()
+        .filter(|file| *file != host && file.contains('.'));
+    format!("[{}]", file.unwrap_or(host))
+}
+
+/// Shorten `text` to at most `length` characters, marking where it was cut.
+pub fn truncate(text: &str, length: usize) -> String {
+    match text.char_indices().nth(length) {
+        None => text.to_owned(),
+        Some((end, _)) => format!("{}…", text[..end].trim_end()),
+    }
+}
+
+/// Break `text` into pieces of at most `length` characters, preferring to break
+/// between lines, then between words.
+pub fn split(text: &str, length: usize) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut rest = text.trim();
+    while let Some((end, _)) = rest.char_indices().nth(length) {
+        let head = &rest[..end];
+        let at = head
+            .rfind('\n')
+            .or_else(|| {
+                if rest[end..].starts_with(char::is_whitespace) {
+                    Some(end)
+                } else {
+                    head.rfind(' ')
+                }
+            })
+            .filter(|at| *at > 0)
+            .unwrap_or(end);
+        pieces.push(rest[..at].trim_end().to_owned());
+        rest = rest[at..].trim_start();
+    }
+    if !rest.is_empty() {
+        pieces.push(rest.to_owned());
+    }
+    pieces
+}
+
+/// Format a number as an English ordinal, e.g. `21st` or `112th`.
+pub fn ordinal(number: u64) -> String {
+    let suffix = match (number % 10, number % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", number, suffix)
+}
+
+/// Format a size in bytes with a binary unit, e.g. `1.5 GiB`.
+pub fn size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    for unit in ["B", "KiB", "MiB", "GiB"] {
+        if size < 1024.0 {
+            return match unit {
+                "B" => format!("{} B", bytes),
+                unit => format!("{:.1} {}", size, unit),
+            };
+        }
+        size /= 1024.0;
+    }
+    format!("{:.1} TiB", size)
+}
+
+/// Format a duration as hours and minutes, e.g. `12h 05m`.
+pub fn duration(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
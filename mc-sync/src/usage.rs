@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::time::Duration;
+
+/// Clock ticks per second in `/proc/[pid]/stat`, which Linux fixes at 100 for
+/// userspace.
+static TICKS: u64 = 100;
+
+/// Memory and CPU time used by the server, including any processes it started,
+/// since a launch script may run Java as a child instead of replacing itself.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Usage {
+    /// Resident memory, in bytes.
+    pub memory: u64,
+
+    /// CPU time, in user and kernel mode, since launch.
+    pub cpu: Duration,
+
+    /// The Java heap limit, from `-Xmx`, in bytes.
+    pub heap: Option<u64>,
+}
+
+/// Measure the process `pid` and its descendants, from `/proc`.
+pub fn sample(pid: u32) -> io::Result<Usage> {
+    let mut children = HashMap::<u32, Vec<u32>>::new();
+    for entry in fs::read_dir("/proc")? {
+        let child = match entry?
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse().ok())
+        {
+            None => continue,
+            Some(child) => child,
+        };
+        // Processes may exit while we look.
+        if let Ok(Some((parent, _))) = stat(child) {
+            children.entry(parent).or_default().push(child);
+        }
+    }
+
+    let mut usage = Usage::default();
+    let mut stack = vec![pid];
+    while let Some(pid) = stack.pop() {
+        let ticks = match stat(pid) {
+            Ok(Some((_, ticks))) => ticks,
+            _ => continue,
+        };
+        usage.cpu += Duration::from_millis(ticks * 1000 / TICKS);
+        usage.memory += rss(pid).unwrap_or(0);
+        usage.heap = usage.heap.or_else(|| heap(pid));
+        stack.extend(children.get(&pid).into_iter().flatten().copied());
+    }
+    Ok(usage)
+}
+
+/// The parent and CPU ticks of a process, from `/proc/[pid]/stat`.
+fn stat(pid: u32) -> io::Result<Option<(u32, u64)>> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid))?;
+
+    // The command name is in parentheses, and may contain spaces.
+    let fields = match stat.rfind(')') {
+        None => return Ok(None),
+        Some(end) => stat[end + 1..].split_whitespace().collect::<Vec<_>>(),
+    };
+    let field = |index: usize| fields.get(index)?.parse::<u64>().ok();
+    Ok(field(1).and_then(|parent| Some((parent as u32, field(11)? + field(12)?))))
+}
+
+/// Resident memory of a process, in bytes.
+fn rss(pid: u32) -> Option<u64> {
+    fs::read_to_string(format!("/proc/{}/status", pid))
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(|kilobytes| kilobytes * 1024)
+}
+
+/// The heap limit passed to Java with `-Xmx`, e.g. `-Xmx4G`.
+fn heap(pid: u32) -> Option<u64> {
+    let cmdline = fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+    // Java uses the last `-Xmx` if there are several.
+    cmdline
+        .split(|byte| *byte == 0)
+        .filter_map(|argument| std::str::from_utf8(argument).ok())
+        .rev()
+        .find_map(|argument| argument.strip_prefix("-Xmx"))
+        .and_then(parse_size)
+}
+
+/// Parse a Java memory size, like `4G`, `4096m`, or `1073741824`.
+pub fn parse_size(size: &str) -> Option<u64> {
+    let (number, unit) = match size.char_indices().last()? {
+        (index, unit) if unit.is_ascii_alphabetic() => (&size[..index], unit),
+        _ => (size, 'b'),
+    };
+    let multiplier = match unit.to_ascii_lowercase() {
+        'b' => 1,
+        'k' => 1 << 10,
+        'm' => 1 << 20,
+        'g' => 1 << 30,
+        't' => 1 << 40,
+        _ => return None,
+    };
+    number.parse::<u64>().ok().map(|number| number * multiplier)
+}